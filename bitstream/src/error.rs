@@ -48,6 +48,14 @@ pub enum BitError {
         /// Current bit position.
         bit_position: usize,
     },
+
+    /// Range-coded input did not end where the decoded symbols ended.
+    InvalidRangeCode {
+        /// Bytes consumed by the decoder.
+        consumed: usize,
+        /// Total input length in bytes.
+        len: usize,
+    },
 }
 
 impl fmt::Display for BitError {
@@ -83,6 +91,9 @@ impl fmt::Display for BitError {
             Self::MisalignedAccess { bit_position } => {
                 write!(f, "misaligned access at bit position {bit_position}")
             }
+            Self::InvalidRangeCode { consumed, len } => {
+                write!(f, "invalid range code: consumed {consumed} of {len} bytes")
+            }
         }
     }
 }
@@ -153,6 +164,18 @@ mod tests {
         assert!(msg.contains("misaligned"));
     }
 
+    #[test]
+    fn error_display_invalid_range_code() {
+        let err = BitError::InvalidRangeCode {
+            consumed: 5,
+            len: 9,
+        };
+        let msg = err.to_string();
+        assert!(msg.contains("range code"));
+        assert!(msg.contains("5"));
+        assert!(msg.contains("9"));
+    }
+
     #[test]
     fn error_equality() {
         let err1 = BitError::UnexpectedEof {
@@ -2,6 +2,8 @@
 //!
 //! This crate provides bounded [`BitWriter`] and [`BitReader`] for bit-level encoding and decoding.
 //! For convenience, [`BitVecWriter`] can be used when a growable buffer is acceptable.
+//! [`RangeEncoder`] and [`RangeDecoder`] provide an optional adaptive range coder for
+//! entropy-coding byte streams.
 //! It is designed for bounded, panic-free operation with explicit error handling.
 //!
 //! # Design Principles
@@ -28,10 +30,14 @@
 //! ```
 
 mod error;
+mod range;
 mod reader;
 mod writer;
 
 pub use error::{BitError, BitResult};
+pub use range::{
+    range_decode_bytes, range_encode_bytes, BitModel, ByteModel, RangeDecoder, RangeEncoder,
+};
 pub use reader::BitReader;
 pub use writer::{BitVecWriter, BitWriter};
 
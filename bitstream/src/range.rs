@@ -0,0 +1,377 @@
+//! Adaptive binary range coder.
+//!
+//! A carry-propagating range coder over binary decisions, in the style of
+//! LZMA. Probabilities are 11-bit integers, so encoding and decoding are
+//! deterministic across platforms. [`BitModel`] may be adaptive (updated after
+//! every bit) or fixed; [`ByteModel`] codes whole bytes as an 8-level bit tree.
+
+use crate::error::{BitError, BitResult};
+
+const PROB_BITS: u32 = 11;
+const PROB_ONE: u16 = 1 << PROB_BITS;
+const ADAPT_SHIFT: u32 = 5;
+const TOP: u32 = 1 << 24;
+
+/// Probability model for a single binary decision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitModel {
+    /// Probability of a zero bit, in units of 1/2048.
+    prob_zero: u16,
+    adaptive: bool,
+}
+
+impl BitModel {
+    /// Creates an adaptive model starting at even odds.
+    #[must_use]
+    pub const fn adaptive() -> Self {
+        Self {
+            prob_zero: PROB_ONE / 2,
+            adaptive: true,
+        }
+    }
+
+    /// Creates a static model with `prob_zero` (1/2048 units, clamped to 1..=2047).
+    #[must_use]
+    pub const fn fixed(prob_zero: u16) -> Self {
+        let prob_zero = if prob_zero == 0 {
+            1
+        } else if prob_zero >= PROB_ONE {
+            PROB_ONE - 1
+        } else {
+            prob_zero
+        };
+        Self {
+            prob_zero,
+            adaptive: false,
+        }
+    }
+
+    /// Returns the current probability of a zero bit (1/2048 units).
+    #[must_use]
+    pub const fn prob_zero(self) -> u16 {
+        self.prob_zero
+    }
+
+    fn update(&mut self, bit: bool) {
+        if !self.adaptive {
+            return;
+        }
+        if bit {
+            self.prob_zero -= self.prob_zero >> ADAPT_SHIFT;
+        } else {
+            self.prob_zero += (PROB_ONE - self.prob_zero) >> ADAPT_SHIFT;
+        }
+    }
+}
+
+impl Default for BitModel {
+    fn default() -> Self {
+        Self::adaptive()
+    }
+}
+
+/// Adaptive order-0 byte model (a bit tree of 255 [`BitModel`]s).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByteModel {
+    tree: [BitModel; 256],
+}
+
+impl ByteModel {
+    /// Creates an adaptive byte model with uniform initial probabilities.
+    #[must_use]
+    pub const fn adaptive() -> Self {
+        Self {
+            tree: [BitModel::adaptive(); 256],
+        }
+    }
+}
+
+impl Default for ByteModel {
+    fn default() -> Self {
+        Self::adaptive()
+    }
+}
+
+/// Range encoder writing into a caller-provided buffer.
+#[derive(Debug)]
+pub struct RangeEncoder<'a> {
+    out: &'a mut [u8],
+    pos: usize,
+    low: u64,
+    range: u32,
+    cache: u8,
+    pending: u64,
+    started: bool,
+}
+
+impl<'a> RangeEncoder<'a> {
+    /// Creates an encoder over `out`.
+    #[must_use]
+    pub fn new(out: &'a mut [u8]) -> Self {
+        Self {
+            out,
+            pos: 0,
+            low: 0,
+            range: u32::MAX,
+            cache: 0,
+            pending: 1,
+            started: false,
+        }
+    }
+
+    /// Encodes one bit with `model`, updating it if adaptive.
+    pub fn encode_bit(&mut self, model: &mut BitModel, bit: bool) -> BitResult<()> {
+        let bound = (self.range >> PROB_BITS) * u32::from(model.prob_zero);
+        if bit {
+            self.low += u64::from(bound);
+            self.range -= bound;
+        } else {
+            self.range = bound;
+        }
+        model.update(bit);
+        while self.range < TOP {
+            self.range <<= 8;
+            self.shift_low()?;
+        }
+        Ok(())
+    }
+
+    /// Encodes one byte with `model`.
+    pub fn encode_byte(&mut self, model: &mut ByteModel, byte: u8) -> BitResult<()> {
+        let mut ctx = 1usize;
+        for shift in (0..8).rev() {
+            let bit = (byte >> shift) & 1 == 1;
+            self.encode_bit(&mut model.tree[ctx], bit)?;
+            ctx = (ctx << 1) | usize::from(bit);
+        }
+        Ok(())
+    }
+
+    /// Flushes the coder state and returns the number of bytes written.
+    pub fn finish(mut self) -> BitResult<usize> {
+        for _ in 0..5 {
+            self.shift_low()?;
+        }
+        Ok(self.pos)
+    }
+
+    fn shift_low(&mut self) -> BitResult<()> {
+        if self.low < 0xFF00_0000 || self.low > u64::from(u32::MAX) {
+            let carry = (self.low >> 32) as u8;
+            let mut byte = self.cache;
+            while self.pending > 0 {
+                // The first byte is always zero; the decoder assumes it.
+                if self.started {
+                    self.push(byte.wrapping_add(carry))?;
+                }
+                self.started = true;
+                byte = 0xFF;
+                self.pending -= 1;
+            }
+            self.cache = (self.low >> 24) as u8;
+        }
+        self.pending += 1;
+        self.low = (self.low & 0x00FF_FFFF) << 8;
+        Ok(())
+    }
+
+    fn push(&mut self, byte: u8) -> BitResult<()> {
+        let Some(slot) = self.out.get_mut(self.pos) else {
+            return Err(BitError::WriteOverflow {
+                attempted: (self.pos + 1) * 8,
+                available: self.out.len() * 8,
+            });
+        };
+        *slot = byte;
+        self.pos += 1;
+        Ok(())
+    }
+}
+
+/// Range decoder over a byte slice produced by [`RangeEncoder`].
+#[derive(Debug)]
+pub struct RangeDecoder<'a> {
+    input: &'a [u8],
+    pos: usize,
+    code: u32,
+    range: u32,
+}
+
+impl<'a> RangeDecoder<'a> {
+    /// Creates a decoder, reading the initial coder state from `input`.
+    pub fn new(input: &'a [u8]) -> BitResult<Self> {
+        let mut decoder = Self {
+            input,
+            pos: 0,
+            code: 0,
+            range: u32::MAX,
+        };
+        for _ in 0..4 {
+            decoder.code = (decoder.code << 8) | u32::from(decoder.next_byte()?);
+        }
+        Ok(decoder)
+    }
+
+    /// Decodes one bit with `model`, updating it if adaptive.
+    pub fn decode_bit(&mut self, model: &mut BitModel) -> BitResult<bool> {
+        let bound = (self.range >> PROB_BITS) * u32::from(model.prob_zero);
+        let bit = if self.code < bound {
+            self.range = bound;
+            false
+        } else {
+            self.code -= bound;
+            self.range -= bound;
+            true
+        };
+        model.update(bit);
+        while self.range < TOP {
+            self.range <<= 8;
+            self.code = (self.code << 8) | u32::from(self.next_byte()?);
+        }
+        Ok(bit)
+    }
+
+    /// Decodes one byte with `model`.
+    pub fn decode_byte(&mut self, model: &mut ByteModel) -> BitResult<u8> {
+        let mut ctx = 1usize;
+        for _ in 0..8 {
+            let bit = self.decode_bit(&mut model.tree[ctx])?;
+            ctx = (ctx << 1) | usize::from(bit);
+        }
+        Ok((ctx & 0xFF) as u8)
+    }
+
+    /// Returns the number of input bytes consumed so far.
+    #[must_use]
+    pub fn bytes_consumed(&self) -> usize {
+        self.pos
+    }
+
+    fn next_byte(&mut self) -> BitResult<u8> {
+        let Some(byte) = self.input.get(self.pos) else {
+            return Err(BitError::UnexpectedEof {
+                requested: 8,
+                available: 0,
+            });
+        };
+        self.pos += 1;
+        Ok(*byte)
+    }
+}
+
+/// Entropy-codes `input` with a fresh adaptive [`ByteModel`] into `out`.
+///
+/// Returns the number of bytes written. The decoder must be told
+/// `input.len()` out of band.
+pub fn range_encode_bytes(input: &[u8], out: &mut [u8]) -> BitResult<usize> {
+    let mut model = ByteModel::adaptive();
+    let mut encoder = RangeEncoder::new(out);
+    for byte in input {
+        encoder.encode_byte(&mut model, *byte)?;
+    }
+    encoder.finish()
+}
+
+/// Decodes `out.len()` bytes produced by [`range_encode_bytes`].
+///
+/// Fails if `input` is exhausted early or has bytes left over.
+pub fn range_decode_bytes(input: &[u8], out: &mut [u8]) -> BitResult<()> {
+    let mut model = ByteModel::adaptive();
+    let mut decoder = RangeDecoder::new(input)?;
+    for slot in out.iter_mut() {
+        *slot = decoder.decode_byte(&mut model)?;
+    }
+    if decoder.bytes_consumed() != input.len() {
+        return Err(BitError::InvalidRangeCode {
+            consumed: decoder.bytes_consumed(),
+            len: input.len(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bits_roundtrip_adaptive_and_fixed() {
+        let bits: Vec<bool> = (0..500).map(|i| i % 7 == 0).collect();
+        let mut buf = [0u8; 256];
+        let mut adaptive = BitModel::adaptive();
+        let mut fixed = BitModel::fixed(1800);
+        let mut encoder = RangeEncoder::new(&mut buf);
+        for (i, bit) in bits.iter().enumerate() {
+            let model = if i % 2 == 0 {
+                &mut adaptive
+            } else {
+                &mut fixed
+            };
+            encoder.encode_bit(model, *bit).unwrap();
+        }
+        let len = encoder.finish().unwrap();
+        assert!(len < bits.len() / 8, "skewed bits should compress");
+
+        let mut adaptive = BitModel::adaptive();
+        let mut fixed = BitModel::fixed(1800);
+        let mut decoder = RangeDecoder::new(&buf[..len]).unwrap();
+        for (i, bit) in bits.iter().enumerate() {
+            let model = if i % 2 == 0 {
+                &mut adaptive
+            } else {
+                &mut fixed
+            };
+            assert_eq!(decoder.decode_bit(model).unwrap(), *bit);
+        }
+        assert_eq!(decoder.bytes_consumed(), len);
+    }
+
+    #[test]
+    fn fixed_model_does_not_adapt() {
+        let mut model = BitModel::fixed(0);
+        assert_eq!(model.prob_zero(), 1);
+        model.update(false);
+        assert_eq!(model.prob_zero(), 1);
+    }
+
+    #[test]
+    fn bytes_roundtrip() {
+        let input: Vec<u8> = (0..1000u32).map(|i| (i % 5) as u8).collect();
+        let mut coded = [0u8; 1024];
+        let len = range_encode_bytes(&input, &mut coded).unwrap();
+        assert!(len < input.len() / 2);
+
+        let mut decoded = vec![0u8; input.len()];
+        range_decode_bytes(&coded[..len], &mut decoded).unwrap();
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn carry_heavy_input_roundtrips() {
+        let input = [0xFFu8; 300];
+        let mut coded = [0u8; 512];
+        let len = range_encode_bytes(&input, &mut coded).unwrap();
+        let mut decoded = [0u8; 300];
+        range_decode_bytes(&coded[..len], &mut decoded).unwrap();
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn encode_overflow_is_error() {
+        let input = [1u8, 2, 3, 4, 5, 6, 7, 8];
+        let mut coded = [0u8; 2];
+        assert!(matches!(
+            range_encode_bytes(&input, &mut coded),
+            Err(BitError::WriteOverflow { .. })
+        ));
+    }
+
+    #[test]
+    fn truncated_input_is_error() {
+        let input = [7u8; 64];
+        let mut coded = [0u8; 128];
+        let len = range_encode_bytes(&input, &mut coded).unwrap();
+        let mut decoded = [0u8; 64];
+        assert!(range_decode_bytes(&coded[..len - 1], &mut decoded).is_err());
+    }
+}
@@ -136,6 +136,23 @@ impl<'a> BitReader<'a> {
         Err(BitError::InvalidVarint)
     }
 
+    /// Reads a byte-aligned varint `u64`.
+    pub fn read_varu64(&mut self) -> BitResult<u64> {
+        self.ensure_aligned()?;
+        let mut result = 0u64;
+        for shift in (0..70).step_by(7) {
+            let byte = self.read_u8_aligned()?;
+            if shift == 63 && byte > 1 {
+                return Err(BitError::InvalidVarint);
+            }
+            result |= u64::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        Err(BitError::InvalidVarint)
+    }
+
     /// Reads a byte-aligned zigzag varint `i32`.
     pub fn read_vars32(&mut self) -> BitResult<i32> {
         let value = self.read_varu32()?;
@@ -220,6 +237,22 @@ mod tests {
         assert_eq!(reader.read_varu32().unwrap(), 300);
     }
 
+    #[test]
+    fn read_varu64() {
+        let mut reader = BitReader::new(&[0xAC, 0x02]);
+        assert_eq!(reader.read_varu64().unwrap(), 300);
+        let mut buf = [0xFF; 10];
+        buf[9] = 0x01;
+        let mut reader = BitReader::new(&buf);
+        assert_eq!(reader.read_varu64().unwrap(), u64::MAX);
+        buf[9] = 0x02;
+        let mut reader = BitReader::new(&buf);
+        assert!(matches!(
+            reader.read_varu64().unwrap_err(),
+            BitError::InvalidVarint
+        ));
+    }
+
     #[test]
     fn read_vars32() {
         let mut reader = BitReader::new(&[0x01]);
@@ -121,6 +121,24 @@ impl<'a> BitWriter<'a> {
         Ok(())
     }
 
+    /// Writes a byte-aligned varint `u64`. Values that fit in a `u32` encode
+    /// exactly as [`Self::write_varu32`] would.
+    pub fn write_varu64(&mut self, mut value: u64) -> BitResult<()> {
+        self.ensure_aligned()?;
+        loop {
+            let mut byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value != 0 {
+                byte |= 0x80;
+            }
+            self.write_u8_aligned(byte)?;
+            if value == 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+
     /// Writes a byte-aligned zigzag varint `i32`.
     pub fn write_vars32(&mut self, value: i32) -> BitResult<()> {
         let zigzag = ((value << 1) ^ (value >> 31)) as u32;
@@ -284,6 +302,24 @@ impl BitVecWriter {
         Ok(())
     }
 
+    /// Writes a byte-aligned varint `u64`. Values that fit in a `u32` encode
+    /// exactly as [`Self::write_varu32`] would.
+    pub fn write_varu64(&mut self, mut value: u64) -> BitResult<()> {
+        self.ensure_aligned()?;
+        loop {
+            let mut byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value != 0 {
+                byte |= 0x80;
+            }
+            self.write_u8_aligned(byte)?;
+            if value == 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+
     /// Writes a byte-aligned zigzag varint `i32`.
     pub fn write_vars32(&mut self, value: i32) -> BitResult<()> {
         let zigzag = ((value << 1) ^ (value >> 31)) as u32;
@@ -390,6 +426,19 @@ mod tests {
         assert_eq!(bytes, vec![0xAC, 0x02]);
     }
 
+    #[test]
+    fn varu64_matches_varu32_encoding() {
+        let mut writer = BitVecWriter::new();
+        writer.write_varu64(300).unwrap();
+        assert_eq!(writer.finish(), vec![0xAC, 0x02]);
+
+        let mut writer = BitVecWriter::new();
+        writer.write_varu64(u64::MAX).unwrap();
+        let bytes = writer.finish();
+        assert_eq!(bytes.len(), 10);
+        assert_eq!(bytes[9], 0x01);
+    }
+
     #[test]
     fn vec_writer_zigzag() {
         let mut writer = BitVecWriter::new();
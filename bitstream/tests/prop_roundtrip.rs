@@ -149,3 +149,14 @@ proptest! {
         }
     }
 }
+
+proptest! {
+    #[test]
+    fn range_coded_bytes_roundtrip(input in proptest::collection::vec(any::<u8>(), 0..512)) {
+        let mut coded = vec![0u8; input.len() * 2 + 16];
+        let len = bitstream::range_encode_bytes(&input, &mut coded).unwrap();
+        let mut decoded = vec![0u8; input.len()];
+        bitstream::range_decode_bytes(&coded[..len], &mut decoded).unwrap();
+        prop_assert_eq!(decoded, input);
+    }
+}
@@ -0,0 +1,300 @@
+//! Per-client acknowledgement tracking and baseline selection.
+//!
+//! The server keeps one `BaselineStore` of the snapshots it has sent and one
+//! `ClientAckTracker` per client. Each tracker records the newest tick the
+//! client has acknowledged, picks the baseline for that client's next delta,
+//! and tells the server when the client can only be recovered with a full
+//! snapshot. [`ClientAckTracker::evict_unneeded`] drops baselines that no
+//! client can still use.
+
+use crate::baseline::BaselineStore;
+use crate::types::SnapshotTick;
+
+/// Tracks the ticks one client has acknowledged.
+#[derive(Debug, Clone, Default)]
+pub struct ClientAckTracker {
+    latest_ack: Option<SnapshotTick>,
+    /// Tick of the last full snapshot sent; acks for older ticks refer to
+    /// state the client has discarded.
+    floor: Option<SnapshotTick>,
+    resync_requested: bool,
+}
+
+/// Result of ingesting an ack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckOutcome {
+    /// The ack is newer than any seen so far and becomes the baseline candidate.
+    Advanced,
+    /// The ack is a duplicate, arrived out of order or predates the last full
+    /// snapshot; it is ignored.
+    Stale,
+    /// The ack reported a decode error; the client awaits a full snapshot.
+    Nacked,
+}
+
+/// What to send a client next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaselineSelection {
+    /// Encode a delta against this acknowledged baseline.
+    Delta { baseline_tick: SnapshotTick },
+    /// No usable baseline; send a full snapshot.
+    Resync { reason: ResyncReason },
+}
+
+/// Why a client needs a full snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResyncReason {
+    /// The client has not acknowledged any tick since its last full snapshot.
+    NoAck,
+    /// The newest acknowledged tick is no longer (or was never) in the
+    /// baseline store.
+    FellOffRing { last_ack: SnapshotTick },
+    /// The client reported that it could not apply a packet.
+    Requested,
+}
+
+impl ClientAckTracker {
+    /// Creates a tracker for a client that has not received anything yet.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that the client has applied the snapshot or delta for `tick`.
+    ///
+    /// Acks may arrive duplicated or out of order; only a tick newer than the
+    /// current latest ack (and not older than the last full snapshot) advances
+    /// the tracker.
+    pub fn ack(&mut self, tick: SnapshotTick) -> AckOutcome {
+        if self.floor.is_some_and(|floor| tick.is_before(floor))
+            || self.latest_ack.is_some_and(|latest| !tick.is_after(latest))
+        {
+            return AckOutcome::Stale;
+        }
+        self.latest_ack = Some(tick);
+        AckOutcome::Advanced
+    }
+
+    /// Ingests an ack packet from the client: an attached error class is a
+    /// nack, otherwise `latest_tick` is acked.
+    pub fn ingest(&mut self, ack: &wire::AckPacket) -> AckOutcome {
+        if ack.error.is_some() {
+            self.nack();
+            return AckOutcome::Nacked;
+        }
+        self.ack(SnapshotTick::new(ack.latest_tick))
+    }
+
+    /// Records that the client failed to apply a packet and needs a full
+    /// snapshot. Its acks are ignored until [`Self::full_snapshot_sent`].
+    pub fn nack(&mut self) {
+        self.resync_requested = true;
+    }
+
+    /// Records that a full snapshot for `tick` was sent, resetting the ack
+    /// state. Acks for ticks before `tick` are ignored from now on.
+    pub fn full_snapshot_sent(&mut self, tick: SnapshotTick) {
+        self.latest_ack = None;
+        self.floor = Some(tick);
+        self.resync_requested = false;
+    }
+
+    /// Records that the client resumed its session holding `tick`, which
+    /// becomes its latest ack. Acks for older ticks are ignored from now on.
+    pub fn resumed(&mut self, tick: SnapshotTick) {
+        self.latest_ack = Some(tick);
+        self.floor = Some(tick);
+        self.resync_requested = false;
+    }
+
+    /// Returns the newest acknowledged tick.
+    #[must_use]
+    pub fn latest_ack(&self) -> Option<SnapshotTick> {
+        self.latest_ack
+    }
+
+    /// Chooses the baseline for the next delta, or reports why a full
+    /// snapshot is needed.
+    #[must_use]
+    pub fn select_baseline<T>(&self, store: &BaselineStore<T>) -> BaselineSelection {
+        if self.resync_requested {
+            return BaselineSelection::Resync {
+                reason: ResyncReason::Requested,
+            };
+        }
+        match self.latest_ack {
+            None => BaselineSelection::Resync {
+                reason: ResyncReason::NoAck,
+            },
+            Some(tick) if store.get(tick).is_some() => BaselineSelection::Delta {
+                baseline_tick: tick,
+            },
+            Some(last_ack) => BaselineSelection::Resync {
+                reason: ResyncReason::FellOffRing { last_ack },
+            },
+        }
+    }
+
+    /// Returns `true` if the client can only be recovered with a full snapshot
+    /// because it asked for one or its last ack has left the baseline store.
+    ///
+    /// A client that simply has not acked its first full snapshot yet is not
+    /// reported here.
+    #[must_use]
+    pub fn needs_resync<T>(&self, store: &BaselineStore<T>) -> bool {
+        matches!(
+            self.select_baseline(store),
+            BaselineSelection::Resync {
+                reason: ResyncReason::Requested | ResyncReason::FellOffRing { .. }
+            }
+        )
+    }
+
+    /// Oldest tick this client may still ack and have used as a baseline.
+    fn pinned_tick(&self) -> Option<SnapshotTick> {
+        if self.resync_requested {
+            return None;
+        }
+        self.latest_ack.or(self.floor)
+    }
+
+    /// Removes baselines older than any tick a client may still use.
+    ///
+    /// Each client pins its latest ack, or its last full snapshot while that
+    /// is unacknowledged; clients awaiting a resync pin nothing. With no pins,
+    /// only the newest baseline is kept. Returns the number of baselines
+    /// removed.
+    pub fn evict_unneeded<'a, T>(
+        store: &mut BaselineStore<T>,
+        clients: impl IntoIterator<Item = &'a Self>,
+    ) -> usize {
+        let oldest_needed = clients
+            .into_iter()
+            .filter_map(Self::pinned_tick)
+            .reduce(|oldest, tick| if tick.is_before(oldest) { tick } else { oldest })
+            .or_else(|| store.newest().map(|(tick, _)| tick));
+        oldest_needed.map_or(0, |tick| store.remove_before(tick))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::NonZeroUsize;
+
+    fn tick(raw: u32) -> SnapshotTick {
+        SnapshotTick::new(raw)
+    }
+
+    fn store(ticks: impl IntoIterator<Item = u32>) -> BaselineStore<u32> {
+        let mut store = BaselineStore::new(NonZeroUsize::new(4).unwrap());
+        for raw in ticks {
+            store.insert(tick(raw), raw).unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn ignores_duplicate_and_reordered_acks() {
+        let mut tracker = ClientAckTracker::new();
+        assert_eq!(tracker.ack(tick(11)), AckOutcome::Advanced);
+        assert_eq!(tracker.ack(tick(13)), AckOutcome::Advanced);
+        assert_eq!(tracker.ack(tick(12)), AckOutcome::Stale);
+        assert_eq!(tracker.ack(tick(13)), AckOutcome::Stale);
+        assert_eq!(tracker.latest_ack(), Some(tick(13)));
+        assert_eq!(
+            tracker.select_baseline(&store(10..=14)),
+            BaselineSelection::Delta {
+                baseline_tick: tick(13)
+            }
+        );
+    }
+
+    #[test]
+    fn ingests_ack_packets() {
+        let mut tracker = ClientAckTracker::new();
+        let ring = store(10..=13);
+        assert_eq!(
+            tracker.ingest(&wire::AckPacket::new(12)),
+            AckOutcome::Advanced
+        );
+        assert!(!tracker.needs_resync(&ring));
+        assert_eq!(
+            tracker.ingest(&wire::AckPacket::new(13).with_error(wire::AckErrorClass::Baseline)),
+            AckOutcome::Nacked
+        );
+        assert_eq!(tracker.latest_ack(), Some(tick(12)));
+        assert!(tracker.needs_resync(&ring));
+    }
+
+    #[test]
+    fn reports_resync_reasons() {
+        let mut tracker = ClientAckTracker::new();
+        let ring = store(20..=23);
+        assert_eq!(
+            tracker.select_baseline(&ring),
+            BaselineSelection::Resync {
+                reason: ResyncReason::NoAck
+            }
+        );
+        assert!(!tracker.needs_resync(&ring));
+
+        tracker.ack(tick(15));
+        assert_eq!(
+            tracker.select_baseline(&ring),
+            BaselineSelection::Resync {
+                reason: ResyncReason::FellOffRing { last_ack: tick(15) }
+            }
+        );
+        assert!(tracker.needs_resync(&ring));
+
+        tracker.full_snapshot_sent(tick(23));
+        assert_eq!(tracker.ack(tick(21)), AckOutcome::Stale);
+        assert_eq!(tracker.ack(tick(23)), AckOutcome::Advanced);
+        assert!(!tracker.needs_resync(&ring));
+
+        tracker.nack();
+        assert_eq!(
+            tracker.select_baseline(&ring),
+            BaselineSelection::Resync {
+                reason: ResyncReason::Requested
+            }
+        );
+    }
+
+    #[test]
+    fn evicts_baselines_no_client_needs() {
+        let mut ring = store(10..=13);
+        let mut slow = ClientAckTracker::new();
+        let mut fast = ClientAckTracker::new();
+        slow.ack(tick(11));
+        fast.ack(tick(13));
+
+        assert_eq!(
+            ClientAckTracker::evict_unneeded(&mut ring, [&slow, &fast]),
+            1
+        );
+        assert_eq!(ring.get(tick(10)), None);
+        assert_eq!(ring.get(tick(11)), Some(&11));
+
+        // A client waiting on its first full snapshot pins that tick.
+        let mut joining = ClientAckTracker::new();
+        joining.full_snapshot_sent(tick(12));
+        slow.nack();
+        assert_eq!(
+            ClientAckTracker::evict_unneeded(&mut ring, [&slow, &fast, &joining]),
+            1
+        );
+        assert_eq!(ring.iter().next().map(|(t, _)| t), Some(tick(12)));
+
+        slow.nack();
+        fast.nack();
+        joining.nack();
+        assert_eq!(
+            ClientAckTracker::evict_unneeded(&mut ring, [&slow, &fast, &joining]),
+            1
+        );
+        assert_eq!(ring.len(), 1);
+        assert_eq!(ring.get(tick(13)), Some(&13));
+    }
+}
@@ -0,0 +1,284 @@
+//! Server-side entity id allocation with generational index reuse.
+
+use std::collections::VecDeque;
+
+use schema::EntityIdWidth;
+
+use crate::error::{CodecError, CodecResult};
+use crate::types::{EntityId, SnapshotTick};
+
+/// Hands out [`EntityId`]s, reusing freed indices with a bumped generation.
+///
+/// A freed index stays quarantined until no baseline still in flight can
+/// hold the old entity: an index freed at tick `T` is reusable once the
+/// oldest in-flight baseline is at or after `T`. Indices are reused in the
+/// order they were freed. Fresh indices never exceed the schema's
+/// [`EntityIdWidth`].
+#[derive(Debug, Clone, Default)]
+pub struct EntityIdAllocator {
+    /// Entity id width of the schema the ids are encoded with.
+    width: EntityIdWidth,
+    /// Current generation of every index handed out so far.
+    generations: Vec<u16>,
+    /// Whether each index is currently allocated.
+    live: Vec<bool>,
+    /// Freed indices with the tick they were freed at, oldest first.
+    free: VecDeque<(u64, SnapshotTick)>,
+    /// Oldest baseline tick a client may still apply a delta against.
+    oldest_in_flight: SnapshotTick,
+}
+
+impl EntityIdAllocator {
+    /// Creates an empty allocator for the default entity id width.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty allocator whose indices fit `width`.
+    #[must_use]
+    pub fn with_width(width: EntityIdWidth) -> Self {
+        Self {
+            width,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the number of live ids.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.live.len() - self.free.len()
+    }
+
+    /// Returns `true` if no ids are live.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if `id` is live with exactly this generation.
+    #[must_use]
+    pub fn is_live(&self, id: EntityId) -> bool {
+        let index = id.index() as usize;
+        self.live.get(index).copied().unwrap_or(false) && self.generations[index] == id.generation()
+    }
+
+    /// Records the oldest baseline tick still in flight, typically the
+    /// oldest tick any client has not yet acknowledged past.
+    ///
+    /// The horizon never moves backwards; ticks compare in serial-number
+    /// order, so it keeps advancing across tick wraparound.
+    pub fn set_oldest_in_flight(&mut self, tick: SnapshotTick) {
+        if tick.is_after(self.oldest_in_flight) {
+            self.oldest_in_flight = tick;
+        }
+    }
+
+    /// Allocates an id, reusing the oldest freed index whose quarantine has
+    /// passed, or a fresh index with generation 0 otherwise.
+    ///
+    /// Fails with [`CodecError::EntityIdOutOfRange`] once every index the
+    /// width allows is live or quarantined.
+    pub fn allocate(&mut self) -> CodecResult<EntityId> {
+        if let Some(&(index, freed_at)) = self.free.front() {
+            if !freed_at.is_after(self.oldest_in_flight) {
+                self.free.pop_front();
+                let slot = index as usize;
+                self.generations[slot] = self.generations[slot].wrapping_add(1);
+                self.live[slot] = true;
+                return Ok(EntityId::with_generation(index, self.generations[slot]));
+            }
+        }
+        let index = self.live.len() as u64;
+        self.ensure_fits(index)?;
+        self.generations.push(0);
+        self.live.push(true);
+        Ok(EntityId::new(index))
+    }
+
+    /// Marks `id` live with its generation, for ids assigned elsewhere, e.g.
+    /// decoded from a peer.
+    ///
+    /// A quarantined index is taken out of quarantine. Fresh indices below
+    /// `id` become immediately reusable, starting at generation 0, so indices
+    /// stay dense.
+    pub fn reserve(&mut self, id: EntityId) -> CodecResult<()> {
+        let index = id.index();
+        self.ensure_fits(index)?;
+        let slot = index as usize;
+        let mut skipped = 0;
+        while self.live.len() <= slot {
+            let fresh = self.live.len() as u64;
+            // Reuse bumps the generation, so this hands out generation 0.
+            self.generations.push(u16::MAX);
+            self.live.push(false);
+            if fresh != index {
+                self.free.insert(skipped, (fresh, self.oldest_in_flight));
+                skipped += 1;
+            }
+        }
+        if !self.live[slot] {
+            if let Some(position) = self.free.iter().position(|(free, _)| *free == index) {
+                self.free.remove(position);
+            }
+        }
+        self.generations[slot] = id.generation();
+        self.live[slot] = true;
+        Ok(())
+    }
+
+    /// Frees `id`, which is absent from every snapshot at or after `tick`.
+    pub fn free(&mut self, id: EntityId, tick: SnapshotTick) -> CodecResult<()> {
+        let slot = id.index() as usize;
+        if !self.live.get(slot).copied().unwrap_or(false) {
+            return Err(CodecError::EntityNotFound {
+                entity_id: id.index(),
+            });
+        }
+        if self.generations[slot] != id.generation() {
+            return Err(CodecError::StaleEntity {
+                entity_id: id.index(),
+                expected_generation: self.generations[slot],
+                found_generation: id.generation(),
+            });
+        }
+        self.live[slot] = false;
+        self.free.push_back((id.index(), tick));
+        Ok(())
+    }
+
+    fn ensure_fits(&self, index: u64) -> CodecResult<()> {
+        if index > self.width.max_index() {
+            return Err(CodecError::EntityIdOutOfRange {
+                entity_id: index,
+                bits: self.width.bits(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocates_fresh_indices() {
+        let mut ids = EntityIdAllocator::new();
+        assert_eq!(ids.allocate().unwrap(), EntityId::new(0));
+        assert_eq!(ids.allocate().unwrap(), EntityId::new(1));
+        assert_eq!(ids.len(), 2);
+    }
+
+    #[test]
+    fn reuses_index_after_quarantine() {
+        let mut ids = EntityIdAllocator::new();
+        let a = ids.allocate().unwrap();
+        ids.free(a, SnapshotTick::new(10)).unwrap();
+        assert!(!ids.is_live(a));
+
+        // A baseline from before the destroy is still in flight.
+        ids.set_oldest_in_flight(SnapshotTick::new(9));
+        assert_eq!(ids.allocate().unwrap(), EntityId::new(1));
+
+        ids.set_oldest_in_flight(SnapshotTick::new(10));
+        let reused = ids.allocate().unwrap();
+        assert_eq!(reused, EntityId::with_generation(0, 1));
+        assert!(ids.is_live(reused));
+        assert!(!ids.is_live(a));
+    }
+
+    #[test]
+    fn reuses_index_across_tick_wraparound() {
+        let mut ids = EntityIdAllocator::new();
+        ids.set_oldest_in_flight(SnapshotTick::new(u32::MAX - 5));
+        let a = ids.allocate().unwrap();
+        ids.free(a, SnapshotTick::new(3)).unwrap();
+
+        // Tick 3 is after u32::MAX - 2 once the counter wraps.
+        ids.set_oldest_in_flight(SnapshotTick::new(u32::MAX - 2));
+        assert_eq!(ids.allocate().unwrap(), EntityId::new(1));
+
+        // The wrapped horizon advances past the pre-wrap one and does not
+        // fall back to it.
+        ids.set_oldest_in_flight(SnapshotTick::new(3));
+        assert_eq!(ids.allocate().unwrap(), EntityId::with_generation(0, 1));
+        ids.set_oldest_in_flight(SnapshotTick::new(u32::MAX));
+        let b = ids.allocate().unwrap();
+        ids.free(b, SnapshotTick::new(4)).unwrap();
+        assert_eq!(ids.allocate().unwrap(), EntityId::new(3));
+    }
+
+    #[test]
+    fn reserve_marks_ids_live_and_keeps_indices_dense() {
+        let mut ids = EntityIdAllocator::new();
+        ids.reserve(EntityId::with_generation(2, 4)).unwrap();
+        assert!(ids.is_live(EntityId::with_generation(2, 4)));
+        assert_eq!(ids.len(), 1);
+        assert_eq!(ids.allocate().unwrap(), EntityId::new(0));
+        assert_eq!(ids.allocate().unwrap(), EntityId::new(1));
+        assert_eq!(ids.allocate().unwrap(), EntityId::new(3));
+
+        // Reserving a quarantined index takes it out of quarantine.
+        ids.free(EntityId::new(0), SnapshotTick::new(5)).unwrap();
+        ids.reserve(EntityId::with_generation(0, 1)).unwrap();
+        ids.set_oldest_in_flight(SnapshotTick::new(5));
+        assert_eq!(ids.allocate().unwrap(), EntityId::new(4));
+        assert_eq!(ids.len(), 5);
+    }
+
+    #[test]
+    fn allocation_respects_id_width() {
+        let mut ids = EntityIdAllocator::with_width(EntityIdWidth::U16);
+        for _ in 0..=u16::MAX {
+            ids.allocate().unwrap();
+        }
+        assert!(matches!(
+            ids.allocate(),
+            Err(CodecError::EntityIdOutOfRange {
+                entity_id: 65_536,
+                bits: 16,
+            })
+        ));
+        assert!(matches!(
+            ids.reserve(EntityId::new(70_000)),
+            Err(CodecError::EntityIdOutOfRange {
+                entity_id: 70_000,
+                bits: 16,
+            })
+        ));
+    }
+
+    #[test]
+    fn horizon_never_moves_backwards() {
+        let mut ids = EntityIdAllocator::new();
+        let a = ids.allocate().unwrap();
+        ids.free(a, SnapshotTick::new(5)).unwrap();
+        ids.set_oldest_in_flight(SnapshotTick::new(8));
+        ids.set_oldest_in_flight(SnapshotTick::new(2));
+        assert_eq!(ids.allocate().unwrap(), EntityId::with_generation(0, 1));
+    }
+
+    #[test]
+    fn free_rejects_stale_and_unknown_ids() {
+        let mut ids = EntityIdAllocator::new();
+        let a = ids.allocate().unwrap();
+        ids.free(a, SnapshotTick::new(1)).unwrap();
+        ids.set_oldest_in_flight(SnapshotTick::new(1));
+        let b = ids.allocate().unwrap();
+        assert_eq!(b.index(), a.index());
+
+        assert!(matches!(
+            ids.free(a, SnapshotTick::new(2)),
+            Err(CodecError::StaleEntity {
+                entity_id: 0,
+                expected_generation: 1,
+                found_generation: 0,
+            })
+        ));
+        assert!(matches!(
+            ids.free(EntityId::new(7), SnapshotTick::new(2)),
+            Err(CodecError::EntityNotFound { entity_id: 7 })
+        ));
+    }
+}
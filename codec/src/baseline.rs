@@ -1,6 +1,8 @@
 //! Baseline history storage for snapshots.
 
+use std::cmp::Ordering;
 use std::num::NonZeroUsize;
+use std::ops::{Bound, Range, RangeBounds};
 
 use crate::SnapshotTick;
 
@@ -64,13 +66,15 @@ impl<T> BaselineStore<T> {
 
     /// Inserts a new baseline at the given tick.
     ///
-    /// Ticks must be strictly increasing.
+    /// Ticks must be strictly increasing in serial-number order (see
+    /// [`SnapshotTick::is_after`]), so the store keeps working across the
+    /// `u32` wrap.
     ///
     /// When the store is full, this overwrites the oldest entry and advances
     /// the head, making the inserted tick the newest entry immediately.
     pub fn insert(&mut self, tick: SnapshotTick, value: T) -> Result<(), BaselineError> {
         if let Some(last) = self.last_tick {
-            if tick <= last {
+            if !tick.is_after(last) {
                 return Err(BaselineError::OutOfOrder {
                     last_tick: last,
                     new_tick: tick,
@@ -93,34 +97,162 @@ impl<T> BaselineStore<T> {
     }
 
     /// Returns the baseline for an exact tick, if present.
+    ///
+    /// O(1) while the stored ticks are contiguous, O(log n) otherwise.
     #[must_use]
     pub fn get(&self, tick: SnapshotTick) -> Option<&T> {
-        self.iter().find(|(t, _)| *t == tick).map(|(_, v)| v)
+        let idx = self.position(tick).ok()?;
+        Some(&self.entry(idx).value)
     }
 
     /// Returns the latest baseline at or before the given tick.
     ///
-    /// This performs an O(capacity) scan and is intended for small windows.
+    /// Same cost as [`Self::get`].
     #[must_use]
     pub fn latest_at_or_before(&self, tick: SnapshotTick) -> Option<(SnapshotTick, &T)> {
-        for (t, v) in self.iter().rev() {
-            if t <= tick {
-                return Some((t, v));
-            }
+        let idx = match self.position(tick) {
+            Ok(idx) => idx,
+            Err(0) => return None,
+            Err(idx) => idx - 1,
+        };
+        let entry = self.entry(idx);
+        Some((entry.tick, &entry.value))
+    }
+
+    /// Returns the oldest baseline.
+    #[must_use]
+    pub fn oldest(&self) -> Option<(SnapshotTick, &T)> {
+        (self.len > 0).then(|| {
+            let entry = self.entry(0);
+            (entry.tick, &entry.value)
+        })
+    }
+
+    /// Returns the newest baseline.
+    #[must_use]
+    pub fn newest(&self) -> Option<(SnapshotTick, &T)> {
+        (self.len > 0).then(|| {
+            let entry = self.entry(self.len - 1);
+            (entry.tick, &entry.value)
+        })
+    }
+
+    /// Removes every baseline older than `tick`, returning how many were removed.
+    ///
+    /// Later inserts must still be newer than the last tick ever inserted.
+    pub fn remove_before(&mut self, tick: SnapshotTick) -> usize {
+        let removed = match self.position(tick) {
+            Ok(idx) | Err(idx) => idx,
+        };
+        let cap = self.entries.len();
+        for _ in 0..removed {
+            self.entries[self.head] = None;
+            self.head = (self.head + 1) % cap;
         }
-        None
+        self.len -= removed;
+        removed
+    }
+
+    /// Removes all baselines and forgets the last inserted tick, so the next
+    /// insert may use any tick.
+    pub fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|entry| *entry = None);
+        self.head = 0;
+        self.len = 0;
+        self.last_tick = None;
     }
 
     /// Returns an iterator from oldest to newest.
     pub fn iter(&self) -> impl DoubleEndedIterator<Item = (SnapshotTick, &T)> {
-        let cap = self.entries.len();
-        (0..self.len).filter_map(move |i| {
-            let idx = (self.head + i) % cap;
-            self.entries[idx]
-                .as_ref()
-                .map(|entry| (entry.tick, &entry.value))
+        self.iter_indices(0..self.len)
+    }
+
+    /// Returns an iterator, oldest to newest, over the baselines whose ticks
+    /// fall in `range`.
+    pub fn range(
+        &self,
+        range: impl RangeBounds<SnapshotTick>,
+    ) -> impl DoubleEndedIterator<Item = (SnapshotTick, &T)> {
+        let start = match range.start_bound() {
+            Bound::Included(tick) => self.lower_bound(*tick),
+            Bound::Excluded(tick) => self.upper_bound(*tick),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(tick) => self.upper_bound(*tick),
+            Bound::Excluded(tick) => self.lower_bound(*tick),
+            Bound::Unbounded => self.len,
+        };
+        self.iter_indices(start..end.max(start))
+    }
+
+    fn iter_indices(
+        &self,
+        indices: Range<usize>,
+    ) -> impl DoubleEndedIterator<Item = (SnapshotTick, &T)> {
+        indices.map(move |idx| {
+            let entry = self.entry(idx);
+            (entry.tick, &entry.value)
         })
     }
+
+    /// Returns the entry at logical index `idx` (0 = oldest).
+    fn entry(&self, idx: usize) -> &Entry<T> {
+        self.entries[(self.head + idx) % self.entries.len()]
+            .as_ref()
+            .expect("baseline ring slot within len is occupied")
+    }
+
+    /// Finds the logical index of `tick`, or where it would be inserted.
+    ///
+    /// Ticks are compared by their serial offset from the oldest tick, which
+    /// is monotonic across the `u32` wrap. When the span from oldest to newest
+    /// equals the entry count the ticks are contiguous and the index is that
+    /// offset. Otherwise this falls back to a binary search.
+    fn position(&self, tick: SnapshotTick) -> Result<usize, usize> {
+        if self.len == 0 {
+            return Err(0);
+        }
+        let oldest = self.entry(0).tick;
+        let newest = self.entry(self.len - 1).tick;
+        let offset = tick.since(oldest);
+        let span = newest.since(oldest);
+        if offset > span {
+            return if tick.is_after(newest) {
+                Err(self.len)
+            } else {
+                Err(0)
+            };
+        }
+        if span as usize == self.len - 1 {
+            return Ok(offset as usize);
+        }
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.entry(mid).tick.since(oldest).cmp(&offset) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(low)
+    }
+
+    /// Logical index of the first entry at or after `tick`.
+    fn lower_bound(&self, tick: SnapshotTick) -> usize {
+        match self.position(tick) {
+            Ok(idx) | Err(idx) => idx,
+        }
+    }
+
+    /// Logical index of the first entry after `tick`.
+    fn upper_bound(&self, tick: SnapshotTick) -> usize {
+        match self.position(tick) {
+            Ok(idx) => idx + 1,
+            Err(idx) => idx,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -172,6 +304,101 @@ mod tests {
         assert_eq!(store.get(SnapshotTick::new(3)), Some(&3));
     }
 
+    #[test]
+    fn remove_before_drops_oldest_entries() {
+        let mut store = BaselineStore::new(NonZeroUsize::new(3).unwrap());
+        for tick in 1..=4 {
+            store.insert(SnapshotTick::new(tick), tick).unwrap();
+        }
+
+        assert_eq!(store.remove_before(SnapshotTick::new(4)), 2);
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.get(SnapshotTick::new(3)), None);
+        assert_eq!(store.remove_before(SnapshotTick::new(4)), 0);
+
+        store.insert(SnapshotTick::new(5), 5).unwrap();
+        store.insert(SnapshotTick::new(6), 6).unwrap();
+        store.insert(SnapshotTick::new(7), 7).unwrap();
+        assert_eq!(store.get(SnapshotTick::new(4)), None);
+        assert_eq!(store.get(SnapshotTick::new(5)), Some(&5));
+        assert!(store.insert(SnapshotTick::new(3), 3).is_err());
+    }
+
+    #[test]
+    fn lookup_with_gaps_uses_binary_search() {
+        let mut store = BaselineStore::new(NonZeroUsize::new(5).unwrap());
+        for tick in [2, 3, 7, 8, 20, 21] {
+            store.insert(SnapshotTick::new(tick), tick).unwrap();
+        }
+
+        assert_eq!(store.get(SnapshotTick::new(2)), None);
+        for tick in [3, 7, 8, 20, 21] {
+            assert_eq!(store.get(SnapshotTick::new(tick)), Some(&tick));
+        }
+        for tick in [4, 9, 19, 22] {
+            assert_eq!(store.get(SnapshotTick::new(tick)), None);
+        }
+        assert_eq!(
+            store
+                .latest_at_or_before(SnapshotTick::new(19))
+                .map(|(t, _)| t),
+            Some(SnapshotTick::new(8))
+        );
+    }
+
+    #[test]
+    fn oldest_newest_and_range() {
+        let mut store = BaselineStore::new(NonZeroUsize::new(4).unwrap());
+        assert_eq!(store.oldest(), None);
+        assert_eq!(store.newest(), None);
+        assert_eq!(store.range(..).count(), 0);
+        for tick in 1..=6 {
+            store.insert(SnapshotTick::new(tick * 10), tick).unwrap();
+        }
+
+        assert_eq!(store.oldest(), Some((SnapshotTick::new(30), &3)));
+        assert_eq!(store.newest(), Some((SnapshotTick::new(60), &6)));
+        let ticks = |range: (Bound<SnapshotTick>, Bound<SnapshotTick>)| {
+            store.range(range).map(|(t, _)| t.raw()).collect::<Vec<_>>()
+        };
+        let tick = SnapshotTick::new;
+        assert_eq!(
+            ticks((Bound::Included(tick(40)), Bound::Included(tick(50)))),
+            [40, 50]
+        );
+        assert_eq!(
+            ticks((Bound::Excluded(tick(40)), Bound::Unbounded)),
+            [50, 60]
+        );
+        assert_eq!(
+            ticks((Bound::Unbounded, Bound::Excluded(tick(45)))),
+            [30, 40]
+        );
+        assert_eq!(
+            ticks((Bound::Included(tick(55)), Bound::Excluded(tick(41)))),
+            Vec::<u32>::new()
+        );
+        assert_eq!(
+            store.range(tick(0)..tick(100)).next_back(),
+            Some((tick(60), &6))
+        );
+    }
+
+    #[test]
+    fn clear_resets_store() {
+        let mut store = BaselineStore::new(NonZeroUsize::new(2).unwrap());
+        store.insert(SnapshotTick::new(5), 5).unwrap();
+        store.insert(SnapshotTick::new(6), 6).unwrap();
+        store.insert(SnapshotTick::new(7), 7).unwrap();
+        store.clear();
+
+        assert!(store.is_empty());
+        assert_eq!(store.get(SnapshotTick::new(7)), None);
+        store.insert(SnapshotTick::new(1), 1).unwrap();
+        assert_eq!(store.get(SnapshotTick::new(1)), Some(&1));
+        assert_eq!(store.oldest(), store.newest());
+    }
+
     #[test]
     fn rejects_out_of_order_ticks() {
         let mut store = BaselineStore::new(NonZeroUsize::new(2).unwrap());
@@ -180,6 +407,56 @@ mod tests {
         assert!(matches!(err, BaselineError::OutOfOrder { .. }));
     }
 
+    #[test]
+    fn ticks_wrap_past_u32_max() {
+        let mut store = BaselineStore::new(NonZeroUsize::new(4).unwrap());
+        let tick = SnapshotTick::new;
+        for raw in [u32::MAX - 1, u32::MAX, 0, 1] {
+            store.insert(tick(raw), raw).unwrap();
+        }
+
+        assert_eq!(store.get(tick(u32::MAX)), Some(&u32::MAX));
+        assert_eq!(store.get(tick(0)), Some(&0));
+        assert_eq!(store.get(tick(2)), None);
+        assert_eq!(store.get(tick(u32::MAX - 2)), None);
+        assert_eq!(store.oldest(), Some((tick(u32::MAX - 1), &(u32::MAX - 1))));
+        assert_eq!(store.newest(), Some((tick(1), &1)));
+        assert_eq!(
+            store
+                .range(tick(u32::MAX)..tick(1))
+                .map(|(t, _)| t.raw())
+                .collect::<Vec<_>>(),
+            [u32::MAX, 0]
+        );
+        assert!(store.insert(tick(u32::MAX), 0).is_err());
+        assert!(store.insert(tick(1), 0).is_err());
+
+        assert_eq!(store.remove_before(tick(0)), 2);
+        assert_eq!(store.oldest().map(|(t, _)| t), Some(tick(0)));
+    }
+
+    #[test]
+    fn gapped_ticks_wrap_past_u32_max() {
+        let mut store = BaselineStore::new(NonZeroUsize::new(4).unwrap());
+        let tick = SnapshotTick::new;
+        for raw in [u32::MAX - 5, u32::MAX, 2, 9] {
+            store.insert(tick(raw), raw).unwrap();
+        }
+
+        assert_eq!(store.get(tick(2)), Some(&2));
+        assert_eq!(store.get(tick(u32::MAX)), Some(&u32::MAX));
+        assert_eq!(store.get(tick(0)), None);
+        assert_eq!(
+            store.latest_at_or_before(tick(1)).map(|(t, _)| t),
+            Some(tick(u32::MAX))
+        );
+        assert_eq!(
+            store.latest_at_or_before(tick(100)).map(|(t, _)| t),
+            Some(tick(9))
+        );
+        assert_eq!(store.latest_at_or_before(tick(u32::MAX - 6)), None);
+    }
+
     #[test]
     fn lookup_after_wraparound() {
         let mut store = BaselineStore::new(NonZeroUsize::new(3).unwrap());
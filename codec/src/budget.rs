@@ -0,0 +1,757 @@
+//! Byte-budgeted delta encoding with prioritized truncation.
+
+use bitstream::{BitError, BitWriter};
+use schema::{schema_hash, EntityIdWidth};
+use wire::{encode_header, SectionTag};
+
+use crate::delta::{
+    build_component_lookup, encode_create_body_from_list, encode_destroy_body_from_list,
+    ensure_entities_sorted, ensure_entity_ids_sorted, validate_updates_for_encoding, varu32_len,
+    write_component_ops_entity, write_create_entity, write_sparse_packed_entity,
+    write_update_sections_from_updates, ComponentLookup, DeltaUpdateEntity, SessionEncoder,
+};
+use crate::error::{CodecError, CodecResult};
+use crate::ids::{IdGapWriter, SparseIdWriter, ID_HEADER_MAX_BYTES};
+use crate::limits::CodecLimits;
+use crate::snapshot::{write_section, EntitySnapshot, VARINT_MAX_BYTES};
+use crate::types::{EntityId, SnapshotTick};
+
+/// Upper bound on the encoded size of one gap-coded id (full-width gap plus
+/// generation) in the destroy section.
+fn destroy_entry_bytes(width: EntityIdWidth) -> usize {
+    (usize::from(width.bits()) + 16).div_ceil(8)
+}
+
+/// Result of a budgeted delta encode.
+///
+/// Deferred ids are sorted by `EntityId` so they can be merged straight back
+/// into the next tick's change lists.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BudgetedDelta {
+    /// Total packet bytes written (header + payload).
+    pub bytes_written: usize,
+    /// Destroys that did not fit into the budget.
+    pub deferred_destroys: Vec<EntityId>,
+    /// Creates that did not fit into the budget.
+    pub deferred_creates: Vec<EntityId>,
+    /// Updates that did not fit into the budget.
+    pub deferred_updates: Vec<EntityId>,
+}
+
+impl BudgetedDelta {
+    /// Returns true if every change was encoded.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.deferred_destroys.is_empty()
+            && self.deferred_creates.is_empty()
+            && self.deferred_updates.is_empty()
+    }
+}
+
+/// Encodes as many changes as fit into `max_bytes`, in priority order.
+///
+/// Entities are taken from `priority` first, then any remaining destroys,
+/// creates and updates in list order. All changes for one entity index are
+/// sent or deferred together, including a destroy and the re-create of the
+/// index with a new generation. An entity that does not fit is deferred and selection
+/// moves on, so smaller lower-priority changes still fill the remaining
+/// budget. Ids in `priority` without a change are ignored.
+///
+/// The budget covers the full packet (header included) and is capped at
+/// `out.len()`. The packet is a regular delta against `baseline_tick`; the
+/// client's reconstructed state for `tick` only reflects the encoded changes,
+/// so deferred changes should be resent against the same baseline.
+#[allow(clippy::too_many_arguments)]
+pub fn encode_delta_from_changes_with_budget(
+    session: &mut SessionEncoder<'_>,
+    tick: SnapshotTick,
+    baseline_tick: SnapshotTick,
+    creates: &[EntitySnapshot],
+    destroys: &[EntityId],
+    updates: &[DeltaUpdateEntity],
+    priority: &[EntityId],
+    max_bytes: usize,
+    out: &mut [u8],
+) -> CodecResult<BudgetedDelta> {
+    let budget = max_bytes.min(out.len());
+    if budget < wire::HEADER_SIZE {
+        return Err(CodecError::OutputTooSmall {
+            needed: wire::HEADER_SIZE,
+            available: budget,
+        });
+    }
+
+    let schema = session.schema();
+    let limits = session.limits();
+    ensure_entity_ids_sorted(destroys)?;
+    ensure_entities_sorted(creates)?;
+    let lookup = build_component_lookup(schema);
+    validate_updates_for_encoding(schema, updates, limits, &lookup)?;
+
+    let change_count = destroys.len() + creates.len() + updates.len();
+    let (measure, selected) = session
+        .scratch_mut()
+        .budget_buffers_mut(budget, change_count);
+    let (destroy_selected, rest) = selected.split_at_mut(destroys.len());
+    let (create_selected, update_selected) = rest.split_at_mut(creates.len());
+
+    let mut planner = BudgetPlanner {
+        schema,
+        limits,
+        lookup: &lookup,
+        destroys,
+        creates,
+        updates,
+        measure,
+        destroy_selected,
+        create_selected,
+        update_selected,
+        destroy_section: SectionTally::with_id_header(),
+        create_section: SectionTally::with_id_header(),
+        ops_section: SectionTally::default(),
+        update_section: SectionTally::default(),
+        budget,
+        out_len: out.len(),
+    };
+    let remaining = destroys
+        .iter()
+        .copied()
+        .chain(creates.iter().map(|entity| entity.id))
+        .chain(updates.iter().map(|entity| entity.id));
+    for id in priority.iter().copied().chain(remaining) {
+        planner.try_take(id)?;
+    }
+    let predicted = planner.packet_len();
+
+    let mut offset = wire::HEADER_SIZE;
+    let sent_destroys = destroys
+        .iter()
+        .zip(destroy_selected.iter())
+        .filter(|(_, selected)| **selected)
+        .map(|(id, _)| id);
+    if sent_destroys.clone().next().is_some() {
+        offset += write_section(
+            SectionTag::EntityDestroy,
+            &mut out[offset..],
+            limits,
+            |writer| {
+                encode_destroy_body_from_list(sent_destroys, schema.entity_id_width, limits, writer)
+            },
+        )?;
+    }
+    let sent_creates = creates
+        .iter()
+        .zip(create_selected.iter())
+        .filter(|(_, selected)| **selected)
+        .map(|(entity, _)| entity);
+    if sent_creates.clone().next().is_some() {
+        offset += write_section(
+            SectionTag::EntityCreate,
+            &mut out[offset..],
+            limits,
+            |writer| encode_create_body_from_list(schema, sent_creates, limits, writer),
+        )?;
+    }
+    let sent_updates = updates
+        .iter()
+        .zip(update_selected.iter())
+        .filter(|(_, selected)| **selected)
+        .map(|(entity, _)| entity);
+    offset += write_update_sections_from_updates(
+        schema,
+        sent_updates,
+        limits,
+        &lookup,
+        &mut out[offset..],
+    )?;
+    // Sizes are planned with worst-case id gaps, so the packet can only shrink.
+    debug_assert!(offset <= predicted);
+
+    let payload_len = offset - wire::HEADER_SIZE;
+    let header = wire::PacketHeader::delta_snapshot(
+        schema_hash(schema),
+        tick.raw(),
+        baseline_tick.raw(),
+        payload_len as u32,
+    );
+    encode_header(&header, &mut out[..wire::HEADER_SIZE]).map_err(|_| {
+        CodecError::OutputTooSmall {
+            needed: wire::HEADER_SIZE,
+            available: out.len(),
+        }
+    })?;
+
+    Ok(BudgetedDelta {
+        bytes_written: offset,
+        deferred_destroys: unselected_ids(destroys.iter().copied(), destroy_selected),
+        deferred_creates: unselected_ids(creates.iter().map(|entity| entity.id), create_selected),
+        deferred_updates: unselected_ids(updates.iter().map(|entity| entity.id), update_selected),
+    })
+}
+
+fn unselected_ids(ids: impl Iterator<Item = EntityId>, selected: &[bool]) -> Vec<EntityId> {
+    ids.zip(selected.iter())
+        .filter(|(_, selected)| !**selected)
+        .map(|(id, _)| id)
+        .collect()
+}
+
+/// Running size of one section: entities, count-prefixed entries and body bytes.
+#[derive(Debug, Clone, Copy, Default)]
+struct SectionTally {
+    entities: usize,
+    entries: usize,
+    entry_bytes: usize,
+    header_bytes: usize,
+}
+
+impl SectionTally {
+    /// A tally for a section that carries a gap-coded id header.
+    fn with_id_header() -> Self {
+        Self {
+            header_bytes: ID_HEADER_MAX_BYTES,
+            ..Self::default()
+        }
+    }
+
+    fn add(&mut self, entries: usize, bytes: usize) {
+        self.entities += 1;
+        self.entries += entries;
+        self.entry_bytes += bytes;
+    }
+
+    fn body_len(&self) -> usize {
+        varu32_len(self.entries as u32) + self.header_bytes + self.entry_bytes
+    }
+
+    fn framed_len(&self) -> usize {
+        let body_len = self.body_len();
+        1 + varu32_len(body_len as u32) + body_len
+    }
+}
+
+struct BudgetPlanner<'s, 'b> {
+    schema: &'s schema::Schema,
+    limits: &'s CodecLimits,
+    lookup: &'b ComponentLookup,
+    destroys: &'s [EntityId],
+    creates: &'s [EntitySnapshot],
+    updates: &'s [DeltaUpdateEntity],
+    measure: &'b mut [u8],
+    destroy_selected: &'b mut [bool],
+    create_selected: &'b mut [bool],
+    update_selected: &'b mut [bool],
+    destroy_section: SectionTally,
+    create_section: SectionTally,
+    ops_section: SectionTally,
+    update_section: SectionTally,
+    budget: usize,
+    out_len: usize,
+}
+
+impl BudgetPlanner<'_, '_> {
+    /// Selects all changes for the index of `id` if they fit; otherwise
+    /// leaves them all deferred.
+    ///
+    /// Changes are grouped by index rather than full id, so destroying a
+    /// generation and creating its successor travel together.
+    fn try_take(&mut self, id: EntityId) -> CodecResult<()> {
+        let index = id.index();
+        let destroy = self
+            .destroys
+            .binary_search_by_key(&index, |id| id.index())
+            .ok();
+        let create = self
+            .creates
+            .binary_search_by_key(&index, |entity| entity.id.index())
+            .ok();
+        let update = self
+            .updates
+            .binary_search_by_key(&index, |entity| entity.id.index())
+            .ok();
+        let already_selected = destroy.is_some_and(|idx| self.destroy_selected[idx])
+            || create.is_some_and(|idx| self.create_selected[idx])
+            || update.is_some_and(|idx| self.update_selected[idx]);
+        if already_selected || (destroy.is_none() && create.is_none() && update.is_none()) {
+            return Ok(());
+        }
+
+        let mut destroy_section = self.destroy_section;
+        let mut create_section = self.create_section;
+        let mut ops_section = self.ops_section;
+        let mut update_section = self.update_section;
+        if destroy.is_some() {
+            destroy_section.add(1, destroy_entry_bytes(self.schema.entity_id_width));
+        }
+        if let Some(idx) = create {
+            let (schema, limits, entity) = (self.schema, self.limits, &self.creates[idx]);
+            let Some(bytes) = measure(self.measure, |writer| {
+                write_create_entity(
+                    schema,
+                    entity,
+                    limits,
+                    &mut IdGapWriter::worst_case(schema.entity_id_width),
+                    writer,
+                )
+            })?
+            else {
+                return Ok(());
+            };
+            create_section.add(1, bytes);
+        }
+        if let Some(idx) = update {
+            let (schema, limits, lookup, entity) =
+                (self.schema, self.limits, self.lookup, &self.updates[idx]);
+            if entity.has_component_ops() {
+                let Some(bytes) = measure(self.measure, |writer| {
+                    write_component_ops_entity(
+                        schema,
+                        entity,
+                        limits,
+                        lookup,
+                        &mut SparseIdWriter::new(schema.entity_id_width),
+                        writer,
+                    )
+                })?
+                else {
+                    return Ok(());
+                };
+                ops_section.add(1, bytes);
+            }
+            if !entity.components.is_empty() {
+                let Some(bytes) = measure(self.measure, |writer| {
+                    write_sparse_packed_entity(
+                        schema,
+                        entity,
+                        lookup,
+                        &mut SparseIdWriter::new(schema.entity_id_width),
+                        writer,
+                    )
+                })?
+                else {
+                    return Ok(());
+                };
+                update_section.add(entity.components.len(), bytes);
+            }
+        }
+        if !self.fits(
+            &destroy_section,
+            &create_section,
+            &ops_section,
+            &update_section,
+        ) {
+            return Ok(());
+        }
+
+        self.destroy_section = destroy_section;
+        self.create_section = create_section;
+        self.ops_section = ops_section;
+        self.update_section = update_section;
+        if let Some(idx) = destroy {
+            self.destroy_selected[idx] = true;
+        }
+        if let Some(idx) = create {
+            self.create_selected[idx] = true;
+        }
+        if let Some(idx) = update {
+            self.update_selected[idx] = true;
+        }
+        Ok(())
+    }
+
+    fn fits(
+        &self,
+        destroy_section: &SectionTally,
+        create_section: &SectionTally,
+        ops_section: &SectionTally,
+        update_section: &SectionTally,
+    ) -> bool {
+        let limits = self.limits;
+        let entry_limit = limits
+            .max_entities_update
+            .saturating_mul(limits.max_components_per_entity);
+        if destroy_section.entities > limits.max_entities_destroy
+            || create_section.entities > limits.max_entities_create
+            || ops_section.entities > limits.max_entities_update
+            || update_section.entities > limits.max_entities_update
+            || update_section.entries > entry_limit
+        {
+            return false;
+        }
+
+        let mut offset = wire::HEADER_SIZE;
+        for section in [destroy_section, create_section, ops_section, update_section] {
+            if section.entities == 0 {
+                continue;
+            }
+            let body_len = section.body_len();
+            if body_len > limits.max_section_bytes {
+                return false;
+            }
+            // `write_section` stages the body behind a worst-case length prefix.
+            if offset + 1 + VARINT_MAX_BYTES + body_len > self.out_len {
+                return false;
+            }
+            offset += section.framed_len();
+        }
+        offset <= self.budget
+    }
+
+    fn packet_len(&self) -> usize {
+        wire::HEADER_SIZE
+            + [
+                &self.destroy_section,
+                &self.create_section,
+                &self.ops_section,
+                &self.update_section,
+            ]
+            .into_iter()
+            .filter(|section| section.entities > 0)
+            .map(SectionTally::framed_len)
+            .sum::<usize>()
+    }
+}
+
+/// Returns the encoded byte length of `write`, or `None` if it overflows `buf`.
+fn measure<F>(buf: &mut [u8], write: F) -> CodecResult<Option<usize>>
+where
+    F: FnOnce(&mut BitWriter<'_>) -> CodecResult<()>,
+{
+    let mut writer = BitWriter::new(buf);
+    match write(&mut writer) {
+        Ok(()) => Ok(Some(writer.finish())),
+        Err(CodecError::Bitstream(BitError::WriteOverflow { .. })) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::delta::{apply_delta_snapshot, DeltaUpdateComponent};
+    use crate::snapshot::{ComponentSnapshot, FieldValue, Snapshot};
+    use schema::{ComponentDef, ComponentId, FieldCodec, FieldDef, FieldId, Schema};
+
+    fn schema_uint() -> Schema {
+        let component = ComponentDef::new(ComponentId::new(1).unwrap()).field(FieldDef::new(
+            FieldId::new(1).unwrap(),
+            FieldCodec::uint(16),
+        ));
+        Schema::new(vec![component]).unwrap()
+    }
+
+    fn entity(id: u32, value: u64) -> EntitySnapshot {
+        EntitySnapshot {
+            id: EntityId::from(id),
+            components: vec![ComponentSnapshot {
+                id: ComponentId::new(1).unwrap(),
+                fields: vec![FieldValue::UInt(value)],
+            }],
+        }
+    }
+
+    fn update(id: u32, value: u64) -> DeltaUpdateEntity {
+        DeltaUpdateEntity::new(
+            EntityId::from(id),
+            vec![DeltaUpdateComponent {
+                id: ComponentId::new(1).unwrap(),
+                fields: vec![(0, FieldValue::UInt(value))],
+            }],
+        )
+    }
+
+    fn baseline() -> Snapshot {
+        Snapshot {
+            tick: SnapshotTick::new(10),
+            entities: (1..=4).map(|id| entity(id, 0)).collect(),
+        }
+    }
+
+    fn encode(
+        schema: &Schema,
+        creates: &[EntitySnapshot],
+        destroys: &[EntityId],
+        updates: &[DeltaUpdateEntity],
+        priority: &[EntityId],
+        max_bytes: usize,
+        out: &mut [u8],
+    ) -> CodecResult<BudgetedDelta> {
+        let limits = CodecLimits::for_testing();
+        let mut session = SessionEncoder::new(schema, &limits);
+        encode_delta_from_changes_with_budget(
+            &mut session,
+            SnapshotTick::new(11),
+            SnapshotTick::new(10),
+            creates,
+            destroys,
+            updates,
+            priority,
+            max_bytes,
+            out,
+        )
+    }
+
+    #[test]
+    fn unlimited_budget_matches_unbudgeted_encoding() {
+        let schema = schema_uint();
+        let limits = CodecLimits::for_testing();
+        let creates = [entity(5, 7)];
+        let destroys = [EntityId::new(1)];
+        let updates = [update(2, 9), update(3, 11)];
+
+        let mut expected = [0u8; 256];
+        let mut session = SessionEncoder::new(&schema, &limits);
+        let expected_len = crate::delta::encode_delta_from_changes(
+            &mut session,
+            SnapshotTick::new(11),
+            SnapshotTick::new(10),
+            &creates,
+            &destroys,
+            &updates,
+            &mut expected,
+        )
+        .unwrap();
+
+        let mut buf = [0u8; 256];
+        let result = encode(&schema, &creates, &destroys, &updates, &[], 256, &mut buf).unwrap();
+        assert!(result.is_complete());
+        assert_eq!(&buf[..result.bytes_written], &expected[..expected_len]);
+    }
+
+    #[test]
+    fn budget_defers_lowest_priority_entities() {
+        let schema = schema_uint();
+        let updates = [update(1, 1), update(2, 2), update(3, 3), update(4, 4)];
+        let priority = [EntityId::new(4), EntityId::new(2)];
+
+        let mut full = [0u8; 256];
+        let full_len = encode(&schema, &[], &[], &updates, &[], 256, &mut full)
+            .unwrap()
+            .bytes_written;
+        let mut buf = [0u8; 256];
+        let result = encode(
+            &schema,
+            &[],
+            &[],
+            &updates,
+            &priority,
+            full_len - 1,
+            &mut buf,
+        )
+        .unwrap();
+        assert!(result.bytes_written < full_len);
+        assert_eq!(result.deferred_updates, vec![EntityId::new(3)]);
+
+        let applied = apply_delta_snapshot(
+            &schema,
+            &baseline(),
+            &buf[..result.bytes_written],
+            &wire::Limits::for_testing(),
+            &CodecLimits::for_testing(),
+        )
+        .unwrap();
+        let values: Vec<_> = applied
+            .entities
+            .iter()
+            .map(|entity| entity.components[0].fields[0])
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                FieldValue::UInt(1),
+                FieldValue::UInt(2),
+                FieldValue::UInt(0),
+                FieldValue::UInt(4),
+            ]
+        );
+    }
+
+    #[test]
+    fn budget_skips_entities_that_do_not_fit() {
+        let small = ComponentDef::new(ComponentId::new(1).unwrap()).field(FieldDef::new(
+            FieldId::new(1).unwrap(),
+            FieldCodec::uint(16),
+        ));
+        let large = (1..=16).fold(
+            ComponentDef::new(ComponentId::new(2).unwrap()),
+            |component, id| {
+                component.field(FieldDef::new(
+                    FieldId::new(id).unwrap(),
+                    FieldCodec::uint(16),
+                ))
+            },
+        );
+        let schema = Schema::new(vec![small, large]).unwrap();
+        let mut big = entity(9, 1);
+        big.components.push(ComponentSnapshot {
+            id: ComponentId::new(2).unwrap(),
+            fields: vec![FieldValue::UInt(0xFFFF); 16],
+        });
+        let creates = [big];
+        let updates = [update(1, 1), update(2, 2), update(3, 3)];
+
+        let mut small_only = [0u8; 256];
+        let small_len = encode(&schema, &[], &[], &updates, &[], 256, &mut small_only)
+            .unwrap()
+            .bytes_written;
+        let mut buf = [0u8; 256];
+        let result = encode(
+            &schema,
+            &creates,
+            &[],
+            &updates,
+            &[EntityId::new(9)],
+            small_len,
+            &mut buf,
+        )
+        .unwrap();
+
+        // The large create ranks first but does not fit; the updates behind it
+        // still fill the budget.
+        assert_eq!(result.deferred_creates, vec![EntityId::new(9)]);
+        assert!(result.deferred_updates.is_empty());
+        assert_eq!(&buf[..result.bytes_written], &small_only[..small_len]);
+    }
+
+    #[test]
+    fn changes_for_one_entity_stay_together() {
+        let schema = schema_uint();
+        let destroys = [EntityId::new(2)];
+        let creates = [entity(2, 5)];
+        let updates = [update(1, 3)];
+
+        let mut buf = [0u8; 256];
+        let result = encode(
+            &schema,
+            &creates,
+            &destroys,
+            &updates,
+            &[EntityId::new(2)],
+            256,
+            &mut buf,
+        )
+        .unwrap();
+        assert!(result.is_complete());
+
+        let mut tight = [0u8; 256];
+        let partial = encode(
+            &schema,
+            &creates,
+            &destroys,
+            &updates,
+            &[EntityId::new(1)],
+            result.bytes_written - 1,
+            &mut tight,
+        )
+        .unwrap();
+        assert_eq!(partial.deferred_destroys, vec![EntityId::new(2)]);
+        assert_eq!(partial.deferred_creates, vec![EntityId::new(2)]);
+        assert!(partial.deferred_updates.is_empty());
+        apply_delta_snapshot(
+            &schema,
+            &baseline(),
+            &tight[..partial.bytes_written],
+            &wire::Limits::for_testing(),
+            &CodecLimits::for_testing(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn reused_index_destroy_and_create_stay_together() {
+        let schema = schema_uint();
+        let destroys = [EntityId::new(2)];
+        let mut reused = entity(2, 5);
+        reused.id = EntityId::with_generation(2, 1);
+        let creates = [reused];
+        let updates = [update(1, 3), update(3, 4), update(4, 5)];
+
+        // The smallest budget that fits the destroy and re-create alone.
+        let mut buf = [0u8; 256];
+        let budget = (wire::HEADER_SIZE..=256)
+            .find(|budget| {
+                encode(&schema, &creates, &destroys, &[], &[], *budget, &mut buf)
+                    .unwrap()
+                    .is_complete()
+            })
+            .unwrap();
+
+        // Prioritizing either generation takes both changes.
+        for priority in [EntityId::new(2), EntityId::with_generation(2, 1)] {
+            let mut tight = [0u8; 256];
+            let partial = encode(
+                &schema,
+                &creates,
+                &destroys,
+                &updates,
+                &[priority],
+                budget,
+                &mut tight,
+            )
+            .unwrap();
+            assert!(partial.deferred_destroys.is_empty());
+            assert!(partial.deferred_creates.is_empty());
+            assert_eq!(partial.deferred_updates.len(), 3);
+            let applied = apply_delta_snapshot(
+                &schema,
+                &baseline(),
+                &tight[..partial.bytes_written],
+                &wire::Limits::for_testing(),
+                &CodecLimits::for_testing(),
+            )
+            .unwrap();
+            assert_eq!(applied.entities[1], creates[0]);
+        }
+
+        // With an update taken first, the pair is deferred whole.
+        let mut tight = [0u8; 256];
+        let partial = encode(
+            &schema,
+            &creates,
+            &destroys,
+            &updates,
+            &[EntityId::new(1)],
+            budget,
+            &mut tight,
+        )
+        .unwrap();
+        assert_eq!(partial.deferred_destroys, vec![EntityId::new(2)]);
+        assert_eq!(
+            partial.deferred_creates,
+            vec![EntityId::with_generation(2, 1)]
+        );
+    }
+
+    #[test]
+    fn budget_respects_entity_limits() {
+        let schema = schema_uint();
+        let limits = CodecLimits {
+            max_entities_update: 2,
+            ..CodecLimits::for_testing()
+        };
+        let updates = [update(1, 1), update(2, 2), update(3, 3)];
+        let mut session = SessionEncoder::new(&schema, &limits);
+        let mut buf = [0u8; 256];
+        let result = encode_delta_from_changes_with_budget(
+            &mut session,
+            SnapshotTick::new(11),
+            SnapshotTick::new(10),
+            &[],
+            &[],
+            &updates,
+            &[],
+            256,
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(result.deferred_updates, vec![EntityId::new(3)]);
+    }
+
+    #[test]
+    fn budget_smaller_than_header_is_rejected() {
+        let schema = schema_uint();
+        let mut buf = [0u8; 256];
+        let err = encode(&schema, &[], &[], &[], &[], wire::HEADER_SIZE - 1, &mut buf).unwrap_err();
+        assert!(matches!(err, CodecError::OutputTooSmall { .. }));
+    }
+}
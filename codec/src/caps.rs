@@ -0,0 +1,331 @@
+//! Capability negotiation between session peers.
+//!
+//! The server lists the optional features it can send in the `CAPABILITIES`
+//! extension of its session init. The client intersects that list with what
+//! it can decode and answers with a session accept packet carrying the
+//! result, so both sides hold the same [`NegotiatedCaps`]. Encoders consult
+//! it before using a feature that an older peer could not decode. A peer that
+//! sends no list (one that predates negotiation) gets
+//! [`NegotiatedCaps::baseline`].
+//!
+//! The list is encoded as `id (u8) | len (u8) | value` entries. Unknown ids
+//! are skipped so later versions can add capabilities.
+
+use bitstream::{BitReader, BitWriter};
+use schema::EntityIdWidth;
+
+use crate::error::{CodecError, CodecResult};
+
+/// Capability identifiers used in the TLV list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum Capability {
+    /// Value: compressor id bitmask (bit `id - 1`).
+    Compression = 1,
+    /// Value: update encoding bitmask (`NegotiatedCaps::UPDATE_*`).
+    UpdateEncodings = 2,
+    /// Value: entity id width bitmask (`NegotiatedCaps::ID_WIDTH_*`).
+    EntityIdWidths = 3,
+    /// Value: empty; presence means `EVENTS` sections are understood.
+    Events = 4,
+}
+
+impl Capability {
+    fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            1 => Some(Self::Compression),
+            2 => Some(Self::UpdateEncodings),
+            3 => Some(Self::EntityIdWidths),
+            4 => Some(Self::Events),
+            _ => None,
+        }
+    }
+}
+
+/// Optional features agreed for a session.
+///
+/// The same type describes what one peer offers; [`NegotiatedCaps::intersect`]
+/// combines two offers into what both support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NegotiatedCaps {
+    /// Compressor ids usable for `COMPRESSED` payloads (bit `id - 1`).
+    pub compressors: u8,
+    /// Update section encodings (`UPDATE_*` bits).
+    pub update_encodings: u8,
+    /// Entity id widths (`ID_WIDTH_*` bits).
+    pub entity_id_widths: u8,
+    /// Whether `EVENTS` sections may be sent.
+    pub events: bool,
+}
+
+impl NegotiatedCaps {
+    /// `ENTITY_UPDATE` sections.
+    pub const UPDATE_MASKED: u8 = 1 << 0;
+    /// `ENTITY_UPDATE_SPARSE` sections.
+    pub const UPDATE_SPARSE: u8 = 1 << 1;
+    /// `ENTITY_UPDATE_SPARSE_PACKED` sections.
+    pub const UPDATE_SPARSE_PACKED: u8 = 1 << 2;
+    /// Range-coded forms of the masked and sparse packed update sections.
+    pub const UPDATE_CODED: u8 = 1 << 3;
+
+    /// 16-bit entity ids.
+    pub const ID_WIDTH_U16: u8 = 1 << 0;
+    /// 32-bit entity ids.
+    pub const ID_WIDTH_U32: u8 = 1 << 1;
+    /// 64-bit entity ids.
+    pub const ID_WIDTH_U64: u8 = 1 << 2;
+
+    /// Features every version 2 peer supports, assumed when no list is sent.
+    ///
+    /// The entity id width is a schema property older peers already agree on
+    /// through the schema hash, so every width is included.
+    #[must_use]
+    pub const fn baseline() -> Self {
+        Self {
+            compressors: 0,
+            update_encodings: Self::UPDATE_MASKED
+                | Self::UPDATE_SPARSE
+                | Self::UPDATE_SPARSE_PACKED,
+            entity_id_widths: Self::ID_WIDTH_U16 | Self::ID_WIDTH_U32 | Self::ID_WIDTH_U64,
+            events: false,
+        }
+    }
+
+    /// Every feature this build implements.
+    #[must_use]
+    pub const fn all() -> Self {
+        Self {
+            compressors: compressor_bit(wire::Lz4Compressor::ID),
+            update_encodings: Self::UPDATE_MASKED
+                | Self::UPDATE_SPARSE
+                | Self::UPDATE_SPARSE_PACKED
+                | Self::UPDATE_CODED,
+            entity_id_widths: Self::ID_WIDTH_U16 | Self::ID_WIDTH_U32 | Self::ID_WIDTH_U64,
+            events: true,
+        }
+    }
+
+    /// Returns the features supported by both `self` and `other`.
+    #[must_use]
+    pub const fn intersect(self, other: Self) -> Self {
+        Self {
+            compressors: self.compressors & other.compressors,
+            update_encodings: self.update_encodings & other.update_encodings,
+            entity_id_widths: self.entity_id_widths & other.entity_id_widths,
+            events: self.events && other.events,
+        }
+    }
+
+    /// Returns `true` if payloads may be compressed with compressor `id`.
+    #[must_use]
+    pub const fn supports_compressor(self, id: u8) -> bool {
+        self.compressors & compressor_bit(id) != 0
+    }
+
+    /// Returns `true` if update sections may be range-coded.
+    #[must_use]
+    pub const fn supports_coded_updates(self) -> bool {
+        self.update_encodings & Self::UPDATE_CODED != 0
+    }
+
+    /// Returns `true` if entities may use ids of `width`.
+    #[must_use]
+    pub const fn supports_id_width(self, width: EntityIdWidth) -> bool {
+        let bit = match width {
+            EntityIdWidth::U16 => Self::ID_WIDTH_U16,
+            EntityIdWidth::U32 => Self::ID_WIDTH_U32,
+            EntityIdWidth::U64 => Self::ID_WIDTH_U64,
+        };
+        self.entity_id_widths & bit != 0
+    }
+
+    /// Checks that the schema's entity id width was negotiated.
+    pub fn ensure_schema(self, schema: &schema::Schema) -> CodecResult<()> {
+        if self.supports_id_width(schema.entity_id_width) {
+            return Ok(());
+        }
+        Err(CodecError::CapabilityUnsupported {
+            capability: Capability::EntityIdWidths,
+        })
+    }
+
+    /// Writes the TLV list. Every capability is written, so a peer can tell
+    /// "not supported" apart from "predates negotiation".
+    pub(crate) fn encode(self, writer: &mut BitWriter<'_>) -> CodecResult<()> {
+        write_capability(writer, Capability::Compression, &[self.compressors])?;
+        write_capability(
+            writer,
+            Capability::UpdateEncodings,
+            &[self.update_encodings],
+        )?;
+        write_capability(writer, Capability::EntityIdWidths, &[self.entity_id_widths])?;
+        if self.events {
+            write_capability(writer, Capability::Events, &[])?;
+        }
+        Ok(())
+    }
+
+    /// Returns the byte length [`encode`](Self::encode) writes.
+    pub(crate) const fn encoded_len(self) -> usize {
+        // Three one-byte bitmasks, plus the empty events flag.
+        3 * 3 + if self.events { 2 } else { 0 }
+    }
+
+    /// Reads a TLV list until the end of `reader`.
+    pub(crate) fn decode(reader: &mut BitReader<'_>) -> CodecResult<Self> {
+        let len = reader.bits_remaining() / 8;
+        Self::decode_len(reader, len)
+    }
+
+    /// Reads a TLV list of exactly `len` bytes from `reader`.
+    ///
+    /// Bitmask capabilities that are absent decode as empty. Unknown ids are
+    /// skipped; a known id with the wrong size or repeated is invalid, as is
+    /// an entry that runs past `len`.
+    pub(crate) fn decode_len(reader: &mut BitReader<'_>, len: usize) -> CodecResult<Self> {
+        let mut caps = Self {
+            compressors: 0,
+            update_encodings: 0,
+            entity_id_widths: 0,
+            events: false,
+        };
+        let mut seen = 0u8;
+        let mut remaining = len;
+        while remaining != 0 {
+            let raw = reader.read_u8_aligned()?;
+            let len = reader.read_u8_aligned()?;
+            remaining = remaining
+                .checked_sub(2 + usize::from(len))
+                .ok_or(CodecError::SessionInitInvalid)?;
+            let Some(capability) = Capability::from_raw(raw) else {
+                for _ in 0..len {
+                    reader.read_u8_aligned()?;
+                }
+                continue;
+            };
+            let bit = 1 << (capability as u8);
+            let expected_len = if capability == Capability::Events {
+                0
+            } else {
+                1
+            };
+            if seen & bit != 0 || len != expected_len {
+                return Err(CodecError::SessionInitInvalid);
+            }
+            seen |= bit;
+            match capability {
+                Capability::Compression => caps.compressors = reader.read_u8_aligned()?,
+                Capability::UpdateEncodings => caps.update_encodings = reader.read_u8_aligned()?,
+                Capability::EntityIdWidths => caps.entity_id_widths = reader.read_u8_aligned()?,
+                Capability::Events => caps.events = true,
+            }
+        }
+        Ok(caps)
+    }
+}
+
+impl Default for NegotiatedCaps {
+    fn default() -> Self {
+        Self::baseline()
+    }
+}
+
+const fn compressor_bit(id: u8) -> u8 {
+    match id {
+        1..=8 => 1 << (id - 1),
+        _ => 0,
+    }
+}
+
+fn write_capability(
+    writer: &mut BitWriter<'_>,
+    capability: Capability,
+    value: &[u8],
+) -> CodecResult<()> {
+    writer.write_u8_aligned(capability as u8)?;
+    writer.write_u8_aligned(value.len() as u8)?;
+    for &byte in value {
+        writer.write_u8_aligned(byte)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(caps: NegotiatedCaps) -> NegotiatedCaps {
+        let mut buf = [0u8; 32];
+        let mut writer = BitWriter::new(&mut buf);
+        caps.encode(&mut writer).unwrap();
+        let len = writer.finish();
+        NegotiatedCaps::decode(&mut BitReader::new(&buf[..len])).unwrap()
+    }
+
+    #[test]
+    fn caps_roundtrip() {
+        for caps in [NegotiatedCaps::baseline(), NegotiatedCaps::all()] {
+            assert_eq!(roundtrip(caps), caps);
+        }
+    }
+
+    #[test]
+    fn encoded_len_matches_encode() {
+        for caps in [NegotiatedCaps::baseline(), NegotiatedCaps::all()] {
+            let mut buf = [0u8; 32];
+            let mut writer = BitWriter::new(&mut buf);
+            caps.encode(&mut writer).unwrap();
+            assert_eq!(writer.finish(), caps.encoded_len());
+        }
+    }
+
+    #[test]
+    fn caps_intersect() {
+        let all = NegotiatedCaps::all();
+        let baseline = NegotiatedCaps::baseline();
+        assert_eq!(all.intersect(baseline), baseline);
+        assert!(all.supports_compressor(wire::Lz4Compressor::ID));
+        assert!(!baseline.supports_compressor(wire::Lz4Compressor::ID));
+        assert!(!all.supports_compressor(0));
+        assert!(all.supports_coded_updates());
+        assert!(!baseline.supports_coded_updates());
+        assert!(baseline.supports_id_width(EntityIdWidth::U64));
+        let narrow = NegotiatedCaps {
+            entity_id_widths: NegotiatedCaps::ID_WIDTH_U32,
+            ..all
+        };
+        assert!(!narrow.supports_id_width(EntityIdWidth::U64));
+        assert!(matches!(
+            narrow.ensure_schema(
+                &schema::Schema::new(Vec::new())
+                    .unwrap()
+                    .with_entity_id_width(EntityIdWidth::U64)
+            ),
+            Err(CodecError::CapabilityUnsupported {
+                capability: Capability::EntityIdWidths
+            })
+        ));
+    }
+
+    #[test]
+    fn caps_decode_skips_unknown_and_rejects_malformed() {
+        let list = [0x7F, 2, 9, 9, 2, 1, NegotiatedCaps::UPDATE_CODED, 4, 0];
+        let caps = NegotiatedCaps::decode(&mut BitReader::new(&list)).unwrap();
+        assert_eq!(caps.update_encodings, NegotiatedCaps::UPDATE_CODED);
+        assert_eq!(caps.compressors, 0);
+        assert!(caps.events);
+
+        for list in [&[2, 1, 0, 2, 1, 0][..], &[1, 2, 0, 0], &[4, 1, 0], &[3, 1]] {
+            assert!(NegotiatedCaps::decode(&mut BitReader::new(list)).is_err());
+        }
+
+        // A list must end exactly at its declared length.
+        let list = [2, 1, 0, 4, 0];
+        let mut reader = BitReader::new(&list);
+        assert!(NegotiatedCaps::decode_len(&mut reader, 2).is_err());
+        let mut reader = BitReader::new(&list);
+        assert!(!NegotiatedCaps::decode_len(&mut reader, 3).unwrap().events);
+        assert_eq!(reader.bits_remaining(), 16);
+    }
+}
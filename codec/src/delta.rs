@@ -1,23 +1,35 @@
 //! Delta snapshot encoding/decoding.
 
+use std::borrow::Borrow;
 use std::cmp::Ordering;
 
 use bitstream::{BitReader, BitWriter};
-use schema::{schema_hash, ChangePolicy, ComponentDef, ComponentId, FieldDef};
+use schema::{schema_hash, ChangePolicy, ComponentDef, ComponentId, EntityIdWidth, FieldDef};
 use wire::{decode_packet, encode_header, SectionTag, WirePacket};
 
 use crate::baseline::BaselineStore;
+use crate::caps::NegotiatedCaps;
+use crate::entropy::{decode_coded_body, entropy_code_payload};
 use crate::error::{CodecError, CodecResult, LimitKind, MaskKind, MaskReason, ValueReason};
+use crate::ids::{IdGapReader, IdGapWriter, IdRun, SparseIdReader, SparseIdWriter};
 use crate::limits::CodecLimits;
+use crate::predict::{
+    encode_update_value, read_update_value, resolve_residual, write_update_value, UpdateValue,
+};
 use crate::scratch::CodecScratch;
+use crate::session::CompactHeaderMode;
 use crate::snapshot::{
-    ensure_known_components, read_field_value, read_field_value_sparse, read_mask, required_bits,
-    write_field_value, write_field_value_sparse, write_section, ComponentSnapshot, EntitySnapshot,
-    FieldValue, Snapshot,
+    codec_name, ensure_known_components, find_component_def, read_field_value,
+    read_field_value_sparse, read_mask, required_bits, value_name, write_field_value,
+    write_field_value_sparse, write_section, ComponentSnapshot, EntitySnapshot, FieldValue,
+    Snapshot, SnapshotView,
 };
 use crate::types::{EntityId, SnapshotTick};
 
 /// Selects the latest baseline tick at or before the ack tick.
+///
+/// For ack bookkeeping across out-of-order acks, nacks and eviction, see
+/// [`crate::ClientAckTracker`].
 #[must_use]
 pub fn select_baseline_tick<T>(
     store: &BaselineStore<T>,
@@ -31,12 +43,12 @@ pub fn select_baseline_tick<T>(
 /// This is the scan-based path and is kept as a convenience/fallback. For
 /// production engines with dirty/change lists, prefer `encode_delta_from_changes`.
 /// Baseline and current snapshots must have entities sorted by `EntityId`.
-pub fn encode_delta_snapshot(
+pub fn encode_delta_snapshot<B: SnapshotView, C: SnapshotView>(
     schema: &schema::Schema,
     tick: SnapshotTick,
     baseline_tick: SnapshotTick,
-    baseline: &Snapshot,
-    current: &Snapshot,
+    baseline: &B,
+    current: &C,
     limits: &CodecLimits,
     out: &mut [u8],
 ) -> CodecResult<usize> {
@@ -55,12 +67,12 @@ pub fn encode_delta_snapshot(
 
 /// Encodes a delta snapshot using reusable scratch buffers.
 #[allow(clippy::too_many_arguments)]
-pub fn encode_delta_snapshot_with_scratch(
+pub fn encode_delta_snapshot_with_scratch<B: SnapshotView, C: SnapshotView>(
     schema: &schema::Schema,
     tick: SnapshotTick,
     baseline_tick: SnapshotTick,
-    baseline: &Snapshot,
-    current: &Snapshot,
+    baseline: &B,
+    current: &C,
     limits: &CodecLimits,
     scratch: &mut CodecScratch,
     out: &mut [u8],
@@ -82,12 +94,12 @@ pub fn encode_delta_snapshot_with_scratch(
 ///
 /// This assumes `baseline` and `current` are already filtered for the client interest set.
 /// Updates are encoded in sparse mode to optimize for small per-client packets.
-pub fn encode_delta_snapshot_for_client(
+pub fn encode_delta_snapshot_for_client<B: SnapshotView, C: SnapshotView>(
     schema: &schema::Schema,
     tick: SnapshotTick,
     baseline_tick: SnapshotTick,
-    baseline: &Snapshot,
-    current: &Snapshot,
+    baseline: &B,
+    current: &C,
     limits: &CodecLimits,
     out: &mut [u8],
 ) -> CodecResult<usize> {
@@ -111,7 +123,6 @@ pub fn encode_delta_snapshot_for_client(
 pub struct SessionEncoder<'a> {
     schema: &'a schema::Schema,
     limits: &'a CodecLimits,
-    #[allow(dead_code)]
     scratch: CodecScratch,
 }
 
@@ -134,6 +145,10 @@ impl<'a> SessionEncoder<'a> {
     pub fn limits(&self) -> &'a CodecLimits {
         self.limits
     }
+
+    pub(crate) fn scratch_mut(&mut self) -> &mut CodecScratch {
+        &mut self.scratch
+    }
 }
 
 /// Encodes a delta snapshot from precomputed change lists.
@@ -207,12 +222,12 @@ pub fn encode_delta_snapshot_from_updates(
 
 /// Encodes a client delta snapshot using a compact session header.
 #[allow(clippy::too_many_arguments)]
-pub fn encode_delta_snapshot_for_client_session(
+pub fn encode_delta_snapshot_for_client_session<B: SnapshotView, C: SnapshotView>(
     schema: &schema::Schema,
     tick: SnapshotTick,
     baseline_tick: SnapshotTick,
-    baseline: &Snapshot,
-    current: &Snapshot,
+    baseline: &B,
+    current: &C,
     limits: &CodecLimits,
     last_tick: &mut SnapshotTick,
     out: &mut [u8],
@@ -233,12 +248,12 @@ pub fn encode_delta_snapshot_for_client_session(
 
 /// Encodes a delta snapshot for a client-specific view using reusable scratch buffers.
 #[allow(clippy::too_many_arguments)]
-pub fn encode_delta_snapshot_for_client_with_scratch(
+pub fn encode_delta_snapshot_for_client_with_scratch<B: SnapshotView, C: SnapshotView>(
     schema: &schema::Schema,
     tick: SnapshotTick,
     baseline_tick: SnapshotTick,
-    baseline: &Snapshot,
-    current: &Snapshot,
+    baseline: &B,
+    current: &C,
     limits: &CodecLimits,
     scratch: &mut CodecScratch,
     out: &mut [u8],
@@ -264,12 +279,79 @@ enum EncodeUpdateMode {
 
 /// Encodes a client delta snapshot using a compact session header.
 #[allow(clippy::too_many_arguments)]
-pub fn encode_delta_snapshot_for_client_session_with_scratch(
+pub fn encode_delta_snapshot_for_client_session_with_scratch<B: SnapshotView, C: SnapshotView>(
     schema: &schema::Schema,
     tick: SnapshotTick,
     baseline_tick: SnapshotTick,
-    baseline: &Snapshot,
-    current: &Snapshot,
+    baseline: &B,
+    current: &C,
+    limits: &CodecLimits,
+    scratch: &mut CodecScratch,
+    last_tick: &mut SnapshotTick,
+    out: &mut [u8],
+) -> CodecResult<usize> {
+    encode_delta_snapshot_for_client_session_with_mode(
+        schema,
+        CompactHeaderMode::SessionV1,
+        tick,
+        baseline_tick,
+        baseline,
+        current,
+        limits,
+        scratch,
+        last_tick,
+        out,
+    )
+}
+
+/// Encodes a client delta snapshot using the compact session header version
+/// negotiated in session init.
+///
+/// `last_tick` is the tick of the previous compact packet; `tick` must be
+/// newer. With [`CompactHeaderMode::SessionV2`] the receiver does not need
+/// that previous packet to reconstruct `tick`.
+#[allow(clippy::too_many_arguments)]
+pub fn encode_delta_snapshot_for_client_session_with_mode<B: SnapshotView, C: SnapshotView>(
+    schema: &schema::Schema,
+    mode: CompactHeaderMode,
+    tick: SnapshotTick,
+    baseline_tick: SnapshotTick,
+    baseline: &B,
+    current: &C,
+    limits: &CodecLimits,
+    scratch: &mut CodecScratch,
+    last_tick: &mut SnapshotTick,
+    out: &mut [u8],
+) -> CodecResult<usize> {
+    encode_delta_snapshot_for_client_session_with_caps(
+        schema,
+        mode,
+        NegotiatedCaps::baseline(),
+        tick,
+        baseline_tick,
+        baseline,
+        current,
+        limits,
+        scratch,
+        last_tick,
+        out,
+    )
+}
+
+/// Encodes a client delta snapshot with a compact session header, using the
+/// optional encodings allowed by `caps`.
+///
+/// With [`NegotiatedCaps::supports_coded_updates`] the update section is
+/// range-coded when that makes it smaller.
+#[allow(clippy::too_many_arguments)]
+pub fn encode_delta_snapshot_for_client_session_with_caps<B: SnapshotView, C: SnapshotView>(
+    schema: &schema::Schema,
+    mode: CompactHeaderMode,
+    caps: NegotiatedCaps,
+    tick: SnapshotTick,
+    baseline_tick: SnapshotTick,
+    baseline: &B,
+    current: &C,
     limits: &CodecLimits,
     scratch: &mut CodecScratch,
     last_tick: &mut SnapshotTick,
@@ -282,20 +364,22 @@ pub fn encode_delta_snapshot_for_client_session_with_scratch(
             available: out.len(),
         });
     }
-    if tick.raw() <= last_tick.raw() {
+    if !tick.is_after(*last_tick) {
         return Err(CodecError::InvalidEntityOrder {
-            previous: last_tick.raw(),
-            current: tick.raw(),
+            previous: u64::from(last_tick.raw()),
+            current: u64::from(tick.raw()),
         });
     }
-    if baseline_tick.raw() > tick.raw() {
+    // Also rejects a baseline after `tick`, whose wrapped distance is >= 2^31.
+    if tick.since(baseline_tick) >= wire::SESSION_MAX_TICK_DELTA {
         return Err(CodecError::BaselineTickMismatch {
             expected: baseline_tick.raw(),
             found: tick.raw(),
         });
     }
-    let payload_len = encode_delta_payload_with_mode(
+    let mut payload_len = encode_delta_payload_with_mode(
         schema,
+        tick,
         baseline_tick,
         baseline,
         current,
@@ -304,15 +388,26 @@ pub fn encode_delta_snapshot_for_client_session_with_scratch(
         &mut out[max_header..],
         EncodeUpdateMode::Sparse,
     )?;
-    let tick_delta = tick.raw() - last_tick.raw();
-    let baseline_delta = tick.raw() - baseline_tick.raw();
-    let header_len = wire::encode_session_header(
-        &mut out[..max_header],
-        wire::SessionFlags::delta_snapshot(),
-        tick_delta,
-        baseline_delta,
-        payload_len as u32,
-    )
+    if caps.supports_coded_updates() {
+        payload_len = entropy_code_payload(&mut out[max_header..], payload_len)?;
+    }
+    let baseline_delta = tick.since(baseline_tick);
+    let header_len = match mode {
+        CompactHeaderMode::SessionV1 => wire::encode_session_header(
+            &mut out[..max_header],
+            wire::SessionFlags::delta_snapshot(),
+            tick.since(*last_tick),
+            baseline_delta,
+            payload_len as u32,
+        ),
+        CompactHeaderMode::SessionV2 => wire::encode_session_header_v2(
+            &mut out[..max_header],
+            wire::SessionFlags::delta_snapshot(),
+            tick.raw(),
+            baseline_delta,
+            payload_len as u32,
+        ),
+    }
     .map_err(|_| CodecError::OutputTooSmall {
         needed: max_header,
         available: out.len(),
@@ -327,12 +422,12 @@ pub fn encode_delta_snapshot_for_client_session_with_scratch(
 }
 
 #[allow(clippy::too_many_arguments)]
-fn encode_delta_snapshot_with_scratch_mode(
+fn encode_delta_snapshot_with_scratch_mode<B: SnapshotView, C: SnapshotView>(
     schema: &schema::Schema,
     tick: SnapshotTick,
     baseline_tick: SnapshotTick,
-    baseline: &Snapshot,
-    current: &Snapshot,
+    baseline: &B,
+    current: &C,
     limits: &CodecLimits,
     scratch: &mut CodecScratch,
     out: &mut [u8],
@@ -346,6 +441,7 @@ fn encode_delta_snapshot_with_scratch_mode(
     }
     let payload_len = encode_delta_payload_with_mode(
         schema,
+        tick,
         baseline_tick,
         baseline,
         current,
@@ -371,25 +467,28 @@ fn encode_delta_snapshot_with_scratch_mode(
 }
 
 #[allow(clippy::too_many_arguments)]
-fn encode_delta_payload_with_mode(
+fn encode_delta_payload_with_mode<B: SnapshotView, C: SnapshotView>(
     schema: &schema::Schema,
+    tick: SnapshotTick,
     baseline_tick: SnapshotTick,
-    baseline: &Snapshot,
-    current: &Snapshot,
+    baseline: &B,
+    current: &C,
     limits: &CodecLimits,
     scratch: &mut CodecScratch,
     out: &mut [u8],
     mode: EncodeUpdateMode,
 ) -> CodecResult<usize> {
-    if baseline.tick != baseline_tick {
+    if baseline.tick() != baseline_tick {
         return Err(CodecError::BaselineTickMismatch {
-            expected: baseline.tick.raw(),
+            expected: baseline.tick().raw(),
             found: baseline_tick.raw(),
         });
     }
 
-    ensure_entities_sorted(&baseline.entities)?;
-    ensure_entities_sorted(&current.entities)?;
+    let baseline = baseline.entities();
+    let current = current.entities();
+    ensure_entities_sorted(baseline)?;
+    ensure_entities_sorted(current)?;
 
     let mut counts = DiffCounts::default();
     diff_counts(schema, baseline, current, limits, &mut counts)?;
@@ -415,6 +514,13 @@ fn encode_delta_payload_with_mode(
             actual: counts.destroys,
         });
     }
+    if counts.component_ops > limits.max_entities_update {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::EntitiesUpdate,
+            limit: limits.max_entities_update,
+            actual: counts.component_ops,
+        });
+    }
 
     let mut offset = 0;
     if counts.destroys > 0 {
@@ -422,7 +528,17 @@ fn encode_delta_payload_with_mode(
             SectionTag::EntityDestroy,
             &mut out[offset..],
             limits,
-            |writer| encode_destroy_body(baseline, current, counts.destroys, limits, writer),
+            |writer| {
+                encode_destroy_body(
+                    baseline,
+                    current,
+                    counts.destroys,
+                    &counts.destroy_ids,
+                    schema.entity_id_width,
+                    limits,
+                    writer,
+                )
+            },
         )?;
         offset += written;
     }
@@ -431,11 +547,40 @@ fn encode_delta_payload_with_mode(
             SectionTag::EntityCreate,
             &mut out[offset..],
             limits,
-            |writer| encode_create_body(schema, baseline, current, counts.creates, limits, writer),
+            |writer| {
+                encode_create_body(
+                    schema,
+                    baseline,
+                    current,
+                    counts.creates,
+                    &counts.create_ids,
+                    limits,
+                    writer,
+                )
+            },
+        )?;
+        offset += written;
+    }
+    if counts.component_ops > 0 {
+        let written = write_section(
+            SectionTag::EntityComponentOps,
+            &mut out[offset..],
+            limits,
+            |writer| {
+                encode_component_ops_body(
+                    schema,
+                    baseline,
+                    current,
+                    counts.component_ops,
+                    limits,
+                    writer,
+                )
+            },
         )?;
         offset += written;
     }
     if counts.updates > 0 {
+        let dt = tick.raw().wrapping_sub(baseline_tick.raw());
         let update_encoding = match mode {
             EncodeUpdateMode::Auto => {
                 select_update_encoding(schema, baseline, current, limits, scratch)?
@@ -457,6 +602,8 @@ fn encode_delta_payload_with_mode(
                         baseline,
                         current,
                         counts.updates,
+                        &counts.update_ids,
+                        dt,
                         limits,
                         scratch,
                         writer,
@@ -466,6 +613,7 @@ fn encode_delta_payload_with_mode(
                         baseline,
                         current,
                         counts.updates,
+                        dt,
                         limits,
                         scratch,
                         writer,
@@ -520,7 +668,14 @@ fn encode_delta_payload_from_updates(
             SectionTag::EntityDestroy,
             &mut out[offset..],
             limits,
-            |writer| encode_destroy_body_from_list(destroys, limits, writer),
+            |writer| {
+                encode_destroy_body_from_list(
+                    destroys.iter(),
+                    schema.entity_id_width,
+                    limits,
+                    writer,
+                )
+            },
         )?;
         offset += written;
     }
@@ -529,23 +684,17 @@ fn encode_delta_payload_from_updates(
             SectionTag::EntityCreate,
             &mut out[offset..],
             limits,
-            |writer| encode_create_body_from_list(schema, creates, limits, writer),
-        )?;
-        offset += written;
-    }
-    if !updates.is_empty() {
-        let written = write_section(
-            SectionTag::EntityUpdateSparsePacked,
-            &mut out[offset..],
-            limits,
-            |writer| {
-                encode_update_body_sparse_packed_from_updates(
-                    schema, updates, limits, &lookup, writer,
-                )
-            },
+            |writer| encode_create_body_from_list(schema, creates.iter(), limits, writer),
         )?;
         offset += written;
     }
+    offset += write_update_sections_from_updates(
+        schema,
+        updates.iter(),
+        limits,
+        &lookup,
+        &mut out[offset..],
+    )?;
 
     Ok(offset)
 }
@@ -570,17 +719,7 @@ pub fn apply_delta_snapshot_from_packet(
     limits: &CodecLimits,
 ) -> CodecResult<Snapshot> {
     let header = packet.header;
-    if !header.flags.is_delta_snapshot() {
-        return Err(CodecError::Wire(wire::DecodeError::InvalidFlags {
-            flags: header.flags.raw(),
-        }));
-    }
-    if header.baseline_tick == 0 {
-        return Err(CodecError::Wire(wire::DecodeError::InvalidBaselineTick {
-            baseline_tick: header.baseline_tick,
-            flags: header.flags.raw(),
-        }));
-    }
+    validate_delta_header(schema, &header)?;
     if header.baseline_tick != baseline.tick.raw() {
         return Err(CodecError::BaselineTickMismatch {
             expected: baseline.tick.raw(),
@@ -588,14 +727,6 @@ pub fn apply_delta_snapshot_from_packet(
         });
     }
 
-    let expected_hash = schema_hash(schema);
-    if header.schema_hash != expected_hash {
-        return Err(CodecError::SchemaMismatch {
-            expected: expected_hash,
-            found: header.schema_hash,
-        });
-    }
-
     let (destroys, creates, updates) = decode_delta_sections(schema, packet, limits)?;
 
     ensure_entities_sorted(&baseline.entities)?;
@@ -610,7 +741,8 @@ pub fn apply_delta_snapshot_from_packet(
             actual: remaining.len(),
         });
     }
-    apply_updates(&mut remaining, &updates)?;
+    let dt = header.tick.wrapping_sub(header.baseline_tick);
+    apply_updates(schema, &mut remaining, &updates, |_| dt)?;
 
     Ok(Snapshot {
         tick: SnapshotTick::new(header.tick),
@@ -625,12 +757,32 @@ pub fn decode_delta_packet(
     limits: &CodecLimits,
 ) -> CodecResult<DeltaDecoded> {
     let header = packet.header;
+    validate_delta_header(schema, &header)?;
+
+    let (destroys, creates, updates) = decode_delta_sections(schema, packet, limits)?;
+
+    Ok(DeltaDecoded {
+        tick: SnapshotTick::new(header.tick),
+        baseline_tick: SnapshotTick::new(header.baseline_tick),
+        destroys,
+        creates,
+        updates,
+    })
+}
+
+pub(crate) fn validate_delta_header(
+    schema: &schema::Schema,
+    header: &wire::PacketHeader,
+) -> CodecResult<()> {
     if !header.flags.is_delta_snapshot() {
         return Err(CodecError::Wire(wire::DecodeError::InvalidFlags {
             flags: header.flags.raw(),
         }));
     }
-    if header.baseline_tick == 0 {
+    // Tick 0 means "no baseline"; a baseline must not be newer than the tick.
+    if header.baseline_tick == 0
+        || header.tick.wrapping_sub(header.baseline_tick) >= wire::SESSION_MAX_TICK_DELTA
+    {
         return Err(CodecError::Wire(wire::DecodeError::InvalidBaselineTick {
             baseline_tick: header.baseline_tick,
             flags: header.flags.raw(),
@@ -643,16 +795,7 @@ pub fn decode_delta_packet(
             found: header.schema_hash,
         });
     }
-
-    let (destroys, creates, updates) = decode_delta_sections(schema, packet, limits)?;
-
-    Ok(DeltaDecoded {
-        tick: SnapshotTick::new(header.tick),
-        baseline_tick: SnapshotTick::new(header.baseline_tick),
-        destroys,
-        creates,
-        updates,
-    })
+    Ok(())
 }
 
 #[derive(Default)]
@@ -660,6 +803,10 @@ struct DiffCounts {
     creates: usize,
     updates: usize,
     destroys: usize,
+    component_ops: usize,
+    create_ids: IdRun,
+    update_ids: IdRun,
+    destroy_ids: IdRun,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -668,40 +815,48 @@ enum UpdateEncoding {
     Sparse,
 }
 
-fn diff_counts(
+fn diff_counts<B: Borrow<EntitySnapshot>, C: Borrow<EntitySnapshot>>(
     schema: &schema::Schema,
-    baseline: &Snapshot,
-    current: &Snapshot,
+    baseline: &[B],
+    current: &[C],
     limits: &CodecLimits,
     counts: &mut DiffCounts,
 ) -> CodecResult<()> {
     let mut i = 0usize;
     let mut j = 0usize;
-    while i < baseline.entities.len() || j < current.entities.len() {
-        let base = baseline.entities.get(i);
-        let curr = current.entities.get(j);
+    while i < baseline.len() || j < current.len() {
+        let base = baseline.get(i).map(Borrow::borrow);
+        let curr = current.get(j).map(Borrow::borrow);
         match (base, curr) {
             (Some(b), Some(c)) => {
-                if b.id.raw() < c.id.raw() {
+                if b.id < c.id {
                     counts.destroys += 1;
+                    counts.destroy_ids.push(b.id);
                     i += 1;
-                } else if b.id.raw() > c.id.raw() {
+                } else if b.id > c.id {
                     counts.creates += 1;
+                    counts.create_ids.push(c.id);
                     j += 1;
                 } else {
                     if entity_has_updates(schema, b, c, limits)? {
                         counts.updates += 1;
+                        counts.update_ids.push(c.id);
+                    }
+                    if has_component_ops(schema, b, c) {
+                        counts.component_ops += 1;
                     }
                     i += 1;
                     j += 1;
                 }
             }
-            (Some(_), None) => {
+            (Some(b), None) => {
                 counts.destroys += 1;
+                counts.destroy_ids.push(b.id);
                 i += 1;
             }
-            (None, Some(_)) => {
+            (None, Some(c)) => {
                 counts.creates += 1;
+                counts.create_ids.push(c.id);
                 j += 1;
             }
             (None, None) => break,
@@ -710,18 +865,19 @@ fn diff_counts(
     Ok(())
 }
 
-fn select_update_encoding(
+fn select_update_encoding<B: Borrow<EntitySnapshot>, C: Borrow<EntitySnapshot>>(
     schema: &schema::Schema,
-    baseline: &Snapshot,
-    current: &Snapshot,
+    baseline: &[B],
+    current: &[C],
     limits: &CodecLimits,
     scratch: &mut CodecScratch,
 ) -> CodecResult<UpdateEncoding> {
     let component_count = schema.components.len();
     let mut mask_bits = 0usize;
     let mut sparse_bits = 0usize;
-    let mut baseline_iter = baseline.entities.iter();
-    let mut current_iter = current.entities.iter();
+    let mut prev_sparse_id = 0u64;
+    let mut baseline_iter = baseline.iter().map(Borrow::borrow);
+    let mut current_iter = current.iter().map(Borrow::borrow);
     let mut baseline_next = baseline_iter.next();
     let mut current_next = current_iter.next();
 
@@ -735,16 +891,8 @@ fn select_update_encoding(
             }
             Ordering::Equal => {
                 for component in &schema.components {
-                    let base_component = find_component(base, component.id);
-                    let curr_component = find_component(curr, component.id);
-                    if base_component.is_some() != curr_component.is_some() {
-                        return Err(CodecError::InvalidMask {
-                            kind: MaskKind::ComponentMask,
-                            reason: MaskReason::ComponentPresenceMismatch {
-                                component: component.id,
-                            },
-                        });
-                    }
+                    let base_component = base.component(component.id);
+                    let curr_component = curr.component(component.id);
                     if let (Some(base_component), Some(curr_component)) =
                         (base_component, curr_component)
                     {
@@ -789,7 +937,8 @@ fn select_update_encoding(
                             // Values are sent in both encodings, so we only estimate index/mask + id overhead.
                             mask_bits += field_count;
                             sparse_bits += index_bits * changed;
-                            sparse_bits += varu32_len(curr.id.raw()) * 8;
+                            sparse_bits += varu64_len(curr.id.raw() - prev_sparse_id) * 8;
+                            prev_sparse_id = curr.id.raw();
                             sparse_bits += varu32_len(component.id.get() as u32) * 8;
                             sparse_bits += varu32_len(changed as u32) * 8;
                         }
@@ -813,7 +962,11 @@ fn select_update_encoding(
     }
 }
 
-fn varu32_len(value: u32) -> usize {
+pub(crate) fn varu64_len(value: u64) -> usize {
+    (required_bits(value).max(1) as usize).div_ceil(7)
+}
+
+pub(crate) fn varu32_len(value: u32) -> usize {
     if value < (1 << 7) {
         1
     } else if value < (1 << 14) {
@@ -827,8 +980,8 @@ fn varu32_len(value: u32) -> usize {
     }
 }
 
-fn ensure_entity_ids_sorted(ids: &[EntityId]) -> CodecResult<()> {
-    let mut prev: Option<u32> = None;
+pub(crate) fn ensure_entity_ids_sorted(ids: &[EntityId]) -> CodecResult<()> {
+    let mut prev: Option<u64> = None;
     for id in ids {
         let raw = id.raw();
         if let Some(prev_id) = prev {
@@ -844,57 +997,69 @@ fn ensure_entity_ids_sorted(ids: &[EntityId]) -> CodecResult<()> {
     Ok(())
 }
 
-fn encode_destroy_body_from_list(
-    destroys: &[EntityId],
+pub(crate) fn encode_destroy_body_from_list<'a, I>(
+    destroys: I,
+    width: EntityIdWidth,
     limits: &CodecLimits,
     writer: &mut BitWriter<'_>,
-) -> CodecResult<()> {
-    if destroys.len() > limits.max_entities_destroy {
+) -> CodecResult<()>
+where
+    I: Iterator<Item = &'a EntityId> + Clone,
+{
+    let count = destroys.clone().count();
+    if count > limits.max_entities_destroy {
         return Err(CodecError::LimitsExceeded {
             kind: LimitKind::EntitiesDestroy,
             limit: limits.max_entities_destroy,
-            actual: destroys.len(),
+            actual: count,
         });
     }
     writer.align_to_byte()?;
-    writer.write_varu32(destroys.len() as u32)?;
+    writer.write_varu32(count as u32)?;
+    let run = IdRun::from_ids(destroys.clone().copied());
+    let mut ids = IdGapWriter::begin(&run, width, writer)?;
     for id in destroys {
-        writer.align_to_byte()?;
-        writer.write_u32_aligned(id.raw())?;
+        ids.write(*id, writer)?;
     }
     writer.align_to_byte()?;
     Ok(())
 }
 
-fn encode_create_body_from_list(
+pub(crate) fn encode_create_body_from_list<'a, I>(
     schema: &schema::Schema,
-    creates: &[EntitySnapshot],
+    creates: I,
     limits: &CodecLimits,
     writer: &mut BitWriter<'_>,
-) -> CodecResult<()> {
-    if creates.len() > limits.max_entities_create {
+) -> CodecResult<()>
+where
+    I: Iterator<Item = &'a EntitySnapshot> + Clone,
+{
+    let count = creates.clone().count();
+    if count > limits.max_entities_create {
         return Err(CodecError::LimitsExceeded {
             kind: LimitKind::EntitiesCreate,
             limit: limits.max_entities_create,
-            actual: creates.len(),
+            actual: count,
         });
     }
     writer.align_to_byte()?;
-    writer.write_varu32(creates.len() as u32)?;
+    writer.write_varu32(count as u32)?;
+    let run = IdRun::from_ids(creates.clone().map(|entity| entity.id));
+    let mut ids = IdGapWriter::begin(&run, schema.entity_id_width, writer)?;
     for entity in creates {
-        write_create_entity(schema, entity, limits, writer)?;
+        write_create_entity(schema, entity, limits, &mut ids, writer)?;
     }
     writer.align_to_byte()?;
     Ok(())
 }
 
-fn validate_updates_for_encoding(
+pub(crate) fn validate_updates_for_encoding<V>(
     schema: &schema::Schema,
-    updates: &[DeltaUpdateEntity],
+    updates: &[DeltaUpdateEntity<V>],
     limits: &CodecLimits,
     lookup: &ComponentLookup,
 ) -> CodecResult<()> {
-    let mut prev: Option<u32> = None;
+    let mut prev: Option<u64> = None;
     for entity_update in updates {
         let id = entity_update.id.raw();
         if let Some(prev_id) = prev {
@@ -906,13 +1071,17 @@ fn validate_updates_for_encoding(
             }
         }
         prev = Some(id);
-        if entity_update.components.len() > limits.max_components_per_entity {
+        let component_count = entity_update.components.len()
+            + entity_update.inserted.len()
+            + entity_update.removed.len();
+        if component_count > limits.max_components_per_entity {
             return Err(CodecError::LimitsExceeded {
                 kind: LimitKind::ComponentsPerEntity,
                 limit: limits.max_components_per_entity,
-                actual: entity_update.components.len(),
+                actual: component_count,
             });
         }
+        validate_component_ops(schema, entity_update, lookup)?;
         for component_update in &entity_update.components {
             let component = lookup.component(schema, component_update.id)?;
             if component_update.fields.is_empty() {
@@ -951,90 +1120,281 @@ fn validate_updates_for_encoding(
     Ok(())
 }
 
-fn encode_update_body_sparse_packed_from_updates(
+/// Checks that inserted and removed components are known, in schema order
+/// and disjoint.
+fn validate_component_ops<V>(
     schema: &schema::Schema,
-    updates: &[DeltaUpdateEntity],
-    limits: &CodecLimits,
+    entity_update: &DeltaUpdateEntity<V>,
     lookup: &ComponentLookup,
-    writer: &mut BitWriter<'_>,
 ) -> CodecResult<()> {
-    if updates.len() > limits.max_entities_update {
-        return Err(CodecError::LimitsExceeded {
-            kind: LimitKind::EntitiesUpdate,
-            limit: limits.max_entities_update,
-            actual: updates.len(),
-        });
-    }
-    let entry_count: usize = updates.iter().map(|entity| entity.components.len()).sum();
-    let entry_limit = limits
-        .max_entities_update
-        .saturating_mul(limits.max_components_per_entity);
-    if entry_count > entry_limit {
-        return Err(CodecError::LimitsExceeded {
-            kind: LimitKind::EntitiesUpdate,
-            limit: entry_limit,
-            actual: entry_count,
-        });
-    }
-    writer.align_to_byte()?;
-    writer.write_varu32(entry_count as u32)?;
-    for entity_update in updates {
-        let entity_id = entity_update.id.raw();
-        for component_update in &entity_update.components {
-            let component = lookup.component(schema, component_update.id)?;
-            writer.align_to_byte()?;
-            writer.write_varu32(entity_id)?;
-            writer.write_varu32(component.id.get() as u32)?;
-            writer.write_varu32(component_update.fields.len() as u32)?;
-            let index_bits = lookup.index_bits(component.id);
-            for (field_idx, value) in &component_update.fields {
-                if index_bits > 0 {
-                    writer.write_bits(*field_idx as u64, index_bits)?;
-                }
-                write_field_value_sparse(
-                    component.id,
-                    component.fields[*field_idx],
-                    *value,
-                    writer,
-                )?;
-            }
-        }
+    let mut prev = None;
+    for id in &entity_update.removed {
+        let position = lookup.position(*id)?;
+        ensure_component_order(*id, prev, position)?;
+        prev = Some(position);
     }
-    writer.align_to_byte()?;
-    Ok(())
-}
-
-struct ComponentLookup {
-    index: Vec<Option<usize>>,
-    index_bits: Vec<u8>,
-}
-
-impl ComponentLookup {
-    fn component<'a>(
-        &self,
-        schema: &'a schema::Schema,
-        id: ComponentId,
-    ) -> CodecResult<&'a ComponentDef> {
-        let idx = id.get() as usize;
-        let Some(Some(component_index)) = self.index.get(idx) else {
+    let mut prev = None;
+    for component in &entity_update.inserted {
+        let position = lookup.position(component.id)?;
+        ensure_component_order(component.id, prev, position)?;
+        prev = Some(position);
+        if entity_update.removed.contains(&component.id) {
             return Err(CodecError::InvalidMask {
                 kind: MaskKind::ComponentMask,
-                reason: MaskReason::UnknownComponent { component: id },
+                reason: MaskReason::UnorderedComponent {
+                    component: component.id,
+                },
             });
-        };
-        Ok(&schema.components[*component_index])
+        }
+        let def = &schema.components[position];
+        if component.fields.len() != def.fields.len() {
+            return Err(CodecError::InvalidMask {
+                kind: MaskKind::FieldMask {
+                    component: component.id,
+                },
+                reason: MaskReason::FieldCountMismatch {
+                    expected: def.fields.len(),
+                    actual: component.fields.len(),
+                },
+            });
+        }
     }
+    Ok(())
+}
 
-    fn index_bits(&self, id: ComponentId) -> u8 {
-        let idx = id.get() as usize;
-        let Some(bits) = self.index_bits.get(idx).copied() else {
-            return 0;
-        };
-        bits
+fn ensure_component_order(
+    id: ComponentId,
+    previous: Option<usize>,
+    position: usize,
+) -> CodecResult<()> {
+    if previous.is_some_and(|previous| position <= previous) {
+        return Err(CodecError::InvalidMask {
+            kind: MaskKind::ComponentMask,
+            reason: MaskReason::UnorderedComponent { component: id },
+        });
     }
+    Ok(())
 }
 
-fn build_component_lookup(schema: &schema::Schema) -> ComponentLookup {
+/// Writes the component op and sparse packed update sections for `updates`,
+/// skipping either when it would be empty.
+pub(crate) fn write_update_sections_from_updates<'a, I, V>(
+    schema: &schema::Schema,
+    updates: I,
+    limits: &CodecLimits,
+    lookup: &ComponentLookup,
+    out: &mut [u8],
+) -> CodecResult<usize>
+where
+    I: Iterator<Item = &'a DeltaUpdateEntity<V>> + Clone,
+    V: Copy + Into<UpdateValue> + 'a,
+{
+    let mut offset = 0;
+    if updates.clone().any(DeltaUpdateEntity::has_component_ops) {
+        offset += write_section(
+            SectionTag::EntityComponentOps,
+            &mut out[offset..],
+            limits,
+            |writer| {
+                encode_component_ops_body_from_updates(
+                    schema,
+                    updates.clone(),
+                    limits,
+                    lookup,
+                    writer,
+                )
+            },
+        )?;
+    }
+    if updates.clone().any(|entity| !entity.components.is_empty()) {
+        offset += write_section(
+            SectionTag::EntityUpdateSparsePacked,
+            &mut out[offset..],
+            limits,
+            |writer| {
+                encode_update_body_sparse_packed_from_updates(
+                    schema,
+                    updates.filter(|entity| !entity.components.is_empty()),
+                    limits,
+                    lookup,
+                    writer,
+                )
+            },
+        )?;
+    }
+    Ok(offset)
+}
+
+pub(crate) fn encode_component_ops_body_from_updates<'a, I, V>(
+    schema: &schema::Schema,
+    updates: I,
+    limits: &CodecLimits,
+    lookup: &ComponentLookup,
+    writer: &mut BitWriter<'_>,
+) -> CodecResult<()>
+where
+    I: Iterator<Item = &'a DeltaUpdateEntity<V>> + Clone,
+    V: Copy + Into<UpdateValue> + 'a,
+{
+    let updates = updates.filter(|entity| entity.has_component_ops());
+    let count = updates.clone().count();
+    if count > limits.max_entities_update {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::EntitiesUpdate,
+            limit: limits.max_entities_update,
+            actual: count,
+        });
+    }
+    writer.align_to_byte()?;
+    writer.write_varu32(count as u32)?;
+    let mut ids = SparseIdWriter::new(schema.entity_id_width);
+    for entity_update in updates {
+        write_component_ops_entity(schema, entity_update, limits, lookup, &mut ids, writer)?;
+    }
+    writer.align_to_byte()?;
+    Ok(())
+}
+
+/// Writes the component op entry for one entity: removals, then inserts with
+/// their full state.
+pub(crate) fn write_component_ops_entity<V>(
+    schema: &schema::Schema,
+    entity_update: &DeltaUpdateEntity<V>,
+    limits: &CodecLimits,
+    lookup: &ComponentLookup,
+    ids: &mut SparseIdWriter,
+    writer: &mut BitWriter<'_>,
+) -> CodecResult<()> {
+    writer.align_to_byte()?;
+    ids.write(entity_update.id.raw(), writer)?;
+    writer.write_varu32(entity_update.removed.len() as u32)?;
+    for id in &entity_update.removed {
+        writer.write_varu32(id.get() as u32)?;
+    }
+    writer.write_varu32(entity_update.inserted.len() as u32)?;
+    for component in &entity_update.inserted {
+        let def = lookup.component(schema, component.id)?;
+        writer.write_varu32(component.id.get() as u32)?;
+        write_full_component(def, component, limits, writer)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn encode_update_body_sparse_packed_from_updates<'a, I, V>(
+    schema: &schema::Schema,
+    updates: I,
+    limits: &CodecLimits,
+    lookup: &ComponentLookup,
+    writer: &mut BitWriter<'_>,
+) -> CodecResult<()>
+where
+    I: Iterator<Item = &'a DeltaUpdateEntity<V>> + Clone,
+    V: Copy + Into<UpdateValue> + 'a,
+{
+    let update_count = updates.clone().count();
+    if update_count > limits.max_entities_update {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::EntitiesUpdate,
+            limit: limits.max_entities_update,
+            actual: update_count,
+        });
+    }
+    let entry_count: usize = updates.clone().map(|entity| entity.components.len()).sum();
+    let entry_limit = limits
+        .max_entities_update
+        .saturating_mul(limits.max_components_per_entity);
+    if entry_count > entry_limit {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::EntitiesUpdate,
+            limit: entry_limit,
+            actual: entry_count,
+        });
+    }
+    writer.align_to_byte()?;
+    writer.write_varu32(entry_count as u32)?;
+    let mut ids = SparseIdWriter::new(schema.entity_id_width);
+    for entity_update in updates {
+        write_sparse_packed_entity(schema, entity_update, lookup, &mut ids, writer)?;
+    }
+    writer.align_to_byte()?;
+    Ok(())
+}
+
+/// Writes the sparse packed entries for one entity (one entry per component).
+pub(crate) fn write_sparse_packed_entity<V: Copy + Into<UpdateValue>>(
+    schema: &schema::Schema,
+    entity_update: &DeltaUpdateEntity<V>,
+    lookup: &ComponentLookup,
+    ids: &mut SparseIdWriter,
+    writer: &mut BitWriter<'_>,
+) -> CodecResult<()> {
+    let entity_id = entity_update.id.raw();
+    for component_update in &entity_update.components {
+        let component = lookup.component(schema, component_update.id)?;
+        writer.align_to_byte()?;
+        ids.write(entity_id, writer)?;
+        writer.write_varu32(component.id.get() as u32)?;
+        writer.write_varu32(component_update.fields.len() as u32)?;
+        let index_bits = lookup.index_bits(component.id);
+        for (field_idx, value) in &component_update.fields {
+            if index_bits > 0 {
+                writer.write_bits(*field_idx as u64, index_bits)?;
+            }
+            write_update_value(
+                component.id,
+                component.fields[*field_idx],
+                (*value).into(),
+                writer,
+                write_field_value_sparse,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) struct ComponentLookup {
+    index: Vec<Option<usize>>,
+    index_bits: Vec<u8>,
+}
+
+impl ComponentLookup {
+    fn component<'a>(
+        &self,
+        schema: &'a schema::Schema,
+        id: ComponentId,
+    ) -> CodecResult<&'a ComponentDef> {
+        let idx = id.get() as usize;
+        let Some(Some(component_index)) = self.index.get(idx) else {
+            return Err(CodecError::InvalidMask {
+                kind: MaskKind::ComponentMask,
+                reason: MaskReason::UnknownComponent { component: id },
+            });
+        };
+        Ok(&schema.components[*component_index])
+    }
+
+    /// Returns the schema position of component `id`.
+    fn position(&self, id: ComponentId) -> CodecResult<usize> {
+        self.index
+            .get(id.get() as usize)
+            .copied()
+            .flatten()
+            .ok_or(CodecError::InvalidMask {
+                kind: MaskKind::ComponentMask,
+                reason: MaskReason::UnknownComponent { component: id },
+            })
+    }
+
+    fn index_bits(&self, id: ComponentId) -> u8 {
+        let idx = id.get() as usize;
+        let Some(bits) = self.index_bits.get(idx).copied() else {
+            return 0;
+        };
+        bits
+    }
+}
+
+pub(crate) fn build_component_lookup(schema: &schema::Schema) -> ComponentLookup {
     let max_id = schema
         .components
         .iter()
@@ -1052,10 +1412,12 @@ fn build_component_lookup(schema: &schema::Schema) -> ComponentLookup {
     ComponentLookup { index, index_bits }
 }
 
-fn encode_destroy_body(
-    baseline: &Snapshot,
-    current: &Snapshot,
+fn encode_destroy_body<B: Borrow<EntitySnapshot>, C: Borrow<EntitySnapshot>>(
+    baseline: &[B],
+    current: &[C],
     destroy_count: usize,
+    destroy_ids: &IdRun,
+    width: EntityIdWidth,
     limits: &CodecLimits,
     writer: &mut BitWriter<'_>,
 ) -> CodecResult<()> {
@@ -1069,19 +1431,19 @@ fn encode_destroy_body(
 
     writer.align_to_byte()?;
     writer.write_varu32(destroy_count as u32)?;
+    let mut ids = IdGapWriter::begin(destroy_ids, width, writer)?;
 
     let mut i = 0usize;
     let mut j = 0usize;
-    while i < baseline.entities.len() || j < current.entities.len() {
-        let base = baseline.entities.get(i);
-        let curr = current.entities.get(j);
+    while i < baseline.len() || j < current.len() {
+        let base = baseline.get(i).map(Borrow::borrow);
+        let curr = current.get(j).map(Borrow::borrow);
         match (base, curr) {
             (Some(b), Some(c)) => {
-                if b.id.raw() < c.id.raw() {
-                    writer.align_to_byte()?;
-                    writer.write_u32_aligned(b.id.raw())?;
+                if b.id < c.id {
+                    ids.write(b.id, writer)?;
                     i += 1;
-                } else if b.id.raw() > c.id.raw() {
+                } else if b.id > c.id {
                     j += 1;
                 } else {
                     i += 1;
@@ -1089,8 +1451,7 @@ fn encode_destroy_body(
                 }
             }
             (Some(b), None) => {
-                writer.align_to_byte()?;
-                writer.write_u32_aligned(b.id.raw())?;
+                ids.write(b.id, writer)?;
                 i += 1;
             }
             (None, Some(_)) => {
@@ -1104,11 +1465,12 @@ fn encode_destroy_body(
     Ok(())
 }
 
-fn encode_create_body(
+fn encode_create_body<B: Borrow<EntitySnapshot>, C: Borrow<EntitySnapshot>>(
     schema: &schema::Schema,
-    baseline: &Snapshot,
-    current: &Snapshot,
+    baseline: &[B],
+    current: &[C],
     create_count: usize,
+    create_ids: &IdRun,
     limits: &CodecLimits,
     writer: &mut BitWriter<'_>,
 ) -> CodecResult<()> {
@@ -1122,18 +1484,19 @@ fn encode_create_body(
 
     writer.align_to_byte()?;
     writer.write_varu32(create_count as u32)?;
+    let mut ids = IdGapWriter::begin(create_ids, schema.entity_id_width, writer)?;
 
     let mut i = 0usize;
     let mut j = 0usize;
-    while i < baseline.entities.len() || j < current.entities.len() {
-        let base = baseline.entities.get(i);
-        let curr = current.entities.get(j);
+    while i < baseline.len() || j < current.len() {
+        let base = baseline.get(i).map(Borrow::borrow);
+        let curr = current.get(j).map(Borrow::borrow);
         match (base, curr) {
             (Some(b), Some(c)) => {
-                if b.id.raw() < c.id.raw() {
+                if b.id < c.id {
                     i += 1;
-                } else if b.id.raw() > c.id.raw() {
-                    write_create_entity(schema, c, limits, writer)?;
+                } else if b.id > c.id {
+                    write_create_entity(schema, c, limits, &mut ids, writer)?;
                     j += 1;
                 } else {
                     i += 1;
@@ -1144,7 +1507,7 @@ fn encode_create_body(
                 i += 1;
             }
             (None, Some(c)) => {
-                write_create_entity(schema, c, limits, writer)?;
+                write_create_entity(schema, c, limits, &mut ids, writer)?;
                 j += 1;
             }
             (None, None) => break,
@@ -1155,11 +1518,70 @@ fn encode_create_body(
     Ok(())
 }
 
-fn encode_update_body_masked(
+fn encode_component_ops_body<B: Borrow<EntitySnapshot>, C: Borrow<EntitySnapshot>>(
     schema: &schema::Schema,
-    baseline: &Snapshot,
-    current: &Snapshot,
+    baseline: &[B],
+    current: &[C],
+    op_count: usize,
+    limits: &CodecLimits,
+    writer: &mut BitWriter<'_>,
+) -> CodecResult<()> {
+    if op_count > limits.max_entities_update {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::EntitiesUpdate,
+            limit: limits.max_entities_update,
+            actual: op_count,
+        });
+    }
+
+    writer.align_to_byte()?;
+    writer.write_varu32(op_count as u32)?;
+    let lookup = build_component_lookup(schema);
+    let mut ids = SparseIdWriter::new(schema.entity_id_width);
+
+    let mut baseline_iter = baseline.iter().map(Borrow::borrow);
+    let mut current_iter = current.iter().map(Borrow::borrow);
+    let mut baseline_next = baseline_iter.next();
+    let mut current_next = current_iter.next();
+    while let (Some(base), Some(curr)) = (baseline_next, current_next) {
+        match base.id.cmp(&curr.id) {
+            Ordering::Less => baseline_next = baseline_iter.next(),
+            Ordering::Greater => current_next = current_iter.next(),
+            Ordering::Equal => {
+                if let Some((inserted, removed)) = component_ops(schema, base, curr) {
+                    let entity_update = DeltaUpdateEntity::<FieldValue> {
+                        id: curr.id,
+                        components: Vec::new(),
+                        inserted,
+                        removed,
+                    };
+                    write_component_ops_entity(
+                        schema,
+                        &entity_update,
+                        limits,
+                        &lookup,
+                        &mut ids,
+                        writer,
+                    )?;
+                }
+                baseline_next = baseline_iter.next();
+                current_next = current_iter.next();
+            }
+        }
+    }
+
+    writer.align_to_byte()?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn encode_update_body_masked<B: Borrow<EntitySnapshot>, C: Borrow<EntitySnapshot>>(
+    schema: &schema::Schema,
+    baseline: &[B],
+    current: &[C],
     update_count: usize,
+    update_ids: &IdRun,
+    dt: u32,
     limits: &CodecLimits,
     scratch: &mut CodecScratch,
     writer: &mut BitWriter<'_>,
@@ -1174,24 +1596,24 @@ fn encode_update_body_masked(
 
     writer.align_to_byte()?;
     writer.write_varu32(update_count as u32)?;
+    let mut ids = IdGapWriter::begin_indices(update_ids, schema.entity_id_width, writer)?;
 
     let mut i = 0usize;
     let mut j = 0usize;
-    while i < baseline.entities.len() || j < current.entities.len() {
-        let base = baseline.entities.get(i);
-        let curr = current.entities.get(j);
+    while i < baseline.len() || j < current.len() {
+        let base = baseline.get(i).map(Borrow::borrow);
+        let curr = current.get(j).map(Borrow::borrow);
         match (base, curr) {
             (Some(b), Some(c)) => {
-                if b.id.raw() < c.id.raw() {
+                if b.id < c.id {
                     i += 1;
-                } else if b.id.raw() > c.id.raw() {
+                } else if b.id > c.id {
                     j += 1;
                 } else {
                     if entity_has_updates(schema, b, c, limits)? {
                         writer.align_to_byte()?;
-                        writer.write_u32_aligned(c.id.raw())?;
-                        ensure_component_presence_matches(schema, b, c)?;
-                        write_update_components(schema, b, c, limits, scratch, writer)?;
+                        ids.write(c.id, writer)?;
+                        write_update_components(schema, b, c, dt, limits, scratch, writer)?;
                     }
                     i += 1;
                     j += 1;
@@ -1207,12 +1629,13 @@ fn encode_update_body_masked(
     Ok(())
 }
 
-#[allow(dead_code)]
-fn encode_update_body_sparse_varint(
+#[allow(dead_code, clippy::too_many_arguments)]
+fn encode_update_body_sparse_varint<B: Borrow<EntitySnapshot>, C: Borrow<EntitySnapshot>>(
     schema: &schema::Schema,
-    baseline: &Snapshot,
-    current: &Snapshot,
+    baseline: &[B],
+    current: &[C],
     update_count: usize,
+    dt: u32,
     limits: &CodecLimits,
     scratch: &mut CodecScratch,
     writer: &mut BitWriter<'_>,
@@ -1239,9 +1662,10 @@ fn encode_update_body_sparse_varint(
 
     writer.align_to_byte()?;
     writer.write_varu32(entry_count as u32)?;
+    let mut ids = SparseIdWriter::new(schema.entity_id_width);
 
-    let mut baseline_iter = baseline.entities.iter();
-    let mut current_iter = current.entities.iter();
+    let mut baseline_iter = baseline.iter().map(Borrow::borrow);
+    let mut current_iter = current.iter().map(Borrow::borrow);
     let mut baseline_next = baseline_iter.next();
     let mut current_next = current_iter.next();
     let component_count = schema.components.len();
@@ -1257,16 +1681,8 @@ fn encode_update_body_sparse_varint(
             Ordering::Equal => {
                 if entity_has_updates(schema, base, curr, limits)? {
                     for component in &schema.components {
-                        let base_component = find_component(base, component.id);
-                        let curr_component = find_component(curr, component.id);
-                        if base_component.is_some() != curr_component.is_some() {
-                            return Err(CodecError::InvalidMask {
-                                kind: MaskKind::ComponentMask,
-                                reason: MaskReason::ComponentPresenceMismatch {
-                                    component: component.id,
-                                },
-                            });
-                        }
+                        let base_component = base.component(component.id);
+                        let curr_component = curr.component(component.id);
                         if let (Some(base_component), Some(curr_component)) =
                             (base_component, curr_component)
                         {
@@ -1306,18 +1722,26 @@ fn encode_update_body_sparse_varint(
                             let changed_fields = field_mask.iter().filter(|bit| **bit).count();
                             if changed_fields > 0 {
                                 writer.align_to_byte()?;
-                                writer.write_u32_aligned(curr.id.raw())?;
+                                ids.write(curr.id.raw(), writer)?;
                                 writer.write_u16_aligned(component.id.get())?;
                                 writer.write_varu32(changed_fields as u32)?;
                                 for (idx, field) in component.fields.iter().enumerate() {
                                     if field_mask[idx] {
                                         writer.align_to_byte()?;
                                         writer.write_varu32(idx as u32)?;
-                                        write_field_value_sparse(
+                                        let value = encode_update_value(
+                                            component,
+                                            idx,
+                                            &base_component.fields,
+                                            curr_component.fields[idx],
+                                            dt,
+                                        );
+                                        write_update_value(
                                             component.id,
                                             *field,
-                                            curr_component.fields[idx],
+                                            value,
                                             writer,
+                                            write_field_value_sparse,
                                         )?;
                                     }
                                 }
@@ -1335,11 +1759,13 @@ fn encode_update_body_sparse_varint(
     Ok(())
 }
 
-fn encode_update_body_sparse_packed(
+#[allow(clippy::too_many_arguments)]
+fn encode_update_body_sparse_packed<B: Borrow<EntitySnapshot>, C: Borrow<EntitySnapshot>>(
     schema: &schema::Schema,
-    baseline: &Snapshot,
-    current: &Snapshot,
+    baseline: &[B],
+    current: &[C],
     update_count: usize,
+    dt: u32,
     limits: &CodecLimits,
     scratch: &mut CodecScratch,
     writer: &mut BitWriter<'_>,
@@ -1366,9 +1792,10 @@ fn encode_update_body_sparse_packed(
 
     writer.align_to_byte()?;
     writer.write_varu32(entry_count as u32)?;
+    let mut ids = SparseIdWriter::new(schema.entity_id_width);
 
-    let mut baseline_iter = baseline.entities.iter();
-    let mut current_iter = current.entities.iter();
+    let mut baseline_iter = baseline.iter().map(Borrow::borrow);
+    let mut current_iter = current.iter().map(Borrow::borrow);
     let mut baseline_next = baseline_iter.next();
     let mut current_next = current_iter.next();
     let component_count = schema.components.len();
@@ -1384,16 +1811,8 @@ fn encode_update_body_sparse_packed(
             Ordering::Equal => {
                 if entity_has_updates(schema, base, curr, limits)? {
                     for component in &schema.components {
-                        let base_component = find_component(base, component.id);
-                        let curr_component = find_component(curr, component.id);
-                        if base_component.is_some() != curr_component.is_some() {
-                            return Err(CodecError::InvalidMask {
-                                kind: MaskKind::ComponentMask,
-                                reason: MaskReason::ComponentPresenceMismatch {
-                                    component: component.id,
-                                },
-                            });
-                        }
+                        let base_component = base.component(component.id);
+                        let curr_component = curr.component(component.id);
                         if let (Some(base_component), Some(curr_component)) =
                             (base_component, curr_component)
                         {
@@ -1433,7 +1852,7 @@ fn encode_update_body_sparse_packed(
                             let changed_fields = field_mask.iter().filter(|bit| **bit).count();
                             if changed_fields > 0 {
                                 writer.align_to_byte()?;
-                                writer.write_varu32(curr.id.raw())?;
+                                ids.write(curr.id.raw(), writer)?;
                                 writer.write_varu32(component.id.get() as u32)?;
                                 writer.write_varu32(changed_fields as u32)?;
                                 let index_bits =
@@ -1443,11 +1862,19 @@ fn encode_update_body_sparse_packed(
                                         if index_bits > 0 {
                                             writer.write_bits(idx as u64, index_bits)?;
                                         }
-                                        write_field_value_sparse(
+                                        let value = encode_update_value(
+                                            component,
+                                            idx,
+                                            &base_component.fields,
+                                            curr_component.fields[idx],
+                                            dt,
+                                        );
+                                        write_update_value(
                                             component.id,
                                             *field,
-                                            curr_component.fields[idx],
+                                            value,
                                             writer,
+                                            write_field_value_sparse,
                                         )?;
                                     }
                                 }
@@ -1465,17 +1892,17 @@ fn encode_update_body_sparse_packed(
     Ok(())
 }
 
-fn count_sparse_update_entries(
+fn count_sparse_update_entries<B: Borrow<EntitySnapshot>, C: Borrow<EntitySnapshot>>(
     schema: &schema::Schema,
-    baseline: &Snapshot,
-    current: &Snapshot,
+    baseline: &[B],
+    current: &[C],
     limits: &CodecLimits,
     scratch: &mut CodecScratch,
 ) -> CodecResult<usize> {
     let component_count = schema.components.len();
     let mut count = 0usize;
-    let mut baseline_iter = baseline.entities.iter();
-    let mut current_iter = current.entities.iter();
+    let mut baseline_iter = baseline.iter().map(Borrow::borrow);
+    let mut current_iter = current.iter().map(Borrow::borrow);
     let mut baseline_next = baseline_iter.next();
     let mut current_next = current_iter.next();
 
@@ -1486,16 +1913,8 @@ fn count_sparse_update_entries(
             Ordering::Equal => {
                 if entity_has_updates(schema, base, curr, limits)? {
                     for component in &schema.components {
-                        let base_component = find_component(base, component.id);
-                        let curr_component = find_component(curr, component.id);
-                        if base_component.is_some() != curr_component.is_some() {
-                            return Err(CodecError::InvalidMask {
-                                kind: MaskKind::ComponentMask,
-                                reason: MaskReason::ComponentPresenceMismatch {
-                                    component: component.id,
-                                },
-                            });
-                        }
+                        let base_component = base.component(component.id);
+                        let curr_component = curr.component(component.id);
                         if let (Some(base_component), Some(curr_component)) =
                             (base_component, curr_component)
                         {
@@ -1547,25 +1966,30 @@ fn count_sparse_update_entries(
     Ok(count)
 }
 
-fn write_create_entity(
+pub(crate) fn write_create_entity(
     schema: &schema::Schema,
     entity: &EntitySnapshot,
     limits: &CodecLimits,
+    ids: &mut IdGapWriter,
     writer: &mut BitWriter<'_>,
 ) -> CodecResult<()> {
     writer.align_to_byte()?;
-    writer.write_u32_aligned(entity.id.raw())?;
+    ids.write(entity.id, writer)?;
     ensure_known_components(schema, entity)?;
     write_component_mask(schema, entity, writer)?;
     for component in schema.components.iter() {
-        if let Some(snapshot) = find_component(entity, component.id) {
+        if let Some(snapshot) = entity.component(component.id) {
             write_full_component(component, snapshot, limits, writer)?;
         }
     }
     Ok(())
 }
 
-fn decode_destroy_section(body: &[u8], limits: &CodecLimits) -> CodecResult<Vec<EntityId>> {
+fn decode_destroy_section(
+    body: &[u8],
+    width: EntityIdWidth,
+    limits: &CodecLimits,
+) -> CodecResult<Vec<EntityId>> {
     if body.len() > limits.max_section_bytes {
         return Err(CodecError::LimitsExceeded {
             kind: LimitKind::SectionBytes,
@@ -1586,20 +2010,9 @@ fn decode_destroy_section(body: &[u8], limits: &CodecLimits) -> CodecResult<Vec<
     }
 
     let mut ids = Vec::with_capacity(count);
-    let mut prev: Option<u32> = None;
+    let mut gaps = IdGapReader::begin(&mut reader, count, width)?;
     for _ in 0..count {
-        reader.align_to_byte()?;
-        let id = reader.read_u32_aligned()?;
-        if let Some(prev_id) = prev {
-            if id <= prev_id {
-                return Err(CodecError::InvalidEntityOrder {
-                    previous: prev_id,
-                    current: id,
-                });
-            }
-        }
-        prev = Some(id);
-        ids.push(EntityId::new(id));
+        ids.push(gaps.read(&mut reader)?);
     }
     reader.align_to_byte()?;
     if reader.bits_remaining() != 0 {
@@ -1636,19 +2049,10 @@ fn decode_create_section(
     }
 
     let mut entities = Vec::with_capacity(count);
-    let mut prev: Option<u32> = None;
+    let mut ids = IdGapReader::begin(&mut reader, count, schema.entity_id_width)?;
     for _ in 0..count {
         reader.align_to_byte()?;
-        let id = reader.read_u32_aligned()?;
-        if let Some(prev_id) = prev {
-            if id <= prev_id {
-                return Err(CodecError::InvalidEntityOrder {
-                    previous: prev_id,
-                    current: id,
-                });
-            }
-        }
-        prev = Some(id);
+        let id = ids.read(&mut reader)?;
 
         let component_mask = read_mask(
             &mut reader,
@@ -1667,10 +2071,7 @@ fn decode_create_section(
             }
         }
 
-        let entity = EntitySnapshot {
-            id: EntityId::new(id),
-            components,
-        };
+        let entity = EntitySnapshot { id, components };
         ensure_known_components(schema, &entity)?;
         entities.push(entity);
     }
@@ -1685,11 +2086,11 @@ fn decode_create_section(
     Ok(entities)
 }
 
-fn decode_update_section_masked(
+pub(crate) fn decode_update_section_masked(
     schema: &schema::Schema,
     body: &[u8],
     limits: &CodecLimits,
-) -> CodecResult<Vec<DeltaUpdateEntity>> {
+) -> CodecResult<Vec<DeltaUpdateEntity<UpdateValue>>> {
     if body.len() > limits.max_section_bytes {
         return Err(CodecError::LimitsExceeded {
             kind: LimitKind::SectionBytes,
@@ -1710,19 +2111,10 @@ fn decode_update_section_masked(
     }
 
     let mut updates = Vec::with_capacity(count);
-    let mut prev: Option<u32> = None;
+    let mut ids = IdGapReader::begin(&mut reader, count, schema.entity_id_width)?;
     for _ in 0..count {
         reader.align_to_byte()?;
-        let id = reader.read_u32_aligned()?;
-        if let Some(prev_id) = prev {
-            if id <= prev_id {
-                return Err(CodecError::InvalidEntityOrder {
-                    previous: prev_id,
-                    current: id,
-                });
-            }
-        }
-        prev = Some(id);
+        let id = ids.read(&mut reader)?;
 
         let component_mask = read_mask(
             &mut reader,
@@ -1740,10 +2132,7 @@ fn decode_update_section_masked(
             }
         }
 
-        updates.push(DeltaUpdateEntity {
-            id: EntityId::new(id),
-            components,
-        });
+        updates.push(DeltaUpdateEntity::new(id, components));
     }
 
     reader.align_to_byte()?;
@@ -1760,7 +2149,7 @@ fn decode_update_section_sparse_varint(
     schema: &schema::Schema,
     body: &[u8],
     limits: &CodecLimits,
-) -> CodecResult<Vec<DeltaUpdateEntity>> {
+) -> CodecResult<Vec<DeltaUpdateEntity<UpdateValue>>> {
     if body.len() > limits.max_section_bytes {
         return Err(CodecError::LimitsExceeded {
             kind: LimitKind::SectionBytes,
@@ -1783,32 +2172,27 @@ fn decode_update_section_sparse_varint(
         });
     }
 
-    let mut updates: Vec<DeltaUpdateEntity> = Vec::new();
-    let mut prev_entity: Option<u32> = None;
+    let mut updates: Vec<DeltaUpdateEntity<UpdateValue>> = Vec::new();
+    let mut prev_entity: Option<u64> = None;
     let mut prev_component: Option<u16> = None;
+    let mut ids = SparseIdReader::new(schema.entity_id_width);
     for _ in 0..entry_count {
         reader.align_to_byte()?;
-        let entity_id = reader.read_u32_aligned()?;
+        let entity_id = ids.read(&mut reader)?;
         let component_raw = reader.read_u16_aligned()?;
         let component_id = ComponentId::new(component_raw).ok_or(CodecError::InvalidMask {
             kind: MaskKind::ComponentMask,
             reason: MaskReason::InvalidComponentId { raw: component_raw },
         })?;
-        if let Some(prev) = prev_entity {
-            if entity_id < prev {
-                return Err(CodecError::InvalidEntityOrder {
-                    previous: prev,
-                    current: entity_id,
-                });
-            }
-            if entity_id == prev {
-                if let Some(prev_component) = prev_component {
-                    if component_raw <= prev_component {
-                        return Err(CodecError::InvalidEntityOrder {
-                            previous: prev,
-                            current: entity_id,
-                        });
-                    }
+        // Gap coding keeps entity ids non-decreasing; components of one
+        // entity must still be strictly increasing.
+        if prev_entity == Some(entity_id) {
+            if let Some(prev_component) = prev_component {
+                if component_raw <= prev_component {
+                    return Err(CodecError::InvalidEntityOrder {
+                        previous: entity_id,
+                        current: entity_id,
+                    });
                 }
             }
         }
@@ -1887,7 +2271,8 @@ fn decode_update_section_sparse_varint(
             }
             prev_index = Some(field_index);
             let field = component.fields[field_index];
-            let value = read_field_value_sparse(component.id, field, &mut reader)?;
+            let value =
+                read_update_value(component.id, field, &mut reader, read_field_value_sparse)?;
             fields.push((field_index, value));
         }
 
@@ -1901,13 +2286,13 @@ fn decode_update_section_sparse_varint(
             }
         }
 
-        updates.push(DeltaUpdateEntity {
-            id: EntityId::new(entity_id),
-            components: vec![DeltaUpdateComponent {
+        updates.push(DeltaUpdateEntity::new(
+            EntityId::new(entity_id),
+            vec![DeltaUpdateComponent {
                 id: component.id,
                 fields,
             }],
-        });
+        ));
     }
 
     reader.align_to_byte()?;
@@ -1934,7 +2319,7 @@ fn decode_update_section_sparse_packed(
     schema: &schema::Schema,
     body: &[u8],
     limits: &CodecLimits,
-) -> CodecResult<Vec<DeltaUpdateEntity>> {
+) -> CodecResult<Vec<DeltaUpdateEntity<UpdateValue>>> {
     if body.len() > limits.max_section_bytes {
         return Err(CodecError::LimitsExceeded {
             kind: LimitKind::SectionBytes,
@@ -1957,12 +2342,13 @@ fn decode_update_section_sparse_packed(
         });
     }
 
-    let mut updates: Vec<DeltaUpdateEntity> = Vec::new();
-    let mut prev_entity: Option<u32> = None;
+    let mut updates: Vec<DeltaUpdateEntity<UpdateValue>> = Vec::new();
+    let mut prev_entity: Option<u64> = None;
     let mut prev_component: Option<u16> = None;
+    let mut ids = SparseIdReader::new(schema.entity_id_width);
     for _ in 0..entry_count {
         reader.align_to_byte()?;
-        let entity_id = reader.read_varu32()?;
+        let entity_id = ids.read(&mut reader)?;
         let component_raw = reader.read_varu32()?;
         if component_raw > u16::MAX as u32 {
             return Err(CodecError::InvalidMask {
@@ -1975,21 +2361,15 @@ fn decode_update_section_sparse_packed(
             kind: MaskKind::ComponentMask,
             reason: MaskReason::InvalidComponentId { raw: component_raw },
         })?;
-        if let Some(prev) = prev_entity {
-            if entity_id < prev {
-                return Err(CodecError::InvalidEntityOrder {
-                    previous: prev,
-                    current: entity_id,
-                });
-            }
-            if entity_id == prev {
-                if let Some(prev_component) = prev_component {
-                    if component_raw <= prev_component {
-                        return Err(CodecError::InvalidEntityOrder {
-                            previous: prev,
-                            current: entity_id,
-                        });
-                    }
+        // Gap coding keeps entity ids non-decreasing; components of one
+        // entity must still be strictly increasing.
+        if prev_entity == Some(entity_id) {
+            if let Some(prev_component) = prev_component {
+                if component_raw <= prev_component {
+                    return Err(CodecError::InvalidEntityOrder {
+                        previous: entity_id,
+                        current: entity_id,
+                    });
                 }
             }
         }
@@ -2072,7 +2452,8 @@ fn decode_update_section_sparse_packed(
             }
             prev_index = Some(field_index);
             let field = component.fields[field_index];
-            let value = read_field_value_sparse(component.id, field, &mut reader)?;
+            let value =
+                read_update_value(component.id, field, &mut reader, read_field_value_sparse)?;
             fields.push((field_index, value));
         }
 
@@ -2086,13 +2467,13 @@ fn decode_update_section_sparse_packed(
             }
         }
 
-        updates.push(DeltaUpdateEntity {
-            id: EntityId::new(entity_id),
-            components: vec![DeltaUpdateComponent {
+        updates.push(DeltaUpdateEntity::new(
+            EntityId::new(entity_id),
+            vec![DeltaUpdateComponent {
                 id: component.id,
                 fields,
             }],
-        });
+        ));
     }
 
     reader.align_to_byte()?;
@@ -2115,25 +2496,176 @@ fn decode_update_section_sparse_packed(
     Ok(updates)
 }
 
-fn decode_delta_sections(
+fn decode_component_ops_section(
     schema: &schema::Schema,
-    packet: &WirePacket<'_>,
+    body: &[u8],
     limits: &CodecLimits,
-) -> CodecResult<(Vec<EntityId>, Vec<EntitySnapshot>, Vec<DeltaUpdateEntity>)> {
-    let mut destroys: Option<Vec<EntityId>> = None;
-    let mut creates: Option<Vec<EntitySnapshot>> = None;
-    let mut updates_masked: Option<Vec<DeltaUpdateEntity>> = None;
-    let mut updates_sparse: Option<Vec<DeltaUpdateEntity>> = None;
-
-    for section in &packet.sections {
-        match section.tag {
-            SectionTag::EntityDestroy => {
-                if destroys.is_some() {
+) -> CodecResult<Vec<DeltaUpdateEntity<UpdateValue>>> {
+    if body.len() > limits.max_section_bytes {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::SectionBytes,
+            limit: limits.max_section_bytes,
+            actual: body.len(),
+        });
+    }
+
+    let mut reader = BitReader::new(body);
+    reader.align_to_byte()?;
+    let count = reader.read_varu32()? as usize;
+    if count > limits.max_entities_update {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::EntitiesUpdate,
+            limit: limits.max_entities_update,
+            actual: count,
+        });
+    }
+
+    let lookup = build_component_lookup(schema);
+    let mut entities = Vec::with_capacity(count);
+    let mut prev: Option<u64> = None;
+    let mut ids = SparseIdReader::new(schema.entity_id_width);
+    for _ in 0..count {
+        reader.align_to_byte()?;
+        let entity_id = ids.read(&mut reader)?;
+        if let Some(prev_id) = prev {
+            if entity_id <= prev_id {
+                return Err(CodecError::InvalidEntityOrder {
+                    previous: prev_id,
+                    current: entity_id,
+                });
+            }
+        }
+        prev = Some(entity_id);
+
+        let removed_count = reader.read_varu32()? as usize;
+        if removed_count > limits.max_components_per_entity {
+            return Err(CodecError::LimitsExceeded {
+                kind: LimitKind::ComponentsPerEntity,
+                limit: limits.max_components_per_entity,
+                actual: removed_count,
+            });
+        }
+        let mut removed = Vec::with_capacity(removed_count);
+        for _ in 0..removed_count {
+            removed.push(read_component_id(&mut reader)?);
+        }
+        let inserted_count = reader.read_varu32()? as usize;
+        if removed_count + inserted_count > limits.max_components_per_entity {
+            return Err(CodecError::LimitsExceeded {
+                kind: LimitKind::ComponentsPerEntity,
+                limit: limits.max_components_per_entity,
+                actual: removed_count + inserted_count,
+            });
+        }
+        let mut inserted = Vec::with_capacity(inserted_count);
+        for _ in 0..inserted_count {
+            let id = read_component_id(&mut reader)?;
+            let def = lookup.component(schema, id)?;
+            inserted.push(ComponentSnapshot {
+                id,
+                fields: decode_full_component(def, &mut reader, limits)?,
+            });
+        }
+
+        let entity = DeltaUpdateEntity {
+            id: EntityId::new(entity_id),
+            components: Vec::new(),
+            inserted,
+            removed,
+        };
+        validate_component_ops(schema, &entity, &lookup)?;
+        entities.push(entity);
+    }
+
+    reader.align_to_byte()?;
+    if reader.bits_remaining() != 0 {
+        return Err(CodecError::TrailingSectionData {
+            section: SectionTag::EntityComponentOps,
+            remaining_bits: reader.bits_remaining(),
+        });
+    }
+
+    Ok(entities)
+}
+
+fn read_component_id(reader: &mut BitReader<'_>) -> CodecResult<ComponentId> {
+    let raw = reader.read_varu32()?;
+    let raw = u16::try_from(raw).map_err(|_| CodecError::InvalidMask {
+        kind: MaskKind::ComponentMask,
+        reason: MaskReason::InvalidComponentId { raw: u16::MAX },
+    })?;
+    ComponentId::new(raw).ok_or(CodecError::InvalidMask {
+        kind: MaskKind::ComponentMask,
+        reason: MaskReason::InvalidComponentId { raw },
+    })
+}
+
+/// Folds decoded component ops into the field updates of the same entities.
+/// Both lists are sorted by entity id.
+fn merge_component_ops(
+    updates: Vec<DeltaUpdateEntity<UpdateValue>>,
+    ops: Vec<DeltaUpdateEntity<UpdateValue>>,
+) -> Vec<DeltaUpdateEntity<UpdateValue>> {
+    if ops.is_empty() {
+        return updates;
+    }
+    let mut merged = Vec::with_capacity(updates.len() + ops.len());
+    let mut updates = updates.into_iter().peekable();
+    let mut ops = ops.into_iter().peekable();
+    loop {
+        let next = match (updates.peek(), ops.peek()) {
+            (Some(update), Some(op)) => match update.id.index().cmp(&op.id.index()) {
+                Ordering::Less => updates.next(),
+                Ordering::Greater => ops.next(),
+                Ordering::Equal => {
+                    let op = ops.next().expect("peeked");
+                    updates.next().map(|update| DeltaUpdateEntity {
+                        inserted: op.inserted,
+                        removed: op.removed,
+                        ..update
+                    })
+                }
+            },
+            (Some(_), None) => updates.next(),
+            (None, Some(_)) => ops.next(),
+            (None, None) => break,
+        };
+        merged.extend(next);
+    }
+    merged
+}
+
+/// Destroys, creates and updates decoded from a delta's sections.
+type DecodedSections = (
+    Vec<EntityId>,
+    Vec<EntitySnapshot>,
+    Vec<DeltaUpdateEntity<UpdateValue>>,
+);
+
+pub(crate) fn decode_delta_sections(
+    schema: &schema::Schema,
+    packet: &WirePacket<'_>,
+    limits: &CodecLimits,
+) -> CodecResult<DecodedSections> {
+    let mut destroys: Option<Vec<EntityId>> = None;
+    let mut creates: Option<Vec<EntitySnapshot>> = None;
+    let mut updates_masked: Option<Vec<DeltaUpdateEntity<UpdateValue>>> = None;
+    let mut updates_sparse: Option<Vec<DeltaUpdateEntity<UpdateValue>>> = None;
+    let mut component_ops: Option<Vec<DeltaUpdateEntity<UpdateValue>>> = None;
+
+    for section in &packet.sections {
+        match section.tag {
+            SectionTag::EntityDestroy => {
+                if destroys.is_some() {
                     return Err(CodecError::DuplicateSection {
                         section: section.tag,
                     });
                 }
-                destroys = Some(decode_destroy_section(section.body, limits)?);
+                destroys = Some(decode_destroy_section(
+                    section.body,
+                    schema.entity_id_width,
+                    limits,
+                )?);
             }
             SectionTag::EntityCreate => {
                 if creates.is_some() {
@@ -2175,6 +2707,34 @@ fn decode_delta_sections(
                     limits,
                 )?);
             }
+            SectionTag::EntityUpdateCoded => {
+                if updates_masked.is_some() {
+                    return Err(CodecError::DuplicateSection {
+                        section: section.tag,
+                    });
+                }
+                let body = decode_coded_body(section.body, limits)?;
+                updates_masked = Some(decode_update_section_masked(schema, &body, limits)?);
+            }
+            SectionTag::EntityUpdateSparsePackedCoded => {
+                if updates_sparse.is_some() {
+                    return Err(CodecError::DuplicateSection {
+                        section: section.tag,
+                    });
+                }
+                let body = decode_coded_body(section.body, limits)?;
+                updates_sparse = Some(decode_update_section_sparse_packed(schema, &body, limits)?);
+            }
+            SectionTag::EntityComponentOps => {
+                if component_ops.is_some() {
+                    return Err(CodecError::DuplicateSection {
+                        section: section.tag,
+                    });
+                }
+                component_ops = Some(decode_component_ops_section(schema, section.body, limits)?);
+            }
+            // Events ride alongside state and are decoded by `decode_events`.
+            SectionTag::Events => {}
             _ => {
                 return Err(CodecError::UnexpectedSection {
                     section: section.tag,
@@ -2183,15 +2743,16 @@ fn decode_delta_sections(
         }
     }
 
+    let updates = match (updates_masked, updates_sparse) {
+        (Some(_), Some(_)) => return Err(CodecError::DuplicateUpdateEncoding),
+        (Some(updates), None) => updates,
+        (None, Some(updates)) => updates,
+        (None, None) => Vec::new(),
+    };
     Ok((
         destroys.unwrap_or_default(),
         creates.unwrap_or_default(),
-        match (updates_masked, updates_sparse) {
-            (Some(_), Some(_)) => return Err(CodecError::DuplicateUpdateEncoding),
-            (Some(updates), None) => updates,
-            (None, Some(updates)) => updates,
-            (None, None) => Vec::new(),
-        },
+        merge_component_ops(updates, component_ops.unwrap_or_default()),
     ))
 }
 
@@ -2201,22 +2762,51 @@ pub struct DeltaDecoded {
     pub baseline_tick: SnapshotTick,
     pub destroys: Vec<EntityId>,
     pub creates: Vec<EntitySnapshot>,
-    pub updates: Vec<DeltaUpdateEntity>,
+    /// Updates as sent; fields with a residual `DeltaMode` may hold residuals
+    /// that only resolve against the baseline.
+    pub updates: Vec<DeltaUpdateEntity<UpdateValue>>,
 }
 
+/// Changes to an existing entity.
+///
+/// `V` is the field value type: [`FieldValue`] for updates built by callers,
+/// [`UpdateValue`] for updates decoded from the wire.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct DeltaUpdateEntity {
+pub struct DeltaUpdateEntity<V = FieldValue> {
     pub id: EntityId,
-    pub components: Vec<DeltaUpdateComponent>,
+    pub components: Vec<DeltaUpdateComponent<V>>,
+    /// Components added to the entity with their full state, in schema order.
+    pub inserted: Vec<ComponentSnapshot>,
+    /// Components removed from the entity, in schema order.
+    pub removed: Vec<ComponentId>,
+}
+
+impl<V> DeltaUpdateEntity<V> {
+    /// Creates an update that only changes fields of existing components.
+    #[must_use]
+    pub fn new(id: EntityId, components: Vec<DeltaUpdateComponent<V>>) -> Self {
+        Self {
+            id,
+            components,
+            inserted: Vec::new(),
+            removed: Vec::new(),
+        }
+    }
+
+    /// Returns `true` if the update inserts or removes components.
+    #[must_use]
+    pub fn has_component_ops(&self) -> bool {
+        !self.inserted.is_empty() || !self.removed.is_empty()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct DeltaUpdateComponent {
+pub struct DeltaUpdateComponent<V = FieldValue> {
     pub id: ComponentId,
-    pub fields: Vec<(usize, FieldValue)>,
+    pub fields: Vec<(usize, V)>,
 }
 
-fn apply_destroys(
+pub(crate) fn apply_destroys(
     baseline: &[EntitySnapshot],
     destroys: &[EntityId],
 ) -> CodecResult<Vec<EntitySnapshot>> {
@@ -2228,12 +2818,13 @@ fn apply_destroys(
         let destroy = destroys.get(j);
         match (base, destroy) {
             (Some(b), Some(d)) => {
-                if b.id.raw() < d.raw() {
+                if b.id.index() < d.index() {
                     result.push(b.clone());
                     i += 1;
-                } else if b.id.raw() > d.raw() {
+                } else if b.id.index() > d.index() {
                     return Err(CodecError::EntityNotFound { entity_id: d.raw() });
                 } else {
+                    ensure_live_generation(b.id, *d)?;
                     i += 1;
                     j += 1;
                 }
@@ -2251,7 +2842,19 @@ fn apply_destroys(
     Ok(result)
 }
 
-fn apply_creates(
+/// Rejects a reference to `live.index()` that names another generation.
+pub(crate) fn ensure_live_generation(live: EntityId, reference: EntityId) -> CodecResult<()> {
+    if live.generation() != reference.generation() {
+        return Err(CodecError::StaleEntity {
+            entity_id: live.index(),
+            expected_generation: live.generation(),
+            found_generation: reference.generation(),
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn apply_creates(
     baseline: Vec<EntitySnapshot>,
     creates: Vec<EntitySnapshot>,
 ) -> CodecResult<Vec<EntitySnapshot>> {
@@ -2263,10 +2866,10 @@ fn apply_creates(
         let create = creates.get(j);
         match (base, create) {
             (Some(b), Some(c)) => {
-                if b.id.raw() < c.id.raw() {
+                if b.id.index() < c.id.index() {
                     result.push(b.clone());
                     i += 1;
-                } else if b.id.raw() > c.id.raw() {
+                } else if b.id.index() > c.id.index() {
                     result.push(c.clone());
                     j += 1;
                 } else {
@@ -2289,17 +2892,30 @@ fn apply_creates(
     Ok(result)
 }
 
-fn apply_updates(
+/// Applies decoded updates in place.
+///
+/// `dt` returns the tick distance from the packet to each entity's baseline,
+/// used to resolve predicted residuals. Residuals are resolved before any
+/// absolute value is written so predictions read baseline velocities.
+pub(crate) fn apply_updates<F>(
+    schema: &schema::Schema,
     entities: &mut [EntitySnapshot],
-    updates: &[DeltaUpdateEntity],
-) -> CodecResult<()> {
+    updates: &[DeltaUpdateEntity<UpdateValue>],
+    dt: F,
+) -> CodecResult<()>
+where
+    F: Fn(EntityId) -> u32,
+{
     for update in updates {
         let idx = entities
-            .binary_search_by_key(&update.id.raw(), |e| e.id.raw())
+            .binary_search_by_key(&update.id.index(), |e| e.id.index())
             .map_err(|_| CodecError::EntityNotFound {
                 entity_id: update.id.raw(),
             })?;
         let entity = &mut entities[idx];
+        if update.has_component_ops() {
+            apply_component_ops(schema, entity, update)?;
+        }
         for component_update in &update.components {
             let component = entity
                 .components
@@ -2309,7 +2925,7 @@ fn apply_updates(
                     entity_id: update.id.raw(),
                     component_id: component_update.id.get(),
                 })?;
-            for (field_idx, value) in &component_update.fields {
+            for (field_idx, _) in &component_update.fields {
                 if *field_idx >= component.fields.len() {
                     return Err(CodecError::InvalidMask {
                         kind: MaskKind::FieldMask {
@@ -2321,16 +2937,82 @@ fn apply_updates(
                         },
                     });
                 }
-                component.fields[*field_idx] = *value;
+            }
+            let mut component_def = None;
+            for (field_idx, value) in &component_update.fields {
+                if let UpdateValue::Residual(residual) = *value {
+                    let def = match component_def {
+                        Some(def) => def,
+                        None => {
+                            let def = find_component_def(schema, component_update.id)?;
+                            component_def = Some(def);
+                            def
+                        }
+                    };
+                    component.fields[*field_idx] = resolve_residual(
+                        def,
+                        *field_idx,
+                        &component.fields,
+                        residual,
+                        dt(update.id),
+                    )?;
+                }
+            }
+            for (field_idx, value) in &component_update.fields {
+                if let UpdateValue::Value(value) = *value {
+                    component.fields[*field_idx] = value;
+                }
             }
         }
     }
     Ok(())
 }
 
-fn ensure_entities_sorted(entities: &[EntitySnapshot]) -> CodecResult<()> {
-    let mut prev: Option<u32> = None;
-    for entity in entities {
+/// Removes and inserts components, keeping the entity's components in schema
+/// order.
+fn apply_component_ops<V>(
+    schema: &schema::Schema,
+    entity: &mut EntitySnapshot,
+    update: &DeltaUpdateEntity<V>,
+) -> CodecResult<()> {
+    for id in &update.removed {
+        let idx = entity
+            .components
+            .iter()
+            .position(|component| component.id == *id)
+            .ok_or_else(|| CodecError::ComponentNotFound {
+                entity_id: update.id.raw(),
+                component_id: id.get(),
+            })?;
+        entity.components.remove(idx);
+    }
+    let position = |id: ComponentId| {
+        schema
+            .components
+            .iter()
+            .position(|component| component.id == id)
+    };
+    for component in &update.inserted {
+        if entity.component(component.id).is_some() {
+            return Err(CodecError::ComponentAlreadyExists {
+                entity_id: update.id.raw(),
+                component_id: component.id.get(),
+            });
+        }
+        let order = position(component.id);
+        let idx = entity
+            .components
+            .iter()
+            .position(|existing| position(existing.id) > order)
+            .unwrap_or(entity.components.len());
+        entity.components.insert(idx, component.clone());
+    }
+    Ok(())
+}
+
+pub(crate) fn ensure_entities_sorted<E: Borrow<EntitySnapshot>>(entities: &[E]) -> CodecResult<()> {
+    let mut prev: Option<u64> = None;
+    for entity in entities.iter().map(Borrow::borrow) {
         if let Some(prev_id) = prev {
             if entity.id.raw() <= prev_id {
                 return Err(CodecError::InvalidEntityOrder {
@@ -2350,7 +3032,7 @@ fn write_component_mask(
     writer: &mut BitWriter<'_>,
 ) -> CodecResult<()> {
     for component in &schema.components {
-        let present = find_component(entity, component.id).is_some();
+        let present = entity.component(component.id).is_some();
         writer.write_bit(present)?;
     }
     Ok(())
@@ -2429,6 +3111,7 @@ fn write_update_components(
     schema: &schema::Schema,
     baseline: &EntitySnapshot,
     current: &EntitySnapshot,
+    dt: u32,
     limits: &CodecLimits,
     scratch: &mut CodecScratch,
     writer: &mut BitWriter<'_>,
@@ -2437,16 +3120,8 @@ fn write_update_components(
     let (component_changed, _) = scratch.component_and_field_masks_mut(component_count, 0);
     component_changed.fill(false);
     for (idx, component) in schema.components.iter().enumerate() {
-        let base = find_component(baseline, component.id);
-        let curr = find_component(current, component.id);
-        if base.is_some() != curr.is_some() {
-            return Err(CodecError::InvalidMask {
-                kind: MaskKind::ComponentMask,
-                reason: MaskReason::ComponentPresenceMismatch {
-                    component: component.id,
-                },
-            });
-        }
+        let base = baseline.component(component.id);
+        let curr = current.component(component.id);
         if let (Some(base), Some(curr)) = (base, curr) {
             if base.fields.len() != component.fields.len()
                 || curr.fields.len() != component.fields.len()
@@ -2484,8 +3159,8 @@ fn write_update_components(
 
     for (idx, component) in schema.components.iter().enumerate() {
         let (base, curr) = match (
-            find_component(baseline, component.id),
-            find_component(current, component.id),
+            baseline.component(component.id),
+            current.component(component.id),
         ) {
             (Some(base), Some(curr)) => (base, curr),
             _ => continue,
@@ -2504,15 +3179,17 @@ fn write_update_components(
             for bit in field_mask {
                 writer.write_bit(*bit)?;
             }
-            for (((field, _base_val), curr_val), changed) in component
+            for (field_idx, ((field, curr_val), changed)) in component
                 .fields
                 .iter()
-                .zip(base.fields.iter())
                 .zip(curr.fields.iter())
                 .zip(field_mask.iter())
+                .enumerate()
             {
                 if *changed {
-                    write_field_value(component.id, *field, *curr_val, writer)?;
+                    let value =
+                        encode_update_value(component, field_idx, &base.fields, *curr_val, dt);
+                    write_update_value(component.id, *field, value, writer, write_field_value)?;
                 }
             }
         }
@@ -2524,7 +3201,7 @@ fn decode_update_component(
     component: &ComponentDef,
     reader: &mut BitReader<'_>,
     limits: &CodecLimits,
-) -> CodecResult<Vec<(usize, FieldValue)>> {
+) -> CodecResult<Vec<(usize, UpdateValue)>> {
     if component.fields.len() > limits.max_fields_per_component {
         return Err(CodecError::LimitsExceeded {
             kind: LimitKind::FieldsPerComponent,
@@ -2552,7 +3229,7 @@ fn decode_update_component(
     let mut fields = Vec::new();
     for (idx, field) in component.fields.iter().enumerate() {
         if mask[idx] {
-            let value = read_field_value(component.id, *field, reader)?;
+            let value = read_update_value(component.id, *field, reader, read_field_value)?;
             fields.push((idx, value));
         }
     }
@@ -2651,10 +3328,9 @@ fn entity_has_updates(
     current: &EntitySnapshot,
     limits: &CodecLimits,
 ) -> CodecResult<bool> {
-    ensure_component_presence_matches(schema, baseline, current)?;
     for component in &schema.components {
-        let base = find_component(baseline, component.id);
-        let curr = find_component(current, component.id);
+        let base = baseline.component(component.id);
+        let curr = current.component(component.id);
         if let (Some(base), Some(curr)) = (base, curr) {
             if base.fields.len() != component.fields.len()
                 || curr.fields.len() != component.fields.len()
@@ -2691,51 +3367,91 @@ fn entity_has_updates(
     Ok(false)
 }
 
-fn ensure_component_presence_matches(
+/// Returns the sparse field changes from `baseline` to `current`, if any.
+///
+/// Predicted fields carry residuals against `baseline` advanced by `dt` ticks.
+pub(crate) fn diff_entity(
     schema: &schema::Schema,
     baseline: &EntitySnapshot,
     current: &EntitySnapshot,
-) -> CodecResult<()> {
-    // In this version, component presence is stable across an entity's lifetime.
+    dt: u32,
+    limits: &CodecLimits,
+) -> CodecResult<Option<DeltaUpdateEntity<UpdateValue>>> {
+    let has_updates = entity_has_updates(schema, baseline, current, limits)?;
+    let ops = component_ops(schema, baseline, current);
+    if !has_updates && ops.is_none() {
+        return Ok(None);
+    }
+    let mut components = Vec::new();
     for component in &schema.components {
-        let base = find_component(baseline, component.id).is_some();
-        let curr = find_component(current, component.id).is_some();
-        if base != curr {
-            return Err(CodecError::InvalidMask {
-                kind: MaskKind::ComponentMask,
-                reason: MaskReason::ComponentPresenceMismatch {
-                    component: component.id,
-                },
+        let (Some(base), Some(curr)) = (
+            baseline.component(component.id),
+            current.component(component.id),
+        ) else {
+            continue;
+        };
+        let mut fields = Vec::new();
+        for (idx, ((field, base_val), curr_val)) in component
+            .fields
+            .iter()
+            .zip(base.fields.iter())
+            .zip(curr.fields.iter())
+            .enumerate()
+        {
+            if field_changed(component.id, *field, *base_val, *curr_val)? {
+                let value = encode_update_value(component, idx, &base.fields, *curr_val, dt);
+                fields.push((idx, value));
+            }
+        }
+        if !fields.is_empty() {
+            components.push(DeltaUpdateComponent {
+                id: component.id,
+                fields,
             });
         }
     }
-    Ok(())
-}
-
-fn find_component(entity: &EntitySnapshot, id: ComponentId) -> Option<&ComponentSnapshot> {
-    entity.components.iter().find(|c| c.id == id)
+    let (inserted, removed) = ops.unwrap_or_default();
+    Ok(Some(DeltaUpdateEntity {
+        id: current.id,
+        components,
+        inserted,
+        removed,
+    }))
 }
 
-fn codec_name(codec: schema::FieldCodec) -> &'static str {
-    match codec {
-        schema::FieldCodec::Bool => "bool",
-        schema::FieldCodec::UInt { .. } => "uint",
-        schema::FieldCodec::SInt { .. } => "sint",
-        schema::FieldCodec::VarUInt => "varuint",
-        schema::FieldCodec::VarSInt => "varsint",
-        schema::FieldCodec::FixedPoint(_) => "fixed-point",
+/// Returns the components `current` gained and lost relative to `baseline`,
+/// both in schema order, or `None` if the component set is unchanged.
+pub(crate) fn component_ops(
+    schema: &schema::Schema,
+    baseline: &EntitySnapshot,
+    current: &EntitySnapshot,
+) -> Option<(Vec<ComponentSnapshot>, Vec<ComponentId>)> {
+    if !has_component_ops(schema, baseline, current) {
+        return None;
     }
+    let mut inserted = Vec::new();
+    let mut removed = Vec::new();
+    for component in &schema.components {
+        match (
+            baseline.component(component.id),
+            current.component(component.id),
+        ) {
+            (None, Some(curr)) => inserted.push(curr.clone()),
+            (Some(_), None) => removed.push(component.id),
+            _ => {}
+        }
+    }
+    Some((inserted, removed))
 }
 
-fn value_name(value: FieldValue) -> &'static str {
-    match value {
-        FieldValue::Bool(_) => "bool",
-        FieldValue::UInt(_) => "uint",
-        FieldValue::SInt(_) => "sint",
-        FieldValue::VarUInt(_) => "varuint",
-        FieldValue::VarSInt(_) => "varsint",
-        FieldValue::FixedPoint(_) => "fixed-point",
-    }
+fn has_component_ops(
+    schema: &schema::Schema,
+    baseline: &EntitySnapshot,
+    current: &EntitySnapshot,
+) -> bool {
+    schema.components.iter().any(|component| {
+        baseline.component(component.id).is_some() != current.component(component.id).is_some()
+    })
 }
 
 #[cfg(test)]
@@ -2955,6 +3671,203 @@ mod tests {
         assert!(matches!(err, CodecError::DuplicateUpdateEncoding));
     }
 
+    #[test]
+    fn sequential_ids_are_gap_coded() {
+        let schema = schema_one_bool();
+        let entity = |id: u32, value: bool| EntitySnapshot {
+            id: EntityId::from(id),
+            components: vec![ComponentSnapshot {
+                id: ComponentId::new(1).unwrap(),
+                fields: vec![FieldValue::Bool(value)],
+            }],
+        };
+        let baseline = Snapshot {
+            tick: SnapshotTick::new(10),
+            entities: (1000..1064).map(|id| entity(id, false)).collect(),
+        };
+        let current = Snapshot {
+            tick: SnapshotTick::new(11),
+            entities: (1000..1032)
+                .map(|id| entity(id, true))
+                .chain((2000..2032).map(|id| entity(id, true)))
+                .collect(),
+        };
+
+        let mut buf = [0u8; 512];
+        let bytes = encode_delta_snapshot(
+            &schema,
+            current.tick,
+            baseline.tick,
+            &baseline,
+            &current,
+            &CodecLimits::for_testing(),
+            &mut buf,
+        )
+        .unwrap();
+        let packet = decode_packet(&buf[..bytes], &wire::Limits::for_testing()).unwrap();
+        let destroy = packet
+            .sections
+            .iter()
+            .find(|section| section.tag == SectionTag::EntityDestroy)
+            .unwrap();
+        // count, first_id (2 bytes), gap_bits = 0, and no per-entity bits.
+        assert_eq!(destroy.body, &[32, 0x88, 0x08, 0]);
+
+        let applied = apply_delta_snapshot(
+            &schema,
+            &baseline,
+            &buf[..bytes],
+            &wire::Limits::for_testing(),
+            &CodecLimits::for_testing(),
+        )
+        .unwrap();
+        assert_eq!(applied.entities, current.entities);
+    }
+
+    #[test]
+    fn entity_id_width_bounds_ids() {
+        let entity = |id: u64| EntitySnapshot {
+            id: EntityId::new(id),
+            components: vec![ComponentSnapshot {
+                id: ComponentId::new(1).unwrap(),
+                fields: vec![FieldValue::Bool(true)],
+            }],
+        };
+        let baseline = Snapshot {
+            tick: SnapshotTick::new(10),
+            entities: Vec::new(),
+        };
+        let current = Snapshot {
+            tick: SnapshotTick::new(11),
+            entities: vec![entity(7), entity(1 << 48)],
+        };
+        let encode = |schema: &Schema, buf: &mut [u8]| {
+            encode_delta_snapshot(
+                schema,
+                current.tick,
+                baseline.tick,
+                &baseline,
+                &current,
+                &CodecLimits::for_testing(),
+                buf,
+            )
+        };
+
+        let wide = schema_one_bool().with_entity_id_width(EntityIdWidth::U64);
+        let mut buf = [0u8; 128];
+        let bytes = encode(&wide, &mut buf).unwrap();
+        let applied = apply_delta_snapshot(
+            &wide,
+            &baseline,
+            &buf[..bytes],
+            &wire::Limits::for_testing(),
+            &CodecLimits::for_testing(),
+        )
+        .unwrap();
+        assert_eq!(applied.entities, current.entities);
+
+        let narrow = schema_one_bool().with_entity_id_width(EntityIdWidth::U16);
+        assert!(matches!(
+            encode(&narrow, &mut buf),
+            Err(CodecError::EntityIdOutOfRange {
+                entity_id: 0x1_0000_0000_0000,
+                bits: 16,
+            })
+        ));
+    }
+
+    #[test]
+    fn reused_index_roundtrips_as_destroy_and_create() {
+        let schema = schema_one_bool();
+        let entity = |id: EntityId, value: bool| EntitySnapshot {
+            id,
+            components: vec![ComponentSnapshot {
+                id: ComponentId::new(1).unwrap(),
+                fields: vec![FieldValue::Bool(value)],
+            }],
+        };
+        let baseline = Snapshot {
+            tick: SnapshotTick::new(10),
+            entities: vec![
+                entity(EntityId::new(1), false),
+                entity(EntityId::new(2), false),
+            ],
+        };
+        let current = Snapshot {
+            tick: SnapshotTick::new(11),
+            entities: vec![
+                entity(EntityId::new(1), true),
+                entity(EntityId::with_generation(2, 1), false),
+            ],
+        };
+
+        let mut buf = [0u8; 256];
+        let bytes = encode_delta_snapshot(
+            &schema,
+            current.tick,
+            baseline.tick,
+            &baseline,
+            &current,
+            &CodecLimits::for_testing(),
+            &mut buf,
+        )
+        .unwrap();
+        let packet = decode_packet(&buf[..bytes], &wire::Limits::for_testing()).unwrap();
+        let decoded = decode_delta_packet(&schema, &packet, &CodecLimits::for_testing()).unwrap();
+        assert_eq!(decoded.destroys, vec![EntityId::new(2)]);
+        assert_eq!(decoded.creates[0].id, EntityId::with_generation(2, 1));
+
+        let applied = apply_delta_snapshot(
+            &schema,
+            &baseline,
+            &buf[..bytes],
+            &wire::Limits::for_testing(),
+            &CodecLimits::for_testing(),
+        )
+        .unwrap();
+        assert_eq!(applied.entities, current.entities);
+    }
+
+    #[test]
+    fn stale_destroy_is_rejected() {
+        let schema = schema_one_bool();
+        let baseline = baseline_snapshot();
+        let mut reused = baseline.clone();
+        reused.entities[0].id = EntityId::with_generation(1, 1);
+        let current = Snapshot {
+            tick: SnapshotTick::new(11),
+            entities: Vec::new(),
+        };
+
+        let mut buf = [0u8; 128];
+        let bytes = encode_delta_snapshot(
+            &schema,
+            current.tick,
+            baseline.tick,
+            &baseline,
+            &current,
+            &CodecLimits::for_testing(),
+            &mut buf,
+        )
+        .unwrap();
+        let err = apply_delta_snapshot(
+            &schema,
+            &reused,
+            &buf[..bytes],
+            &wire::Limits::for_testing(),
+            &CodecLimits::for_testing(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            CodecError::StaleEntity {
+                entity_id: 1,
+                expected_generation: 1,
+                found_generation: 0,
+            }
+        ));
+    }
+
     #[test]
     fn delta_roundtrip_create_destroy_update() {
         let schema = schema_one_bool();
@@ -3129,6 +4042,218 @@ mod tests {
         assert_eq!(applied.entities, current.entities);
     }
 
+    fn bool_component(id: u16, value: bool) -> ComponentSnapshot {
+        ComponentSnapshot {
+            id: ComponentId::new(id).unwrap(),
+            fields: vec![FieldValue::Bool(value)],
+        }
+    }
+
+    #[test]
+    fn component_insert_and_remove_roundtrip() {
+        let schema = schema_two_components();
+        let entity = |id: u64, components| EntitySnapshot {
+            id: EntityId::new(id),
+            components,
+        };
+        let baseline = Snapshot {
+            tick: SnapshotTick::new(10),
+            entities: vec![
+                entity(1, vec![bool_component(1, false)]),
+                entity(2, vec![bool_component(1, false), bool_component(2, true)]),
+                entity(3, vec![bool_component(2, false)]),
+            ],
+        };
+        let current = Snapshot {
+            tick: SnapshotTick::new(11),
+            entities: vec![
+                entity(1, vec![bool_component(1, true), bool_component(2, true)]),
+                entity(2, vec![bool_component(1, false)]),
+                entity(3, vec![bool_component(1, true)]),
+            ],
+        };
+
+        let mut buf = [0u8; 256];
+        let bytes = encode_delta_snapshot(
+            &schema,
+            current.tick,
+            baseline.tick,
+            &baseline,
+            &current,
+            &CodecLimits::for_testing(),
+            &mut buf,
+        )
+        .unwrap();
+        let packet = decode_packet(&buf[..bytes], &wire::Limits::for_testing()).unwrap();
+        let decoded = decode_delta_packet(&schema, &packet, &CodecLimits::for_testing()).unwrap();
+        assert!(decoded.destroys.is_empty() && decoded.creates.is_empty());
+        assert_eq!(decoded.updates.len(), 3);
+        assert_eq!(decoded.updates[0].inserted, vec![bool_component(2, true)]);
+        assert_eq!(decoded.updates[0].components.len(), 1);
+        assert_eq!(
+            decoded.updates[1].removed,
+            vec![ComponentId::new(2).unwrap()]
+        );
+        assert!(decoded.updates[1].components.is_empty());
+
+        let applied = apply_delta_snapshot_from_packet(
+            &schema,
+            &baseline,
+            &packet,
+            &CodecLimits::for_testing(),
+        )
+        .unwrap();
+        assert_eq!(applied.entities, current.entities);
+    }
+
+    #[test]
+    fn component_ops_from_updates_roundtrip() {
+        let schema = schema_two_components();
+        let baseline = baseline_snapshot();
+        let mut update = DeltaUpdateEntity::new(EntityId::new(1), Vec::new());
+        update.inserted.push(bool_component(2, true));
+
+        let mut buf = [0u8; 128];
+        let bytes = encode_delta_snapshot_from_updates(
+            &schema,
+            SnapshotTick::new(11),
+            baseline.tick,
+            &[],
+            &[],
+            std::slice::from_ref(&update),
+            &CodecLimits::for_testing(),
+            &mut buf,
+        )
+        .unwrap();
+        let packet = decode_packet(&buf[..bytes], &wire::Limits::for_testing()).unwrap();
+        assert_eq!(packet.sections.len(), 1);
+        assert_eq!(packet.sections[0].tag, SectionTag::EntityComponentOps);
+        let applied = apply_delta_snapshot_from_packet(
+            &schema,
+            &baseline,
+            &packet,
+            &CodecLimits::for_testing(),
+        )
+        .unwrap();
+        assert_eq!(
+            applied.entities[0].components,
+            vec![bool_component(1, false), bool_component(2, true)]
+        );
+
+        // Inserting the component again conflicts with the applied state.
+        let err = apply_delta_snapshot_from_packet(
+            &schema,
+            &applied,
+            &WirePacket {
+                header: wire::PacketHeader {
+                    baseline_tick: applied.tick.raw(),
+                    ..packet.header
+                },
+                sections: packet.sections.clone(),
+                skipped: Vec::new(),
+            },
+            &CodecLimits::for_testing(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            CodecError::ComponentAlreadyExists {
+                entity_id: 1,
+                component_id: 2,
+            }
+        ));
+    }
+
+    #[test]
+    fn component_ops_must_be_in_schema_order() {
+        let schema = schema_two_components();
+        let mut update = DeltaUpdateEntity::new(EntityId::new(1), Vec::new());
+        update.removed = vec![ComponentId::new(2).unwrap(), ComponentId::new(1).unwrap()];
+        let mut buf = [0u8; 128];
+        let result = encode_delta_snapshot_from_updates(
+            &schema,
+            SnapshotTick::new(11),
+            SnapshotTick::new(10),
+            &[],
+            &[],
+            &[update],
+            &CodecLimits::for_testing(),
+            &mut buf,
+        );
+        assert!(matches!(
+            result,
+            Err(CodecError::InvalidMask {
+                kind: MaskKind::ComponentMask,
+                reason: MaskReason::UnorderedComponent { .. },
+            })
+        ));
+    }
+
+    /// The v3 golden delta from `wire`'s packet tests.
+    const V3_GOLDEN_DELTA: [u8; 40] = [
+        0x43, 0x45, 0x44, 0x53, 0x03, 0x00, 0x02, 0x00, // magic, version, flags
+        0x15, 0xEE, 0x7B, 0x65, 0x24, 0xA2, 0xF5, 0x32, // schema_hash
+        0x0B, 0x00, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x00, // tick, baseline_tick
+        0x0C, 0x00, 0x00, 0x00, // payload_len
+        0x01, 0x04, 0x01, 0x02, 0x00, 0xE0, // ENTITY_CREATE
+        0x03, 0x04, 0x01, 0x01, 0x00, 0xE0, // ENTITY_UPDATE
+    ];
+
+    #[test]
+    fn v3_golden_delta_applies_with_and_without_optional_sections() {
+        let schema = schema_one_bool();
+        let baseline = baseline_snapshot();
+        let mut expected = baseline.clone();
+        expected.tick = SnapshotTick::new(11);
+        expected.entities[0].components[0].fields[0] = FieldValue::Bool(true);
+        expected.entities.push(EntitySnapshot {
+            id: EntityId::new(2),
+            components: vec![ComponentSnapshot {
+                id: ComponentId::new(1).unwrap(),
+                fields: vec![FieldValue::Bool(true)],
+            }],
+        });
+
+        let limits = CodecLimits::for_testing();
+        let mut buf = [0u8; 64];
+        let len = encode_delta_snapshot(
+            &schema,
+            expected.tick,
+            baseline.tick,
+            &baseline,
+            &expected,
+            &limits,
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(&buf[..len], V3_GOLDEN_DELTA.as_slice());
+
+        let applied = apply_delta_snapshot(
+            &schema,
+            &baseline,
+            &V3_GOLDEN_DELTA,
+            &wire::Limits::for_testing(),
+            &limits,
+        )
+        .unwrap();
+        assert_eq!(applied, expected);
+
+        // A later encoder's optional section between the two is skipped.
+        let mut later = V3_GOLDEN_DELTA[..wire::HEADER_SIZE + 6].to_vec();
+        later.extend_from_slice(&[0x90, 0x02, 0xDE, 0xAD]);
+        later.extend_from_slice(&V3_GOLDEN_DELTA[wire::HEADER_SIZE + 6..]);
+        later[wire::HEADER_SIZE - 4..wire::HEADER_SIZE].copy_from_slice(&16u32.to_le_bytes());
+        let applied = apply_delta_snapshot(
+            &schema,
+            &baseline,
+            &later,
+            &wire::Limits::for_testing(),
+            &limits,
+        )
+        .unwrap();
+        assert_eq!(applied, expected);
+    }
+
     #[test]
     fn baseline_tick_mismatch_is_error() {
         let schema = schema_one_bool();
@@ -3146,7 +4271,7 @@ mod tests {
         )
         .unwrap();
         let mut packet = wire::decode_packet(&buf[..bytes], &wire::Limits::for_testing()).unwrap();
-        packet.header.baseline_tick = 999;
+        packet.header.baseline_tick = 9;
         wire::encode_header(&packet.header, &mut buf[..wire::HEADER_SIZE]).unwrap();
         let err = apply_delta_snapshot(
             &schema,
@@ -3159,6 +4284,104 @@ mod tests {
         assert!(matches!(err, CodecError::BaselineTickMismatch { .. }));
     }
 
+    #[test]
+    fn baseline_newer_than_tick_is_rejected() {
+        let schema = schema_one_bool();
+        let baseline = baseline_snapshot();
+        let mut buf = [0u8; 128];
+        let bytes = encode_delta_snapshot(
+            &schema,
+            SnapshotTick::new(11),
+            baseline.tick,
+            &baseline,
+            &baseline,
+            &CodecLimits::for_testing(),
+            &mut buf,
+        )
+        .unwrap();
+        let mut packet = wire::decode_packet(&buf[..bytes], &wire::Limits::for_testing()).unwrap();
+        packet.header.baseline_tick = 999;
+        wire::encode_header(&packet.header, &mut buf[..wire::HEADER_SIZE]).unwrap();
+        let err = apply_delta_snapshot(
+            &schema,
+            &baseline,
+            &buf[..bytes],
+            &wire::Limits::for_testing(),
+            &CodecLimits::for_testing(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            CodecError::Wire(wire::DecodeError::InvalidBaselineTick { .. })
+        ));
+    }
+
+    #[test]
+    fn session_delta_across_tick_wrap() {
+        let schema = schema_one_bool();
+        let mut baseline = baseline_snapshot();
+        baseline.tick = SnapshotTick::new(u32::MAX - 1);
+        let mut current = baseline.clone();
+        current.entities[0].components[0].fields[0] = FieldValue::Bool(true);
+        current.tick = SnapshotTick::new(2);
+        let mut last_tick = SnapshotTick::new(u32::MAX);
+        let mut buf = [0u8; 128];
+        let bytes = encode_delta_snapshot_for_client_session(
+            &schema,
+            current.tick,
+            baseline.tick,
+            &baseline,
+            &current,
+            &CodecLimits::for_testing(),
+            &mut last_tick,
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(last_tick, current.tick);
+
+        let mut session = crate::SessionState {
+            schema_hash: schema_hash(&schema),
+            session_id: None,
+            last_tick: SnapshotTick::new(u32::MAX),
+            compact_mode: crate::CompactHeaderMode::SessionV1,
+            model_hash: None,
+            resume_token: None,
+            capabilities: None,
+        };
+        let packet = crate::decode_session_packet(
+            &schema,
+            &mut session,
+            &buf[..bytes],
+            &wire::Limits::for_testing(),
+        )
+        .unwrap();
+        assert_eq!(packet.header.baseline_tick, u32::MAX - 1);
+        let applied = apply_delta_snapshot_from_packet(
+            &schema,
+            &baseline,
+            &packet,
+            &CodecLimits::for_testing(),
+        )
+        .unwrap();
+        assert_eq!(applied, current);
+        assert_eq!(session.last_tick, SnapshotTick::new(2));
+
+        // A tick that is not after `last_tick` in serial order is rejected.
+        let mut last_tick = SnapshotTick::new(5);
+        let err = encode_delta_snapshot_for_client_session(
+            &schema,
+            SnapshotTick::new(u32::MAX),
+            baseline.tick,
+            &baseline,
+            &current,
+            &CodecLimits::for_testing(),
+            &mut last_tick,
+            &mut buf,
+        )
+        .unwrap_err();
+        assert!(matches!(err, CodecError::InvalidEntityOrder { .. }));
+    }
+
     #[test]
     fn threshold_suppresses_small_change() {
         let schema = schema_uint_threshold(5);
@@ -0,0 +1,1766 @@
+//! Server and client session state machines.
+//!
+//! [`ServerEndpoint`] and [`ClientEndpoint`] wrap the session, snapshot, delta
+//! and ack APIs into the lifecycle described in `docs/recovery.md`: the
+//! server sends a session init and a full snapshot, waits for the client to
+//! ack it, then sends deltas against the newest acked baseline. Client decode
+//! errors that [`CodecError::ack_error_class`] maps to a resync become nacks,
+//! and the server answers them with a new session init and full snapshot.
+//!
+//! Both endpoints are driven by the caller: incoming bytes go to
+//! [`SessionEndpoint::receive`], outgoing packets are drained with
+//! [`SessionEndpoint::poll_transmit`], and time only advances through the
+//! `now` arguments, so a simulated link is fully deterministic.
+//!
+//! With [`CompactHeaderMode::SessionV2`] (the default) every delta uses a
+//! compact header, and late or duplicated packets are dropped. A
+//! [`CompactHeaderMode::SessionV1`] header encodes the tick relative to the
+//! previous packet, which the client cannot reconstruct if that packet was
+//! lost, so in that mode the server only uses a compact header once the
+//! client has acked the previous packet and falls back to the standard header
+//! otherwise; V1 packets must arrive in order.
+//!
+//! A server endpoint created [`ServerEndpoint::with_resume_token`] announces
+//! the token in its session inits. A client that reconnects within the
+//! server's [`EndpointConfig::resume_window`] calls
+//! [`ClientEndpoint::request_resume`] to send it back with the newest tick it
+//! holds; if that tick is still a retained baseline the server continues with
+//! deltas against it, otherwise it falls back to a full snapshot.
+//!
+//! Endpoints created `with_model` code the create and update sections of
+//! standard-header packets against the [`CodecModel`] histograms and
+//! templates, compress LZ4 payloads with its dictionary, and the server
+//! announces the model hash in its session inits. A client whose model does not match rejects the session
+//! init with [`CodecError::ModelMismatch`].
+//!
+//! The server offers [`EndpointConfig::capabilities`] in its session init and
+//! the client answers with a session accept holding the subset both support.
+//! Until that arrives the server only uses [`NegotiatedCaps::baseline`]
+//! features, so clients that predate negotiation keep working. A resumed
+//! session keeps the capabilities negotiated before the disconnect.
+
+use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use crate::ack::{AckOutcome, BaselineSelection, ClientAckTracker, ResyncReason};
+use crate::baseline::BaselineStore;
+use crate::caps::NegotiatedCaps;
+use crate::delta::{
+    apply_delta_snapshot_from_packet, encode_delta_snapshot_for_client_session_with_caps,
+    encode_delta_snapshot_for_client_with_scratch,
+};
+use crate::entropy::entropy_code_update_sections;
+use crate::error::{CodecError, CodecResult};
+use crate::limits::CodecLimits;
+use crate::model::CodecModel;
+use crate::model_coding::{expand_model_sections, model_code_sections};
+use crate::scratch::CodecScratch;
+use crate::session::{
+    decode_session_accept_packet, decode_session_init_packet, decode_session_packet,
+    encode_session_accept_packet, encode_session_init_packet, CompactHeaderMode, SessionAccept,
+    SessionInitOptions, SessionState,
+};
+use crate::snapshot::{decode_full_snapshot_from_packet, encode_full_snapshot, Snapshot};
+use crate::types::SnapshotTick;
+
+/// Configuration shared by both endpoints.
+#[derive(Debug, Clone)]
+pub struct EndpointConfig {
+    pub codec_limits: CodecLimits,
+    pub wire_limits: wire::Limits,
+    /// Number of snapshots kept as delta baselines.
+    pub baseline_capacity: NonZeroUsize,
+    /// Size of the buffer each outgoing packet is encoded into.
+    pub max_packet_bytes: usize,
+    /// Compact header version the server announces in session init. The
+    /// client follows whatever the server announces.
+    pub compact_mode: CompactHeaderMode,
+    /// How long the server waits for the ack of a full snapshot before
+    /// sending a new one.
+    pub bootstrap_timeout: Duration,
+    /// How long an endpoint waits without receiving anything before it
+    /// considers the peer gone.
+    pub idle_timeout: Duration,
+    /// How long after last hearing from a client the server still accepts
+    /// its resume request.
+    pub resume_window: Duration,
+    /// Optional features the server offers, or the client supports.
+    pub capabilities: NegotiatedCaps,
+}
+
+impl Default for EndpointConfig {
+    fn default() -> Self {
+        Self {
+            codec_limits: CodecLimits::default(),
+            wire_limits: wire::Limits::default(),
+            baseline_capacity: NonZeroUsize::new(32).unwrap(),
+            max_packet_bytes: 64 * 1024,
+            compact_mode: CompactHeaderMode::SessionV2,
+            bootstrap_timeout: Duration::from_millis(250),
+            idle_timeout: Duration::from_secs(5),
+            resume_window: Duration::from_secs(10),
+            capabilities: NegotiatedCaps::all(),
+        }
+    }
+}
+
+/// Lifecycle state of an endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndpointState {
+    /// No full snapshot has been acked (server) or applied (client) yet.
+    Connecting,
+    /// Deltas are flowing.
+    Established,
+    /// A full snapshot is needed to recover from a decode error or lost
+    /// baseline.
+    Resyncing,
+    /// Nothing was received within the idle timeout; the endpoint is inert.
+    TimedOut,
+}
+
+/// What an endpoint did with an incoming packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndpointEvent {
+    /// Client: a session init was accepted.
+    SessionStarted,
+    /// Client: the snapshot for this tick was applied and acked.
+    Applied(SnapshotTick),
+    /// Server: the client acknowledged this tick.
+    Acked(SnapshotTick),
+    /// A decode error requires a full snapshot. The client has queued a
+    /// nack; the server will send a full snapshot next.
+    ResyncRequested(wire::AckErrorClass),
+    /// The session was resumed from this tick without a full snapshot.
+    Resumed(SnapshotTick),
+    /// Server: a resume request was refused; a full snapshot follows.
+    ResumeRejected(ResumeRejection),
+    /// Server: the client accepted the session with these capabilities.
+    Negotiated(NegotiatedCaps),
+    /// The packet was a duplicate, stale, or arrived while awaiting a resync.
+    Ignored,
+}
+
+/// Why the server refused to resume a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumeRejection {
+    /// The session id or resume token does not match this endpoint.
+    UnknownToken,
+    /// The client was last heard longer than the resume window ago.
+    Expired,
+    /// The tick the client holds is no longer a retained baseline.
+    BaselineGone { tick: SnapshotTick },
+}
+
+/// What the server sent for a tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerSend {
+    /// A session init and a full snapshot were queued.
+    Full { reason: ResyncReason },
+    /// A delta against `baseline_tick` was queued.
+    Delta {
+        baseline_tick: SnapshotTick,
+        /// Whether the delta uses a compact session header.
+        compact: bool,
+    },
+    /// The last full snapshot is unacknowledged and the bootstrap timeout has
+    /// not elapsed; nothing was sent.
+    AwaitingAck,
+    /// The endpoint timed out; nothing was sent.
+    TimedOut,
+}
+
+/// Common interface of the server and client state machines.
+pub trait SessionEndpoint {
+    /// Processes one incoming packet received at `now`.
+    ///
+    /// Errors that require a resync are consumed and reported as
+    /// [`EndpointEvent::ResyncRequested`], late packets as
+    /// [`EndpointEvent::Ignored`]; other errors (e.g. malformed
+    /// packets) are returned and the packet is dropped.
+    fn receive(
+        &mut self,
+        schema: &schema::Schema,
+        bytes: &[u8],
+        now: Duration,
+    ) -> CodecResult<EndpointEvent>;
+
+    /// Advances timers to `now` and returns the resulting state.
+    fn handle_timeout(&mut self, now: Duration) -> EndpointState;
+
+    /// Returns the next queued outgoing packet.
+    fn poll_transmit(&mut self) -> Option<Vec<u8>>;
+
+    /// Returns the current lifecycle state.
+    fn state(&self) -> EndpointState;
+}
+
+/// Server side of a session with one client.
+#[derive(Debug)]
+pub struct ServerEndpoint {
+    config: EndpointConfig,
+    session_id: Option<u64>,
+    resume_token: Option<u64>,
+    model: Option<CodecModel>,
+    compressor: wire::Lz4Compressor,
+    /// Capabilities accepted by the client.
+    caps: NegotiatedCaps,
+    tracker: ClientAckTracker,
+    /// Snapshots sent to the client, keyed by tick.
+    baselines: BaselineStore<Snapshot>,
+    last_sent: Option<SnapshotTick>,
+    /// Tick of the last full snapshot sent.
+    last_full: Option<SnapshotTick>,
+    /// When the unacknowledged last full snapshot was sent.
+    full_pending_since: Option<Duration>,
+    established: bool,
+    timed_out: bool,
+    last_heard: Duration,
+    scratch: CodecScratch,
+    transmit: VecDeque<Vec<u8>>,
+}
+
+impl ServerEndpoint {
+    /// Creates a server endpoint at time `now`.
+    #[must_use]
+    pub fn new(config: EndpointConfig, session_id: Option<u64>, now: Duration) -> Self {
+        Self {
+            baselines: BaselineStore::new(config.baseline_capacity),
+            config,
+            session_id,
+            resume_token: None,
+            model: None,
+            compressor: wire::Lz4Compressor::new(),
+            caps: NegotiatedCaps::baseline(),
+            tracker: ClientAckTracker::new(),
+            last_sent: None,
+            last_full: None,
+            full_pending_since: None,
+            established: false,
+            timed_out: false,
+            last_heard: now,
+            scratch: CodecScratch::default(),
+            transmit: VecDeque::new(),
+        }
+    }
+
+    /// Sets the token announced in session inits that lets the client resume
+    /// this session after a reconnect. It should be random and unique per
+    /// session; resuming also requires a session id.
+    #[must_use]
+    pub fn with_resume_token(mut self, token: u64) -> Self {
+        self.resume_token = Some(token);
+        self
+    }
+
+    /// Codes standard-header packets against `model`, uses its dictionary
+    /// for LZ4 payloads and announces its hash in session inits. The client
+    /// must load the same model.
+    #[must_use]
+    pub fn with_model(mut self, model: &CodecModel) -> Self {
+        self.compressor = model.compressor();
+        self.model = Some(model.clone());
+        self
+    }
+
+    /// Returns the client's ack tracker.
+    #[must_use]
+    pub fn tracker(&self) -> &ClientAckTracker {
+        &self.tracker
+    }
+
+    /// Returns the capabilities the encoders currently use.
+    #[must_use]
+    pub fn capabilities(&self) -> NegotiatedCaps {
+        self.caps
+    }
+
+    /// Handles a resume request, i.e. a session init sent by a reconnecting
+    /// client, received at `now`.
+    ///
+    /// On success the tick the client holds becomes its latest ack, a session
+    /// init confirming it is queued, and the next snapshot is sent as a delta
+    /// against it. Otherwise the next snapshot is sent in full with a new
+    /// session init. Either way a timed-out endpoint becomes live again.
+    pub fn resume(
+        &mut self,
+        schema: &schema::Schema,
+        request: &SessionState,
+        now: Duration,
+    ) -> CodecResult<EndpointEvent> {
+        let tick = request.last_tick;
+        let rejection = if request.session_id.is_none()
+            || request.session_id != self.session_id
+            || request.resume_token.is_none()
+            || request.resume_token != self.resume_token
+        {
+            Some(ResumeRejection::UnknownToken)
+        } else if now.saturating_sub(self.last_heard) > self.config.resume_window {
+            Some(ResumeRejection::Expired)
+        } else if self.baselines.get(tick).is_none() {
+            Some(ResumeRejection::BaselineGone { tick })
+        } else {
+            None
+        };
+        self.timed_out = false;
+        self.last_heard = now;
+        self.transmit.clear();
+        if let Some(reason) = rejection {
+            self.tracker.nack();
+            self.full_pending_since = None;
+            return Ok(EndpointEvent::ResumeRejected(reason));
+        }
+
+        let init = self.encode_init(schema, tick)?;
+        self.transmit.push_back(init);
+        self.tracker.resumed(tick);
+        self.full_pending_since = None;
+        self.established = true;
+        ClientAckTracker::evict_unneeded(&mut self.baselines, [&self.tracker]);
+        Ok(EndpointEvent::Resumed(tick))
+    }
+
+    /// Encodes `snapshot` for the client and queues the resulting packets.
+    ///
+    /// Ticks must be strictly increasing across calls.
+    pub fn send_snapshot(
+        &mut self,
+        schema: &schema::Schema,
+        snapshot: Snapshot,
+        now: Duration,
+    ) -> CodecResult<ServerSend> {
+        if self.timed_out {
+            return Ok(ServerSend::TimedOut);
+        }
+        let tick = snapshot.tick;
+        if let Some(previous) = self.last_sent.filter(|&previous| !tick.is_after(previous)) {
+            return Err(CodecError::SessionOutOfOrder {
+                previous: previous.raw(),
+                current: tick.raw(),
+            });
+        }
+
+        let sent = match self.tracker.select_baseline(&self.baselines) {
+            BaselineSelection::Delta { baseline_tick } => {
+                self.send_delta(schema, baseline_tick, &snapshot)?
+            }
+            BaselineSelection::Resync {
+                reason: ResyncReason::NoAck,
+            } if self
+                .full_pending_since
+                .is_some_and(|since| now.saturating_sub(since) < self.config.bootstrap_timeout) =>
+            {
+                return Ok(ServerSend::AwaitingAck);
+            }
+            BaselineSelection::Resync { reason } => {
+                self.send_full(schema, &snapshot, now)?;
+                ServerSend::Full { reason }
+            }
+        };
+
+        self.baselines
+            .insert(tick, snapshot)
+            .map_err(|_| CodecError::SessionOutOfOrder {
+                previous: self.last_sent.map_or(0, SnapshotTick::raw),
+                current: tick.raw(),
+            })?;
+        self.last_sent = Some(tick);
+        ClientAckTracker::evict_unneeded(&mut self.baselines, [&self.tracker]);
+        Ok(sent)
+    }
+
+    fn send_full(
+        &mut self,
+        schema: &schema::Schema,
+        snapshot: &Snapshot,
+        now: Duration,
+    ) -> CodecResult<()> {
+        let init = self.encode_init(schema, snapshot.tick)?;
+        let mut full = vec![0u8; self.config.max_packet_bytes];
+        let len = encode_full_snapshot(
+            schema,
+            snapshot.tick,
+            &snapshot.entities,
+            &self.config.codec_limits,
+            &mut full,
+        )?;
+        let len = self.model_code(schema, &mut full, len)?;
+        full.truncate(len);
+        let full = self.compress(full)?;
+
+        self.transmit.push_back(init);
+        self.transmit.push_back(full);
+        self.tracker.full_snapshot_sent(snapshot.tick);
+        self.last_full = Some(snapshot.tick);
+        self.full_pending_since = Some(now);
+        Ok(())
+    }
+
+    fn encode_init(&self, schema: &schema::Schema, tick: SnapshotTick) -> CodecResult<Vec<u8>> {
+        let mut init = vec![0u8; self.config.max_packet_bytes];
+        let options = SessionInitOptions::new(self.config.compact_mode)
+            .session_id(self.session_id)
+            .model(self.model.as_ref())
+            .resume_token(self.resume_token.filter(|_| self.session_id.is_some()))
+            .capabilities(Some(self.config.capabilities));
+        let len = encode_session_init_packet(
+            schema,
+            tick,
+            &options,
+            &self.config.codec_limits,
+            &mut init,
+        )?;
+        init.truncate(len);
+        Ok(init)
+    }
+
+    /// Codes the create and update sections of a standard-header packet
+    /// against the model, if one is loaded.
+    fn model_code(
+        &self,
+        schema: &schema::Schema,
+        out: &mut [u8],
+        len: usize,
+    ) -> CodecResult<usize> {
+        match &self.model {
+            Some(model) => model_code_sections(schema, model, out, len, &self.config.codec_limits),
+            None => Ok(len),
+        }
+    }
+
+    /// Compresses a standard-header packet if the client accepted LZ4.
+    fn compress(&self, packet: Vec<u8>) -> CodecResult<Vec<u8>> {
+        if !self.caps.supports_compressor(wire::Lz4Compressor::ID) {
+            return Ok(packet);
+        }
+        let mut out = vec![0u8; packet.len()];
+        let len = wire::compress_packet(&packet, &self.compressor, &mut out).map_err(|_| {
+            CodecError::OutputTooSmall {
+                needed: packet.len(),
+                available: out.len(),
+            }
+        })?;
+        out.truncate(len);
+        Ok(out)
+    }
+
+    fn send_delta(
+        &mut self,
+        schema: &schema::Schema,
+        baseline_tick: SnapshotTick,
+        current: &Snapshot,
+    ) -> CodecResult<ServerSend> {
+        let baseline = self
+            .baselines
+            .get(baseline_tick)
+            .ok_or(CodecError::BaselineNotFound {
+                requested_tick: baseline_tick.raw(),
+            })?;
+        // A v1 client reconstructs compact ticks from the last packet it
+        // decoded, which is only known to be `last_sent` once that is acked.
+        let compact = match self.config.compact_mode {
+            CompactHeaderMode::SessionV1 => {
+                self.last_sent.is_some() && self.tracker.latest_ack() == self.last_sent
+            }
+            CompactHeaderMode::SessionV2 => true,
+        };
+        let mut out = vec![0u8; self.config.max_packet_bytes];
+        let len = if compact {
+            let mut last_tick = self.last_sent.unwrap_or(baseline_tick);
+            encode_delta_snapshot_for_client_session_with_caps(
+                schema,
+                self.config.compact_mode,
+                self.caps,
+                current.tick,
+                baseline_tick,
+                baseline,
+                current,
+                &self.config.codec_limits,
+                &mut self.scratch,
+                &mut last_tick,
+                &mut out,
+            )?
+        } else {
+            let len = encode_delta_snapshot_for_client_with_scratch(
+                schema,
+                current.tick,
+                baseline_tick,
+                baseline,
+                current,
+                &self.config.codec_limits,
+                &mut self.scratch,
+                &mut out,
+            )?;
+            let len = self.model_code(schema, &mut out, len)?;
+            if self.caps.supports_coded_updates() {
+                entropy_code_update_sections(&mut out, len)?
+            } else {
+                len
+            }
+        };
+        out.truncate(len);
+        let out = if compact { out } else { self.compress(out)? };
+        self.transmit.push_back(out);
+        Ok(ServerSend::Delta {
+            baseline_tick,
+            compact,
+        })
+    }
+
+    fn decode_ack(
+        &self,
+        schema: &schema::Schema,
+        packet: &wire::WirePacket<'_>,
+    ) -> CodecResult<wire::AckPacket> {
+        let expected = schema::schema_hash(schema);
+        if packet.header.schema_hash != expected {
+            return Err(CodecError::SchemaMismatch {
+                expected,
+                found: packet.header.schema_hash,
+            });
+        }
+        wire::decode_ack_packet(packet).map_err(CodecError::Wire)
+    }
+
+    /// Adopts the capabilities of a session accept for this session.
+    fn accept(
+        &mut self,
+        schema: &schema::Schema,
+        packet: &wire::WirePacket<'_>,
+    ) -> CodecResult<EndpointEvent> {
+        let accept = decode_session_accept_packet(schema, packet, &self.config.codec_limits)?;
+        if accept.session_id != self.session_id {
+            return Ok(EndpointEvent::Ignored);
+        }
+        // The client can only narrow what was offered.
+        let caps = accept.capabilities.intersect(self.config.capabilities);
+        caps.ensure_schema(schema)?;
+        self.caps = caps;
+        Ok(EndpointEvent::Negotiated(caps))
+    }
+}
+
+impl SessionEndpoint for ServerEndpoint {
+    fn receive(
+        &mut self,
+        schema: &schema::Schema,
+        bytes: &[u8],
+        now: Duration,
+    ) -> CodecResult<EndpointEvent> {
+        let ack = if is_standard_packet(bytes) {
+            let packet =
+                wire::decode_packet(bytes, &self.config.wire_limits).map_err(CodecError::Wire)?;
+            if packet.header.flags.is_session_init() {
+                let request =
+                    decode_session_init_packet(schema, &packet, &self.config.codec_limits)?;
+                return self.resume(schema, &request, now);
+            }
+            if self.timed_out {
+                return Ok(EndpointEvent::Ignored);
+            }
+            if packet.header.flags.is_session_accept() {
+                self.last_heard = now;
+                return self.accept(schema, &packet);
+            }
+            self.decode_ack(schema, &packet)?
+        } else {
+            if self.timed_out {
+                return Ok(EndpointEvent::Ignored);
+            }
+            wire::decode_session_ack_packet(bytes, &self.config.wire_limits)
+                .map_err(CodecError::Wire)?
+        };
+        self.last_heard = now;
+
+        // A nack sent before the client applied the last full snapshot is
+        // already answered by it.
+        if ack.error.is_some()
+            && self
+                .last_full
+                .is_some_and(|full| SnapshotTick::new(ack.latest_tick).is_before(full))
+        {
+            return Ok(EndpointEvent::Ignored);
+        }
+        match self.tracker.ingest(&ack) {
+            AckOutcome::Advanced => {
+                self.full_pending_since = None;
+                self.established = true;
+                ClientAckTracker::evict_unneeded(&mut self.baselines, [&self.tracker]);
+                Ok(EndpointEvent::Acked(SnapshotTick::new(ack.latest_tick)))
+            }
+            AckOutcome::Stale => Ok(EndpointEvent::Ignored),
+            AckOutcome::Nacked => Ok(EndpointEvent::ResyncRequested(
+                ack.error.unwrap_or(wire::AckErrorClass::Session),
+            )),
+        }
+    }
+
+    fn handle_timeout(&mut self, now: Duration) -> EndpointState {
+        if now.saturating_sub(self.last_heard) >= self.config.idle_timeout {
+            self.timed_out = true;
+            self.transmit.clear();
+        }
+        self.state()
+    }
+
+    fn poll_transmit(&mut self) -> Option<Vec<u8>> {
+        self.transmit.pop_front()
+    }
+
+    fn state(&self) -> EndpointState {
+        if self.timed_out {
+            EndpointState::TimedOut
+        } else if !self.established {
+            EndpointState::Connecting
+        } else if self.full_pending_since.is_some() || self.tracker.needs_resync(&self.baselines) {
+            EndpointState::Resyncing
+        } else {
+            EndpointState::Established
+        }
+    }
+}
+
+/// Client side of a session.
+#[derive(Debug)]
+pub struct ClientEndpoint {
+    config: EndpointConfig,
+    session: Option<SessionState>,
+    model: Option<CodecModel>,
+    compressor: wire::Lz4Compressor,
+    /// Capabilities accepted for the current session.
+    caps: NegotiatedCaps,
+    /// Applied snapshots, usable as delta baselines.
+    baselines: BaselineStore<Snapshot>,
+    /// Newest applied tick and the ticks received before it.
+    ack: Option<wire::AckPacket>,
+    /// Error class of the nack sent while awaiting a full snapshot.
+    resync: Option<wire::AckErrorClass>,
+    timed_out: bool,
+    last_heard: Duration,
+    transmit: VecDeque<Vec<u8>>,
+}
+
+impl ClientEndpoint {
+    /// Creates a client endpoint at time `now`.
+    #[must_use]
+    pub fn new(config: EndpointConfig, now: Duration) -> Self {
+        Self {
+            baselines: BaselineStore::new(config.baseline_capacity),
+            config,
+            session: None,
+            model: None,
+            compressor: wire::Lz4Compressor::new(),
+            caps: NegotiatedCaps::baseline(),
+            ack: None,
+            resync: None,
+            timed_out: false,
+            last_heard: now,
+            transmit: VecDeque::new(),
+        }
+    }
+
+    /// Expands model-coded sections with `model` and uses its dictionary for
+    /// LZ4 payloads. Session inits must announce the same model.
+    #[must_use]
+    pub fn with_model(mut self, model: &CodecModel) -> Self {
+        self.compressor = model.compressor();
+        self.model = Some(model.clone());
+        self
+    }
+
+    /// Returns the session announced by the server, if any.
+    #[must_use]
+    pub fn session(&self) -> Option<&SessionState> {
+        self.session.as_ref()
+    }
+
+    /// Returns the capabilities accepted for the current session.
+    #[must_use]
+    pub fn capabilities(&self) -> NegotiatedCaps {
+        self.caps
+    }
+
+    /// Returns the newest applied snapshot.
+    #[must_use]
+    pub fn latest(&self) -> Option<&Snapshot> {
+        self.baselines.newest().map(|(_, snapshot)| snapshot)
+    }
+
+    fn latest_tick(&self) -> Option<SnapshotTick> {
+        self.ack.map(|ack| SnapshotTick::new(ack.latest_tick))
+    }
+
+    /// Queues a request to resume the session after reconnecting at `now`.
+    ///
+    /// Returns `false` (and queues nothing) if the server issued no resume
+    /// token, nothing was applied yet, or a full snapshot is awaited; the
+    /// caller should then start over with a new endpoint.
+    pub fn request_resume(&mut self, schema: &schema::Schema, now: Duration) -> CodecResult<bool> {
+        let (Some(session), Some(latest)) = (&self.session, self.latest_tick()) else {
+            return Ok(false);
+        };
+        if session.session_id.is_none() || session.resume_token.is_none() || self.resync.is_some() {
+            return Ok(false);
+        }
+        let mut out = vec![0u8; self.config.max_packet_bytes];
+        let options = SessionInitOptions::new(session.compact_mode)
+            .session_id(session.session_id)
+            .resume_token(session.resume_token);
+        let len = encode_session_init_packet(
+            schema,
+            latest,
+            &options,
+            &self.config.codec_limits,
+            &mut out,
+        )?;
+        out.truncate(len);
+        self.transmit.clear();
+        self.transmit.push_back(out);
+        self.timed_out = false;
+        self.last_heard = now;
+        Ok(true)
+    }
+
+    /// Returns `true` if `init` confirms a resume of the current session
+    /// from the newest applied tick.
+    fn confirms_resume(&self, init: &SessionState) -> bool {
+        self.session.as_ref().is_some_and(|session| {
+            init.resume_token.is_some()
+                && init.session_id == session.session_id
+                && init.resume_token == session.resume_token
+        }) && self.latest_tick() == Some(init.last_tick)
+    }
+
+    fn process(&mut self, schema: &schema::Schema, bytes: &[u8]) -> CodecResult<EndpointEvent> {
+        let limits = &self.config.codec_limits;
+        if !is_standard_packet(bytes) {
+            let session = self.session.as_mut().ok_or(CodecError::SessionMissing)?;
+            let packet = decode_session_packet(schema, session, bytes, &self.config.wire_limits)?;
+            return self.apply_delta(schema, &packet);
+        }
+
+        let mut decompressed = Vec::new();
+        let bytes = if is_compressed_packet(bytes)
+            && self.caps.supports_compressor(wire::Lz4Compressor::ID)
+        {
+            wire::decompress_packet(
+                bytes,
+                &self.compressor,
+                &self.config.wire_limits,
+                &mut decompressed,
+            )
+            .map_err(CodecError::Wire)?;
+            &decompressed
+        } else {
+            bytes
+        };
+        let expanded = match &self.model {
+            Some(model) => expand_model_sections(schema, model, bytes, limits)?,
+            None => None,
+        };
+        let bytes = expanded.as_deref().unwrap_or(bytes);
+        let packet =
+            wire::decode_packet(bytes, &self.config.wire_limits).map_err(CodecError::Wire)?;
+        let flags = packet.header.flags;
+        if flags.is_session_init() {
+            let session = decode_session_init_packet(schema, &packet, limits)?;
+            session.ensure_model(self.model.as_ref())?;
+            if self.confirms_resume(&session) {
+                let tick = session.last_tick;
+                self.session = Some(session);
+                self.queue_ack(schema, None);
+                return Ok(EndpointEvent::Resumed(tick));
+            }
+            // A delayed copy of an init that was already followed by a full
+            // snapshot would rewind the session. An init for another session
+            // (e.g. after a server restart) starts over whatever its tick.
+            let same_session = self
+                .session
+                .as_ref()
+                .is_some_and(|current| current.session_id == session.session_id);
+            if same_session
+                && self
+                    .latest_tick()
+                    .is_some_and(|latest| !session.last_tick.is_after(latest))
+            {
+                return Ok(EndpointEvent::Ignored);
+            }
+            if !same_session {
+                self.baselines.clear();
+                self.ack = None;
+                self.resync = None;
+            }
+            self.caps = session.negotiate(self.config.capabilities);
+            self.caps.ensure_schema(schema)?;
+            if session.capabilities.is_some() {
+                self.queue_accept(schema, &session)?;
+            }
+            self.session = Some(session);
+            return Ok(EndpointEvent::SessionStarted);
+        }
+        if self.session.is_none() {
+            return Err(CodecError::SessionMissing);
+        }
+        if flags.is_full_snapshot() {
+            let snapshot = decode_full_snapshot_from_packet(schema, &packet, limits)?;
+            return Ok(self.apply_full(schema, snapshot));
+        }
+        if flags.is_delta_snapshot() {
+            return self.apply_delta(schema, &packet);
+        }
+        Err(CodecError::Wire(wire::DecodeError::InvalidFlags {
+            flags: flags.raw(),
+        }))
+    }
+
+    fn apply_full(&mut self, schema: &schema::Schema, snapshot: Snapshot) -> EndpointEvent {
+        let tick = snapshot.tick;
+        if self
+            .latest_tick()
+            .is_some_and(|latest| !tick.is_after(latest))
+        {
+            return EndpointEvent::Ignored;
+        }
+        self.baselines.clear();
+        // The store is empty, so the insert cannot fail.
+        let _ = self.baselines.insert(tick, snapshot);
+        self.resync = None;
+        self.ack = Some(wire::AckPacket::new(tick.raw()));
+        self.applied(schema, tick)
+    }
+
+    fn apply_delta(
+        &mut self,
+        schema: &schema::Schema,
+        packet: &wire::WirePacket<'_>,
+    ) -> CodecResult<EndpointEvent> {
+        if let Some(class) = self.resync {
+            // The earlier nack may have been lost; repeat it.
+            self.queue_ack(schema, Some(class));
+            return Ok(EndpointEvent::Ignored);
+        }
+        let tick = SnapshotTick::new(packet.header.tick);
+        if self
+            .latest_tick()
+            .is_some_and(|latest| !tick.is_after(latest))
+        {
+            return Ok(EndpointEvent::Ignored);
+        }
+        let baseline_tick = SnapshotTick::new(packet.header.baseline_tick);
+        let baseline = self
+            .baselines
+            .get(baseline_tick)
+            .ok_or(CodecError::BaselineNotFound {
+                requested_tick: baseline_tick.raw(),
+            })?;
+        let snapshot =
+            apply_delta_snapshot_from_packet(schema, baseline, packet, &self.config.codec_limits)?;
+        let previous = self.latest_tick().map_or(0, SnapshotTick::raw);
+        self.baselines
+            .insert(tick, snapshot)
+            .map_err(|_| CodecError::SessionOutOfOrder {
+                previous,
+                current: tick.raw(),
+            })?;
+
+        let mut ack = wire::AckPacket::new(tick.raw());
+        if let Some(previous) = self.ack {
+            for received in previous.received_ticks() {
+                ack.mark_received(received);
+            }
+        }
+        self.ack = Some(ack);
+        Ok(self.applied(schema, tick))
+    }
+
+    /// Records `tick` as the session's last tick and acks it.
+    fn applied(&mut self, schema: &schema::Schema, tick: SnapshotTick) -> EndpointEvent {
+        if let Some(session) = self.session.as_mut() {
+            session.last_tick = tick;
+        }
+        self.queue_ack(schema, None);
+        EndpointEvent::Applied(tick)
+    }
+
+    /// Queues the session accept answering `init`.
+    fn queue_accept(&mut self, schema: &schema::Schema, init: &SessionState) -> CodecResult<()> {
+        let accept = SessionAccept {
+            session_id: init.session_id,
+            tick: init.last_tick,
+            capabilities: self.caps,
+        };
+        let mut out = vec![0u8; self.config.max_packet_bytes];
+        let len =
+            encode_session_accept_packet(schema, &accept, &self.config.codec_limits, &mut out)?;
+        out.truncate(len);
+        self.transmit.push_back(out);
+        Ok(())
+    }
+
+    fn queue_ack(&mut self, schema: &schema::Schema, error: Option<wire::AckErrorClass>) {
+        let mut ack = self.ack.unwrap_or_default();
+        ack.error = error;
+        let mut out = vec![0u8; wire::HEADER_SIZE + wire::SESSION_MAX_HEADER_SIZE + 16];
+        // Before a session exists the ack needs the standard header.
+        let len = match &self.session {
+            Some(_) => wire::encode_session_ack_packet(&ack, &mut out),
+            None => wire::encode_ack_packet(&ack, schema::schema_hash(schema), &mut out),
+        };
+        if let Ok(len) = len {
+            out.truncate(len);
+            self.transmit.push_back(out);
+        }
+    }
+}
+
+impl SessionEndpoint for ClientEndpoint {
+    fn receive(
+        &mut self,
+        schema: &schema::Schema,
+        bytes: &[u8],
+        now: Duration,
+    ) -> CodecResult<EndpointEvent> {
+        if self.timed_out {
+            return Ok(EndpointEvent::Ignored);
+        }
+        self.last_heard = now;
+        match self.process(schema, bytes) {
+            Ok(event) => Ok(event),
+            Err(err) if err.is_droppable() => Ok(EndpointEvent::Ignored),
+            Err(err) => match err.ack_error_class() {
+                Some(class) => {
+                    self.resync = Some(class);
+                    self.queue_ack(schema, Some(class));
+                    Ok(EndpointEvent::ResyncRequested(class))
+                }
+                None => Err(err),
+            },
+        }
+    }
+
+    fn handle_timeout(&mut self, now: Duration) -> EndpointState {
+        if now.saturating_sub(self.last_heard) >= self.config.idle_timeout {
+            self.timed_out = true;
+            self.transmit.clear();
+        }
+        self.state()
+    }
+
+    fn poll_transmit(&mut self) -> Option<Vec<u8>> {
+        self.transmit.pop_front()
+    }
+
+    fn state(&self) -> EndpointState {
+        if self.timed_out {
+            EndpointState::TimedOut
+        } else if self.resync.is_some() {
+            EndpointState::Resyncing
+        } else if self.ack.is_none() {
+            EndpointState::Connecting
+        } else {
+            EndpointState::Established
+        }
+    }
+}
+
+/// Returns `true` if `bytes` start with the standard header magic rather than
+/// a compact session header.
+fn is_standard_packet(bytes: &[u8]) -> bool {
+    bytes
+        .get(..4)
+        .is_some_and(|magic| magic == wire::MAGIC.to_le_bytes())
+}
+
+/// Returns `true` if a standard-header packet has the `COMPRESSED` flag.
+fn is_compressed_packet(bytes: &[u8]) -> bool {
+    bytes.get(6..8).is_some_and(|flags| {
+        wire::PacketFlags::from_raw(u16::from_le_bytes([flags[0], flags[1]])).is_compressed()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{ModelTrainer, ModelTrainingConfig};
+    use crate::snapshot::{ComponentSnapshot, EntitySnapshot, FieldValue};
+    use crate::types::EntityId;
+    use schema::{ComponentDef, ComponentId, FieldCodec, FieldDef, FieldId, Schema};
+
+    const STEP: Duration = Duration::from_millis(16);
+
+    fn schema() -> Schema {
+        let component = ComponentDef::new(ComponentId::new(1).unwrap())
+            .field(FieldDef::new(FieldId::new(1).unwrap(), FieldCodec::uint(8)));
+        Schema::new(vec![component]).unwrap()
+    }
+
+    /// World state for `tick`: three moving entities and one that is
+    /// created and destroyed periodically.
+    fn world(tick: u32) -> Snapshot {
+        let entity = |id: u64, value: u32| EntitySnapshot {
+            id: EntityId::new(id),
+            components: vec![ComponentSnapshot {
+                id: ComponentId::new(1).unwrap(),
+                fields: vec![FieldValue::UInt(u64::from(value % 256))],
+            }],
+        };
+        let mut entities = vec![entity(1, tick), entity(2, tick / 3), entity(3, 7)];
+        if tick % 10 < 5 {
+            entities.push(entity(4, tick.wrapping_mul(5)));
+        }
+        Snapshot {
+            tick: SnapshotTick::new(tick),
+            entities,
+        }
+    }
+
+    fn config() -> EndpointConfig {
+        EndpointConfig {
+            bootstrap_timeout: STEP * 6,
+            idle_timeout: STEP * 60,
+            ..EndpointConfig::default()
+        }
+    }
+
+    /// Seeded link that drops, delays and duplicates fixed percentages of
+    /// packets. A delayed packet arrives one step late, after that step's
+    /// packets.
+    struct LossyLink {
+        state: u64,
+        loss_percent: u64,
+        delay_percent: u64,
+        duplicate_percent: u64,
+        held_for_client: Vec<Vec<u8>>,
+        held_for_server: Vec<Vec<u8>>,
+    }
+
+    impl LossyLink {
+        fn new(seed: u64, loss_percent: u64) -> Self {
+            Self {
+                state: seed,
+                loss_percent,
+                delay_percent: 0,
+                duplicate_percent: 0,
+                held_for_client: Vec::new(),
+                held_for_server: Vec::new(),
+            }
+        }
+
+        fn with_reordering(mut self, delay_percent: u64, duplicate_percent: u64) -> Self {
+            self.delay_percent = delay_percent;
+            self.duplicate_percent = duplicate_percent;
+            self
+        }
+
+        fn roll(&mut self) -> u64 {
+            self.state ^= self.state << 13;
+            self.state ^= self.state >> 7;
+            self.state ^= self.state << 17;
+            self.state % 100
+        }
+
+        /// Returns the packets delivered this step and those held for the next.
+        fn carry(
+            &mut self,
+            packets: Vec<Vec<u8>>,
+            earlier: Vec<Vec<u8>>,
+        ) -> (Vec<Vec<u8>>, Vec<Vec<u8>>) {
+            let (mut delivered, mut held) = (Vec::new(), Vec::new());
+            for packet in packets {
+                let roll = self.roll();
+                if roll < self.loss_percent {
+                    continue;
+                }
+                if roll < self.loss_percent + self.delay_percent {
+                    held.push(packet);
+                } else if roll < self.loss_percent + self.delay_percent + self.duplicate_percent {
+                    held.push(packet.clone());
+                    delivered.push(packet);
+                } else {
+                    delivered.push(packet);
+                }
+            }
+            delivered.extend(earlier);
+            (delivered, held)
+        }
+    }
+
+    fn drain(endpoint: &mut impl SessionEndpoint) -> Vec<Vec<u8>> {
+        std::iter::from_fn(|| endpoint.poll_transmit()).collect()
+    }
+
+    /// Sends one tick and exchanges packets; returns the server's choice and
+    /// the client's events.
+    fn step(
+        schema: &Schema,
+        server: &mut ServerEndpoint,
+        client: &mut ClientEndpoint,
+        link: &mut LossyLink,
+        tick: u32,
+    ) -> (ServerSend, Vec<EndpointEvent>) {
+        let now = STEP * tick;
+        let sent = server.send_snapshot(schema, world(tick), now).unwrap();
+        let earlier = std::mem::take(&mut link.held_for_client);
+        let (delivered, held) = link.carry(drain(server), earlier);
+        link.held_for_client = held;
+        let events = delivered
+            .iter()
+            .map(|packet| client.receive(schema, packet, now).unwrap())
+            .collect();
+
+        let earlier = std::mem::take(&mut link.held_for_server);
+        let (delivered, held) = link.carry(drain(client), earlier);
+        link.held_for_server = held;
+        for packet in delivered {
+            server.receive(schema, &packet, now).unwrap();
+        }
+        server.handle_timeout(now);
+        client.handle_timeout(now);
+        (sent, events)
+    }
+
+    #[test]
+    fn bootstraps_then_sends_compact_deltas() {
+        let schema = schema();
+        let mut server = ServerEndpoint::new(config(), Some(7), Duration::ZERO);
+        let mut client = ClientEndpoint::new(config(), Duration::ZERO);
+        let mut link = LossyLink::new(1, 0);
+        assert_eq!(server.state(), EndpointState::Connecting);
+
+        let (sent, events) = step(&schema, &mut server, &mut client, &mut link, 1);
+        assert_eq!(
+            sent,
+            ServerSend::Full {
+                reason: ResyncReason::NoAck
+            }
+        );
+        assert_eq!(
+            events,
+            [
+                EndpointEvent::SessionStarted,
+                EndpointEvent::Applied(SnapshotTick::new(1))
+            ]
+        );
+        assert_eq!(client.session().unwrap().session_id, Some(7));
+        assert_eq!(server.state(), EndpointState::Established);
+
+        for tick in 2..=12 {
+            let (sent, events) = step(&schema, &mut server, &mut client, &mut link, tick);
+            assert_eq!(
+                sent,
+                ServerSend::Delta {
+                    baseline_tick: SnapshotTick::new(tick - 1),
+                    compact: true
+                }
+            );
+            assert_eq!(events, [EndpointEvent::Applied(SnapshotTick::new(tick))]);
+            assert_eq!(client.latest(), Some(&world(tick)));
+        }
+        assert_eq!(client.state(), EndpointState::Established);
+    }
+
+    /// Bootstraps a V1 session, loses tick 2 and returns the packets sent
+    /// for tick 3, which need a standard header.
+    fn standard_delta_after_loss(
+        schema: &Schema,
+        server: &mut ServerEndpoint,
+        client: &mut ClientEndpoint,
+    ) -> Vec<Vec<u8>> {
+        let mut link = LossyLink::new(1, 0);
+        step(schema, server, client, &mut link, 1);
+        server.send_snapshot(schema, world(2), STEP * 2).unwrap();
+        drain(server);
+        server.send_snapshot(schema, world(3), STEP * 3).unwrap();
+        drain(server)
+    }
+
+    #[test]
+    fn model_dictionary_compresses_standard_packets() {
+        let schema = schema();
+        let config = EndpointConfig {
+            compact_mode: CompactHeaderMode::SessionV1,
+            ..config()
+        };
+        let mut server = ServerEndpoint::new(config.clone(), None, Duration::ZERO);
+        let mut client = ClientEndpoint::new(config.clone(), Duration::ZERO);
+        let plain = standard_delta_after_loss(&schema, &mut server, &mut client);
+        assert_eq!(plain.len(), 1);
+        assert!(!is_compressed_packet(&plain[0]));
+
+        let mut trainer = ModelTrainer::new(&schema);
+        for _ in 0..2 {
+            trainer.observe_payload(&plain[0][wire::HEADER_SIZE..]);
+        }
+        let model = trainer.finish(&ModelTrainingConfig::default());
+
+        let mut server =
+            ServerEndpoint::new(config.clone(), None, Duration::ZERO).with_model(&model);
+        let mut client = ClientEndpoint::new(config.clone(), Duration::ZERO).with_model(&model);
+        let packets = standard_delta_after_loss(&schema, &mut server, &mut client);
+        assert_eq!(client.session().unwrap().model_hash, Some(model.hash()));
+        assert!(is_compressed_packet(&packets[0]));
+        assert!(packets[0].len() < plain[0].len());
+        assert_eq!(
+            client.receive(&schema, &packets[0], STEP * 3).unwrap(),
+            EndpointEvent::Applied(SnapshotTick::new(3))
+        );
+        assert_eq!(client.latest(), Some(&world(3)));
+
+        // A client without the model cannot join the session.
+        let mut server =
+            ServerEndpoint::new(config.clone(), None, Duration::ZERO).with_model(&model);
+        let mut client = ClientEndpoint::new(config, Duration::ZERO);
+        server.send_snapshot(&schema, world(1), STEP).unwrap();
+        let init = drain(&mut server).remove(0);
+        assert!(matches!(
+            client.receive(&schema, &init, STEP),
+            Err(CodecError::ModelMismatch { found: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn model_codes_full_snapshots() {
+        let schema = schema();
+        let mut trainer = ModelTrainer::new(&schema);
+        for entity in &world(1).entities {
+            trainer.observe_create(entity);
+        }
+        let model = trainer.finish(&ModelTrainingConfig::default());
+
+        let mut server = ServerEndpoint::new(config(), None, Duration::ZERO).with_model(&model);
+        let mut client = ClientEndpoint::new(config(), Duration::ZERO).with_model(&model);
+        server.send_snapshot(&schema, world(1), STEP).unwrap();
+        let packets = drain(&mut server);
+        let full = packets.last().unwrap();
+        let sections = wire::decode_packet(full, &wire::Limits::for_testing())
+            .unwrap()
+            .sections;
+        assert_eq!(sections[0].tag, wire::SectionTag::EntityCreateModeled);
+
+        let mut events = Vec::new();
+        for packet in &packets {
+            events.push(client.receive(&schema, packet, STEP).unwrap());
+        }
+        assert_eq!(
+            events.last(),
+            Some(&EndpointEvent::Applied(SnapshotTick::new(1)))
+        );
+        assert_eq!(client.latest(), Some(&world(1)));
+    }
+
+    /// Runs 600 ticks over `link`, checking every applied snapshot, then 20
+    /// clean ticks. Returns the full, compact delta, standard delta and
+    /// ignored packet counts.
+    fn run_lossy(mode: CompactHeaderMode, link: &mut LossyLink) -> [usize; 4] {
+        let schema = schema();
+        let config = EndpointConfig {
+            compact_mode: mode,
+            ..config()
+        };
+        let mut server = ServerEndpoint::new(config.clone(), None, Duration::ZERO);
+        let mut client = ClientEndpoint::new(config, Duration::ZERO);
+        let mut counts = [0; 4];
+
+        for tick in 1..=600 {
+            let (sent, events) = step(&schema, &mut server, &mut client, link, tick);
+            match sent {
+                ServerSend::Full { .. } => counts[0] += 1,
+                ServerSend::Delta { compact: true, .. } => counts[1] += 1,
+                ServerSend::Delta { compact: false, .. } => counts[2] += 1,
+                ServerSend::AwaitingAck => {}
+                ServerSend::TimedOut => panic!("endpoint timed out"),
+            }
+            for event in events {
+                match event {
+                    EndpointEvent::Applied(applied) => {
+                        assert_eq!(client.latest(), Some(&world(applied.raw())));
+                    }
+                    EndpointEvent::Ignored => counts[3] += 1,
+                    _ => {}
+                }
+            }
+        }
+
+        link.loss_percent = 0;
+        link.delay_percent = 0;
+        link.duplicate_percent = 0;
+        for tick in 601..=620 {
+            step(&schema, &mut server, &mut client, link, tick);
+        }
+        assert_eq!(client.latest(), Some(&world(620)));
+        assert_eq!(server.state(), EndpointState::Established);
+        assert_eq!(client.state(), EndpointState::Established);
+        counts
+    }
+
+    #[test]
+    fn converges_over_lossy_link() {
+        let mut link = LossyLink::new(0x5DEC_5EED, 25);
+        let [fulls, compact, standard, _] = run_lossy(CompactHeaderMode::SessionV2, &mut link);
+        assert!(fulls > 1, "no full snapshot was lost");
+        assert!(compact > 0);
+        assert_eq!(standard, 0);
+    }
+
+    #[test]
+    fn v1_falls_back_to_standard_headers_under_loss() {
+        let mut link = LossyLink::new(0x5DEC_5EED, 25);
+        let [_, compact, standard, _] = run_lossy(CompactHeaderMode::SessionV1, &mut link);
+        assert!(compact > 0 && standard > 0);
+    }
+
+    #[test]
+    fn tolerates_reordered_and_duplicate_packets() {
+        let mut link = LossyLink::new(0xC0FF_EE11, 10).with_reordering(20, 10);
+        let [_, compact, standard, ignored] = run_lossy(CompactHeaderMode::SessionV2, &mut link);
+        assert!(compact > 0);
+        assert_eq!(standard, 0);
+        assert!(ignored > 0, "no packet arrived late");
+    }
+
+    #[test]
+    fn survives_tick_wraparound() {
+        let schema = schema();
+        let mut server = ServerEndpoint::new(config(), None, Duration::ZERO);
+        let mut client = ClientEndpoint::new(config(), Duration::ZERO);
+        let mut link = LossyLink::new(9, 0);
+        let mut tick = SnapshotTick::new(u32::MAX - 3);
+        step(&schema, &mut server, &mut client, &mut link, tick.raw());
+
+        for _ in 0..8 {
+            let previous = tick;
+            tick = tick.next();
+            let (sent, events) = step(&schema, &mut server, &mut client, &mut link, tick.raw());
+            assert_eq!(
+                sent,
+                ServerSend::Delta {
+                    baseline_tick: previous,
+                    compact: true
+                }
+            );
+            assert_eq!(events, [EndpointEvent::Applied(tick)]);
+        }
+        assert_eq!(tick, SnapshotTick::new(5));
+        assert_eq!(client.latest(), Some(&world(5)));
+    }
+
+    #[test]
+    fn missing_baseline_nacks_and_resyncs() {
+        let schema = schema();
+        let mut server = ServerEndpoint::new(config(), None, Duration::ZERO);
+        let client_config = EndpointConfig {
+            baseline_capacity: NonZeroUsize::new(2).unwrap(),
+            ..config()
+        };
+        let mut client = ClientEndpoint::new(client_config, Duration::ZERO);
+        let mut link = LossyLink::new(3, 0);
+        step(&schema, &mut server, &mut client, &mut link, 1);
+
+        // Lose every ack so the server keeps using tick 1, which falls out of
+        // the client's two-entry ring at tick 4.
+        for tick in 2..=4 {
+            let now = STEP * tick;
+            let sent = server.send_snapshot(&schema, world(tick), now).unwrap();
+            assert_eq!(
+                sent,
+                ServerSend::Delta {
+                    baseline_tick: SnapshotTick::new(1),
+                    compact: true
+                }
+            );
+            let packet = server.poll_transmit().unwrap();
+            let event = client.receive(&schema, &packet, now).unwrap();
+            while client.poll_transmit().is_some() {}
+            if tick == 4 {
+                assert_eq!(
+                    event,
+                    EndpointEvent::ResyncRequested(wire::AckErrorClass::Baseline)
+                );
+            }
+        }
+        assert_eq!(client.state(), EndpointState::Resyncing);
+
+        // The next delta is ignored and the nack repeated.
+        let now = STEP * 5;
+        server.send_snapshot(&schema, world(5), now).unwrap();
+        let packet = server.poll_transmit().unwrap();
+        assert_eq!(
+            client.receive(&schema, &packet, now).unwrap(),
+            EndpointEvent::Ignored
+        );
+        let nack = client.poll_transmit().unwrap();
+        assert_eq!(
+            server.receive(&schema, &nack, now).unwrap(),
+            EndpointEvent::ResyncRequested(wire::AckErrorClass::Baseline)
+        );
+        assert_eq!(server.state(), EndpointState::Resyncing);
+
+        let (sent, events) = step(&schema, &mut server, &mut client, &mut link, 6);
+        assert_eq!(
+            sent,
+            ServerSend::Full {
+                reason: ResyncReason::Requested
+            }
+        );
+        assert_eq!(
+            events.last(),
+            Some(&EndpointEvent::Applied(SnapshotTick::new(6)))
+        );
+        assert_eq!(client.latest(), Some(&world(6)));
+        assert_eq!(server.state(), EndpointState::Established);
+    }
+
+    #[test]
+    fn resends_full_snapshot_and_times_out() {
+        let schema = schema();
+        let mut server = ServerEndpoint::new(config(), None, Duration::ZERO);
+        let mut client = ClientEndpoint::new(config(), Duration::ZERO);
+
+        server.send_snapshot(&schema, world(1), STEP).unwrap();
+        while server.poll_transmit().is_some() {}
+        for tick in 2..=6 {
+            assert_eq!(
+                server
+                    .send_snapshot(&schema, world(tick), STEP * tick)
+                    .unwrap(),
+                ServerSend::AwaitingAck
+            );
+        }
+        assert_eq!(
+            server.send_snapshot(&schema, world(7), STEP * 7).unwrap(),
+            ServerSend::Full {
+                reason: ResyncReason::NoAck
+            }
+        );
+        let mut link = LossyLink::new(5, 0);
+        while let Some(packet) = server.poll_transmit() {
+            client.receive(&schema, &packet, STEP * 7).unwrap();
+        }
+        assert_eq!(client.latest(), Some(&world(7)));
+
+        // A compact delta before any session is a session error.
+        let mut orphan = ClientEndpoint::new(config(), Duration::ZERO);
+        step(&schema, &mut server, &mut client, &mut link, 8);
+        server.send_snapshot(&schema, world(9), STEP * 9).unwrap();
+        let compact = server.poll_transmit().unwrap();
+        assert_eq!(
+            orphan.receive(&schema, &compact, STEP * 9).unwrap(),
+            EndpointEvent::ResyncRequested(wire::AckErrorClass::Session)
+        );
+
+        let silent = STEP * 9 + config().idle_timeout;
+        assert_eq!(server.handle_timeout(silent), EndpointState::TimedOut);
+        assert_eq!(client.handle_timeout(silent), EndpointState::TimedOut);
+        assert_eq!(
+            server.send_snapshot(&schema, world(100), silent).unwrap(),
+            ServerSend::TimedOut
+        );
+        assert!(server.poll_transmit().is_none());
+    }
+
+    /// Delivers everything `from` has queued to `to`.
+    fn deliver(
+        schema: &Schema,
+        from: &mut impl SessionEndpoint,
+        to: &mut impl SessionEndpoint,
+        now: Duration,
+    ) -> Vec<EndpointEvent> {
+        drain(from)
+            .iter()
+            .map(|packet| to.receive(schema, packet, now).unwrap())
+            .collect()
+    }
+
+    /// Runs ticks 1..=10 cleanly, then loses ticks 11..=13 and everything
+    /// else until both endpoints time out.
+    fn disconnect_after_ten_ticks(
+        schema: &Schema,
+        server: &mut ServerEndpoint,
+        client: &mut ClientEndpoint,
+    ) -> Duration {
+        let mut link = LossyLink::new(11, 0);
+        for tick in 1..=10 {
+            step(schema, server, client, &mut link, tick);
+        }
+        for tick in 11..=13 {
+            server
+                .send_snapshot(schema, world(tick), STEP * tick)
+                .unwrap();
+            drain(server);
+        }
+        let later = STEP * 13 + config().idle_timeout;
+        assert_eq!(server.handle_timeout(later), EndpointState::TimedOut);
+        assert_eq!(client.handle_timeout(later), EndpointState::TimedOut);
+        later
+    }
+
+    #[test]
+    fn resumes_after_reconnect_without_full_snapshot() {
+        let schema = schema();
+        let mut server =
+            ServerEndpoint::new(config(), Some(7), Duration::ZERO).with_resume_token(0x5EC2E7);
+        let mut client = ClientEndpoint::new(config(), Duration::ZERO);
+        let now = disconnect_after_ten_ticks(&schema, &mut server, &mut client);
+        assert_eq!(client.session().unwrap().resume_token, Some(0x5EC2E7));
+
+        assert!(client.request_resume(&schema, now).unwrap());
+        assert_eq!(
+            deliver(&schema, &mut client, &mut server, now),
+            [EndpointEvent::Resumed(SnapshotTick::new(10))]
+        );
+        assert_eq!(server.state(), EndpointState::Established);
+        assert_eq!(
+            deliver(&schema, &mut server, &mut client, now),
+            [EndpointEvent::Resumed(SnapshotTick::new(10))]
+        );
+        assert_eq!(
+            deliver(&schema, &mut client, &mut server, now),
+            [EndpointEvent::Ignored]
+        );
+
+        let sent = server.send_snapshot(&schema, world(14), now).unwrap();
+        assert_eq!(
+            sent,
+            ServerSend::Delta {
+                baseline_tick: SnapshotTick::new(10),
+                compact: true
+            }
+        );
+        assert_eq!(
+            deliver(&schema, &mut server, &mut client, now),
+            [EndpointEvent::Applied(SnapshotTick::new(14))]
+        );
+        assert_eq!(client.latest(), Some(&world(14)));
+        assert_eq!(client.state(), EndpointState::Established);
+    }
+
+    #[test]
+    fn stale_or_unknown_resume_falls_back_to_full_snapshot() {
+        let schema = schema();
+        let small = EndpointConfig {
+            baseline_capacity: NonZeroUsize::new(2).unwrap(),
+            ..config()
+        };
+        let cases = [
+            (
+                config(),
+                0xBAD,
+                Duration::ZERO,
+                ResumeRejection::UnknownToken,
+            ),
+            (
+                config(),
+                0x5EC2E7,
+                config().resume_window,
+                ResumeRejection::Expired,
+            ),
+            (
+                small,
+                0x5EC2E7,
+                Duration::ZERO,
+                ResumeRejection::BaselineGone {
+                    tick: SnapshotTick::new(10),
+                },
+            ),
+        ];
+        for (server_config, token, extra_delay, rejection) in cases {
+            let mut server = ServerEndpoint::new(server_config, Some(7), Duration::ZERO)
+                .with_resume_token(token);
+            let mut client = ClientEndpoint::new(config(), Duration::ZERO);
+            let mut now = disconnect_after_ten_ticks(&schema, &mut server, &mut client);
+            now += extra_delay;
+            if token != 0x5EC2E7 {
+                // The client resumes with the token of a different session.
+                let mut forged = client.session().unwrap().clone();
+                forged.resume_token = Some(0x5EC2E7);
+                client.session = Some(forged);
+            }
+
+            assert!(client.request_resume(&schema, now).unwrap());
+            assert_eq!(
+                deliver(&schema, &mut client, &mut server, now),
+                [EndpointEvent::ResumeRejected(rejection)]
+            );
+            assert_eq!(
+                server.send_snapshot(&schema, world(14), now).unwrap(),
+                ServerSend::Full {
+                    reason: ResyncReason::Requested
+                }
+            );
+            assert_eq!(
+                deliver(&schema, &mut server, &mut client, now),
+                [
+                    EndpointEvent::SessionStarted,
+                    EndpointEvent::Applied(SnapshotTick::new(14))
+                ]
+            );
+            deliver(&schema, &mut client, &mut server, now);
+            assert_eq!(server.state(), EndpointState::Established);
+            assert_eq!(client.latest(), Some(&world(14)));
+        }
+
+        // Without a token from the server there is nothing to resume.
+        let mut server = ServerEndpoint::new(config(), Some(7), Duration::ZERO);
+        let mut client = ClientEndpoint::new(config(), Duration::ZERO);
+        let now = disconnect_after_ten_ticks(&schema, &mut server, &mut client);
+        assert_eq!(client.session().unwrap().resume_token, None);
+        assert!(!client.request_resume(&schema, now).unwrap());
+    }
+
+    #[test]
+    fn negotiates_capabilities_before_using_them() {
+        let schema = schema();
+        let all = NegotiatedCaps::all();
+        let baseline = NegotiatedCaps::baseline();
+        for (client_caps, expected) in [(all, all), (baseline, baseline)] {
+            let mut server = ServerEndpoint::new(config(), Some(7), Duration::ZERO);
+            let client_config = EndpointConfig {
+                capabilities: client_caps,
+                ..config()
+            };
+            let mut client = ClientEndpoint::new(client_config, Duration::ZERO);
+            server
+                .send_snapshot(&schema, world(1), Duration::ZERO)
+                .unwrap();
+            assert_eq!(server.capabilities(), baseline);
+            assert_eq!(
+                deliver(&schema, &mut server, &mut client, Duration::ZERO),
+                [
+                    EndpointEvent::SessionStarted,
+                    EndpointEvent::Applied(SnapshotTick::new(1))
+                ]
+            );
+            assert_eq!(client.capabilities(), expected);
+            assert_eq!(
+                deliver(&schema, &mut client, &mut server, Duration::ZERO),
+                [
+                    EndpointEvent::Negotiated(expected),
+                    EndpointEvent::Acked(SnapshotTick::new(1))
+                ]
+            );
+            assert_eq!(server.capabilities(), expected);
+
+            let mut link = LossyLink::new(5, 0);
+            for tick in 2..=30 {
+                let (_, events) = step(&schema, &mut server, &mut client, &mut link, tick);
+                assert_eq!(events, [EndpointEvent::Applied(SnapshotTick::new(tick))]);
+                assert_eq!(client.latest(), Some(&world(tick)));
+            }
+        }
+    }
+
+    #[test]
+    fn server_without_capabilities_gets_no_accept() {
+        let schema = schema();
+        let mut client = ClientEndpoint::new(config(), Duration::ZERO);
+        let mut init = vec![0u8; 128];
+        let len = encode_session_init_packet(
+            &schema,
+            SnapshotTick::new(1),
+            &SessionInitOptions::new(CompactHeaderMode::SessionV2).session_id(Some(7)),
+            &CodecLimits::default(),
+            &mut init,
+        )
+        .unwrap();
+        assert_eq!(
+            client
+                .receive(&schema, &init[..len], Duration::ZERO)
+                .unwrap(),
+            EndpointEvent::SessionStarted
+        );
+        assert_eq!(client.capabilities(), NegotiatedCaps::baseline());
+        assert!(client.poll_transmit().is_none());
+    }
+
+    #[test]
+    fn compressed_packets_need_negotiated_lz4() {
+        let schema = schema();
+        let mut client = ClientEndpoint::new(config(), Duration::ZERO);
+        let mut init = vec![0u8; 128];
+        let len = encode_session_init_packet(
+            &schema,
+            SnapshotTick::new(1),
+            &SessionInitOptions::new(CompactHeaderMode::SessionV2).session_id(Some(7)),
+            &CodecLimits::default(),
+            &mut init,
+        )
+        .unwrap();
+        client
+            .receive(&schema, &init[..len], Duration::ZERO)
+            .unwrap();
+        assert!(config()
+            .capabilities
+            .supports_compressor(wire::Lz4Compressor::ID));
+        assert!(!client
+            .capabilities()
+            .supports_compressor(wire::Lz4Compressor::ID));
+
+        let entities: Vec<EntitySnapshot> = (1..=64)
+            .map(|id| EntitySnapshot {
+                id: EntityId::new(id),
+                components: vec![ComponentSnapshot {
+                    id: ComponentId::new(1).unwrap(),
+                    fields: vec![FieldValue::UInt(7)],
+                }],
+            })
+            .collect();
+        let mut full = vec![0u8; 1024];
+        let len = crate::snapshot::encode_full_snapshot(
+            &schema,
+            SnapshotTick::new(1),
+            &entities,
+            &CodecLimits::default(),
+            &mut full,
+        )
+        .unwrap();
+        let mut compressed = vec![0u8; len];
+        let len = wire::compress_packet(&full[..len], &wire::Lz4Compressor::new(), &mut compressed)
+            .unwrap();
+        assert!(is_compressed_packet(&compressed[..len]));
+        assert!(matches!(
+            client.receive(&schema, &compressed[..len], Duration::ZERO),
+            Err(CodecError::Wire(wire::DecodeError::CompressedPacket))
+        ));
+    }
+
+    #[test]
+    fn server_restart_starts_a_new_session() {
+        let schema = schema();
+        let mut server = ServerEndpoint::new(config(), Some(7), Duration::ZERO);
+        let mut client = ClientEndpoint::new(config(), Duration::ZERO);
+        let mut link = LossyLink::new(1, 0);
+        for tick in 1..=12 {
+            step(&schema, &mut server, &mut client, &mut link, tick);
+        }
+        assert_eq!(client.latest(), Some(&world(12)));
+
+        // A delayed init of the current session is still ignored.
+        let mut init = vec![0u8; 128];
+        let len = encode_session_init_packet(
+            &schema,
+            SnapshotTick::new(1),
+            &SessionInitOptions::new(CompactHeaderMode::SessionV2).session_id(Some(7)),
+            &CodecLimits::default(),
+            &mut init,
+        )
+        .unwrap();
+        assert_eq!(
+            client.receive(&schema, &init[..len], STEP * 12).unwrap(),
+            EndpointEvent::Ignored
+        );
+
+        // The restarted server counts ticks from scratch.
+        let now = STEP * 13;
+        let mut server = ServerEndpoint::new(config(), Some(8), now);
+        server.send_snapshot(&schema, world(3), now).unwrap();
+        assert_eq!(
+            deliver(&schema, &mut server, &mut client, now),
+            [
+                EndpointEvent::SessionStarted,
+                EndpointEvent::Applied(SnapshotTick::new(3))
+            ]
+        );
+        assert_eq!(client.session().unwrap().session_id, Some(8));
+        assert_eq!(client.latest(), Some(&world(3)));
+    }
+}
@@ -0,0 +1,258 @@
+//! Optional entropy coding of update section bodies.
+//!
+//! Bit-packed update bodies still carry redundancy for skewed value
+//! distributions (mostly-unchanged masks, small residuals). This pass re-codes
+//! `ENTITY_UPDATE` and `ENTITY_UPDATE_SPARSE_PACKED` bodies with the adaptive
+//! range coder from `bitstream` and swaps in the coded section tag only when
+//! the result is strictly smaller. Decoders accept either form.
+
+use bitstream::{range_decode_bytes, range_encode_bytes, BitReader, BitVecWriter};
+use wire::SectionTag;
+
+use crate::delta::varu32_len;
+use crate::error::{CodecError, CodecResult, LimitKind};
+use crate::limits::CodecLimits;
+
+/// Entropy-codes the update sections of an encoded packet in place.
+///
+/// `out[..packet_len]` must hold a packet with a standard header (full or
+/// delta). Each update section is replaced by its coded form when that is
+/// smaller; other sections are copied unchanged and the header's
+/// `payload_len` is patched. Returns the new packet length, which is never
+/// larger than `packet_len`.
+pub fn entropy_code_update_sections(out: &mut [u8], packet_len: usize) -> CodecResult<usize> {
+    if packet_len < wire::HEADER_SIZE || packet_len > out.len() {
+        return Err(CodecError::OutputTooSmall {
+            needed: wire::HEADER_SIZE.max(packet_len),
+            available: out.len(),
+        });
+    }
+
+    let payload_len = entropy_code_payload(
+        &mut out[wire::HEADER_SIZE..],
+        packet_len - wire::HEADER_SIZE,
+    )?;
+    out[wire::HEADER_SIZE - 4..wire::HEADER_SIZE]
+        .copy_from_slice(&(payload_len as u32).to_le_bytes());
+    Ok(wire::HEADER_SIZE + payload_len)
+}
+
+/// Entropy-codes the update sections of a section payload in place.
+///
+/// `payload[..payload_len]` holds the sections of a packet with either header
+/// form. Returns the new payload length, which is never larger than
+/// `payload_len`. A payload carrying optional sections this build does not
+/// know is left as is, since re-encoding would drop them.
+pub(crate) fn entropy_code_payload(payload: &mut [u8], payload_len: usize) -> CodecResult<usize> {
+    let (sections, skipped) =
+        wire::decode_sections_with_skipped(&payload[..payload_len], &wire::Limits::unlimited())?;
+    if !skipped.is_empty() {
+        return Ok(payload_len);
+    }
+    let mut writer = BitVecWriter::new();
+    let mut coded = Vec::new();
+    for section in &sections {
+        let coded_tag = match section.tag {
+            SectionTag::EntityUpdate => Some(SectionTag::EntityUpdateCoded),
+            SectionTag::EntityUpdateSparsePacked => Some(SectionTag::EntityUpdateSparsePackedCoded),
+            _ => None,
+        };
+        match coded_tag.zip(code_body(section.body, &mut coded)) {
+            Some((tag, coded_len)) => {
+                let decoded_len = section.body.len() as u32;
+                let body_len = varu32_len(decoded_len) + coded_len;
+                writer.write_u8_aligned(tag as u8)?;
+                writer.write_varu32(body_len as u32)?;
+                writer.write_varu32(decoded_len)?;
+                write_bytes(&mut writer, &coded[..coded_len])?;
+            }
+            None => {
+                writer.write_u8_aligned(section.tag as u8)?;
+                writer.write_varu32(section.body.len() as u32)?;
+                write_bytes(&mut writer, section.body)?;
+            }
+        }
+    }
+
+    let coded_payload = writer.finish();
+    payload[..coded_payload.len()].copy_from_slice(&coded_payload);
+    Ok(coded_payload.len())
+}
+
+/// Decodes the body of a coded update section back to its plain form.
+///
+/// The decoded length is bounded by `limits.max_section_bytes` before any
+/// allocation happens.
+pub(crate) fn decode_coded_body(body: &[u8], limits: &CodecLimits) -> CodecResult<Vec<u8>> {
+    if body.len() > limits.max_section_bytes {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::SectionBytes,
+            limit: limits.max_section_bytes,
+            actual: body.len(),
+        });
+    }
+    let mut reader = BitReader::new(body);
+    let decoded_len = reader.read_varu32()? as usize;
+    if decoded_len > limits.max_section_bytes {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::SectionBytes,
+            limit: limits.max_section_bytes,
+            actual: decoded_len,
+        });
+    }
+    let start = reader.bit_position() / 8;
+    let mut decoded = vec![0u8; decoded_len];
+    range_decode_bytes(&body[start..], &mut decoded)?;
+    Ok(decoded)
+}
+
+/// Range-codes `body` into `scratch`, returning the coded length only if the
+/// coded section body (length prefix included) is smaller than `body`.
+fn code_body(body: &[u8], scratch: &mut Vec<u8>) -> Option<usize> {
+    let len = u32::try_from(body.len()).ok()?;
+    let budget = body.len().checked_sub(varu32_len(len) + 1)?;
+    scratch.clear();
+    scratch.resize(budget, 0);
+    range_encode_bytes(body, scratch).ok()
+}
+
+pub(crate) fn write_bytes(writer: &mut BitVecWriter, bytes: &[u8]) -> CodecResult<()> {
+    for byte in bytes {
+        writer.write_u8_aligned(*byte)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::delta::{apply_delta_snapshot, encode_delta_snapshot};
+    use crate::snapshot::{ComponentSnapshot, EntitySnapshot, FieldValue, Snapshot};
+    use crate::types::{EntityId, SnapshotTick};
+    use schema::{ComponentDef, ComponentId, FieldCodec, FieldDef, FieldId, Schema};
+
+    fn schema_flag_and_counter() -> Schema {
+        let component = ComponentDef::new(ComponentId::new(1).unwrap())
+            .field(FieldDef::new(FieldId::new(1).unwrap(), FieldCodec::bool()))
+            .field(FieldDef::new(
+                FieldId::new(2).unwrap(),
+                FieldCodec::uint(12),
+            ));
+        Schema::new(vec![component]).unwrap()
+    }
+
+    fn snapshot(tick: u32, flag: impl Fn(u32) -> bool, counter: u64) -> Snapshot {
+        Snapshot {
+            tick: SnapshotTick::new(tick),
+            entities: (1..=64)
+                .map(|id| EntitySnapshot {
+                    id: EntityId::from(id),
+                    components: vec![ComponentSnapshot {
+                        id: ComponentId::new(1).unwrap(),
+                        fields: vec![FieldValue::Bool(flag(id)), FieldValue::UInt(counter)],
+                    }],
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn coded_delta_roundtrip_is_smaller() {
+        let schema = schema_flag_and_counter();
+        let limits = CodecLimits::for_testing();
+        let baseline = snapshot(10, |_| false, 100);
+        let current = snapshot(11, |id| id % 16 == 0, 101);
+
+        let mut buf = [0u8; 2048];
+        let plain_len = encode_delta_snapshot(
+            &schema,
+            current.tick,
+            baseline.tick,
+            &baseline,
+            &current,
+            &limits,
+            &mut buf,
+        )
+        .unwrap();
+        let coded_len = entropy_code_update_sections(&mut buf, plain_len).unwrap();
+        assert!(coded_len < plain_len, "{coded_len} >= {plain_len}");
+
+        let packet = wire::decode_packet(&buf[..coded_len], &wire::Limits::for_testing()).unwrap();
+        assert!(packet.sections.iter().any(|section| matches!(
+            section.tag,
+            SectionTag::EntityUpdateCoded | SectionTag::EntityUpdateSparsePackedCoded
+        )));
+        let applied = apply_delta_snapshot(
+            &schema,
+            &baseline,
+            &buf[..coded_len],
+            &wire::Limits::for_testing(),
+            &limits,
+        )
+        .unwrap();
+        assert_eq!(applied.entities, current.entities);
+    }
+
+    #[test]
+    fn small_packet_is_left_unchanged() {
+        let schema = schema_flag_and_counter();
+        let limits = CodecLimits::for_testing();
+        let baseline = snapshot(10, |_| false, 100);
+        let mut current = baseline.clone();
+        current.tick = SnapshotTick::new(11);
+        current.entities[3].components[0].fields[0] = FieldValue::Bool(true);
+
+        let mut buf = [0u8; 2048];
+        let plain_len = encode_delta_snapshot(
+            &schema,
+            current.tick,
+            baseline.tick,
+            &baseline,
+            &current,
+            &limits,
+            &mut buf,
+        )
+        .unwrap();
+        let before = buf[..plain_len].to_vec();
+        let coded_len = entropy_code_update_sections(&mut buf, plain_len).unwrap();
+        assert_eq!(&buf[..coded_len], before.as_slice());
+    }
+
+    #[test]
+    fn coded_body_roundtrip() {
+        let body: Vec<u8> = (0..200u32).map(|i| u8::from(i % 9 == 0)).collect();
+        let mut scratch = Vec::new();
+        let coded_len = code_body(&body, &mut scratch).expect("skewed body should win");
+
+        let mut writer = BitVecWriter::new();
+        writer.write_varu32(body.len() as u32).unwrap();
+        let mut section = writer.finish();
+        section.extend_from_slice(&scratch[..coded_len]);
+        let decoded = decode_coded_body(&section, &CodecLimits::for_testing()).unwrap();
+        assert_eq!(decoded, body);
+    }
+
+    #[test]
+    fn incompressible_body_is_not_coded() {
+        let body = [0x5Au8, 0x13, 0xC7];
+        let mut scratch = Vec::new();
+        assert_eq!(code_body(&body, &mut scratch), None);
+    }
+
+    #[test]
+    fn decoded_len_over_limit_is_rejected() {
+        let limits = CodecLimits {
+            max_section_bytes: 8,
+            ..CodecLimits::for_testing()
+        };
+        let body = [64u8, 0, 0, 0, 0];
+        assert!(matches!(
+            decode_coded_body(&body, &limits),
+            Err(CodecError::LimitsExceeded {
+                kind: LimitKind::SectionBytes,
+                actual: 64,
+                ..
+            })
+        ));
+    }
+}
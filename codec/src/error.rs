@@ -2,7 +2,9 @@
 
 use std::fmt;
 
-use schema::{ComponentId, FieldId};
+use schema::{ComponentId, EventId, FieldId};
+
+use crate::caps::Capability;
 
 /// Result type for codec operations.
 pub type CodecResult<T> = Result<T, CodecError>;
@@ -40,7 +42,7 @@ pub enum CodecError {
     },
 
     /// Entities are not provided in deterministic order.
-    InvalidEntityOrder { previous: u32, current: u32 },
+    InvalidEntityOrder { previous: u64, current: u64 },
 
     /// Section body had trailing bits after parsing.
     TrailingSectionData {
@@ -69,27 +71,43 @@ pub enum CodecError {
     /// Entity not found when applying delta.
     EntityNotFound {
         /// The missing entity ID.
-        entity_id: u32,
+        entity_id: u64,
     },
 
     /// Component not found when applying delta.
     ComponentNotFound {
         /// The entity ID.
-        entity_id: u32,
+        entity_id: u64,
         /// The missing component ID.
         component_id: u16,
     },
 
+    /// Field id not defined for a component in the schema.
+    FieldNotFound {
+        /// The component ID.
+        component_id: u16,
+        /// The unknown field ID.
+        field_id: u16,
+    },
+
+    /// Component already present when inserting it.
+    ComponentAlreadyExists {
+        /// The entity ID.
+        entity_id: u64,
+        /// The existing component ID.
+        component_id: u16,
+    },
+
     /// Duplicate entity in create section.
     DuplicateEntity {
         /// The duplicate entity ID.
-        entity_id: u32,
+        entity_id: u64,
     },
 
     /// Entity already exists when creating.
     EntityAlreadyExists {
         /// The existing entity ID.
-        entity_id: u32,
+        entity_id: u64,
     },
 
     /// Missing session state for compact packets.
@@ -103,6 +121,71 @@ pub enum CodecError {
 
     /// Session packets arrived out of order.
     SessionOutOfOrder { previous: u32, current: u32 },
+
+    /// A session packet is a duplicate or older than the newest one decoded.
+    /// It is safe to drop; see [`CodecError::is_droppable`].
+    SessionLatePacket { latest: u32, tick: u32 },
+
+    /// Fragment index/count is invalid or inconsistent with earlier fragments.
+    InvalidFragment { index: u32, count: u32 },
+
+    /// Per-entity baseline reference does not point before the packet tick.
+    InvalidBaselineRef { entity_id: u64, baseline_delta: u32 },
+
+    /// Event ID is not defined in the schema.
+    UnknownEvent { event_id: u32 },
+
+    /// Invalid field value for an event.
+    InvalidEventValue {
+        event: EventId,
+        field: FieldId,
+        reason: ValueReason,
+    },
+
+    /// Event field values do not match the schema definition.
+    EventFieldCountMismatch {
+        event: EventId,
+        expected: usize,
+        actual: usize,
+    },
+
+    /// Event sequence numbers are not strictly increasing.
+    InvalidEventOrder { previous: u32, current: u32 },
+
+    /// Event outbox is full of unacknowledged events.
+    EventOutboxFull { capacity: usize },
+
+    /// Model artifact bytes are malformed.
+    InvalidModel,
+
+    /// Peer announced a different model than the one loaded locally (0 = none).
+    ModelMismatch { expected: u64, found: u64 },
+
+    /// Modeled section names a histogram value or create template the model
+    /// does not have.
+    ModelIndexOutOfRange { index: u64, count: usize },
+
+    /// A feature the session needs was not negotiated with the peer.
+    CapabilityUnsupported { capability: Capability },
+
+    /// Id list header declares a gap wider than the schema's entity id width.
+    InvalidIdGapBits { bits: u8 },
+
+    /// Gap-coded entity id does not fit in a `u64`.
+    IdGapOverflow { previous: u64, gap: u64 },
+
+    /// Entity id does not fit the schema's entity id width.
+    EntityIdOutOfRange { entity_id: u64, bits: u8 },
+
+    /// Id list header declares a generation width outside 1..=16 bits.
+    InvalidGenerationBits { bits: u8 },
+
+    /// A reference names a live entity index with a different generation.
+    StaleEntity {
+        entity_id: u64,
+        expected_generation: u16,
+        found_generation: u16,
+    },
 }
 
 impl CodecError {
@@ -110,18 +193,35 @@ impl CodecError {
     /// session init + full snapshot.
     #[must_use]
     pub fn needs_resync(&self) -> bool {
-        matches!(
-            self,
+        self.ack_error_class().is_some()
+    }
+
+    /// Returns `true` if the packet was late or duplicated and should simply
+    /// be ignored; session and baseline state are unaffected.
+    #[must_use]
+    pub fn is_droppable(&self) -> bool {
+        matches!(self, Self::SessionLatePacket { .. })
+    }
+
+    /// Returns the error class a client reports in its ack when this error
+    /// requires a resync, or `None` if it does not.
+    #[must_use]
+    pub fn ack_error_class(&self) -> Option<wire::AckErrorClass> {
+        match self {
             Self::SessionMissing
-                | Self::SessionInitInvalid
-                | Self::SessionUnsupportedMode { .. }
-                | Self::SessionOutOfOrder { .. }
-                | Self::SchemaMismatch { .. }
-                | Self::BaselineNotFound { .. }
-                | Self::BaselineTickMismatch { .. }
-                | Self::Wire(wire::DecodeError::InvalidBaselineTick { .. })
-                | Self::Wire(wire::DecodeError::InvalidFlags { .. })
-        )
+            | Self::SessionInitInvalid
+            | Self::SessionUnsupportedMode { .. }
+            | Self::SessionOutOfOrder { .. } => Some(wire::AckErrorClass::Session),
+            Self::SchemaMismatch { .. } => Some(wire::AckErrorClass::Schema),
+            Self::BaselineNotFound { .. } | Self::BaselineTickMismatch { .. } => {
+                Some(wire::AckErrorClass::Baseline)
+            }
+            Self::Wire(
+                wire::DecodeError::InvalidBaselineTick { .. }
+                | wire::DecodeError::InvalidFlags { .. },
+            ) => Some(wire::AckErrorClass::Framing),
+            _ => None,
+        }
     }
 }
 
@@ -135,6 +235,9 @@ pub enum LimitKind {
     ComponentsPerEntity,
     FieldsPerComponent,
     SectionBytes,
+    Fragments,
+    ReassemblyBytes,
+    Events,
 }
 
 /// Mask validation error kinds.
@@ -153,7 +256,7 @@ pub enum MaskReason {
     UnknownComponent { component: ComponentId },
     InvalidComponentId { raw: u16 },
     InvalidFieldIndex { field_index: usize, max: usize },
-    ComponentPresenceMismatch { component: ComponentId },
+    UnorderedComponent { component: ComponentId },
     EmptyFieldMask { component: ComponentId },
 }
 
@@ -183,6 +286,13 @@ pub enum ValueReason {
         expected: &'static str,
         found: &'static str,
     },
+    ResidualOutOfRange {
+        bits: u8,
+        value: i64,
+    },
+    PredictionOutOfRange {
+        value: i64,
+    },
 }
 
 impl fmt::Display for CodecError {
@@ -258,6 +368,24 @@ impl fmt::Display for CodecError {
                     "component {component_id} not found on entity {entity_id}"
                 )
             }
+            Self::FieldNotFound {
+                component_id,
+                field_id,
+            } => {
+                write!(
+                    f,
+                    "field {field_id} not defined for component {component_id}"
+                )
+            }
+            Self::ComponentAlreadyExists {
+                entity_id,
+                component_id,
+            } => {
+                write!(
+                    f,
+                    "component {component_id} already exists on entity {entity_id}"
+                )
+            }
             Self::DuplicateEntity { entity_id } => {
                 write!(f, "duplicate entity {entity_id} in create section")
             }
@@ -276,6 +404,88 @@ impl fmt::Display for CodecError {
             Self::SessionOutOfOrder { previous, current } => {
                 write!(f, "session packet out of order: {previous} then {current}")
             }
+            Self::SessionLatePacket { latest, tick } => {
+                write!(
+                    f,
+                    "late session packet for tick {tick}, already at {latest}"
+                )
+            }
+            Self::InvalidFragment { index, count } => {
+                write!(f, "invalid fragment {index} of {count}")
+            }
+            Self::InvalidBaselineRef {
+                entity_id,
+                baseline_delta,
+            } => {
+                write!(
+                    f,
+                    "invalid baseline delta {baseline_delta} for entity {entity_id}"
+                )
+            }
+            Self::UnknownEvent { event_id } => {
+                write!(f, "unknown event {event_id}")
+            }
+            Self::InvalidEventValue {
+                event,
+                field,
+                reason,
+            } => {
+                write!(f, "invalid value for event {event:?}:{field:?}: {reason}")
+            }
+            Self::EventFieldCountMismatch {
+                event,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "event {event:?} field count mismatch: expected {expected}, got {actual}"
+                )
+            }
+            Self::InvalidEventOrder { previous, current } => {
+                write!(f, "event order invalid: seq {previous} then {current}")
+            }
+            Self::EventOutboxFull { capacity } => {
+                write!(f, "event outbox full: {capacity} unacknowledged events")
+            }
+            Self::InvalidModel => write!(f, "invalid model artifact"),
+            Self::ModelMismatch { expected, found } => {
+                write!(
+                    f,
+                    "model mismatch: expected 0x{expected:016X}, found 0x{found:016X}"
+                )
+            }
+            Self::ModelIndexOutOfRange { index, count } => {
+                write!(f, "model index {index} out of range ({count} entries)")
+            }
+            Self::CapabilityUnsupported { capability } => {
+                write!(f, "capability {capability:?} not negotiated with peer")
+            }
+            Self::InvalidIdGapBits { bits } => {
+                write!(
+                    f,
+                    "invalid id gap width: {bits} bits exceeds entity id width"
+                )
+            }
+            Self::IdGapOverflow { previous, gap } => {
+                write!(f, "entity id overflow: gap {gap} after id {previous}")
+            }
+            Self::EntityIdOutOfRange { entity_id, bits } => {
+                write!(f, "entity id {entity_id} does not fit in {bits} bits")
+            }
+            Self::InvalidGenerationBits { bits } => {
+                write!(f, "invalid generation width: {bits} bits (expected 1..=16)")
+            }
+            Self::StaleEntity {
+                entity_id,
+                expected_generation,
+                found_generation,
+            } => {
+                write!(
+                    f,
+                    "stale entity {entity_id}: generation {found_generation}, live generation {expected_generation}"
+                )
+            }
         }
     }
 }
@@ -290,6 +500,9 @@ impl fmt::Display for LimitKind {
             Self::ComponentsPerEntity => "components per entity",
             Self::FieldsPerComponent => "fields per component",
             Self::SectionBytes => "section bytes",
+            Self::Fragments => "fragments",
+            Self::ReassemblyBytes => "reassembly bytes",
+            Self::Events => "events",
         };
         write!(f, "{name}")
     }
@@ -328,8 +541,8 @@ impl fmt::Display for MaskReason {
             Self::InvalidFieldIndex { field_index, max } => {
                 write!(f, "field index {field_index} exceeds max {max}")
             }
-            Self::ComponentPresenceMismatch { component } => {
-                write!(f, "component presence mismatch for {component:?}")
+            Self::UnorderedComponent { component } => {
+                write!(f, "component {component:?} is repeated or out of order")
             }
             Self::EmptyFieldMask { component } => {
                 write!(f, "empty field mask for {component:?} is invalid")
@@ -363,6 +576,12 @@ impl fmt::Display for ValueReason {
             Self::TypeMismatch { expected, found } => {
                 write!(f, "expected {expected} but got {found}")
             }
+            Self::ResidualOutOfRange { bits, value } => {
+                write!(f, "residual {value} does not fit in {bits} bits")
+            }
+            Self::PredictionOutOfRange { value } => {
+                write!(f, "predicted value {value} outside field range")
+            }
         }
     }
 }
@@ -401,6 +620,94 @@ mod tests {
         assert!(msg.contains("baseline"), "should mention baseline");
     }
 
+    #[test]
+    fn error_display_invalid_fragment() {
+        let err = CodecError::InvalidFragment { index: 4, count: 3 };
+        let msg = err.to_string();
+        assert!(
+            msg.contains("fragment 4 of 3"),
+            "should mention index and count"
+        );
+    }
+
+    #[test]
+    fn error_display_invalid_baseline_ref() {
+        let err = CodecError::InvalidBaselineRef {
+            entity_id: 7,
+            baseline_delta: 0,
+        };
+        let msg = err.to_string();
+        assert!(msg.contains("entity 7"), "should mention entity id");
+    }
+
+    #[test]
+    fn error_display_unknown_event() {
+        let err = CodecError::UnknownEvent { event_id: 9 };
+        let msg = err.to_string();
+        assert!(msg.contains("event 9"), "should mention event id");
+    }
+
+    #[test]
+    fn error_display_model_mismatch() {
+        let err = CodecError::ModelMismatch {
+            expected: 0xAB,
+            found: 0,
+        };
+        let msg = err.to_string();
+        assert!(msg.contains("model mismatch"));
+        assert!(msg.contains("00000000000000AB"));
+    }
+
+    #[test]
+    fn error_display_capability_unsupported() {
+        let err = CodecError::CapabilityUnsupported {
+            capability: Capability::EntityIdWidths,
+        };
+        let msg = err.to_string();
+        assert!(msg.contains("EntityIdWidths"), "should name the capability");
+    }
+
+    #[test]
+    fn error_display_id_gap_overflow() {
+        let err = CodecError::IdGapOverflow {
+            previous: 4_000_000_000,
+            gap: 500_000_000,
+        };
+        let msg = err.to_string();
+        assert!(msg.contains("4000000000"), "should mention previous id");
+        assert!(msg.contains("500000000"), "should mention gap");
+    }
+
+    #[test]
+    fn error_display_entity_id_out_of_range() {
+        let err = CodecError::EntityIdOutOfRange {
+            entity_id: 70_000,
+            bits: 16,
+        };
+        let msg = err.to_string();
+        assert!(msg.contains("70000"), "should mention entity id");
+        assert!(msg.contains("16 bits"), "should mention width");
+    }
+
+    #[test]
+    fn error_display_stale_entity() {
+        let err = CodecError::StaleEntity {
+            entity_id: 12,
+            expected_generation: 3,
+            found_generation: 2,
+        };
+        let msg = err.to_string();
+        assert!(msg.contains("stale entity 12"), "should mention entity id");
+        assert!(
+            msg.contains("generation 2"),
+            "should mention stale generation"
+        );
+        assert!(
+            msg.contains("live generation 3"),
+            "should mention live generation"
+        );
+    }
+
     #[test]
     fn error_display_entity_not_found() {
         let err = CodecError::EntityNotFound { entity_id: 123 };
@@ -419,6 +726,28 @@ mod tests {
         assert!(msg.contains('5'), "should mention component id");
     }
 
+    #[test]
+    fn error_display_field_not_found() {
+        let err = CodecError::FieldNotFound {
+            component_id: 3,
+            field_id: 7,
+        };
+        let msg = err.to_string();
+        assert!(msg.contains("field 7"), "should mention field id");
+        assert!(msg.contains("component 3"), "should mention component id");
+    }
+
+    #[test]
+    fn error_display_component_already_exists() {
+        let err = CodecError::ComponentAlreadyExists {
+            entity_id: 10,
+            component_id: 5,
+        };
+        let msg = err.to_string();
+        assert!(msg.contains("10"), "should mention entity id");
+        assert!(msg.contains("already exists"), "should mention exists");
+    }
+
     #[test]
     fn error_display_duplicate_entity() {
         let err = CodecError::DuplicateEntity { entity_id: 42 };
@@ -482,4 +811,31 @@ mod tests {
         fn assert_error<E: std::error::Error>() {}
         assert_error::<CodecError>();
     }
+
+    #[test]
+    fn resync_errors_map_to_ack_error_classes() {
+        assert_eq!(
+            CodecError::SessionMissing.ack_error_class(),
+            Some(wire::AckErrorClass::Session)
+        );
+        assert_eq!(
+            CodecError::BaselineNotFound { requested_tick: 3 }.ack_error_class(),
+            Some(wire::AckErrorClass::Baseline)
+        );
+        assert_eq!(
+            CodecError::Wire(wire::DecodeError::InvalidFlags { flags: 0 }).ack_error_class(),
+            Some(wire::AckErrorClass::Framing)
+        );
+        let err = CodecError::EntityNotFound { entity_id: 1 };
+        assert_eq!(err.ack_error_class(), None);
+        assert!(!err.needs_resync());
+        assert!(!err.is_droppable());
+
+        let late = CodecError::SessionLatePacket {
+            latest: 10,
+            tick: 9,
+        };
+        assert!(late.is_droppable());
+        assert!(!late.needs_resync());
+    }
 }
@@ -0,0 +1,593 @@
+//! Sequenced event messages carried in the EVENTS section.
+//!
+//! Events are one-shot messages (hit confirms, sounds) described by the
+//! schema's event table. They ride alongside state sections in the same
+//! packets and carry their own sequence numbers, independent of ticks. A
+//! per-client [`EventOutbox`] resends every unacknowledged event until the
+//! client acknowledges it; an [`EventInbox`] delivers each event exactly once
+//! and in order.
+
+use bitstream::{BitReader, BitWriter};
+use schema::{ComponentId, EventDef, EventId};
+use wire::{SectionTag, WirePacket};
+
+use crate::error::{CodecError, CodecResult, LimitKind};
+use crate::limits::CodecLimits;
+use crate::snapshot::{read_field_value, write_field_value, write_section, FieldValue};
+
+/// A decoded or pending event message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventMessage {
+    /// Sequence number assigned by the sender (starts at 1 and wraps from
+    /// `u32::MAX` to 1; compared in serial-number order).
+    pub seq: u32,
+    pub id: EventId,
+    /// Field values in schema order.
+    pub fields: Vec<FieldValue>,
+}
+
+/// Appends an EVENTS section to an encoded packet with a standard header.
+///
+/// `out[..packet_len]` must hold a packet produced by one of the snapshot
+/// encoders (full, delta, or session init). The section is written at
+/// `packet_len` and the header's `payload_len` is patched. Returns the new
+/// packet length; an empty `events` slice leaves the packet unchanged.
+///
+/// Sequence numbers must increase in serial-number order and at most
+/// `limits.max_events` may be sent per packet.
+pub fn append_events_section(
+    schema: &schema::Schema,
+    events: &[EventMessage],
+    limits: &CodecLimits,
+    out: &mut [u8],
+    packet_len: usize,
+) -> CodecResult<usize> {
+    if packet_len < wire::HEADER_SIZE || packet_len > out.len() {
+        return Err(CodecError::OutputTooSmall {
+            needed: wire::HEADER_SIZE.max(packet_len),
+            available: out.len(),
+        });
+    }
+    if events.is_empty() {
+        return Ok(packet_len);
+    }
+
+    let written = write_section(
+        SectionTag::Events,
+        &mut out[packet_len..],
+        limits,
+        |writer| encode_events_body(schema, events, limits, writer),
+    )?;
+    let offset = packet_len + written;
+    let payload_len = offset - wire::HEADER_SIZE;
+    let payload_len = u32::try_from(payload_len).map_err(|_| CodecError::OutputTooSmall {
+        needed: payload_len,
+        available: u32::MAX as usize,
+    })?;
+    out[wire::HEADER_SIZE - 4..wire::HEADER_SIZE].copy_from_slice(&payload_len.to_le_bytes());
+    Ok(offset)
+}
+
+/// Decodes the events carried by a parsed packet.
+///
+/// Returns an empty list when the packet has no EVENTS section. Works for
+/// both standard packets and session packets from `decode_session_packet`.
+pub fn decode_events(
+    schema: &schema::Schema,
+    packet: &WirePacket<'_>,
+    limits: &CodecLimits,
+) -> CodecResult<Vec<EventMessage>> {
+    let mut events: Option<Vec<EventMessage>> = None;
+    for section in &packet.sections {
+        if section.tag != SectionTag::Events {
+            continue;
+        }
+        if events.is_some() {
+            return Err(CodecError::DuplicateSection {
+                section: section.tag,
+            });
+        }
+        events = Some(decode_events_section(schema, section.body, limits)?);
+    }
+    Ok(events.unwrap_or_default())
+}
+
+fn encode_events_body(
+    schema: &schema::Schema,
+    events: &[EventMessage],
+    limits: &CodecLimits,
+    writer: &mut BitWriter<'_>,
+) -> CodecResult<()> {
+    if events.len() > limits.max_events {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::Events,
+            limit: limits.max_events,
+            actual: events.len(),
+        });
+    }
+
+    writer.align_to_byte()?;
+    writer.write_varu32(events.len() as u32)?;
+
+    let mut prev_seq: Option<u32> = None;
+    for event in events {
+        if let Some(prev) = prev_seq {
+            if !seq_is_after(event.seq, prev) {
+                return Err(CodecError::InvalidEventOrder {
+                    previous: prev,
+                    current: event.seq,
+                });
+            }
+        }
+        prev_seq = Some(event.seq);
+
+        let def = find_event(schema, u32::from(event.id.get()))?;
+        if event.fields.len() != def.fields.len() {
+            return Err(CodecError::EventFieldCountMismatch {
+                event: event.id,
+                expected: def.fields.len(),
+                actual: event.fields.len(),
+            });
+        }
+
+        writer.align_to_byte()?;
+        writer.write_varu32(event.seq)?;
+        writer.write_varu32(u32::from(event.id.get()))?;
+        let component_id = event_component_id(event.id)?;
+        for (field, value) in def.fields.iter().zip(event.fields.iter()) {
+            write_field_value(component_id, *field, *value, writer)
+                .map_err(|err| event_value_error(event.id, err))?;
+        }
+    }
+    Ok(())
+}
+
+fn decode_events_section(
+    schema: &schema::Schema,
+    body: &[u8],
+    limits: &CodecLimits,
+) -> CodecResult<Vec<EventMessage>> {
+    if body.len() > limits.max_section_bytes {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::SectionBytes,
+            limit: limits.max_section_bytes,
+            actual: body.len(),
+        });
+    }
+
+    let mut reader = BitReader::new(body);
+    reader.align_to_byte()?;
+    let count = reader.read_varu32()? as usize;
+    if count > limits.max_events {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::Events,
+            limit: limits.max_events,
+            actual: count,
+        });
+    }
+
+    let mut events = Vec::with_capacity(count);
+    let mut prev_seq: Option<u32> = None;
+    for _ in 0..count {
+        reader.align_to_byte()?;
+        let seq = reader.read_varu32()?;
+        if let Some(prev) = prev_seq {
+            if !seq_is_after(seq, prev) {
+                return Err(CodecError::InvalidEventOrder {
+                    previous: prev,
+                    current: seq,
+                });
+            }
+        }
+        prev_seq = Some(seq);
+
+        let def = find_event(schema, reader.read_varu32()?)?;
+        if def.fields.len() > limits.max_fields_per_component {
+            return Err(CodecError::LimitsExceeded {
+                kind: LimitKind::FieldsPerComponent,
+                limit: limits.max_fields_per_component,
+                actual: def.fields.len(),
+            });
+        }
+
+        let component_id = event_component_id(def.id)?;
+        let mut fields = Vec::with_capacity(def.fields.len());
+        for field in &def.fields {
+            let value = read_field_value(component_id, *field, &mut reader)
+                .map_err(|err| event_value_error(def.id, err))?;
+            fields.push(value);
+        }
+        events.push(EventMessage {
+            seq,
+            id: def.id,
+            fields,
+        });
+    }
+
+    reader.align_to_byte()?;
+    let remaining_bits = reader.bits_remaining();
+    if remaining_bits != 0 {
+        return Err(CodecError::TrailingSectionData {
+            section: SectionTag::Events,
+            remaining_bits,
+        });
+    }
+
+    Ok(events)
+}
+
+/// Returns the sequence number after `seq`, wrapping from `u32::MAX` to 1.
+///
+/// 0 is skipped so an ACK of 0 always means "nothing delivered yet".
+const fn next_seq(seq: u32) -> u32 {
+    match seq.wrapping_add(1) {
+        0 => 1,
+        next => next,
+    }
+}
+
+/// Returns `true` if `seq` is newer than `other` in serial-number order, as
+/// for [`SnapshotTick::is_after`](crate::SnapshotTick::is_after).
+const fn seq_is_after(seq: u32, other: u32) -> bool {
+    let distance = seq.wrapping_sub(other);
+    distance != 0 && distance < 1 << 31
+}
+
+fn find_event(schema: &schema::Schema, raw: u32) -> CodecResult<&EventDef> {
+    u16::try_from(raw)
+        .ok()
+        .and_then(EventId::new)
+        .and_then(|id| schema.event(id))
+        .ok_or(CodecError::UnknownEvent { event_id: raw })
+}
+
+/// Field I/O is shared with components; the component ID only labels errors,
+/// which are re-tagged with the event ID.
+fn event_component_id(id: EventId) -> CodecResult<ComponentId> {
+    ComponentId::new(id.get()).ok_or(CodecError::UnknownEvent {
+        event_id: u32::from(id.get()),
+    })
+}
+
+fn event_value_error(event: EventId, err: CodecError) -> CodecError {
+    match err {
+        CodecError::InvalidValue { field, reason, .. } => CodecError::InvalidEventValue {
+            event,
+            field,
+            reason,
+        },
+        other => other,
+    }
+}
+
+/// Per-client queue of events awaiting acknowledgement.
+///
+/// Every pending event is resent with each packet until the client acks its
+/// sequence number, which makes delivery reliable over a lossy transport
+/// without a separate retransmit timer.
+#[derive(Debug, Clone)]
+pub struct EventOutbox {
+    next_seq: u32,
+    pending: Vec<EventMessage>,
+    capacity: usize,
+}
+
+impl EventOutbox {
+    /// Creates an outbox holding at most `capacity` unacknowledged events.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            next_seq: 1,
+            pending: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// Queues an event and returns its sequence number.
+    ///
+    /// Fails if the outbox already holds `capacity` unacknowledged events;
+    /// callers typically treat this as a stalled client and resync it.
+    pub fn push(&mut self, id: EventId, fields: Vec<FieldValue>) -> CodecResult<u32> {
+        if self.pending.len() >= self.capacity {
+            return Err(CodecError::EventOutboxFull {
+                capacity: self.capacity,
+            });
+        }
+        let seq = self.next_seq;
+        self.next_seq = next_seq(self.next_seq);
+        self.pending.push(EventMessage { seq, id, fields });
+        Ok(seq)
+    }
+
+    /// Returns the unacknowledged events, oldest first.
+    ///
+    /// When more than `max_events` are pending, send a prefix so the receiver
+    /// never sees a gap.
+    #[must_use]
+    pub fn pending(&self) -> &[EventMessage] {
+        &self.pending
+    }
+
+    /// Acknowledges every event with a sequence number up to and including
+    /// `seq` in serial-number order. An ack of 0 acknowledges nothing.
+    pub fn ack(&mut self, seq: u32) {
+        if seq == 0 {
+            return;
+        }
+        let acked = self
+            .pending
+            .partition_point(|event| !seq_is_after(event.seq, seq));
+        self.pending.drain(..acked);
+    }
+
+    /// Returns the number of unacknowledged events.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns `true` if every event has been acknowledged.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Receiver-side event sequencing.
+///
+/// Delivers each event once and in sequence order. Resent duplicates are
+/// dropped, and events past a gap are dropped until the missing ones arrive
+/// (the sender keeps resending them).
+#[derive(Debug, Clone, Default)]
+pub struct EventInbox {
+    last_delivered: u32,
+}
+
+impl EventInbox {
+    /// Creates an inbox that expects sequence number 1 next.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Filters decoded events down to the ones not yet delivered, in order.
+    pub fn receive(&mut self, events: Vec<EventMessage>) -> Vec<EventMessage> {
+        let mut delivered = Vec::new();
+        for event in events {
+            if event.seq == next_seq(self.last_delivered) {
+                self.last_delivered = event.seq;
+                delivered.push(event);
+            }
+        }
+        delivered
+    }
+
+    /// Returns the highest contiguous sequence number delivered so far, to be
+    /// sent back to the sender as the event ACK (0 if nothing was delivered).
+    #[must_use]
+    pub fn ack(&self) -> u32 {
+        self.last_delivered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::{decode_full_snapshot, encode_full_snapshot};
+    use crate::types::SnapshotTick;
+    use schema::{ComponentDef, FieldCodec, FieldDef, FieldId, Schema};
+
+    fn event_id(id: u16) -> EventId {
+        EventId::new(id).unwrap()
+    }
+
+    fn schema_with_events() -> Schema {
+        let component = ComponentDef::new(ComponentId::new(1).unwrap())
+            .field(FieldDef::new(FieldId::new(1).unwrap(), FieldCodec::bool()));
+        let hit = EventDef::new(event_id(1))
+            .field(FieldDef::new(FieldId::new(1).unwrap(), FieldCodec::uint(8)))
+            .field(FieldDef::new(
+                FieldId::new(2).unwrap(),
+                FieldCodec::var_uint(),
+            ));
+        let sound = EventDef::new(event_id(2));
+        Schema::builder()
+            .component(component)
+            .event(hit)
+            .event(sound)
+            .build()
+            .unwrap()
+    }
+
+    fn hit(seq: u32, damage: u64) -> EventMessage {
+        EventMessage {
+            seq,
+            id: event_id(1),
+            fields: vec![FieldValue::UInt(damage), FieldValue::VarUInt(300)],
+        }
+    }
+
+    fn packet_with_events(schema: &Schema, events: &[EventMessage], buf: &mut [u8]) -> usize {
+        let limits = CodecLimits::for_testing();
+        let len = encode_full_snapshot(schema, SnapshotTick::new(1), &[], &limits, buf).unwrap();
+        append_events_section(schema, events, &limits, buf, len).unwrap()
+    }
+
+    #[test]
+    fn events_roundtrip_alongside_snapshot() {
+        let schema = schema_with_events();
+        let events = vec![
+            hit(1, 25),
+            EventMessage {
+                seq: 2,
+                id: event_id(2),
+                fields: Vec::new(),
+            },
+        ];
+        let mut buf = [0u8; 128];
+        let len = packet_with_events(&schema, &events, &mut buf);
+
+        let wire_limits = wire::Limits::for_testing();
+        let limits = CodecLimits::for_testing();
+        let packet = wire::decode_packet(&buf[..len], &wire_limits).unwrap();
+        assert_eq!(decode_events(&schema, &packet, &limits).unwrap(), events);
+
+        let snapshot = decode_full_snapshot(&schema, &buf[..len], &wire_limits, &limits).unwrap();
+        assert!(snapshot.entities.is_empty());
+    }
+
+    #[test]
+    fn append_empty_events_leaves_packet_unchanged() {
+        let schema = schema_with_events();
+        let mut buf = [0u8; 64];
+        let limits = CodecLimits::for_testing();
+        let len =
+            encode_full_snapshot(&schema, SnapshotTick::new(1), &[], &limits, &mut buf).unwrap();
+        let appended = append_events_section(&schema, &[], &limits, &mut buf, len).unwrap();
+        assert_eq!(appended, len);
+    }
+
+    #[test]
+    fn encode_rejects_unknown_event() {
+        let schema = schema_with_events();
+        let event = EventMessage {
+            seq: 1,
+            id: event_id(9),
+            fields: Vec::new(),
+        };
+        let mut buf = [0u8; 64];
+        let limits = CodecLimits::for_testing();
+        let len =
+            encode_full_snapshot(&schema, SnapshotTick::new(1), &[], &limits, &mut buf).unwrap();
+        let err = append_events_section(&schema, &[event], &limits, &mut buf, len).unwrap_err();
+        assert!(matches!(err, CodecError::UnknownEvent { event_id: 9 }));
+    }
+
+    #[test]
+    fn encode_rejects_invalid_event_value() {
+        let schema = schema_with_events();
+        let mut buf = [0u8; 64];
+        let limits = CodecLimits::for_testing();
+        let len =
+            encode_full_snapshot(&schema, SnapshotTick::new(1), &[], &limits, &mut buf).unwrap();
+        let err =
+            append_events_section(&schema, &[hit(1, 256)], &limits, &mut buf, len).unwrap_err();
+        assert!(matches!(err, CodecError::InvalidEventValue { .. }));
+    }
+
+    #[test]
+    fn encode_rejects_out_of_order_seq() {
+        let schema = schema_with_events();
+        let mut buf = [0u8; 64];
+        let limits = CodecLimits::for_testing();
+        let len =
+            encode_full_snapshot(&schema, SnapshotTick::new(1), &[], &limits, &mut buf).unwrap();
+        let err = append_events_section(&schema, &[hit(2, 1), hit(2, 1)], &limits, &mut buf, len)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            CodecError::InvalidEventOrder {
+                previous: 2,
+                current: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn decode_enforces_event_limit() {
+        let schema = schema_with_events();
+        let events: Vec<_> = (1..=4).map(|seq| hit(seq, 1)).collect();
+        let mut buf = [0u8; 128];
+        let len = packet_with_events(&schema, &events, &mut buf);
+
+        let packet = wire::decode_packet(&buf[..len], &wire::Limits::for_testing()).unwrap();
+        let limits = CodecLimits {
+            max_events: 3,
+            ..CodecLimits::for_testing()
+        };
+        let err = decode_events(&schema, &packet, &limits).unwrap_err();
+        assert!(matches!(
+            err,
+            CodecError::LimitsExceeded {
+                kind: LimitKind::Events,
+                limit: 3,
+                actual: 4,
+            }
+        ));
+    }
+
+    #[test]
+    fn outbox_resends_until_acked() {
+        let mut outbox = EventOutbox::new(4);
+        let first = outbox.push(event_id(2), Vec::new()).unwrap();
+        let second = outbox.push(event_id(2), Vec::new()).unwrap();
+        assert_eq!((first, second), (1, 2));
+        assert_eq!(outbox.pending().len(), 2);
+
+        outbox.ack(first);
+        assert_eq!(outbox.len(), 1);
+        assert_eq!(outbox.pending()[0].seq, second);
+
+        outbox.ack(second);
+        assert!(outbox.is_empty());
+    }
+
+    #[test]
+    fn outbox_rejects_push_when_full() {
+        let mut outbox = EventOutbox::new(1);
+        outbox.push(event_id(2), Vec::new()).unwrap();
+        let err = outbox.push(event_id(2), Vec::new()).unwrap_err();
+        assert!(matches!(err, CodecError::EventOutboxFull { capacity: 1 }));
+    }
+
+    #[test]
+    fn inbox_delivers_once_in_order() {
+        let mut inbox = EventInbox::new();
+        let delivered = inbox.receive(vec![hit(1, 1), hit(2, 2)]);
+        assert_eq!(delivered.len(), 2);
+        assert_eq!(inbox.ack(), 2);
+
+        // Resent events are dropped, new ones delivered.
+        let delivered = inbox.receive(vec![hit(2, 2), hit(3, 3)]);
+        assert_eq!(delivered, vec![hit(3, 3)]);
+
+        // Events past a gap wait for the missing sequence number.
+        assert!(inbox.receive(vec![hit(5, 5)]).is_empty());
+        assert_eq!(inbox.ack(), 3);
+    }
+
+    #[test]
+    fn sequence_numbers_wrap_past_u32_max() {
+        let schema = schema_with_events();
+        let mut outbox = EventOutbox::new(4);
+        outbox.next_seq = u32::MAX - 1;
+        let seqs: Vec<_> = (0..3)
+            .map(|damage| {
+                outbox
+                    .push(
+                        event_id(1),
+                        vec![FieldValue::UInt(damage), FieldValue::VarUInt(300)],
+                    )
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(seqs, vec![u32::MAX - 1, u32::MAX, 1]);
+
+        let mut buf = [0u8; 128];
+        let len = packet_with_events(&schema, outbox.pending(), &mut buf);
+        let packet = wire::decode_packet(&buf[..len], &wire::Limits::for_testing()).unwrap();
+        let events = decode_events(&schema, &packet, &CodecLimits::for_testing()).unwrap();
+        assert_eq!(events, outbox.pending());
+
+        let mut inbox = EventInbox {
+            last_delivered: u32::MAX - 2,
+        };
+        assert_eq!(inbox.receive(events).len(), 3);
+        assert_eq!(inbox.ack(), 1);
+
+        outbox.ack(u32::MAX);
+        assert_eq!(outbox.pending()[0].seq, 1);
+        outbox.ack(inbox.ack());
+        assert!(outbox.is_empty());
+    }
+}
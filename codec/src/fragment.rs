@@ -0,0 +1,706 @@
+//! Fragmentation and reassembly of oversized full snapshots.
+//!
+//! A snapshot that does not fit into one packet is split on entity
+//! boundaries into N full-snapshot packets. Each packet carries a `FRAGMENT`
+//! section (index + count) next to its `ENTITY_CREATE` section, so every
+//! fragment is independently decodable; [`FragmentAssembler`] merges them
+//! back into one snapshot.
+
+use bitstream::{BitReader, BitWriter};
+use schema::schema_hash;
+use wire::{encode_header, SectionTag, WirePacket};
+
+use crate::delta::{ensure_entities_sorted, varu32_len};
+use crate::error::{CodecError, CodecResult, LimitKind};
+use crate::limits::{CodecLimits, ReassemblyLimits};
+use crate::snapshot::{
+    decode_create_section, encode_create_body, validate_full_snapshot_header, write_section,
+    EntitySnapshot, Snapshot, VARINT_MAX_BYTES,
+};
+use crate::types::SnapshotTick;
+
+/// Worst-case framed size of a `FRAGMENT` section (tag + len + two varints).
+const FRAGMENT_SECTION_MAX_BYTES: usize = 2 + 2 * VARINT_MAX_BYTES;
+
+/// Position of a packet within a fragmented snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FragmentInfo {
+    /// Zero-based fragment index.
+    pub index: u32,
+    /// Total number of fragments.
+    pub count: u32,
+}
+
+/// Encodes a full snapshot as one or more packets of at most `out.len()` bytes.
+///
+/// If the snapshot fits, a single regular full snapshot packet is emitted.
+/// Otherwise entities are split on entity boundaries and each fragment is
+/// emitted in index order. `out` is reused for every packet, so `emit` must
+/// copy or send the bytes before returning. Returns the number of packets.
+pub fn encode_full_snapshot_fragments<F>(
+    schema: &schema::Schema,
+    tick: SnapshotTick,
+    entities: &[EntitySnapshot],
+    limits: &CodecLimits,
+    out: &mut [u8],
+    mut emit: F,
+) -> CodecResult<u32>
+where
+    F: FnMut(&[u8]),
+{
+    ensure_entities_sorted(entities)?;
+
+    // First pass only counts fragments; `out` doubles as the measure buffer.
+    let mut count = 0u32;
+    let mut start = 0;
+    while start < entities.len() || count == 0 {
+        start = next_fragment_end(schema, entities, start, limits, out)?;
+        count += 1;
+    }
+
+    if count == 1 {
+        let written = crate::snapshot::encode_full_snapshot(schema, tick, entities, limits, out)?;
+        emit(&out[..written]);
+        return Ok(1);
+    }
+
+    let mut start = 0;
+    for index in 0..count {
+        let end = next_fragment_end(schema, entities, start, limits, out)?;
+        let info = FragmentInfo { index, count };
+        let written =
+            encode_fragment_packet(schema, tick, info, &entities[start..end], limits, out)?;
+        emit(&out[..written]);
+        start = end;
+    }
+    debug_assert_eq!(start, entities.len());
+    Ok(count)
+}
+
+/// Returns the end of the fragment starting at `start` (greedy packing).
+fn next_fragment_end(
+    schema: &schema::Schema,
+    entities: &[EntitySnapshot],
+    start: usize,
+    limits: &CodecLimits,
+    scratch: &mut [u8],
+) -> CodecResult<usize> {
+    let overhead = wire::HEADER_SIZE + FRAGMENT_SECTION_MAX_BYTES + 1 + VARINT_MAX_BYTES;
+    let mut entity_bytes = 0;
+    let mut end = start;
+    while end < entities.len() {
+        let cost = measure_entity(schema, &entities[end], limits, scratch)?;
+        let count = end - start + 1;
+        let body_len = varu32_len(count as u32) + entity_bytes + cost;
+        if count > limits.max_entities_create
+            || body_len > limits.max_section_bytes
+            || overhead + body_len > scratch.len()
+        {
+            if end == start {
+                return Err(CodecError::OutputTooSmall {
+                    needed: overhead + body_len,
+                    available: scratch.len(),
+                });
+            }
+            break;
+        }
+        entity_bytes += cost;
+        end += 1;
+    }
+    Ok(end)
+}
+
+fn measure_entity(
+    schema: &schema::Schema,
+    entity: &EntitySnapshot,
+    limits: &CodecLimits,
+    scratch: &mut [u8],
+) -> CodecResult<usize> {
+    let mut writer = BitWriter::new(scratch);
+    encode_create_body(schema, std::slice::from_ref(entity), limits, &mut writer)?;
+    Ok(writer.finish() - varu32_len(1))
+}
+
+fn encode_fragment_packet(
+    schema: &schema::Schema,
+    tick: SnapshotTick,
+    info: FragmentInfo,
+    entities: &[EntitySnapshot],
+    limits: &CodecLimits,
+    out: &mut [u8],
+) -> CodecResult<usize> {
+    let mut offset = wire::HEADER_SIZE;
+    offset += write_section(SectionTag::Fragment, &mut out[offset..], limits, |writer| {
+        writer.align_to_byte()?;
+        writer.write_varu32(info.index)?;
+        writer.write_varu32(info.count)?;
+        writer.align_to_byte()?;
+        Ok(())
+    })?;
+    offset += write_section(
+        SectionTag::EntityCreate,
+        &mut out[offset..],
+        limits,
+        |writer| encode_create_body(schema, entities, limits, writer),
+    )?;
+
+    let payload_len = offset - wire::HEADER_SIZE;
+    let header =
+        wire::PacketHeader::full_snapshot(schema_hash(schema), tick.raw(), payload_len as u32);
+    encode_header(&header, &mut out[..wire::HEADER_SIZE]).map_err(|_| {
+        CodecError::OutputTooSmall {
+            needed: wire::HEADER_SIZE,
+            available: out.len(),
+        }
+    })?;
+    Ok(offset)
+}
+
+/// Decodes a full snapshot packet that may be a fragment.
+///
+/// Returns the fragment position (if any) and the entities carried by this
+/// packet. Non-fragmented packets decode exactly like `decode_full_snapshot`.
+pub fn decode_full_snapshot_fragment(
+    schema: &schema::Schema,
+    packet: &WirePacket<'_>,
+    limits: &CodecLimits,
+) -> CodecResult<(Option<FragmentInfo>, Snapshot)> {
+    let header = packet.header;
+    validate_full_snapshot_header(schema, &header)?;
+
+    let mut info = None;
+    let mut entities = None;
+    for section in &packet.sections {
+        match section.tag {
+            SectionTag::Fragment => {
+                if info.is_some() {
+                    return Err(CodecError::DuplicateSection {
+                        section: section.tag,
+                    });
+                }
+                info = Some(decode_fragment_body(section.body, limits)?);
+            }
+            SectionTag::EntityCreate => {
+                if entities.is_some() {
+                    return Err(CodecError::DuplicateSection {
+                        section: section.tag,
+                    });
+                }
+                entities = Some(decode_create_section(schema, section.body, limits)?);
+            }
+            // Events ride alongside state and are decoded by `decode_events`.
+            SectionTag::Events => {}
+            _ => {
+                return Err(CodecError::UnexpectedSection {
+                    section: section.tag,
+                });
+            }
+        }
+    }
+
+    Ok((
+        info,
+        Snapshot {
+            tick: SnapshotTick::new(header.tick),
+            entities: entities.unwrap_or_default(),
+        },
+    ))
+}
+
+fn decode_fragment_body(body: &[u8], limits: &CodecLimits) -> CodecResult<FragmentInfo> {
+    if body.len() > limits.max_section_bytes {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::SectionBytes,
+            limit: limits.max_section_bytes,
+            actual: body.len(),
+        });
+    }
+    let mut reader = BitReader::new(body);
+    reader.align_to_byte()?;
+    let index = reader.read_varu32()?;
+    let count = reader.read_varu32()?;
+    reader.align_to_byte()?;
+    if reader.bits_remaining() != 0 {
+        return Err(CodecError::TrailingSectionData {
+            section: SectionTag::Fragment,
+            remaining_bits: reader.bits_remaining(),
+        });
+    }
+    if count == 0 || index >= count {
+        return Err(CodecError::InvalidFragment { index, count });
+    }
+    Ok(FragmentInfo { index, count })
+}
+
+/// Bounded reassembly buffer for fragmented full snapshots.
+///
+/// Fragments may arrive in any order. A snapshot is returned once all of its
+/// fragments are present; older pending snapshots are then discarded, and
+/// fragments for ticks at or before the last completed snapshot are ignored.
+/// Pending snapshots are dropped after `timeout_ms`, and the oldest pending
+/// snapshot is evicted when `max_pending` or `max_buffered_bytes` is reached.
+#[derive(Debug)]
+pub struct FragmentAssembler {
+    limits: ReassemblyLimits,
+    pending: Vec<PendingSnapshot>,
+    buffered_bytes: usize,
+    last_completed: Option<SnapshotTick>,
+}
+
+#[derive(Debug)]
+struct PendingSnapshot {
+    tick: SnapshotTick,
+    started_ms: u64,
+    bytes: usize,
+    received: u32,
+    fragments: Vec<Option<Vec<EntitySnapshot>>>,
+}
+
+impl FragmentAssembler {
+    /// Creates an empty assembler.
+    #[must_use]
+    pub fn new(limits: ReassemblyLimits) -> Self {
+        Self {
+            limits,
+            pending: Vec::new(),
+            buffered_bytes: 0,
+            last_completed: None,
+        }
+    }
+
+    /// Returns the reassembly limits.
+    #[must_use]
+    pub fn limits(&self) -> &ReassemblyLimits {
+        &self.limits
+    }
+
+    /// Returns the number of snapshots currently being reassembled.
+    #[must_use]
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns the payload bytes currently buffered.
+    #[must_use]
+    pub fn buffered_bytes(&self) -> usize {
+        self.buffered_bytes
+    }
+
+    /// Drops all pending fragments.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.buffered_bytes = 0;
+    }
+
+    /// Drops pending snapshots older than the timeout. Returns how many were dropped.
+    pub fn expire(&mut self, now_ms: u64) -> usize {
+        let timeout_ms = self.limits.timeout_ms;
+        let before = self.pending.len();
+        let mut freed = 0;
+        self.pending.retain(|pending| {
+            let keep = now_ms.saturating_sub(pending.started_ms) <= timeout_ms;
+            if !keep {
+                freed += pending.bytes;
+            }
+            keep
+        });
+        self.buffered_bytes -= freed;
+        before - self.pending.len()
+    }
+
+    /// Feeds one full snapshot packet (fragmented or not).
+    ///
+    /// Returns the completed snapshot once all fragments for its tick have
+    /// arrived. Duplicate and stale fragments return `Ok(None)`.
+    pub fn insert(
+        &mut self,
+        schema: &schema::Schema,
+        packet: &WirePacket<'_>,
+        limits: &CodecLimits,
+        now_ms: u64,
+    ) -> CodecResult<Option<Snapshot>> {
+        self.expire(now_ms);
+        let (info, snapshot) = decode_full_snapshot_fragment(schema, packet, limits)?;
+        if self
+            .last_completed
+            .is_some_and(|last| !snapshot.tick.is_after(last))
+        {
+            return Ok(None);
+        }
+        let Some(info) = info else {
+            self.complete(snapshot.tick);
+            return Ok(Some(snapshot));
+        };
+        if info.count > self.limits.max_fragments {
+            return Err(CodecError::LimitsExceeded {
+                kind: LimitKind::Fragments,
+                limit: self.limits.max_fragments as usize,
+                actual: info.count as usize,
+            });
+        }
+
+        let tick = snapshot.tick;
+        if let Some(position) = self.position(tick) {
+            let pending = &self.pending[position];
+            if pending.fragments.len() != info.count as usize {
+                self.remove(position);
+                return Err(CodecError::InvalidFragment {
+                    index: info.index,
+                    count: info.count,
+                });
+            }
+            if pending.fragments[info.index as usize].is_some() {
+                return Ok(None);
+            }
+        }
+
+        let bytes = packet.header.payload_len as usize;
+        while self.buffered_bytes + bytes > self.limits.max_buffered_bytes {
+            if !self.evict_oldest(Some(tick)) {
+                let actual = self.buffered_bytes + bytes;
+                if let Some(position) = self.position(tick) {
+                    self.remove(position);
+                }
+                return Err(CodecError::LimitsExceeded {
+                    kind: LimitKind::ReassemblyBytes,
+                    limit: self.limits.max_buffered_bytes,
+                    actual,
+                });
+            }
+        }
+
+        let position = if let Some(position) = self.position(tick) {
+            position
+        } else {
+            if self.pending.len() >= self.limits.max_pending {
+                self.evict_oldest(None);
+            }
+            self.pending.push(PendingSnapshot {
+                tick,
+                started_ms: now_ms,
+                bytes: 0,
+                received: 0,
+                fragments: vec![None; info.count as usize],
+            });
+            self.pending.len() - 1
+        };
+        let pending = &mut self.pending[position];
+        pending.fragments[info.index as usize] = Some(snapshot.entities);
+        pending.bytes += bytes;
+        pending.received += 1;
+        self.buffered_bytes += bytes;
+        if pending.received < info.count {
+            return Ok(None);
+        }
+
+        let pending = self.remove(position);
+        self.complete(tick);
+        let entities = merge_fragments(pending.fragments, limits)?;
+        Ok(Some(Snapshot { tick, entities }))
+    }
+
+    fn position(&self, tick: SnapshotTick) -> Option<usize> {
+        self.pending.iter().position(|pending| pending.tick == tick)
+    }
+
+    fn remove(&mut self, position: usize) -> PendingSnapshot {
+        let pending = self.pending.swap_remove(position);
+        self.buffered_bytes -= pending.bytes;
+        pending
+    }
+
+    /// Evicts the oldest pending snapshot, in serial tick order, other than
+    /// `keep`. Returns false if nothing could be evicted.
+    fn evict_oldest(&mut self, keep: Option<SnapshotTick>) -> bool {
+        let oldest = self
+            .pending
+            .iter()
+            .enumerate()
+            .filter(|(_, pending)| Some(pending.tick) != keep)
+            .reduce(|oldest, candidate| {
+                if candidate.1.tick.is_before(oldest.1.tick) {
+                    candidate
+                } else {
+                    oldest
+                }
+            })
+            .map(|(position, _)| position);
+        match oldest {
+            Some(position) => {
+                self.remove(position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Records `tick` as completed and drops pending snapshots it supersedes.
+    fn complete(&mut self, tick: SnapshotTick) {
+        self.last_completed = Some(tick);
+        let mut freed = 0;
+        self.pending.retain(|pending| {
+            let keep = pending.tick.is_after(tick);
+            if !keep {
+                freed += pending.bytes;
+            }
+            keep
+        });
+        self.buffered_bytes -= freed;
+    }
+}
+
+fn merge_fragments(
+    fragments: Vec<Option<Vec<EntitySnapshot>>>,
+    limits: &CodecLimits,
+) -> CodecResult<Vec<EntitySnapshot>> {
+    let total: usize = fragments.iter().flatten().map(Vec::len).sum();
+    if total > limits.max_total_entities_after_apply {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::TotalEntitiesAfterApply,
+            limit: limits.max_total_entities_after_apply,
+            actual: total,
+        });
+    }
+    let mut entities = Vec::with_capacity(total);
+    for fragment in fragments.into_iter().flatten() {
+        entities.extend(fragment);
+    }
+    ensure_entities_sorted(&entities)?;
+    Ok(entities)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::{ComponentSnapshot, FieldValue};
+    use crate::types::EntityId;
+    use schema::{ComponentDef, ComponentId, FieldCodec, FieldDef, FieldId, Schema};
+
+    const MTU: usize = 96;
+
+    fn schema_uint() -> Schema {
+        let component = ComponentDef::new(ComponentId::new(1).unwrap()).field(FieldDef::new(
+            FieldId::new(1).unwrap(),
+            FieldCodec::uint(32),
+        ));
+        Schema::new(vec![component]).unwrap()
+    }
+
+    fn entities(count: u32) -> Vec<EntitySnapshot> {
+        (1..=count)
+            .map(|id| EntitySnapshot {
+                id: EntityId::from(id),
+                components: vec![ComponentSnapshot {
+                    id: ComponentId::new(1).unwrap(),
+                    fields: vec![FieldValue::UInt(u64::from(id) * 1000)],
+                }],
+            })
+            .collect()
+    }
+
+    fn fragments(
+        schema: &Schema,
+        tick: u32,
+        entities: &[EntitySnapshot],
+        mtu: usize,
+    ) -> Vec<Vec<u8>> {
+        let mut out = vec![0u8; mtu];
+        let mut packets = Vec::new();
+        encode_full_snapshot_fragments(
+            schema,
+            SnapshotTick::new(tick),
+            entities,
+            &CodecLimits::for_testing(),
+            &mut out,
+            |bytes| packets.push(bytes.to_vec()),
+        )
+        .unwrap();
+        packets
+    }
+
+    fn insert(
+        assembler: &mut FragmentAssembler,
+        schema: &Schema,
+        bytes: &[u8],
+        now_ms: u64,
+    ) -> CodecResult<Option<Snapshot>> {
+        let packet = wire::decode_packet(bytes, &wire::Limits::for_testing()).unwrap();
+        assembler.insert(schema, &packet, &CodecLimits::for_testing(), now_ms)
+    }
+
+    #[test]
+    fn small_snapshot_is_not_fragmented() {
+        let schema = schema_uint();
+        let entities = entities(2);
+        let packets = fragments(&schema, 5, &entities, 256);
+        assert_eq!(packets.len(), 1);
+        let decoded = crate::snapshot::decode_full_snapshot(
+            &schema,
+            &packets[0],
+            &wire::Limits::for_testing(),
+            &CodecLimits::for_testing(),
+        )
+        .unwrap();
+        assert_eq!(decoded.entities, entities);
+    }
+
+    #[test]
+    fn fragments_fit_mtu_and_decode_independently() {
+        let schema = schema_uint();
+        let entities = entities(20);
+        let packets = fragments(&schema, 5, &entities, MTU);
+        assert!(packets.len() > 1);
+        let mut decoded = Vec::new();
+        for (index, bytes) in packets.iter().enumerate() {
+            assert!(bytes.len() <= MTU);
+            let packet = wire::decode_packet(bytes, &wire::Limits::for_testing()).unwrap();
+            let (info, snapshot) =
+                decode_full_snapshot_fragment(&schema, &packet, &CodecLimits::for_testing())
+                    .unwrap();
+            assert_eq!(
+                info,
+                Some(FragmentInfo {
+                    index: index as u32,
+                    count: packets.len() as u32,
+                })
+            );
+            decoded.extend(snapshot.entities);
+        }
+        assert_eq!(decoded, entities);
+    }
+
+    #[test]
+    fn reassembles_out_of_order_with_duplicates() {
+        let schema = schema_uint();
+        let entities = entities(20);
+        let packets = fragments(&schema, 5, &entities, MTU);
+        let mut assembler = FragmentAssembler::new(ReassemblyLimits::for_testing());
+        for bytes in packets.iter().skip(1).rev() {
+            assert!(insert(&mut assembler, &schema, bytes, 0).unwrap().is_none());
+            assert!(insert(&mut assembler, &schema, bytes, 0).unwrap().is_none());
+        }
+        let snapshot = insert(&mut assembler, &schema, &packets[0], 0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(snapshot.tick, SnapshotTick::new(5));
+        assert_eq!(snapshot.entities, entities);
+        assert_eq!(assembler.pending_len(), 0);
+        assert_eq!(assembler.buffered_bytes(), 0);
+
+        // Late duplicates of a completed snapshot are ignored.
+        assert!(insert(&mut assembler, &schema, &packets[1], 0)
+            .unwrap()
+            .is_none());
+        assert_eq!(assembler.pending_len(), 0);
+    }
+
+    #[test]
+    fn pending_snapshot_times_out() {
+        let schema = schema_uint();
+        let packets = fragments(&schema, 5, &entities(20), MTU);
+        let mut assembler = FragmentAssembler::new(ReassemblyLimits::for_testing());
+        insert(&mut assembler, &schema, &packets[0], 0).unwrap();
+        assert_eq!(assembler.pending_len(), 1);
+        assert_eq!(assembler.expire(100), 0);
+        assert_eq!(assembler.expire(101), 1);
+        assert_eq!(assembler.buffered_bytes(), 0);
+    }
+
+    #[test]
+    fn oldest_pending_snapshot_is_evicted() {
+        let schema = schema_uint();
+        let entities = entities(20);
+        let mut assembler = FragmentAssembler::new(ReassemblyLimits::for_testing());
+        for tick in [5, 6, 7] {
+            let packets = fragments(&schema, tick, &entities, MTU);
+            insert(&mut assembler, &schema, &packets[0], 0).unwrap();
+        }
+        assert_eq!(assembler.pending_len(), 2);
+        assert!(assembler.position(SnapshotTick::new(5)).is_none());
+    }
+
+    #[test]
+    fn eviction_follows_serial_order_across_wrap() {
+        let schema = schema_uint();
+        let entities = entities(20);
+        let mut assembler = FragmentAssembler::new(ReassemblyLimits::for_testing());
+        for tick in [u32::MAX - 1, u32::MAX, 1] {
+            let packets = fragments(&schema, tick, &entities, MTU);
+            insert(&mut assembler, &schema, &packets[0], 0).unwrap();
+        }
+        assert_eq!(assembler.pending_len(), 2);
+        assert!(assembler
+            .position(SnapshotTick::new(u32::MAX - 1))
+            .is_none());
+        assert!(assembler.position(SnapshotTick::new(1)).is_some());
+    }
+
+    #[test]
+    fn buffered_bytes_are_bounded() {
+        let schema = schema_uint();
+        let packets = fragments(&schema, 5, &entities(20), MTU);
+        let limits = ReassemblyLimits {
+            max_buffered_bytes: 60,
+            ..ReassemblyLimits::for_testing()
+        };
+        let mut assembler = FragmentAssembler::new(limits);
+        insert(&mut assembler, &schema, &packets[0], 0).unwrap();
+        let err = insert(&mut assembler, &schema, &packets[1], 0).unwrap_err();
+        assert!(matches!(
+            err,
+            CodecError::LimitsExceeded {
+                kind: LimitKind::ReassemblyBytes,
+                ..
+            }
+        ));
+        assert_eq!(assembler.pending_len(), 0);
+        assert_eq!(assembler.buffered_bytes(), 0);
+    }
+
+    #[test]
+    fn fragment_count_is_bounded() {
+        let schema = schema_uint();
+        let packets = fragments(&schema, 5, &entities(30), 64);
+        assert!(packets.len() > ReassemblyLimits::for_testing().max_fragments as usize);
+        let mut assembler = FragmentAssembler::new(ReassemblyLimits::for_testing());
+        let err = insert(&mut assembler, &schema, &packets[0], 0).unwrap_err();
+        assert!(matches!(
+            err,
+            CodecError::LimitsExceeded {
+                kind: LimitKind::Fragments,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn entity_larger_than_mtu_is_rejected() {
+        let schema = schema_uint();
+        let mut out = [0u8; wire::HEADER_SIZE + 8];
+        let err = encode_full_snapshot_fragments(
+            &schema,
+            SnapshotTick::new(1),
+            &entities(1),
+            &CodecLimits::for_testing(),
+            &mut out,
+            |_| {},
+        )
+        .unwrap_err();
+        assert!(matches!(err, CodecError::OutputTooSmall { .. }));
+    }
+
+    #[test]
+    fn invalid_fragment_index_is_rejected() {
+        for (index, count) in [(0u8, 0u8), (3, 3)] {
+            let err =
+                decode_fragment_body(&[index, count], &CodecLimits::for_testing()).unwrap_err();
+            assert_eq!(
+                err,
+                CodecError::InvalidFragment {
+                    index: u32::from(index),
+                    count: u32::from(count),
+                }
+            );
+        }
+    }
+}
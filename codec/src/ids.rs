@@ -0,0 +1,488 @@
+//! Gap coding of sorted entity id lists.
+//!
+//! Destroy, create and masked update sections list entities in strictly
+//! increasing id order. Rather than a full-width id per entity, a non-empty
+//! section sends an id header (`first_id` varu64, `gap_bits` u8) after its
+//! count, and every later entity carries `id - previous - 1` in `gap_bits`
+//! bits. Runs of sequential ids cost zero bits per entity.
+//!
+//! Create and destroy sections also carry each entity's generation, but only
+//! when one of them is non-zero (flagged in the header). Update sections
+//! never do: they refer to entities the receiver already holds.
+//!
+//! Sparse update entries are byte-aligned and repeat the entity per
+//! component, so they use [`SparseIdWriter`]: a varu64 of `id - previous`.
+//!
+//! Every coder is bound to the schema's [`EntityIdWidth`]: ids beyond it are
+//! rejected on both ends, and gaps may be no wider than it.
+
+use bitstream::{BitReader, BitWriter};
+use schema::EntityIdWidth;
+
+use crate::error::{CodecError, CodecResult};
+use crate::snapshot::required_bits;
+use crate::types::EntityId;
+
+/// Upper bound on the id header size (`first_id` varu64, `gap_bits` u8 and
+/// `generation_bits` u8).
+pub(crate) const ID_HEADER_MAX_BYTES: usize = 12;
+
+/// Flag in the `gap_bits` byte: entities carry a generation.
+const GENERATIONS_FLAG: u8 = 0x80;
+
+/// Running statistics of a sorted id list, used to pick the gap and
+/// generation widths before the section is written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct IdRun {
+    first: Option<u64>,
+    prev: u64,
+    max_gap: u64,
+    max_generation: u16,
+}
+
+impl IdRun {
+    pub(crate) fn from_ids(ids: impl IntoIterator<Item = EntityId>) -> Self {
+        let mut run = Self::default();
+        for id in ids {
+            run.push(id);
+        }
+        run
+    }
+
+    /// Records the next id. Ids must be pushed in strictly increasing index
+    /// order.
+    pub(crate) fn push(&mut self, id: EntityId) {
+        let index = id.index();
+        if self.first.is_none() {
+            self.first = Some(index);
+        } else {
+            self.max_gap = self
+                .max_gap
+                .max(index.wrapping_sub(self.prev).wrapping_sub(1));
+        }
+        self.prev = index;
+        self.max_generation = self.max_generation.max(id.generation());
+    }
+
+    fn gap_bits(&self) -> u8 {
+        required_bits(self.max_gap)
+    }
+}
+
+/// Rejects an index that does not fit `width`.
+fn check_index(index: u64, width: EntityIdWidth) -> CodecResult<u64> {
+    if index > width.max_index() {
+        return Err(CodecError::EntityIdOutOfRange {
+            entity_id: index,
+            bits: width.bits(),
+        });
+    }
+    Ok(index)
+}
+
+/// Writes gap-coded ids for one section.
+#[derive(Debug)]
+pub(crate) struct IdGapWriter {
+    width: EntityIdWidth,
+    gap_bits: u8,
+    generation_bits: u8,
+    prev: Option<u64>,
+}
+
+impl IdGapWriter {
+    /// Writes the id header for `run` (nothing if it is empty) and returns
+    /// the writer for its entities. Generations are carried only if some id
+    /// in `run` has a non-zero generation.
+    pub(crate) fn begin(
+        run: &IdRun,
+        width: EntityIdWidth,
+        writer: &mut BitWriter<'_>,
+    ) -> CodecResult<Self> {
+        let generation_bits = required_bits(u64::from(run.max_generation));
+        Self::begin_with(run, width, generation_bits, writer)
+    }
+
+    /// Like [`IdGapWriter::begin`], but never carries generations. Used by
+    /// update sections, whose entities already exist at the receiver.
+    pub(crate) fn begin_indices(
+        run: &IdRun,
+        width: EntityIdWidth,
+        writer: &mut BitWriter<'_>,
+    ) -> CodecResult<Self> {
+        Self::begin_with(run, width, 0, writer)
+    }
+
+    fn begin_with(
+        run: &IdRun,
+        width: EntityIdWidth,
+        generation_bits: u8,
+        writer: &mut BitWriter<'_>,
+    ) -> CodecResult<Self> {
+        let gap_bits = run.gap_bits();
+        if let Some(first) = run.first {
+            writer.align_to_byte()?;
+            writer.write_varu64(check_index(first, width)?)?;
+            if generation_bits > 0 {
+                writer.write_u8_aligned(gap_bits | GENERATIONS_FLAG)?;
+                writer.write_u8_aligned(generation_bits)?;
+            } else {
+                writer.write_u8_aligned(gap_bits)?;
+            }
+        }
+        Ok(Self {
+            width,
+            gap_bits,
+            generation_bits,
+            prev: None,
+        })
+    }
+
+    /// A writer that codes every id at full width with no header, giving an
+    /// upper bound on the per-entity cost for size estimates.
+    pub(crate) fn worst_case(width: EntityIdWidth) -> Self {
+        Self {
+            width,
+            gap_bits: width.bits(),
+            generation_bits: 16,
+            prev: Some(0),
+        }
+    }
+
+    /// Writes the next id. The first index is carried by the header.
+    pub(crate) fn write(&mut self, id: EntityId, writer: &mut BitWriter<'_>) -> CodecResult<()> {
+        let index = check_index(id.index(), self.width)?;
+        if let Some(prev) = self.prev {
+            let gap = index.wrapping_sub(prev).wrapping_sub(1);
+            if self.gap_bits > 0 {
+                writer.write_bits(gap, self.gap_bits)?;
+            }
+        }
+        if self.generation_bits > 0 {
+            writer.write_bits(u64::from(id.generation()), self.generation_bits)?;
+        }
+        self.prev = Some(index);
+        Ok(())
+    }
+}
+
+/// Reads gap-coded ids for one section.
+#[derive(Debug)]
+pub(crate) struct IdGapReader {
+    width: EntityIdWidth,
+    first: u64,
+    gap_bits: u8,
+    generation_bits: u8,
+    prev: Option<u64>,
+}
+
+impl IdGapReader {
+    /// Reads the id header of a section holding `count` entities.
+    pub(crate) fn begin(
+        reader: &mut BitReader<'_>,
+        count: usize,
+        width: EntityIdWidth,
+    ) -> CodecResult<Self> {
+        let mut ids = Self {
+            width,
+            first: 0,
+            gap_bits: 0,
+            generation_bits: 0,
+            prev: None,
+        };
+        if count == 0 {
+            return Ok(ids);
+        }
+        reader.align_to_byte()?;
+        ids.first = check_index(reader.read_varu64()?, width)?;
+        let gap_byte = reader.read_u8_aligned()?;
+        ids.gap_bits = gap_byte & !GENERATIONS_FLAG;
+        if ids.gap_bits > width.bits() {
+            return Err(CodecError::InvalidIdGapBits { bits: ids.gap_bits });
+        }
+        if gap_byte & GENERATIONS_FLAG != 0 {
+            ids.generation_bits = reader.read_u8_aligned()?;
+            if ids.generation_bits == 0 || ids.generation_bits > 16 {
+                return Err(CodecError::InvalidGenerationBits {
+                    bits: ids.generation_bits,
+                });
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Reads the next id. Indices are strictly increasing by construction.
+    pub(crate) fn read(&mut self, reader: &mut BitReader<'_>) -> CodecResult<EntityId> {
+        let index = match self.prev {
+            None => self.first,
+            Some(prev) => {
+                let gap = if self.gap_bits > 0 {
+                    reader.read_bits(self.gap_bits)?
+                } else {
+                    0
+                };
+                let index = prev
+                    .checked_add(gap)
+                    .and_then(|id| id.checked_add(1))
+                    .ok_or(CodecError::IdGapOverflow {
+                        previous: prev,
+                        gap,
+                    })?;
+                check_index(index, self.width)?
+            }
+        };
+        let generation = if self.generation_bits > 0 {
+            reader.read_bits(self.generation_bits)? as u16
+        } else {
+            0
+        };
+        self.prev = Some(index);
+        Ok(EntityId::with_generation(index, generation))
+    }
+}
+
+/// Writes varu64 id gaps for sparse update entries.
+///
+/// Entries are sorted by entity id, so each carries `id - previous` (the
+/// first is relative to 0); further entries of the same entity write `0`.
+#[derive(Debug)]
+pub(crate) struct SparseIdWriter {
+    width: EntityIdWidth,
+    prev: u64,
+}
+
+impl SparseIdWriter {
+    pub(crate) fn new(width: EntityIdWidth) -> Self {
+        Self { width, prev: 0 }
+    }
+
+    pub(crate) fn write(&mut self, id: u64, writer: &mut BitWriter<'_>) -> CodecResult<()> {
+        let id = check_index(id, self.width)?;
+        writer.write_varu64(id.wrapping_sub(self.prev))?;
+        self.prev = id;
+        Ok(())
+    }
+}
+
+/// Reads varu64 id gaps written by [`SparseIdWriter`].
+#[derive(Debug)]
+pub(crate) struct SparseIdReader {
+    width: EntityIdWidth,
+    prev: u64,
+}
+
+impl SparseIdReader {
+    pub(crate) fn new(width: EntityIdWidth) -> Self {
+        Self { width, prev: 0 }
+    }
+
+    pub(crate) fn read(&mut self, reader: &mut BitReader<'_>) -> CodecResult<u64> {
+        let gap = reader.read_varu64()?;
+        let id = self
+            .prev
+            .checked_add(gap)
+            .ok_or(CodecError::IdGapOverflow {
+                previous: self.prev,
+                gap,
+            })?;
+        self.prev = check_index(id, self.width)?;
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip_ids(ids: &[EntityId]) -> (Vec<EntityId>, usize) {
+        roundtrip_width(ids, EntityIdWidth::U32)
+    }
+
+    fn roundtrip_width(ids: &[EntityId], width: EntityIdWidth) -> (Vec<EntityId>, usize) {
+        let run = IdRun::from_ids(ids.iter().copied());
+        let mut buf = [0u8; 256];
+        let mut writer = BitWriter::new(&mut buf);
+        let mut gaps = IdGapWriter::begin(&run, width, &mut writer).unwrap();
+        for id in ids {
+            gaps.write(*id, &mut writer).unwrap();
+        }
+        let len = writer.finish();
+
+        let mut reader = BitReader::new(&buf[..len]);
+        let mut gaps = IdGapReader::begin(&mut reader, ids.len(), width).unwrap();
+        let decoded = ids
+            .iter()
+            .map(|_| gaps.read(&mut reader).unwrap())
+            .collect();
+        (decoded, len)
+    }
+
+    fn roundtrip(ids: &[u32]) -> (Vec<u32>, usize) {
+        let ids: Vec<EntityId> = ids.iter().copied().map(EntityId::from).collect();
+        let (decoded, len) = roundtrip_ids(&ids);
+        let decoded = decoded
+            .into_iter()
+            .map(|id| u32::try_from(id).unwrap())
+            .collect();
+        (decoded, len)
+    }
+
+    #[test]
+    fn sequential_ids_cost_only_the_header() {
+        let ids: Vec<u32> = (1000..1064).collect();
+        let (decoded, len) = roundtrip(&ids);
+        assert_eq!(decoded, ids);
+        assert_eq!(len, 3);
+    }
+
+    #[test]
+    fn sparse_ids_use_widest_gap() {
+        let ids = [3, 4, 10, 11, 40, u32::MAX];
+        let (decoded, len) = roundtrip(&ids);
+        assert_eq!(decoded, ids);
+        // 1 byte first id, 1 byte width, five 32-bit gaps.
+        assert_eq!(len, 2 + 20);
+    }
+
+    #[test]
+    fn generations_are_carried_only_when_needed() {
+        let ids = [
+            EntityId::new(10),
+            EntityId::with_generation(11, 3),
+            EntityId::new(12),
+        ];
+        let (decoded, len) = roundtrip_ids(&ids);
+        assert_eq!(decoded, ids);
+        // first id, flagged gap width, generation width, 3 x 2 generation bits.
+        assert_eq!(len, 4);
+
+        let plain = [EntityId::new(10), EntityId::new(11), EntityId::new(12)];
+        assert_eq!(roundtrip_ids(&plain).1, 2);
+    }
+
+    #[test]
+    fn update_ids_drop_generations() {
+        let ids = [
+            EntityId::with_generation(4, 9),
+            EntityId::with_generation(5, 2),
+        ];
+        let run = IdRun::from_ids(ids);
+        let mut buf = [0u8; 16];
+        let mut writer = BitWriter::new(&mut buf);
+        let mut gaps = IdGapWriter::begin_indices(&run, EntityIdWidth::U32, &mut writer).unwrap();
+        for id in ids {
+            gaps.write(id, &mut writer).unwrap();
+        }
+        let len = writer.finish();
+        assert_eq!(len, 2);
+
+        let mut reader = BitReader::new(&buf[..len]);
+        let mut gaps = IdGapReader::begin(&mut reader, 2, EntityIdWidth::U32).unwrap();
+        assert_eq!(gaps.read(&mut reader).unwrap(), EntityId::new(4));
+        assert_eq!(gaps.read(&mut reader).unwrap(), EntityId::new(5));
+    }
+
+    #[test]
+    fn empty_list_has_no_header() {
+        let (decoded, len) = roundtrip(&[]);
+        assert!(decoded.is_empty());
+        assert_eq!(len, 0);
+    }
+
+    #[test]
+    fn gap_bits_wider_than_ids_are_rejected() {
+        let buf = [5u8, 33];
+        let mut reader = BitReader::new(&buf);
+        assert!(matches!(
+            IdGapReader::begin(&mut reader, 2, EntityIdWidth::U32),
+            Err(CodecError::InvalidIdGapBits { bits: 33 })
+        ));
+        let buf = [5u8, 17];
+        let mut reader = BitReader::new(&buf);
+        assert!(matches!(
+            IdGapReader::begin(&mut reader, 2, EntityIdWidth::U16),
+            Err(CodecError::InvalidIdGapBits { bits: 17 })
+        ));
+    }
+
+    #[test]
+    fn ids_past_the_width_are_rejected() {
+        // first = u32::MAX, gap_bits = 1, gap = 0.
+        let buf = [0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 1, 0];
+        let mut reader = BitReader::new(&buf);
+        let mut gaps = IdGapReader::begin(&mut reader, 2, EntityIdWidth::U32).unwrap();
+        assert_eq!(gaps.read(&mut reader).unwrap(), EntityId::from(u32::MAX));
+        assert!(matches!(
+            gaps.read(&mut reader),
+            Err(CodecError::EntityIdOutOfRange {
+                entity_id: 0x1_0000_0000,
+                bits: 32
+            })
+        ));
+
+        let mut reader = BitReader::new(&buf);
+        assert!(matches!(
+            IdGapReader::begin(&mut reader, 2, EntityIdWidth::U16),
+            Err(CodecError::EntityIdOutOfRange { bits: 16, .. })
+        ));
+
+        let mut buf = [0u8; 16];
+        let mut writer = BitWriter::new(&mut buf);
+        let run = IdRun::from_ids([EntityId::new(70_000)]);
+        assert!(matches!(
+            IdGapWriter::begin(&run, EntityIdWidth::U16, &mut writer),
+            Err(CodecError::EntityIdOutOfRange {
+                entity_id: 70_000,
+                bits: 16
+            })
+        ));
+    }
+
+    #[test]
+    fn wide_ids_roundtrip() {
+        let ids = [
+            EntityId::new(1 << 40),
+            EntityId::new((1 << 40) + 1),
+            EntityId::new(u64::MAX),
+        ];
+        let (decoded, _) = roundtrip_width(&ids, EntityIdWidth::U64);
+        assert_eq!(decoded, ids);
+    }
+
+    #[test]
+    fn gap_overflow_is_rejected() {
+        // first = u64::MAX, gap_bits = 1, gap = 0.
+        let mut buf = [0xFF; 12];
+        buf[9] = 0x01;
+        buf[10] = 1;
+        buf[11] = 0;
+        let mut reader = BitReader::new(&buf);
+        let mut gaps = IdGapReader::begin(&mut reader, 2, EntityIdWidth::U64).unwrap();
+        assert_eq!(gaps.read(&mut reader).unwrap(), EntityId::new(u64::MAX));
+        assert!(matches!(
+            gaps.read(&mut reader),
+            Err(CodecError::IdGapOverflow {
+                previous: u64::MAX,
+                gap: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn sparse_ids_roundtrip_with_repeats() {
+        let ids = [7, 7, 9, 300, 300];
+        let mut buf = [0u8; 32];
+        let mut writer = BitWriter::new(&mut buf);
+        let mut sparse = SparseIdWriter::new(EntityIdWidth::U32);
+        for id in ids {
+            sparse.write(id, &mut writer).unwrap();
+        }
+        let len = writer.finish();
+        assert_eq!(len, 6);
+
+        let mut reader = BitReader::new(&buf[..len]);
+        let mut sparse = SparseIdReader::new(EntityIdWidth::U32);
+        for id in ids {
+            assert_eq!(sparse.read(&mut reader).unwrap(), id);
+        }
+    }
+}
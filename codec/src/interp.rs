@@ -0,0 +1,529 @@
+//! Client-side interpolation between decoded snapshots.
+//!
+//! Clients usually render slightly in the past and blend between the two
+//! snapshots that bracket the render time. [`interpolate`] blends a pair of
+//! snapshots according to each field's [`Interpolation`] hint, and
+//! [`JitterBuffer`] holds recent snapshots keyed by tick so they can be
+//! sampled at fractional render ticks.
+//!
+//! Interpolation never touches the wire format; it only produces new
+//! `Snapshot` values on the receiving side.
+
+use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+
+use schema::{ComponentDef, FieldCodec, FieldDef, Interpolation};
+
+use crate::error::{CodecError, CodecResult, MaskKind, MaskReason, ValueReason};
+use crate::snapshot::{
+    find_component_def, value_name, ComponentSnapshot, EntitySnapshot, FieldValue, Snapshot,
+};
+use crate::types::SnapshotTick;
+
+/// Blends snapshot `a` towards snapshot `b` by `alpha` (clamped to `[0, 1]`).
+///
+/// Field values follow the field's [`Interpolation`]: fixed-point fields blend
+/// linearly by default, angles take the shortest arc, and bools and integers
+/// step (holding `a` until `alpha` reaches 1). Blended values are rounded and
+/// clamped to the field's codec range.
+///
+/// Entity and component membership steps as well: until `alpha` reaches 1
+/// the result holds exactly the entities and components of `a`, blended with
+/// `b` wherever `b` has the same entity (index and generation) and component.
+/// The result carries `a`'s tick; at `alpha >= 1` it is a copy of `b`.
+pub fn interpolate(
+    schema: &schema::Schema,
+    a: &Snapshot,
+    b: &Snapshot,
+    alpha: f32,
+) -> CodecResult<Snapshot> {
+    let alpha = if alpha.is_nan() {
+        0.0
+    } else {
+        f64::from(alpha.clamp(0.0, 1.0))
+    };
+    if alpha >= 1.0 {
+        return Ok(b.clone());
+    }
+
+    let mut entities = Vec::with_capacity(a.entities.len());
+    for from in &a.entities {
+        let to = b.get(from.id);
+        let mut components = Vec::with_capacity(from.components.len());
+        for component in &from.components {
+            let def = find_component_def(schema, component.id)?;
+            let fields = match to.and_then(|entity| entity.component(component.id)) {
+                Some(target) => blend_component(def, component, target, alpha)?,
+                None => component.fields.clone(),
+            };
+            components.push(ComponentSnapshot {
+                id: component.id,
+                fields,
+            });
+        }
+        entities.push(EntitySnapshot {
+            id: from.id,
+            components,
+        });
+    }
+    Ok(Snapshot {
+        tick: a.tick,
+        entities,
+    })
+}
+
+/// A fixed-capacity buffer of decoded snapshots in serial tick order, for sampling
+/// at render time.
+#[derive(Debug, Clone)]
+pub struct JitterBuffer {
+    snapshots: VecDeque<Snapshot>,
+    capacity: usize,
+}
+
+impl JitterBuffer {
+    /// Creates an empty buffer holding at most `capacity` snapshots.
+    #[must_use]
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(capacity.get()),
+            capacity: capacity.get(),
+        }
+    }
+
+    /// Returns the capacity of the buffer.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of snapshots held.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// Returns `true` if no snapshots are held.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Returns the tick of the oldest snapshot held.
+    #[must_use]
+    pub fn oldest_tick(&self) -> Option<SnapshotTick> {
+        self.snapshots.front().map(|snapshot| snapshot.tick)
+    }
+
+    /// Returns the tick of the newest snapshot held.
+    #[must_use]
+    pub fn newest_tick(&self) -> Option<SnapshotTick> {
+        self.snapshots.back().map(|snapshot| snapshot.tick)
+    }
+
+    /// Returns the snapshot for `tick`, if held.
+    #[must_use]
+    pub fn get(&self, tick: SnapshotTick) -> Option<&Snapshot> {
+        let idx = self.position(tick).ok()?;
+        Some(&self.snapshots[idx])
+    }
+
+    /// Inserts `snapshot` in tick order, evicting the oldest snapshot when full.
+    ///
+    /// Snapshots may arrive out of order. Returns `false` (and drops the
+    /// snapshot) for a tick already held, or for one older than everything in
+    /// a full buffer.
+    pub fn push(&mut self, snapshot: Snapshot) -> bool {
+        let idx = match self.position(snapshot.tick) {
+            Ok(_) => return false,
+            Err(idx) => idx,
+        };
+        if idx == 0 && self.snapshots.len() == self.capacity {
+            return false;
+        }
+        self.snapshots.insert(idx, snapshot);
+        if self.snapshots.len() > self.capacity {
+            self.snapshots.pop_front();
+        }
+        true
+    }
+
+    /// Samples the buffer at `render_tick`, which may be fractional.
+    ///
+    /// Interpolates between the snapshots bracketing `render_tick`. Times at
+    /// or past the newest snapshot return that snapshot (no extrapolation);
+    /// times before the oldest snapshot, or an empty buffer, return `None`.
+    /// `render_tick` is taken modulo 2^32 and placed in serial order relative
+    /// to the oldest snapshot, so sampling keeps working across tick wrap.
+    pub fn sample(
+        &self,
+        schema: &schema::Schema,
+        render_tick: f64,
+    ) -> CodecResult<Option<Snapshot>> {
+        let (Some(oldest), Some(newest)) = (self.snapshots.front(), self.snapshots.back()) else {
+            return Ok(None);
+        };
+        let origin = oldest.tick;
+        let render = render_offset(render_tick, origin);
+        if render >= tick_offset(newest.tick, origin) as f64 {
+            return Ok(Some(newest.clone()));
+        }
+        let next = self
+            .snapshots
+            .partition_point(|snapshot| tick_offset(snapshot.tick, origin) as f64 <= render);
+        if next == 0 {
+            return Ok(None);
+        }
+        let from = &self.snapshots[next - 1];
+        let to = &self.snapshots[next];
+        let start = tick_offset(from.tick, origin) as f64;
+        let span = tick_offset(to.tick, origin) as f64 - start;
+        let alpha = ((render - start) / span) as f32;
+        interpolate(schema, from, to, alpha).map(Some)
+    }
+
+    /// Drops snapshots no longer needed to sample at or after `render_tick`,
+    /// keeping the newest snapshot at or before it.
+    pub fn discard_before(&mut self, render_tick: f64) {
+        let Some(origin) = self.oldest_tick() else {
+            return;
+        };
+        let render = render_offset(render_tick, origin);
+        let keep_from = self
+            .snapshots
+            .partition_point(|snapshot| tick_offset(snapshot.tick, origin) as f64 <= render)
+            .saturating_sub(1);
+        self.snapshots.drain(..keep_from);
+    }
+
+    /// Removes all snapshots.
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+
+    /// Finds `tick` by its serial offset from the oldest snapshot, so ticks
+    /// past a wrap sort after the ticks before it.
+    fn position(&self, tick: SnapshotTick) -> Result<usize, usize> {
+        let Some(origin) = self.oldest_tick() else {
+            return Err(0);
+        };
+        let offset = tick_offset(tick, origin);
+        self.snapshots
+            .binary_search_by_key(&offset, |snapshot| tick_offset(snapshot.tick, origin))
+    }
+}
+
+/// Signed serial distance from `origin` to `tick`.
+fn tick_offset(tick: SnapshotTick, origin: SnapshotTick) -> i64 {
+    i64::from(tick.since(origin) as i32)
+}
+
+/// Signed distance from `origin` to `render_tick`, modulo 2^32.
+fn render_offset(render_tick: f64, origin: SnapshotTick) -> f64 {
+    const TICK_SPACE: f64 = 4_294_967_296.0;
+    let offset = (render_tick - f64::from(origin.raw())).rem_euclid(TICK_SPACE);
+    if offset >= TICK_SPACE / 2.0 {
+        offset - TICK_SPACE
+    } else {
+        offset
+    }
+}
+
+fn blend_component(
+    def: &ComponentDef,
+    from: &ComponentSnapshot,
+    to: &ComponentSnapshot,
+    alpha: f64,
+) -> CodecResult<Vec<FieldValue>> {
+    for component in [from, to] {
+        if component.fields.len() != def.fields.len() {
+            return Err(CodecError::InvalidMask {
+                kind: MaskKind::FieldMask { component: def.id },
+                reason: MaskReason::FieldCountMismatch {
+                    expected: def.fields.len(),
+                    actual: component.fields.len(),
+                },
+            });
+        }
+    }
+    def.fields
+        .iter()
+        .zip(from.fields.iter().zip(&to.fields))
+        .map(|(field, (a, b))| blend_field(def, field, *a, *b, alpha))
+        .collect()
+}
+
+fn blend_field(
+    component: &ComponentDef,
+    field: &FieldDef,
+    a: FieldValue,
+    b: FieldValue,
+    alpha: f64,
+) -> CodecResult<FieldValue> {
+    let (Some(start), Some(end)) = (numeric(a), numeric(b)) else {
+        return match (a, b) {
+            (FieldValue::Bool(_), FieldValue::Bool(_)) => Ok(a),
+            _ => Err(type_mismatch(component, field, a, b)),
+        };
+    };
+    if std::mem::discriminant(&a) != std::mem::discriminant(&b) {
+        return Err(type_mismatch(component, field, a, b));
+    }
+    let (min, max) = codec_range(field.codec);
+    let value = match field.interpolation {
+        Interpolation::Auto if matches!(field.codec, FieldCodec::FixedPoint(_)) => {
+            lerp(start, end - start, alpha)
+        }
+        Interpolation::Auto | Interpolation::Step => return Ok(a),
+        Interpolation::Linear => lerp(start, end - start, alpha),
+        Interpolation::Angle { period_q } => {
+            let period = i128::from(period_q);
+            let mut diff = (end - start).rem_euclid(period);
+            if diff * 2 > period {
+                diff -= period;
+            }
+            let mut value = lerp(start, diff, alpha);
+            if value < min {
+                value += period;
+            } else if value > max {
+                value -= period;
+            }
+            value
+        }
+    };
+    Ok(with_numeric(a, value.clamp(min, max)))
+}
+
+fn lerp(start: i128, diff: i128, alpha: f64) -> i128 {
+    start + (diff as f64 * alpha).round() as i128
+}
+
+fn numeric(value: FieldValue) -> Option<i128> {
+    match value {
+        FieldValue::UInt(v) | FieldValue::VarUInt(v) => Some(i128::from(v)),
+        FieldValue::SInt(v) | FieldValue::VarSInt(v) | FieldValue::FixedPoint(v) => {
+            Some(i128::from(v))
+        }
+        FieldValue::Bool(_) => None,
+    }
+}
+
+/// Replaces the numeric payload of `like`; `value` is already in range.
+fn with_numeric(like: FieldValue, value: i128) -> FieldValue {
+    match like {
+        FieldValue::UInt(_) => FieldValue::UInt(value as u64),
+        FieldValue::VarUInt(_) => FieldValue::VarUInt(value as u64),
+        FieldValue::SInt(_) => FieldValue::SInt(value as i64),
+        FieldValue::VarSInt(_) => FieldValue::VarSInt(value as i64),
+        FieldValue::FixedPoint(_) => FieldValue::FixedPoint(value as i64),
+        FieldValue::Bool(_) => like,
+    }
+}
+
+/// Inclusive range of values the codec can encode.
+fn codec_range(codec: FieldCodec) -> (i128, i128) {
+    match codec {
+        FieldCodec::Bool => (0, 1),
+        FieldCodec::UInt { bits } => (0, (1i128 << bits) - 1),
+        FieldCodec::SInt { bits } => {
+            let half = 1i128 << (bits - 1);
+            (-half, half - 1)
+        }
+        FieldCodec::VarUInt => (0, i128::from(u32::MAX)),
+        FieldCodec::VarSInt => (i128::from(i32::MIN), i128::from(i32::MAX)),
+        FieldCodec::FixedPoint(fp) => (i128::from(fp.min_q), i128::from(fp.max_q)),
+    }
+}
+
+fn type_mismatch(
+    component: &ComponentDef,
+    field: &FieldDef,
+    a: FieldValue,
+    b: FieldValue,
+) -> CodecError {
+    CodecError::InvalidValue {
+        component: component.id,
+        field: field.id,
+        reason: ValueReason::TypeMismatch {
+            expected: value_name(a),
+            found: value_name(b),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::EntityId;
+    use schema::{ComponentId, FieldId, Schema};
+
+    fn cid(id: u16) -> ComponentId {
+        ComponentId::new(id).unwrap()
+    }
+
+    fn fid(id: u16) -> FieldId {
+        FieldId::new(id).unwrap()
+    }
+
+    fn schema() -> Schema {
+        let component = ComponentDef::new(cid(1))
+            .field(FieldDef::new(
+                fid(1),
+                FieldCodec::fixed_point(-10_000, 10_000, 100),
+            ))
+            .field(
+                FieldDef::new(fid(2), FieldCodec::fixed_point(0, 35_999, 100))
+                    .interpolation(Interpolation::Angle { period_q: 36_000 }),
+            )
+            .field(FieldDef::new(fid(3), FieldCodec::bool()))
+            .field(FieldDef::new(fid(4), FieldCodec::uint(8)))
+            .field(
+                FieldDef::new(fid(5), FieldCodec::var_uint()).interpolation(Interpolation::Linear),
+            );
+        Schema::new(vec![component]).unwrap()
+    }
+
+    fn snapshot(tick: u32, position: i64, yaw: i64, alive: bool, health: u64) -> Snapshot {
+        Snapshot {
+            tick: SnapshotTick::new(tick),
+            entities: vec![EntitySnapshot {
+                id: EntityId::new(1),
+                components: vec![ComponentSnapshot {
+                    id: cid(1),
+                    fields: vec![
+                        FieldValue::FixedPoint(position),
+                        FieldValue::FixedPoint(yaw),
+                        FieldValue::Bool(alive),
+                        FieldValue::UInt(health),
+                        FieldValue::VarUInt(health),
+                    ],
+                }],
+            }],
+        }
+    }
+
+    fn fields(snapshot: &Snapshot) -> &[FieldValue] {
+        &snapshot.entities[0].components[0].fields
+    }
+
+    #[test]
+    fn interpolate_blends_by_field_mode() {
+        let schema = schema();
+        let a = snapshot(10, -100, 35_000, true, 100);
+        let b = snapshot(11, 100, 1_000, false, 50);
+
+        let mid = interpolate(&schema, &a, &b, 0.25).unwrap();
+        assert_eq!(mid.tick, a.tick);
+        assert_eq!(
+            fields(&mid),
+            [
+                FieldValue::FixedPoint(-50),
+                // 350.00 -> 10.00 degrees takes the 20 degree arc through 0.
+                FieldValue::FixedPoint(35_500),
+                FieldValue::Bool(true),
+                FieldValue::UInt(100),
+                FieldValue::VarUInt(87),
+            ]
+        );
+
+        let wrapped = interpolate(&schema, &a, &b, 0.75).unwrap();
+        assert_eq!(fields(&wrapped)[1], FieldValue::FixedPoint(500));
+
+        assert_eq!(interpolate(&schema, &a, &b, 0.0).unwrap(), a);
+        assert_eq!(interpolate(&schema, &a, &b, 1.5).unwrap(), b);
+    }
+
+    #[test]
+    fn interpolate_membership_steps_with_alpha() {
+        let schema = schema();
+        let a = snapshot(10, 0, 0, true, 1);
+        let mut b = snapshot(11, 0, 0, true, 1);
+        b.entities[0].id = EntityId::with_generation(1, 1);
+        b.entities.push(EntitySnapshot {
+            id: EntityId::new(2),
+            components: Vec::new(),
+        });
+
+        let mid = interpolate(&schema, &a, &b, 0.5).unwrap();
+        assert_eq!(mid, a);
+        assert_eq!(interpolate(&schema, &a, &b, 1.0).unwrap(), b);
+    }
+
+    #[test]
+    fn interpolate_rejects_mismatched_values() {
+        let schema = schema();
+        let a = snapshot(10, 0, 0, true, 1);
+        let mut b = a.clone();
+        b.entities[0].components[0].fields[0] = FieldValue::SInt(3);
+        let err = interpolate(&schema, &a, &b, 0.5).unwrap_err();
+        assert!(matches!(
+            err,
+            CodecError::InvalidValue {
+                reason: ValueReason::TypeMismatch { .. },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn jitter_buffer_orders_and_samples() {
+        let schema = schema();
+        let mut buffer = JitterBuffer::new(NonZeroUsize::new(3).unwrap());
+        assert_eq!(buffer.sample(&schema, 10.0).unwrap(), None);
+
+        assert!(buffer.push(snapshot(12, 200, 0, true, 1)));
+        assert!(buffer.push(snapshot(10, 0, 0, true, 1)));
+        assert!(!buffer.push(snapshot(10, 0, 0, true, 1)));
+        assert!(buffer.push(snapshot(11, 100, 0, true, 1)));
+        assert_eq!(buffer.oldest_tick(), Some(SnapshotTick::new(10)));
+        assert_eq!(buffer.newest_tick(), Some(SnapshotTick::new(12)));
+
+        // Full: older than everything held is dropped, newer evicts the oldest.
+        assert!(!buffer.push(snapshot(9, 0, 0, true, 1)));
+        assert!(buffer.push(snapshot(13, 300, 0, true, 1)));
+        assert_eq!(buffer.oldest_tick(), Some(SnapshotTick::new(11)));
+        assert!(buffer.get(SnapshotTick::new(10)).is_none());
+
+        assert_eq!(buffer.sample(&schema, 10.5).unwrap(), None);
+        let sampled = buffer.sample(&schema, 11.5).unwrap().unwrap();
+        assert_eq!(fields(&sampled)[0], FieldValue::FixedPoint(150));
+        let clamped = buffer.sample(&schema, 20.0).unwrap().unwrap();
+        assert_eq!(clamped.tick, SnapshotTick::new(13));
+
+        buffer.discard_before(12.5);
+        assert_eq!(buffer.oldest_tick(), Some(SnapshotTick::new(12)));
+        assert_eq!(buffer.len(), 2);
+    }
+
+    #[test]
+    fn jitter_buffer_orders_and_samples_across_wrap() {
+        let schema = schema();
+        let mut buffer = JitterBuffer::new(NonZeroUsize::new(3).unwrap());
+        assert!(buffer.push(snapshot(u32::MAX - 1, 0, 0, true, 1)));
+        assert!(buffer.push(snapshot(1, 300, 0, true, 1)));
+        assert!(buffer.push(snapshot(u32::MAX, 100, 0, true, 1)));
+        assert_eq!(buffer.oldest_tick(), Some(SnapshotTick::new(u32::MAX - 1)));
+        assert_eq!(buffer.newest_tick(), Some(SnapshotTick::new(1)));
+
+        // Full: a tick past the wrap is newer and evicts the oldest.
+        assert!(buffer.push(snapshot(2, 400, 0, true, 1)));
+        assert_eq!(buffer.oldest_tick(), Some(SnapshotTick::new(u32::MAX)));
+        assert!(!buffer.push(snapshot(u32::MAX - 1, 0, 0, true, 1)));
+
+        // Tick 1 is two raw ticks after u32::MAX; wrapped and unwrapped render
+        // times agree.
+        let wrapped = buffer.sample(&schema, 0.0).unwrap().unwrap();
+        assert_eq!(fields(&wrapped)[0], FieldValue::FixedPoint(200));
+        let unwrapped = buffer.sample(&schema, 4_294_967_296.0).unwrap().unwrap();
+        assert_eq!(unwrapped, wrapped);
+        let sampled = buffer.sample(&schema, 1.5).unwrap().unwrap();
+        assert_eq!(fields(&sampled)[0], FieldValue::FixedPoint(350));
+        assert_eq!(
+            buffer.sample(&schema, f64::from(u32::MAX - 1)).unwrap(),
+            None
+        );
+
+        buffer.discard_before(1.5);
+        assert_eq!(buffer.oldest_tick(), Some(SnapshotTick::new(1)));
+        assert_eq!(buffer.len(), 2);
+    }
+}
@@ -7,9 +7,21 @@
 //!
 //! - Full snapshot encoding/decoding
 //! - Delta encoding relative to a baseline
-//! - Baseline history management
-//! - Entity create/update/destroy operations
+//! - Baseline history management with per-client ack tracking
+//! - Shared baseline history that stores unchanged entities once across ticks
+//! - Entity create/update/destroy operations, plus component insert/remove
 //! - Per-component and per-field change masks
+//! - Byte-budgeted deltas with prioritized truncation
+//! - Fragmentation and reassembly of oversized full snapshots
+//! - Per-entity baselines for deltas that survive partial ACK loss
+//! - Sequenced event messages with resend-until-acked delivery
+//! - Predicted-value residuals for moving fields
+//! - Trained codec models: histogram- and template-coded sections plus a
+//!   shared LZ4 dictionary
+//! - Generational entity ids with baseline-aware index reuse
+//! - Server and client session endpoints with ack-driven resync, timeouts and
+//!   session resumption after reconnects
+//! - Client-side snapshot interpolation and a jitter buffer for rendering
 //!
 //! # Design Principles
 //!
@@ -17,35 +29,78 @@
 //! - **No steady-state allocations** - Uses caller-provided buffers.
 //! - **Deterministic** - Same inputs produce same outputs.
 
+mod ack;
+mod allocator;
 mod baseline;
+mod budget;
+mod caps;
 mod delta;
+mod endpoint;
+mod entropy;
 mod error;
+mod events;
+mod fragment;
+mod ids;
+pub mod interp;
 mod limits;
+mod model;
+mod model_coding;
+mod multi_baseline;
+mod predict;
 mod scratch;
 mod session;
+mod shared_baseline;
 mod snapshot;
 mod types;
 
+pub use ack::{AckOutcome, BaselineSelection, ClientAckTracker, ResyncReason};
+pub use allocator::EntityIdAllocator;
 pub use baseline::{BaselineError, BaselineStore};
+pub use budget::{encode_delta_from_changes_with_budget, BudgetedDelta};
+pub use caps::{Capability, NegotiatedCaps};
 pub use delta::{
     apply_delta_snapshot, apply_delta_snapshot_from_packet, decode_delta_packet,
     encode_delta_from_changes, encode_delta_snapshot, encode_delta_snapshot_for_client,
-    encode_delta_snapshot_for_client_session,
+    encode_delta_snapshot_for_client_session, encode_delta_snapshot_for_client_session_with_caps,
+    encode_delta_snapshot_for_client_session_with_mode,
     encode_delta_snapshot_for_client_session_with_scratch,
     encode_delta_snapshot_for_client_with_scratch, encode_delta_snapshot_from_updates,
     encode_delta_snapshot_with_scratch, select_baseline_tick, DeltaDecoded, DeltaUpdateComponent,
     DeltaUpdateEntity, SessionEncoder,
 };
+pub use endpoint::{
+    ClientEndpoint, EndpointConfig, EndpointEvent, EndpointState, ResumeRejection, ServerEndpoint,
+    ServerSend, SessionEndpoint,
+};
+pub use entropy::entropy_code_update_sections;
 pub use error::{CodecError, CodecResult, LimitKind, MaskKind, MaskReason, ValueReason};
-pub use limits::CodecLimits;
+pub use events::{append_events_section, decode_events, EventInbox, EventMessage, EventOutbox};
+pub use fragment::{
+    decode_full_snapshot_fragment, encode_full_snapshot_fragments, FragmentAssembler, FragmentInfo,
+};
+pub use interp::{interpolate, JitterBuffer};
+pub use limits::{CodecLimits, ReassemblyLimits};
+pub use model::{
+    field_value_bits, CodecModel, CreateTemplate, FieldHistogram, ModelTrainer, ModelTrainingConfig,
+};
+pub use model_coding::{expand_model_sections, model_code_sections};
+pub use multi_baseline::{
+    apply_delta_snapshot_multi_baseline, apply_delta_snapshot_multi_baseline_from_packet,
+    encode_delta_snapshot_multi_baseline,
+};
+pub use predict::{predict, UpdateValue};
+pub use schema::EntityIdWidth;
 pub use scratch::CodecScratch;
 pub use session::{
-    decode_session_init_packet, decode_session_packet, encode_session_init_packet,
-    CompactHeaderMode, SessionState,
+    decode_session_accept_packet, decode_session_init_packet, decode_session_packet,
+    encode_session_accept_packet, encode_session_init_packet, CompactHeaderMode, SessionAccept,
+    SessionInitOptions, SessionState,
 };
+pub use shared_baseline::{SharedBaselineHistory, SharedSnapshot};
 pub use snapshot::{
     decode_full_snapshot, decode_full_snapshot_from_packet, encode_full_snapshot,
-    ComponentSnapshot, EntitySnapshot, FieldValue, Snapshot,
+    encode_full_snapshot_from_view, ComponentSnapshot, EntitySnapshot, FieldValue, Snapshot,
+    SnapshotView,
 };
 pub use types::{EntityId, SnapshotTick};
 pub use wire::Limits as WireLimits;
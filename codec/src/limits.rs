@@ -17,6 +17,8 @@ pub struct CodecLimits {
     pub max_section_bytes: usize,
     /// Maximum number of entities after applying a delta.
     pub max_total_entities_after_apply: usize,
+    /// Maximum number of events in an EVENTS section.
+    pub max_events: usize,
 }
 
 impl Default for CodecLimits {
@@ -29,6 +31,7 @@ impl Default for CodecLimits {
             max_fields_per_component: 64,
             max_section_bytes: 64 * 1024,
             max_total_entities_after_apply: 4096,
+            max_events: 256,
         }
     }
 }
@@ -45,6 +48,7 @@ impl CodecLimits {
             max_fields_per_component: 16,
             max_section_bytes: 4096,
             max_total_entities_after_apply: 128,
+            max_events: 16,
         }
     }
 
@@ -59,6 +63,55 @@ impl CodecLimits {
             max_fields_per_component: usize::MAX,
             max_section_bytes: usize::MAX,
             max_total_entities_after_apply: usize::MAX,
+            max_events: usize::MAX,
+        }
+    }
+}
+
+/// Limits for reassembling fragmented snapshots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReassemblyLimits {
+    /// Maximum number of fragments per snapshot.
+    pub max_fragments: u32,
+    /// Maximum number of snapshots being reassembled at once.
+    pub max_pending: usize,
+    /// Maximum payload bytes buffered across all pending snapshots.
+    pub max_buffered_bytes: usize,
+    /// Time after the first fragment before a pending snapshot is dropped.
+    pub timeout_ms: u64,
+}
+
+impl Default for ReassemblyLimits {
+    fn default() -> Self {
+        Self {
+            max_fragments: 64,
+            max_pending: 4,
+            max_buffered_bytes: 256 * 1024,
+            timeout_ms: 1000,
+        }
+    }
+}
+
+impl ReassemblyLimits {
+    /// Creates limits suitable for testing with smaller values.
+    #[must_use]
+    pub const fn for_testing() -> Self {
+        Self {
+            max_fragments: 8,
+            max_pending: 2,
+            max_buffered_bytes: 4096,
+            timeout_ms: 100,
+        }
+    }
+
+    /// Creates limits with no restrictions (use with caution).
+    #[must_use]
+    pub const fn unlimited() -> Self {
+        Self {
+            max_fragments: u32::MAX,
+            max_pending: usize::MAX,
+            max_buffered_bytes: usize::MAX,
+            timeout_ms: u64::MAX,
         }
     }
 }
@@ -88,4 +141,12 @@ mod tests {
         assert_eq!(limits.max_entities_create, usize::MAX);
         assert_eq!(limits.max_section_bytes, usize::MAX);
     }
+
+    #[test]
+    fn reassembly_testing_limits_smaller() {
+        let test_limits = ReassemblyLimits::for_testing();
+        let default_limits = ReassemblyLimits::default();
+        assert!(test_limits.max_fragments < default_limits.max_fragments);
+        assert!(test_limits.max_buffered_bytes < default_limits.max_buffered_bytes);
+    }
 }
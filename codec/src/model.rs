@@ -0,0 +1,503 @@
+//! Trained codec models built from packet captures.
+//!
+//! A [`CodecModel`] is a static artifact produced offline (see
+//! `sdec-tools train`) from a corpus of packets: per-field value histograms,
+//! the most common create templates, and a shared compression dictionary.
+//! Both ends load the same artifact; its [`CodecModel::hash`] is announced in
+//! `SESSION_INIT` so a mismatched model is detected before any packet is
+//! decoded with the wrong one. Histograms and templates drive the modeled
+//! create and update sections (see `model_coding`), and the dictionary primes
+//! LZ4.
+
+use std::collections::{HashMap, HashSet};
+
+use bitstream::{BitReader, BitVecWriter};
+use schema::{ComponentId, FieldCodec, FieldDef, FieldId};
+
+use crate::delta::DeltaUpdateEntity;
+use crate::error::{CodecError, CodecResult};
+use crate::predict::UpdateValue;
+use crate::snapshot::{EntitySnapshot, FieldValue};
+
+const MODEL_MAGIC: [u8; 4] = *b"SDMD";
+const MODEL_VERSION: u8 = 3;
+const DICTIONARY_GRAM: usize = 8;
+
+/// Value histogram for one schema field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldHistogram {
+    pub component: ComponentId,
+    pub field: FieldId,
+    /// Most frequent values as raw quantized bits (see [`field_value_bits`]),
+    /// with their counts, by descending count. Modeled sections code a value
+    /// as its index in this list.
+    pub values: Vec<(u64, u32)>,
+}
+
+/// A commonly created component set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreateTemplate {
+    /// Components present on the created entity, in snapshot order. Modeled
+    /// create sections code this set as the template's index.
+    pub components: Vec<ComponentId>,
+    /// Number of creates observed with exactly this component set.
+    pub count: u32,
+}
+
+/// A trained model shared by encoder and decoder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodecModel {
+    /// Hash of the schema the model was trained against.
+    pub schema_hash: u64,
+    pub field_histograms: Vec<FieldHistogram>,
+    pub create_templates: Vec<CreateTemplate>,
+    /// Shared dictionary for [`wire::Lz4Compressor`].
+    pub dictionary: Vec<u8>,
+}
+
+impl CodecModel {
+    /// Serializes the model into its artifact bytes.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = BitVecWriter::new();
+        self.write(&mut writer)
+            .expect("growable writer cannot overflow");
+        writer.finish()
+    }
+
+    /// Parses model artifact bytes.
+    pub fn from_bytes(bytes: &[u8]) -> CodecResult<Self> {
+        Self::read(bytes).map_err(|_| CodecError::InvalidModel)
+    }
+
+    /// Returns the deterministic hash negotiated in `SESSION_INIT`.
+    #[must_use]
+    pub fn hash(&self) -> u64 {
+        let hash = blake3::hash(&self.to_bytes());
+        u64::from_le_bytes(hash.as_bytes()[0..8].try_into().unwrap())
+    }
+
+    /// Returns an LZ4 compressor primed with the model's dictionary.
+    #[must_use]
+    pub fn compressor(&self) -> wire::Lz4Compressor {
+        wire::Lz4Compressor::with_dictionary(self.dictionary.clone())
+    }
+
+    /// Returns the histogram for a field, if the model has one.
+    #[must_use]
+    pub fn histogram(&self, component: ComponentId, field: FieldId) -> Option<&FieldHistogram> {
+        self.field_histograms
+            .iter()
+            .find(|histogram| histogram.component == component && histogram.field == field)
+    }
+
+    fn write(&self, writer: &mut BitVecWriter) -> bitstream::BitResult<()> {
+        for byte in MODEL_MAGIC {
+            writer.write_u8_aligned(byte)?;
+        }
+        writer.write_u8_aligned(MODEL_VERSION)?;
+        writer.write_u64_aligned(self.schema_hash)?;
+
+        writer.write_varu32(self.field_histograms.len() as u32)?;
+        for histogram in &self.field_histograms {
+            writer.write_u16_aligned(histogram.component.get())?;
+            writer.write_u16_aligned(histogram.field.get())?;
+            writer.write_varu32(histogram.values.len() as u32)?;
+            for (value, count) in &histogram.values {
+                writer.write_u64_aligned(*value)?;
+                writer.write_varu32(*count)?;
+            }
+        }
+
+        writer.write_varu32(self.create_templates.len() as u32)?;
+        for template in &self.create_templates {
+            writer.write_varu32(template.count)?;
+            writer.write_varu32(template.components.len() as u32)?;
+            for component in &template.components {
+                writer.write_u16_aligned(component.get())?;
+            }
+        }
+
+        writer.write_varu32(self.dictionary.len() as u32)?;
+        for byte in &self.dictionary {
+            writer.write_u8_aligned(*byte)?;
+        }
+        Ok(())
+    }
+
+    fn read(bytes: &[u8]) -> CodecResult<Self> {
+        let mut reader = BitReader::new(bytes);
+        for expected in MODEL_MAGIC {
+            if reader.read_u8_aligned()? != expected {
+                return Err(CodecError::InvalidModel);
+            }
+        }
+        if reader.read_u8_aligned()? != MODEL_VERSION {
+            return Err(CodecError::InvalidModel);
+        }
+        let schema_hash = reader.read_u64_aligned()?;
+
+        // Every entry takes at least one byte, so counts are bounded by the
+        // remaining input before anything is allocated.
+        let histogram_count = read_count(&mut reader)?;
+        let mut field_histograms = Vec::with_capacity(histogram_count);
+        for _ in 0..histogram_count {
+            let component =
+                ComponentId::new(reader.read_u16_aligned()?).ok_or(CodecError::InvalidModel)?;
+            let field = FieldId::new(reader.read_u16_aligned()?).ok_or(CodecError::InvalidModel)?;
+            let value_count = read_count(&mut reader)?;
+            let mut values = Vec::with_capacity(value_count);
+            for _ in 0..value_count {
+                values.push((reader.read_u64_aligned()?, reader.read_varu32()?));
+            }
+            field_histograms.push(FieldHistogram {
+                component,
+                field,
+                values,
+            });
+        }
+
+        let template_count = read_count(&mut reader)?;
+        let mut create_templates = Vec::with_capacity(template_count);
+        for _ in 0..template_count {
+            let count = reader.read_varu32()?;
+            let component_count = read_count(&mut reader)?;
+            let mut components = Vec::with_capacity(component_count);
+            for _ in 0..component_count {
+                components.push(
+                    ComponentId::new(reader.read_u16_aligned()?).ok_or(CodecError::InvalidModel)?,
+                );
+            }
+            create_templates.push(CreateTemplate { components, count });
+        }
+
+        let dictionary_len = read_count(&mut reader)?;
+        let mut dictionary = Vec::with_capacity(dictionary_len);
+        for _ in 0..dictionary_len {
+            dictionary.push(reader.read_u8_aligned()?);
+        }
+        if reader.bits_remaining() != 0 {
+            return Err(CodecError::InvalidModel);
+        }
+
+        Ok(Self {
+            schema_hash,
+            field_histograms,
+            create_templates,
+            dictionary,
+        })
+    }
+}
+
+fn read_count(reader: &mut BitReader<'_>) -> CodecResult<usize> {
+    let count = reader.read_varu32()? as usize;
+    if count > reader.bits_remaining() / 8 {
+        return Err(CodecError::InvalidModel);
+    }
+    Ok(count)
+}
+
+/// Returns the raw quantized bits of a field value, as stored in histograms.
+///
+/// Signed values use their two's complement bit pattern.
+#[must_use]
+pub fn field_value_bits(value: &FieldValue) -> u64 {
+    match *value {
+        FieldValue::Bool(value) => u64::from(value),
+        FieldValue::UInt(value) | FieldValue::VarUInt(value) => value,
+        FieldValue::SInt(value) | FieldValue::VarSInt(value) | FieldValue::FixedPoint(value) => {
+            value as u64
+        }
+    }
+}
+
+/// Rebuilds a field value of `codec` from its raw quantized bits, the
+/// inverse of [`field_value_bits`]. The value is not range-checked.
+pub(crate) fn field_value_from_bits(codec: FieldCodec, bits: u64) -> FieldValue {
+    match codec {
+        FieldCodec::Bool => FieldValue::Bool(bits != 0),
+        FieldCodec::UInt { .. } => FieldValue::UInt(bits),
+        FieldCodec::VarUInt => FieldValue::VarUInt(bits),
+        FieldCodec::SInt { .. } => FieldValue::SInt(bits as i64),
+        FieldCodec::VarSInt => FieldValue::VarSInt(bits as i64),
+        FieldCodec::FixedPoint(_) => FieldValue::FixedPoint(bits as i64),
+    }
+}
+
+/// Size caps for a trained model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelTrainingConfig {
+    /// Maximum values kept per field histogram.
+    pub max_values_per_field: usize,
+    /// Maximum create templates kept.
+    pub max_create_templates: usize,
+    /// Maximum dictionary size in bytes.
+    pub dictionary_bytes: usize,
+}
+
+impl Default for ModelTrainingConfig {
+    fn default() -> Self {
+        Self {
+            max_values_per_field: 16,
+            max_create_templates: 8,
+            dictionary_bytes: 4096,
+        }
+    }
+}
+
+/// Accumulates observations from decoded packets into a [`CodecModel`].
+#[derive(Debug)]
+pub struct ModelTrainer<'a> {
+    schema: &'a schema::Schema,
+    values: HashMap<(ComponentId, FieldId), HashMap<u64, u32>>,
+    templates: HashMap<Vec<ComponentId>, u32>,
+    grams: HashMap<[u8; DICTIONARY_GRAM], u32>,
+}
+
+impl<'a> ModelTrainer<'a> {
+    /// Creates a trainer for packets encoded with `schema`.
+    #[must_use]
+    pub fn new(schema: &'a schema::Schema) -> Self {
+        Self {
+            schema,
+            values: HashMap::new(),
+            templates: HashMap::new(),
+            grams: HashMap::new(),
+        }
+    }
+
+    /// Records an entity that was created (from a full snapshot or delta).
+    pub fn observe_create(&mut self, entity: &EntitySnapshot) {
+        let components = entity.components.iter().map(|c| c.id).collect();
+        *self.templates.entry(components).or_default() += 1;
+        for component in &entity.components {
+            let Some(def) = self.schema.components.iter().find(|c| c.id == component.id) else {
+                continue;
+            };
+            for (field, value) in def.fields.iter().zip(&component.fields) {
+                self.observe_value(component.id, field, value);
+            }
+        }
+    }
+
+    /// Records the field values carried by delta updates.
+    ///
+    /// Residuals are skipped; they are not values of the field itself.
+    pub fn observe_updates(&mut self, updates: &[DeltaUpdateEntity<UpdateValue>]) {
+        for entity in updates {
+            for component in &entity.components {
+                let Some(def) = self.schema.components.iter().find(|c| c.id == component.id) else {
+                    continue;
+                };
+                for (index, value) in &component.fields {
+                    let UpdateValue::Value(value) = value else {
+                        continue;
+                    };
+                    if let Some(field) = def.fields.get(*index) {
+                        self.observe_value(component.id, field, value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Records a packet payload (sections only, no header) for the dictionary.
+    pub fn observe_payload(&mut self, payload: &[u8]) {
+        for gram in payload.windows(DICTIONARY_GRAM) {
+            let gram: [u8; DICTIONARY_GRAM] = gram.try_into().unwrap();
+            *self.grams.entry(gram).or_default() += 1;
+        }
+    }
+
+    /// Builds the model. Output is deterministic for a given set of observations.
+    #[must_use]
+    pub fn finish(&self, config: &ModelTrainingConfig) -> CodecModel {
+        let mut field_histograms: Vec<FieldHistogram> = self
+            .values
+            .iter()
+            .map(|(&(component, field), counts)| FieldHistogram {
+                component,
+                field,
+                values: top_counts(counts, config.max_values_per_field),
+            })
+            .collect();
+        field_histograms.sort_by_key(|h| (h.component, h.field));
+
+        let create_templates = top_counts(&self.templates, config.max_create_templates)
+            .into_iter()
+            .map(|(components, count)| CreateTemplate { components, count })
+            .collect();
+
+        CodecModel {
+            schema_hash: schema::schema_hash(self.schema),
+            field_histograms,
+            create_templates,
+            dictionary: self.build_dictionary(config.dictionary_bytes),
+        }
+    }
+
+    /// Bool fields are not tracked: a histogram hit can never beat one bit.
+    fn observe_value(&mut self, component: ComponentId, field: &FieldDef, value: &FieldValue) {
+        if field.codec == FieldCodec::Bool {
+            return;
+        }
+        *self
+            .values
+            .entry((component, field.id))
+            .or_default()
+            .entry(field_value_bits(value))
+            .or_default() += 1;
+    }
+
+    /// Greedily concatenates the most frequent repeated byte grams, merging
+    /// grams that overlap the dictionary tail and skipping ones it already
+    /// contains.
+    fn build_dictionary(&self, max_bytes: usize) -> Vec<u8> {
+        let mut dictionary: Vec<u8> = Vec::new();
+        let mut contained: HashSet<[u8; DICTIONARY_GRAM]> = HashSet::new();
+        for (gram, count) in top_counts(&self.grams, usize::MAX) {
+            if count < 2 {
+                break;
+            }
+            if contained.contains(&gram) {
+                continue;
+            }
+            let overlap = (1..DICTIONARY_GRAM)
+                .rev()
+                .find(|&n| dictionary.ends_with(&gram[..n]))
+                .unwrap_or(0);
+            if dictionary.len() + DICTIONARY_GRAM - overlap > max_bytes {
+                break;
+            }
+            let start = dictionary.len().saturating_sub(DICTIONARY_GRAM - 1);
+            dictionary.extend_from_slice(&gram[overlap..]);
+            for window in dictionary[start..].windows(DICTIONARY_GRAM) {
+                contained.insert(window.try_into().unwrap());
+            }
+        }
+        dictionary
+    }
+}
+
+/// Returns up to `limit` entries by descending count, ties broken by key.
+fn top_counts<K: Clone + Ord>(counts: &HashMap<K, u32>, limit: usize) -> Vec<(K, u32)> {
+    let mut entries: Vec<(K, u32)> = counts.iter().map(|(k, v)| (k.clone(), *v)).collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    entries.truncate(limit);
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::delta::DeltaUpdateComponent;
+    use crate::snapshot::ComponentSnapshot;
+    use crate::types::EntityId;
+    use schema::{ComponentDef, FieldCodec, FieldDef, Schema};
+
+    fn schema() -> Schema {
+        let component = ComponentDef::new(ComponentId::new(1).unwrap())
+            .field(FieldDef::new(FieldId::new(1).unwrap(), FieldCodec::bool()))
+            .field(FieldDef::new(FieldId::new(2).unwrap(), FieldCodec::sint(8)));
+        Schema::new(vec![component]).unwrap()
+    }
+
+    fn entity(id: u32, flag: bool, value: i64) -> EntitySnapshot {
+        EntitySnapshot {
+            id: EntityId::from(id),
+            components: vec![ComponentSnapshot {
+                id: ComponentId::new(1).unwrap(),
+                fields: vec![FieldValue::Bool(flag), FieldValue::SInt(value)],
+            }],
+        }
+    }
+
+    fn trained() -> CodecModel {
+        let schema = schema();
+        let mut trainer = ModelTrainer::new(&schema);
+        for id in 1..=5 {
+            trainer.observe_create(&entity(id, id % 2 == 0, -1));
+        }
+        trainer.observe_updates(&[DeltaUpdateEntity::new(
+            EntityId::new(1),
+            vec![DeltaUpdateComponent {
+                id: ComponentId::new(1).unwrap(),
+                fields: vec![
+                    (1, UpdateValue::Value(FieldValue::SInt(-1))),
+                    (1, UpdateValue::Residual(3)),
+                ],
+            }],
+        )]);
+        trainer.observe_payload(&b"abcdefgh-abcdefgh-abcdefgh-12345678".repeat(2));
+        trainer.finish(&ModelTrainingConfig::default())
+    }
+
+    #[test]
+    fn trainer_builds_histograms_and_templates() {
+        let model = trained();
+        assert_eq!(model.schema_hash, schema::schema_hash(&schema()));
+
+        assert!(model
+            .histogram(ComponentId::new(1).unwrap(), FieldId::new(1).unwrap())
+            .is_none());
+        let values = model
+            .histogram(ComponentId::new(1).unwrap(), FieldId::new(2).unwrap())
+            .unwrap();
+        assert_eq!(values.values, vec![(u64::MAX, 6)]);
+
+        assert_eq!(
+            model.create_templates,
+            vec![CreateTemplate {
+                components: vec![ComponentId::new(1).unwrap()],
+                count: 5,
+            }]
+        );
+        assert!(!model.dictionary.is_empty());
+        assert!(model.dictionary.len() <= ModelTrainingConfig::default().dictionary_bytes);
+    }
+
+    #[test]
+    fn model_bytes_roundtrip_and_hash_is_stable() {
+        let model = trained();
+        let bytes = model.to_bytes();
+        let decoded = CodecModel::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, model);
+        assert_eq!(decoded.hash(), model.hash());
+        assert_eq!(trained().hash(), model.hash());
+
+        let mut other = model.clone();
+        other.dictionary.push(0);
+        assert_ne!(other.hash(), model.hash());
+    }
+
+    #[test]
+    fn invalid_model_bytes_are_rejected() {
+        let bytes = trained().to_bytes();
+        assert_eq!(
+            CodecModel::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(CodecError::InvalidModel)
+        );
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert_eq!(
+            CodecModel::from_bytes(&bad_magic),
+            Err(CodecError::InvalidModel)
+        );
+        // A huge histogram count must not allocate before failing.
+        let mut huge = bytes[..13].to_vec();
+        huge.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF, 0x0F]);
+        assert_eq!(CodecModel::from_bytes(&huge), Err(CodecError::InvalidModel));
+    }
+
+    #[test]
+    fn dictionary_compresses_similar_payloads() {
+        let model = trained();
+        let compressor = model.compressor();
+        let payload = b"abcdefgh-abcdefgh-12345678".to_vec();
+        let mut primed = Vec::new();
+        wire::Compressor::compress(&compressor, &payload, &mut primed);
+        let mut plain = Vec::new();
+        wire::Compressor::compress(&wire::Lz4Compressor::new(), &payload, &mut plain);
+        assert!(primed.len() < plain.len());
+    }
+}
@@ -0,0 +1,798 @@
+//! Model coding of create and update section bodies.
+//!
+//! With the same [`CodecModel`] loaded on both ends, `ENTITY_CREATE` and
+//! `ENTITY_UPDATE` bodies are re-coded against it: a created entity whose
+//! component set matches a create template sends the template's index in
+//! place of its component mask, and a field value found in its field's
+//! histogram sends the value's index in place of the value. Like entropy
+//! coding, the modeled tag is swapped in only when the body gets strictly
+//! smaller. The receiver expands modeled sections back to their plain form
+//! before decoding, so this works on standard-header packets only.
+
+use bitstream::{BitReader, BitVecWriter, BitWriter};
+use schema::{ComponentId, FieldCodec, FieldDef};
+use wire::SectionTag;
+
+use crate::delta::{decode_update_section_masked, DeltaUpdateComponent, DeltaUpdateEntity};
+use crate::entropy::write_bytes;
+use crate::error::{CodecError, CodecResult, LimitKind, MaskKind};
+use crate::ids::{IdGapReader, IdGapWriter, IdRun};
+use crate::limits::CodecLimits;
+use crate::model::{field_value_bits, field_value_from_bits, CodecModel};
+use crate::predict::{read_update_value, write_update_value, UpdateValue};
+use crate::snapshot::{
+    decode_create_section, encode_create_body, read_field_value, read_mask, required_bits,
+    write_field_value, write_section, ComponentSnapshot, EntitySnapshot, FieldValue,
+    VARINT_MAX_BYTES,
+};
+
+/// Model-codes the create and masked update sections of an encoded packet in
+/// place.
+///
+/// `out[..packet_len]` must hold a packet with a standard header. Each such
+/// section is replaced by its modeled form when that is smaller; other
+/// sections are copied unchanged and the header's `payload_len` is patched.
+/// Returns the new packet length, which is never larger than `packet_len`. A
+/// payload carrying optional sections this build does not know is left as
+/// is, since re-encoding would drop them.
+pub fn model_code_sections(
+    schema: &schema::Schema,
+    model: &CodecModel,
+    out: &mut [u8],
+    packet_len: usize,
+    limits: &CodecLimits,
+) -> CodecResult<usize> {
+    if packet_len < wire::HEADER_SIZE || packet_len > out.len() {
+        return Err(CodecError::OutputTooSmall {
+            needed: wire::HEADER_SIZE.max(packet_len),
+            available: out.len(),
+        });
+    }
+    let payload = &out[wire::HEADER_SIZE..packet_len];
+    let (sections, skipped) =
+        wire::decode_sections_with_skipped(payload, &wire::Limits::unlimited())?;
+    if !skipped.is_empty() {
+        return Ok(packet_len);
+    }
+    let tables = ModelTables::new(schema, model)?;
+
+    let mut writer = BitVecWriter::new();
+    for section in &sections {
+        let modeled = match section.tag {
+            SectionTag::EntityCreate => {
+                let entities = decode_create_section(schema, section.body, limits)?;
+                shrink(section.body.len(), |writer| {
+                    tables.write_create_body(schema, &entities, writer)
+                })
+                .map(|body| (SectionTag::EntityCreateModeled, body))
+            }
+            SectionTag::EntityUpdate => {
+                let updates = decode_update_section_masked(schema, section.body, limits)?;
+                shrink(section.body.len(), |writer| {
+                    write_update_body(schema, &updates, Some(&tables), writer)
+                })
+                .map(|body| (SectionTag::EntityUpdateModeled, body))
+            }
+            _ => None,
+        };
+        match modeled {
+            Some((tag, body)) => write_raw_section(&mut writer, tag as u8, &body)?,
+            None => write_raw_section(&mut writer, section.tag as u8, section.body)?,
+        }
+    }
+
+    let coded_payload = writer.finish();
+    let payload_len = coded_payload.len();
+    out[wire::HEADER_SIZE..wire::HEADER_SIZE + payload_len].copy_from_slice(&coded_payload);
+    out[wire::HEADER_SIZE - 4..wire::HEADER_SIZE]
+        .copy_from_slice(&(payload_len as u32).to_le_bytes());
+    Ok(wire::HEADER_SIZE + payload_len)
+}
+
+/// Rewrites the modeled sections of a standard-header packet to their plain
+/// form, ready for the usual decoders.
+///
+/// Returns `None` if the packet has no modeled sections. Expanded sections
+/// are bounded by `limits.max_section_bytes`. Optional sections this build
+/// does not know are moved after the known ones.
+pub fn expand_model_sections(
+    schema: &schema::Schema,
+    model: &CodecModel,
+    packet: &[u8],
+    limits: &CodecLimits,
+) -> CodecResult<Option<Vec<u8>>> {
+    if packet.len() < wire::HEADER_SIZE {
+        return Ok(None);
+    }
+    let (sections, skipped) = wire::decode_sections_with_skipped(
+        &packet[wire::HEADER_SIZE..],
+        &wire::Limits::unlimited(),
+    )?;
+    let is_modeled = |tag| {
+        matches!(
+            tag,
+            SectionTag::EntityCreateModeled | SectionTag::EntityUpdateModeled
+        )
+    };
+    if !sections.iter().any(|section| is_modeled(section.tag)) {
+        return Ok(None);
+    }
+    let tables = ModelTables::new(schema, model)?;
+
+    let mut writer = BitVecWriter::new();
+    let mut section = vec![0u8; 1 + VARINT_MAX_BYTES + limits.max_section_bytes];
+    for wire_section in &sections {
+        let len = match wire_section.tag {
+            SectionTag::EntityCreateModeled => {
+                let entities = tables.read_create_body(schema, wire_section.body, limits)?;
+                write_section(SectionTag::EntityCreate, &mut section, limits, |writer| {
+                    encode_create_body(schema, &entities, limits, writer)
+                })?
+            }
+            SectionTag::EntityUpdateModeled => {
+                let updates = tables.read_update_body(schema, wire_section.body, limits)?;
+                write_section(SectionTag::EntityUpdate, &mut section, limits, |writer| {
+                    write_update_body(schema, &updates, None, writer)
+                })?
+            }
+            tag => {
+                write_raw_section(&mut writer, tag as u8, wire_section.body)?;
+                continue;
+            }
+        };
+        write_bytes(&mut writer, &section[..len])?;
+    }
+    for skipped in &skipped {
+        write_raw_section(&mut writer, skipped.tag, skipped.body)?;
+    }
+
+    let mut out = packet[..wire::HEADER_SIZE].to_vec();
+    out.extend_from_slice(&writer.finish());
+    let payload_len = (out.len() - wire::HEADER_SIZE) as u32;
+    out[wire::HEADER_SIZE - 4..wire::HEADER_SIZE].copy_from_slice(&payload_len.to_le_bytes());
+    Ok(Some(out))
+}
+
+fn write_raw_section(writer: &mut BitVecWriter, tag: u8, body: &[u8]) -> CodecResult<()> {
+    writer.write_u8_aligned(tag)?;
+    writer.write_varu32(body.len() as u32)?;
+    write_bytes(writer, body)
+}
+
+/// Writes a body with `write` into a buffer one byte shorter than
+/// `plain_len`, returning it only if it fits, i.e. is strictly smaller.
+fn shrink<F>(plain_len: usize, write: F) -> Option<Vec<u8>>
+where
+    F: FnOnce(&mut BitWriter<'_>) -> CodecResult<()>,
+{
+    let mut body = vec![0u8; plain_len.checked_sub(1)?];
+    let mut writer = BitWriter::new(&mut body);
+    write(&mut writer).ok()?;
+    let len = writer.finish();
+    body.truncate(len);
+    Some(body)
+}
+
+/// A model's histograms and templates laid out in schema order.
+#[derive(Debug)]
+struct ModelTables {
+    /// `fields[c][f]`: histogram values of field `f` of schema component `c`,
+    /// as raw bits. Empty when the field has no histogram or is a bool.
+    fields: Vec<Vec<Vec<u64>>>,
+    /// Component masks of the create templates.
+    templates: Vec<Vec<bool>>,
+}
+
+impl ModelTables {
+    fn new(schema: &schema::Schema, model: &CodecModel) -> CodecResult<Self> {
+        let expected = schema::schema_hash(schema);
+        if model.schema_hash != expected {
+            return Err(CodecError::SchemaMismatch {
+                expected,
+                found: model.schema_hash,
+            });
+        }
+        let fields = schema
+            .components
+            .iter()
+            .map(|component| {
+                component
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        if field.codec == FieldCodec::Bool {
+                            return Vec::new();
+                        }
+                        model
+                            .histogram(component.id, field.id)
+                            .map(|histogram| {
+                                histogram.values.iter().map(|(bits, _)| *bits).collect()
+                            })
+                            .unwrap_or_default()
+                    })
+                    .collect()
+            })
+            .collect();
+        let templates = model
+            .create_templates
+            .iter()
+            .map(|template| {
+                let mask: Vec<bool> = schema
+                    .components
+                    .iter()
+                    .map(|component| template.components.contains(&component.id))
+                    .collect();
+                // A template naming a component outside the schema could
+                // never be expanded into a valid create.
+                if mask.iter().filter(|present| **present).count() != template.components.len() {
+                    return Err(CodecError::InvalidModel);
+                }
+                Ok(mask)
+            })
+            .collect::<CodecResult<_>>()?;
+        Ok(Self { fields, templates })
+    }
+
+    fn template_bits(&self) -> u8 {
+        required_bits(self.templates.len() as u64)
+    }
+
+    /// Writes `value` as a histogram hit (`1` and its index) or a miss (`0`
+    /// and the plain value). Fields without a histogram write the plain value.
+    fn write_value(
+        &self,
+        component_idx: usize,
+        field_idx: usize,
+        component_id: ComponentId,
+        field: FieldDef,
+        value: FieldValue,
+        writer: &mut BitWriter<'_>,
+    ) -> CodecResult<()> {
+        let values = &self.fields[component_idx][field_idx];
+        if values.is_empty() {
+            return write_field_value(component_id, field, value, writer);
+        }
+        let bits = field_value_bits(&value);
+        match values.iter().position(|candidate| *candidate == bits) {
+            Some(index) => {
+                writer.write_bit(true)?;
+                let index_bits = required_bits(values.len() as u64 - 1);
+                if index_bits > 0 {
+                    writer.write_bits(index as u64, index_bits)?;
+                }
+                Ok(())
+            }
+            None => {
+                writer.write_bit(false)?;
+                write_field_value(component_id, field, value, writer)
+            }
+        }
+    }
+
+    fn read_value(
+        &self,
+        component_idx: usize,
+        field_idx: usize,
+        component_id: ComponentId,
+        field: FieldDef,
+        reader: &mut BitReader<'_>,
+    ) -> CodecResult<FieldValue> {
+        let values = &self.fields[component_idx][field_idx];
+        if values.is_empty() || !reader.read_bit()? {
+            return read_field_value(component_id, field, reader);
+        }
+        let index_bits = required_bits(values.len() as u64 - 1);
+        let index = if index_bits > 0 {
+            reader.read_bits(index_bits)?
+        } else {
+            0
+        };
+        let bits = values
+            .get(index as usize)
+            .ok_or(CodecError::ModelIndexOutOfRange {
+                index,
+                count: values.len(),
+            })?;
+        Ok(field_value_from_bits(field.codec, *bits))
+    }
+
+    fn write_create_body(
+        &self,
+        schema: &schema::Schema,
+        entities: &[EntitySnapshot],
+        writer: &mut BitWriter<'_>,
+    ) -> CodecResult<()> {
+        writer.align_to_byte()?;
+        writer.write_varu32(entities.len() as u32)?;
+        let run = IdRun::from_ids(entities.iter().map(|entity| entity.id));
+        let mut ids = IdGapWriter::begin(&run, schema.entity_id_width, writer)?;
+        let template_bits = self.template_bits();
+        for entity in entities {
+            writer.align_to_byte()?;
+            ids.write(entity.id, writer)?;
+
+            let mask: Vec<bool> = schema
+                .components
+                .iter()
+                .map(|component| entity.component(component.id).is_some())
+                .collect();
+            let template = self.templates.iter().position(|template| *template == mask);
+            if template_bits > 0 {
+                writer.write_bits(template.map_or(0, |index| index as u64 + 1), template_bits)?;
+            }
+            if template.is_none() {
+                for present in &mask {
+                    writer.write_bit(*present)?;
+                }
+            }
+
+            for (component_idx, component) in schema.components.iter().enumerate() {
+                let Some(snapshot) = entity.component(component.id) else {
+                    continue;
+                };
+                for (field_idx, (field, value)) in
+                    component.fields.iter().zip(&snapshot.fields).enumerate()
+                {
+                    self.write_value(
+                        component_idx,
+                        field_idx,
+                        component.id,
+                        *field,
+                        *value,
+                        writer,
+                    )?;
+                }
+            }
+        }
+        writer.align_to_byte()?;
+        Ok(())
+    }
+
+    fn read_create_body(
+        &self,
+        schema: &schema::Schema,
+        body: &[u8],
+        limits: &CodecLimits,
+    ) -> CodecResult<Vec<EntitySnapshot>> {
+        let mut reader = BitReader::new(body);
+        let count = read_section_count(
+            &mut reader,
+            body,
+            LimitKind::EntitiesCreate,
+            limits.max_entities_create,
+            limits,
+        )?;
+        let template_bits = self.template_bits();
+        let mut entities = Vec::with_capacity(count);
+        let mut ids = IdGapReader::begin(&mut reader, count, schema.entity_id_width)?;
+        for _ in 0..count {
+            reader.align_to_byte()?;
+            let id = ids.read(&mut reader)?;
+
+            let template = if template_bits > 0 {
+                reader.read_bits(template_bits)?
+            } else {
+                0
+            };
+            let mask = match template {
+                0 => read_mask(
+                    &mut reader,
+                    schema.components.len(),
+                    MaskKind::ComponentMask,
+                )?,
+                index => self.templates.get(index as usize - 1).cloned().ok_or(
+                    CodecError::ModelIndexOutOfRange {
+                        index: index - 1,
+                        count: self.templates.len(),
+                    },
+                )?,
+            };
+
+            let mut components = Vec::new();
+            for (component_idx, component) in schema.components.iter().enumerate() {
+                if !mask[component_idx] {
+                    continue;
+                }
+                ensure_field_limit(component.fields.len(), limits)?;
+                let fields = component
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .map(|(field_idx, field)| {
+                        self.read_value(component_idx, field_idx, component.id, *field, &mut reader)
+                    })
+                    .collect::<CodecResult<_>>()?;
+                components.push(ComponentSnapshot {
+                    id: component.id,
+                    fields,
+                });
+            }
+            entities.push(EntitySnapshot { id, components });
+        }
+        finish_section(&mut reader, SectionTag::EntityCreateModeled)?;
+        Ok(entities)
+    }
+
+    fn read_update_body(
+        &self,
+        schema: &schema::Schema,
+        body: &[u8],
+        limits: &CodecLimits,
+    ) -> CodecResult<Vec<DeltaUpdateEntity<UpdateValue>>> {
+        let mut reader = BitReader::new(body);
+        let count = read_section_count(
+            &mut reader,
+            body,
+            LimitKind::EntitiesUpdate,
+            limits.max_entities_update,
+            limits,
+        )?;
+        let mut updates = Vec::with_capacity(count);
+        let mut ids = IdGapReader::begin(&mut reader, count, schema.entity_id_width)?;
+        for _ in 0..count {
+            reader.align_to_byte()?;
+            let id = ids.read(&mut reader)?;
+            let component_mask = read_mask(
+                &mut reader,
+                schema.components.len(),
+                MaskKind::ComponentMask,
+            )?;
+
+            let mut components = Vec::new();
+            for (component_idx, component) in schema.components.iter().enumerate() {
+                if !component_mask[component_idx] {
+                    continue;
+                }
+                ensure_field_limit(component.fields.len(), limits)?;
+                let field_mask = read_mask(
+                    &mut reader,
+                    component.fields.len(),
+                    MaskKind::FieldMask {
+                        component: component.id,
+                    },
+                )?;
+                let mut fields = Vec::new();
+                for (field_idx, field) in component.fields.iter().enumerate() {
+                    if !field_mask[field_idx] {
+                        continue;
+                    }
+                    let value = read_update_value(
+                        component.id,
+                        *field,
+                        &mut reader,
+                        |component_id, field, reader| {
+                            self.read_value(component_idx, field_idx, component_id, field, reader)
+                        },
+                    )?;
+                    fields.push((field_idx, value));
+                }
+                components.push(DeltaUpdateComponent {
+                    id: component.id,
+                    fields,
+                });
+            }
+            updates.push(DeltaUpdateEntity::new(id, components));
+        }
+        finish_section(&mut reader, SectionTag::EntityUpdateModeled)?;
+        Ok(updates)
+    }
+}
+
+/// Writes a masked update body, modeled with `tables` or plain (tag 3)
+/// without.
+fn write_update_body(
+    schema: &schema::Schema,
+    updates: &[DeltaUpdateEntity<UpdateValue>],
+    tables: Option<&ModelTables>,
+    writer: &mut BitWriter<'_>,
+) -> CodecResult<()> {
+    writer.align_to_byte()?;
+    writer.write_varu32(updates.len() as u32)?;
+    let run = IdRun::from_ids(updates.iter().map(|entity| entity.id));
+    let mut ids = IdGapWriter::begin_indices(&run, schema.entity_id_width, writer)?;
+    for entity in updates {
+        writer.align_to_byte()?;
+        ids.write(entity.id, writer)?;
+        for component in &schema.components {
+            writer.write_bit(entity.components.iter().any(|c| c.id == component.id))?;
+        }
+        for (component_idx, component) in schema.components.iter().enumerate() {
+            let Some(update) = entity.components.iter().find(|c| c.id == component.id) else {
+                continue;
+            };
+            for field_idx in 0..component.fields.len() {
+                writer.write_bit(update.fields.iter().any(|(idx, _)| *idx == field_idx))?;
+            }
+            for (field_idx, value) in &update.fields {
+                let field_idx = *field_idx;
+                write_update_value(
+                    component.id,
+                    component.fields[field_idx],
+                    *value,
+                    writer,
+                    |component_id, field, value, writer| match tables {
+                        Some(tables) => tables.write_value(
+                            component_idx,
+                            field_idx,
+                            component_id,
+                            field,
+                            value,
+                            writer,
+                        ),
+                        None => write_field_value(component_id, field, value, writer),
+                    },
+                )?;
+            }
+        }
+    }
+    writer.align_to_byte()?;
+    Ok(())
+}
+
+/// Checks the body size and reads the entity count of a modeled section.
+fn read_section_count(
+    reader: &mut BitReader<'_>,
+    body: &[u8],
+    kind: LimitKind,
+    limit: usize,
+    limits: &CodecLimits,
+) -> CodecResult<usize> {
+    if body.len() > limits.max_section_bytes {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::SectionBytes,
+            limit: limits.max_section_bytes,
+            actual: body.len(),
+        });
+    }
+    reader.align_to_byte()?;
+    let count = reader.read_varu32()? as usize;
+    if count > limit {
+        return Err(CodecError::LimitsExceeded {
+            kind,
+            limit,
+            actual: count,
+        });
+    }
+    Ok(count)
+}
+
+fn ensure_field_limit(fields: usize, limits: &CodecLimits) -> CodecResult<()> {
+    if fields > limits.max_fields_per_component {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::FieldsPerComponent,
+            limit: limits.max_fields_per_component,
+            actual: fields,
+        });
+    }
+    Ok(())
+}
+
+fn finish_section(reader: &mut BitReader<'_>, section: SectionTag) -> CodecResult<()> {
+    reader.align_to_byte()?;
+    let remaining_bits = reader.bits_remaining();
+    if remaining_bits != 0 {
+        return Err(CodecError::TrailingSectionData {
+            section,
+            remaining_bits,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::delta::{apply_delta_snapshot, encode_delta_snapshot};
+    use crate::model::{ModelTrainer, ModelTrainingConfig};
+    use crate::snapshot::{decode_full_snapshot, encode_full_snapshot, Snapshot};
+    use crate::types::{EntityId, SnapshotTick};
+    use schema::{ComponentDef, FieldCodec, FieldId, Schema};
+
+    fn schema() -> Schema {
+        let unit = ComponentDef::new(ComponentId::new(1).unwrap())
+            .field(FieldDef::new(FieldId::new(1).unwrap(), FieldCodec::uint(8)))
+            .field(FieldDef::new(
+                FieldId::new(2).unwrap(),
+                FieldCodec::uint(10),
+            ));
+        let marker = ComponentDef::new(ComponentId::new(2).unwrap())
+            .field(FieldDef::new(FieldId::new(1).unwrap(), FieldCodec::bool()));
+        Schema::new(vec![unit, marker]).unwrap()
+    }
+
+    /// 32 units of three kinds at full health; even ids carry a marker.
+    fn snapshot(tick: u32, kind: impl Fn(u32) -> u64, health: impl Fn(u32) -> u64) -> Snapshot {
+        Snapshot {
+            tick: SnapshotTick::new(tick),
+            entities: (1..=32)
+                .map(|id| {
+                    let mut components = vec![ComponentSnapshot {
+                        id: ComponentId::new(1).unwrap(),
+                        fields: vec![FieldValue::UInt(kind(id)), FieldValue::UInt(health(id))],
+                    }];
+                    if id % 2 == 0 {
+                        components.push(ComponentSnapshot {
+                            id: ComponentId::new(2).unwrap(),
+                            fields: vec![FieldValue::Bool(true)],
+                        });
+                    }
+                    EntitySnapshot {
+                        id: EntityId::from(id),
+                        components,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    fn trained(schema: &Schema) -> CodecModel {
+        let mut trainer = ModelTrainer::new(schema);
+        for entity in &snapshot(1, |id| u64::from(id % 3), |_| 1000).entities {
+            trainer.observe_create(entity);
+        }
+        trainer.finish(&ModelTrainingConfig::default())
+    }
+
+    fn tags(packet: &[u8]) -> Vec<SectionTag> {
+        wire::decode_packet(packet, &wire::Limits::for_testing())
+            .unwrap()
+            .sections
+            .iter()
+            .map(|section| section.tag)
+            .collect()
+    }
+
+    #[test]
+    fn modeled_full_snapshot_roundtrip_is_smaller() {
+        let schema = schema();
+        let model = trained(&schema);
+        let limits = CodecLimits::for_testing();
+        // Entity 5 has a health value outside the histogram.
+        let snapshot = snapshot(
+            7,
+            |id| u64::from(id % 3),
+            |id| if id == 5 { 7 } else { 1000 },
+        );
+
+        let mut buf = [0u8; 2048];
+        let plain_len = encode_full_snapshot(
+            &schema,
+            snapshot.tick,
+            &snapshot.entities,
+            &limits,
+            &mut buf,
+        )
+        .unwrap();
+        let coded_len = model_code_sections(&schema, &model, &mut buf, plain_len, &limits).unwrap();
+        assert!(coded_len < plain_len, "{coded_len} >= {plain_len}");
+        let coded = &buf[..coded_len];
+        assert_eq!(tags(coded), [SectionTag::EntityCreateModeled]);
+
+        // Decoders without the model refuse the modeled section.
+        assert!(matches!(
+            decode_full_snapshot(&schema, coded, &wire::Limits::for_testing(), &limits),
+            Err(CodecError::UnexpectedSection {
+                section: SectionTag::EntityCreateModeled
+            })
+        ));
+        let expanded = expand_model_sections(&schema, &model, coded, &limits)
+            .unwrap()
+            .unwrap();
+        assert_eq!(expanded.len(), plain_len);
+        let decoded =
+            decode_full_snapshot(&schema, &expanded, &wire::Limits::for_testing(), &limits)
+                .unwrap();
+        assert_eq!(decoded, snapshot);
+    }
+
+    #[test]
+    fn modeled_delta_updates_roundtrip() {
+        let schema = schema();
+        let model = trained(&schema);
+        let limits = CodecLimits::for_testing();
+        let baseline = snapshot(7, |id| u64::from(id % 3), |_| 1000);
+        let current = snapshot(8, |id| u64::from((id + 1) % 3), |_| 1000);
+
+        let mut buf = [0u8; 2048];
+        let plain_len = encode_delta_snapshot(
+            &schema,
+            current.tick,
+            baseline.tick,
+            &baseline,
+            &current,
+            &limits,
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(tags(&buf[..plain_len]), [SectionTag::EntityUpdate]);
+        let coded_len = model_code_sections(&schema, &model, &mut buf, plain_len, &limits).unwrap();
+        assert!(coded_len < plain_len, "{coded_len} >= {plain_len}");
+        assert_eq!(tags(&buf[..coded_len]), [SectionTag::EntityUpdateModeled]);
+
+        let expanded = expand_model_sections(&schema, &model, &buf[..coded_len], &limits)
+            .unwrap()
+            .unwrap();
+        let applied = apply_delta_snapshot(
+            &schema,
+            &baseline,
+            &expanded,
+            &wire::Limits::for_testing(),
+            &limits,
+        )
+        .unwrap();
+        assert_eq!(applied.entities, current.entities);
+    }
+
+    #[test]
+    fn plain_packets_are_not_expanded() {
+        let schema = schema();
+        let model = trained(&schema);
+        let limits = CodecLimits::for_testing();
+        let snapshot = snapshot(7, |_| 0, |_| 1000);
+        let mut buf = [0u8; 2048];
+        let len = encode_full_snapshot(
+            &schema,
+            snapshot.tick,
+            &snapshot.entities,
+            &limits,
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(
+            expand_model_sections(&schema, &model, &buf[..len], &limits).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn out_of_range_template_is_rejected() {
+        let schema = schema();
+        let model = trained(&schema);
+        assert_eq!(model.create_templates.len(), 2);
+        let limits = CodecLimits::for_testing();
+
+        // One entity naming template index 2 (coded as 3) of two.
+        let mut body = BitVecWriter::new();
+        body.write_varu32(1).unwrap();
+        body.write_varu64(1).unwrap();
+        body.write_u8_aligned(0).unwrap();
+        body.write_bits(3, 2).unwrap();
+        let body = body.finish();
+        let mut packet = vec![0u8; 64];
+        let mut len = wire::encode_header(
+            &wire::PacketHeader::full_snapshot(schema::schema_hash(&schema), 1, 0),
+            &mut packet,
+        )
+        .unwrap();
+        len += wire::encode_section(SectionTag::EntityCreateModeled, &body, &mut packet[len..])
+            .unwrap();
+        packet.truncate(len);
+
+        assert_eq!(
+            expand_model_sections(&schema, &model, &packet, &limits).unwrap_err(),
+            CodecError::ModelIndexOutOfRange { index: 2, count: 2 }
+        );
+    }
+
+    #[test]
+    fn model_for_another_schema_is_rejected() {
+        let schema = schema();
+        let mut model = trained(&schema);
+        model.schema_hash ^= 1;
+        let limits = CodecLimits::for_testing();
+        let snapshot = snapshot(7, |_| 0, |_| 1000);
+        let mut buf = [0u8; 2048];
+        let len = encode_full_snapshot(
+            &schema,
+            snapshot.tick,
+            &snapshot.entities,
+            &limits,
+            &mut buf,
+        )
+        .unwrap();
+        assert!(matches!(
+            model_code_sections(&schema, &model, &mut buf, len, &limits),
+            Err(CodecError::SchemaMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,555 @@
+//! Delta encoding against per-entity baselines.
+//!
+//! A regular delta diffs every entity against the packet `baseline_tick`, so a
+//! single lost ACK pushes the whole packet back to an old baseline. Here the
+//! packet baseline only provides the entity set; entities the client has
+//! acknowledged more recently are rebased onto their own baseline through an
+//! `ENTITY_BASELINE_REF` section and diffed from there.
+
+use bitstream::{BitReader, BitWriter};
+use schema::{schema_hash, EntityIdWidth};
+use wire::{decode_packet, encode_header, SectionTag, WirePacket};
+
+use crate::baseline::BaselineStore;
+use crate::delta::{
+    apply_creates, apply_destroys, apply_updates, build_component_lookup, decode_delta_sections,
+    diff_entity, encode_create_body_from_list, encode_destroy_body_from_list,
+    ensure_entities_sorted, validate_delta_header, write_update_sections_from_updates,
+};
+use crate::error::{CodecError, CodecResult, LimitKind};
+use crate::limits::CodecLimits;
+use crate::snapshot::{write_section, EntitySnapshot, Snapshot};
+use crate::types::{EntityId, SnapshotTick};
+
+/// Encodes a delta where each entity may use its own acknowledged baseline.
+///
+/// `baseline_tick` must be present in `baselines` and decides which entities
+/// are created or destroyed. For every other entity, `entity_baseline`
+/// returns the newest tick the client acknowledged for it; when that
+/// baseline is stored, older than `tick` and differs from the packet
+/// baseline, the entity is rebased onto it before diffing. Returning `None`
+/// falls back to the packet baseline.
+#[allow(clippy::too_many_arguments)]
+pub fn encode_delta_snapshot_multi_baseline<F>(
+    schema: &schema::Schema,
+    tick: SnapshotTick,
+    baseline_tick: SnapshotTick,
+    baselines: &BaselineStore<Snapshot>,
+    current: &Snapshot,
+    mut entity_baseline: F,
+    limits: &CodecLimits,
+    out: &mut [u8],
+) -> CodecResult<usize>
+where
+    F: FnMut(EntityId) -> Option<SnapshotTick>,
+{
+    if out.len() < wire::HEADER_SIZE {
+        return Err(CodecError::OutputTooSmall {
+            needed: wire::HEADER_SIZE,
+            available: out.len(),
+        });
+    }
+    let baseline = baselines
+        .get(baseline_tick)
+        .ok_or(CodecError::BaselineNotFound {
+            requested_tick: baseline_tick.raw(),
+        })?;
+    ensure_entities_sorted(&baseline.entities)?;
+    ensure_entities_sorted(&current.entities)?;
+
+    let mut destroys = Vec::new();
+    let mut creates = Vec::new();
+    let mut rebases = Vec::new();
+    let mut updates = Vec::new();
+    let mut i = 0usize;
+    let mut j = 0usize;
+    while i < baseline.entities.len() || j < current.entities.len() {
+        let base = baseline.entities.get(i);
+        let curr = current.entities.get(j);
+        match (base, curr) {
+            (Some(b), Some(c)) if b.id == c.id => {
+                let dt = tick.raw().wrapping_sub(baseline_tick.raw());
+                if let Some(base_update) = diff_entity(schema, b, c, dt, limits)? {
+                    let reference = entity_baseline(c.id)
+                        .filter(|ref_tick| *ref_tick != baseline_tick && ref_tick.is_before(tick))
+                        .and_then(|ref_tick| {
+                            baselines
+                                .get(ref_tick)?
+                                .get(c.id)
+                                .filter(|entity| *entity != b)
+                                .map(|entity| (ref_tick, entity))
+                        });
+                    let update = match reference {
+                        Some((ref_tick, entity)) => {
+                            let ref_dt = tick.since(ref_tick);
+                            rebases.push((c.id, ref_dt));
+                            diff_entity(schema, entity, c, ref_dt, limits)?
+                        }
+                        None => Some(base_update),
+                    };
+                    updates.extend(update);
+                }
+                i += 1;
+                j += 1;
+            }
+            (Some(b), Some(c)) if b.id < c.id => {
+                destroys.push(b.id);
+                i += 1;
+            }
+            (Some(b), None) => {
+                destroys.push(b.id);
+                i += 1;
+            }
+            (_, Some(c)) => {
+                creates.push(c.clone());
+                j += 1;
+            }
+            (None, None) => break,
+        }
+    }
+
+    let mut offset = wire::HEADER_SIZE;
+    if !destroys.is_empty() {
+        offset += write_section(
+            SectionTag::EntityDestroy,
+            &mut out[offset..],
+            limits,
+            |writer| {
+                encode_destroy_body_from_list(
+                    destroys.iter(),
+                    schema.entity_id_width,
+                    limits,
+                    writer,
+                )
+            },
+        )?;
+    }
+    if !creates.is_empty() {
+        offset += write_section(
+            SectionTag::EntityCreate,
+            &mut out[offset..],
+            limits,
+            |writer| encode_create_body_from_list(schema, creates.iter(), limits, writer),
+        )?;
+    }
+    if !rebases.is_empty() {
+        offset += write_section(
+            SectionTag::EntityBaselineRef,
+            &mut out[offset..],
+            limits,
+            |writer| encode_baseline_ref_body(&rebases, limits, writer),
+        )?;
+    }
+    let lookup = build_component_lookup(schema);
+    offset += write_update_sections_from_updates(
+        schema,
+        updates.iter(),
+        limits,
+        &lookup,
+        &mut out[offset..],
+    )?;
+
+    let payload_len = offset - wire::HEADER_SIZE;
+    let header = wire::PacketHeader::delta_snapshot(
+        schema_hash(schema),
+        tick.raw(),
+        baseline_tick.raw(),
+        payload_len as u32,
+    );
+    encode_header(&header, &mut out[..wire::HEADER_SIZE]).map_err(|_| {
+        CodecError::OutputTooSmall {
+            needed: wire::HEADER_SIZE,
+            available: out.len(),
+        }
+    })?;
+    Ok(offset)
+}
+
+fn encode_baseline_ref_body(
+    rebases: &[(EntityId, u32)],
+    limits: &CodecLimits,
+    writer: &mut BitWriter<'_>,
+) -> CodecResult<()> {
+    if rebases.len() > limits.max_entities_update {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::EntitiesUpdate,
+            limit: limits.max_entities_update,
+            actual: rebases.len(),
+        });
+    }
+    writer.align_to_byte()?;
+    writer.write_varu32(rebases.len() as u32)?;
+    for (id, baseline_delta) in rebases {
+        writer.write_varu64(id.raw())?;
+        writer.write_varu32(*baseline_delta)?;
+    }
+    writer.align_to_byte()?;
+    Ok(())
+}
+
+fn decode_baseline_ref_section(
+    body: &[u8],
+    tick: u32,
+    width: EntityIdWidth,
+    limits: &CodecLimits,
+) -> CodecResult<Vec<(EntityId, SnapshotTick)>> {
+    if body.len() > limits.max_section_bytes {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::SectionBytes,
+            limit: limits.max_section_bytes,
+            actual: body.len(),
+        });
+    }
+    let mut reader = BitReader::new(body);
+    reader.align_to_byte()?;
+    let count = reader.read_varu32()? as usize;
+    if count > limits.max_entities_update {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::EntitiesUpdate,
+            limit: limits.max_entities_update,
+            actual: count,
+        });
+    }
+
+    let mut rebases = Vec::with_capacity(count);
+    let mut prev_id: Option<u64> = None;
+    for _ in 0..count {
+        let entity_id = reader.read_varu64()?;
+        if !EntityId::new(entity_id).fits(width) {
+            return Err(CodecError::EntityIdOutOfRange {
+                entity_id,
+                bits: width.bits(),
+            });
+        }
+        let baseline_delta = reader.read_varu32()?;
+        if let Some(prev) = prev_id {
+            if entity_id <= prev {
+                return Err(CodecError::InvalidEntityOrder {
+                    previous: prev,
+                    current: entity_id,
+                });
+            }
+        }
+        prev_id = Some(entity_id);
+        // The entity baseline must be older than `tick` in serial order and
+        // must not land on tick 0 ("no baseline").
+        if baseline_delta == 0
+            || baseline_delta >= wire::SESSION_MAX_TICK_DELTA
+            || baseline_delta == tick
+        {
+            return Err(CodecError::InvalidBaselineRef {
+                entity_id,
+                baseline_delta,
+            });
+        }
+        rebases.push((
+            EntityId::new(entity_id),
+            SnapshotTick::new(tick.wrapping_sub(baseline_delta)),
+        ));
+    }
+
+    reader.align_to_byte()?;
+    if reader.bits_remaining() != 0 {
+        return Err(CodecError::TrailingSectionData {
+            section: SectionTag::EntityBaselineRef,
+            remaining_bits: reader.bits_remaining(),
+        });
+    }
+    Ok(rebases)
+}
+
+/// Applies a per-entity baseline delta using the client's baseline history.
+pub fn apply_delta_snapshot_multi_baseline(
+    schema: &schema::Schema,
+    baselines: &BaselineStore<Snapshot>,
+    bytes: &[u8],
+    wire_limits: &wire::Limits,
+    limits: &CodecLimits,
+) -> CodecResult<Snapshot> {
+    let packet = decode_packet(bytes, wire_limits)?;
+    apply_delta_snapshot_multi_baseline_from_packet(schema, baselines, &packet, limits)
+}
+
+/// Applies a per-entity baseline delta from a parsed wire packet.
+///
+/// Plain delta packets (without `ENTITY_BASELINE_REF`) are accepted as well.
+pub fn apply_delta_snapshot_multi_baseline_from_packet(
+    schema: &schema::Schema,
+    baselines: &BaselineStore<Snapshot>,
+    packet: &WirePacket<'_>,
+    limits: &CodecLimits,
+) -> CodecResult<Snapshot> {
+    let header = packet.header;
+    validate_delta_header(schema, &header)?;
+    let baseline = baselines
+        .get(SnapshotTick::new(header.baseline_tick))
+        .ok_or(CodecError::BaselineNotFound {
+            requested_tick: header.baseline_tick,
+        })?;
+
+    let mut rebases: Option<Vec<(EntityId, SnapshotTick)>> = None;
+    let mut delta_sections = Vec::with_capacity(packet.sections.len());
+    for section in &packet.sections {
+        if section.tag == SectionTag::EntityBaselineRef {
+            if rebases.is_some() {
+                return Err(CodecError::DuplicateSection {
+                    section: section.tag,
+                });
+            }
+            rebases = Some(decode_baseline_ref_section(
+                section.body,
+                header.tick,
+                schema.entity_id_width,
+                limits,
+            )?);
+        } else {
+            delta_sections.push(*section);
+        }
+    }
+    let delta_packet = WirePacket {
+        header,
+        sections: delta_sections,
+        skipped: packet.skipped.clone(),
+    };
+    let (destroys, creates, updates) = decode_delta_sections(schema, &delta_packet, limits)?;
+
+    ensure_entities_sorted(&baseline.entities)?;
+    ensure_entities_sorted(&creates)?;
+
+    let mut remaining = apply_destroys(&baseline.entities, &destroys)?;
+    remaining = apply_creates(remaining, creates)?;
+    if remaining.len() > limits.max_total_entities_after_apply {
+        return Err(CodecError::LimitsExceeded {
+            kind: LimitKind::TotalEntitiesAfterApply,
+            limit: limits.max_total_entities_after_apply,
+            actual: remaining.len(),
+        });
+    }
+    let rebases = rebases.unwrap_or_default();
+    apply_rebases(&mut remaining, baselines, &rebases)?;
+    let packet_dt = header.tick.wrapping_sub(header.baseline_tick);
+    apply_updates(schema, &mut remaining, &updates, |id| {
+        rebases
+            .binary_search_by_key(&id.index(), |(entity_id, _)| entity_id.index())
+            .map_or(packet_dt, |idx| header.tick - rebases[idx].1.raw())
+    })?;
+
+    Ok(Snapshot {
+        tick: SnapshotTick::new(header.tick),
+        entities: remaining,
+    })
+}
+
+fn apply_rebases(
+    entities: &mut [EntitySnapshot],
+    baselines: &BaselineStore<Snapshot>,
+    rebases: &[(EntityId, SnapshotTick)],
+) -> CodecResult<()> {
+    for (id, ref_tick) in rebases {
+        let reference = baselines
+            .get(*ref_tick)
+            .ok_or(CodecError::BaselineNotFound {
+                requested_tick: ref_tick.raw(),
+            })?;
+        let idx = entities
+            .binary_search_by_key(&id.index(), |entity| entity.id.index())
+            .map_err(|_| CodecError::EntityNotFound {
+                entity_id: id.raw(),
+            })?;
+        let source = reference
+            .get(entities[idx].id)
+            .ok_or(CodecError::EntityNotFound {
+                entity_id: id.raw(),
+            })?;
+        entities[idx].components.clone_from(&source.components);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::{ComponentSnapshot, FieldValue};
+    use schema::{ComponentDef, ComponentId, FieldCodec, FieldDef, FieldId, Schema};
+    use std::num::NonZeroUsize;
+
+    const FIELDS: u16 = 6;
+
+    fn schema_fields() -> Schema {
+        let component = (1..=FIELDS).fold(
+            ComponentDef::new(ComponentId::new(1).unwrap()),
+            |component, id| {
+                component.field(FieldDef::new(
+                    FieldId::new(id).unwrap(),
+                    FieldCodec::uint(16),
+                ))
+            },
+        );
+        Schema::new(vec![component]).unwrap()
+    }
+
+    /// Entity whose first field is `a` and remaining fields are `b`.
+    fn entity(id: u32, a: u64, b: u64) -> EntitySnapshot {
+        let mut fields = vec![FieldValue::UInt(b); FIELDS as usize];
+        fields[0] = FieldValue::UInt(a);
+        EntitySnapshot {
+            id: EntityId::from(id),
+            components: vec![ComponentSnapshot {
+                id: ComponentId::new(1).unwrap(),
+                fields,
+            }],
+        }
+    }
+
+    fn snapshot(tick: u32, entities: Vec<EntitySnapshot>) -> Snapshot {
+        Snapshot {
+            tick: SnapshotTick::new(tick),
+            entities,
+        }
+    }
+
+    /// Client history: tick 10 is the last full ACK; tick 12 was only
+    /// partially acknowledged (entity 2 was updated there).
+    fn history() -> BaselineStore<Snapshot> {
+        let mut store = BaselineStore::new(NonZeroUsize::new(8).unwrap());
+        let t10 = snapshot(10, vec![entity(1, 1, 1), entity(2, 2, 2)]);
+        let t12 = snapshot(12, vec![entity(1, 1, 1), entity(2, 200, 300)]);
+        store.insert(t10.tick, t10).unwrap();
+        store.insert(t12.tick, t12).unwrap();
+        store
+    }
+
+    fn encode(
+        schema: &Schema,
+        store: &BaselineStore<Snapshot>,
+        current: &Snapshot,
+        entity_baseline: impl FnMut(EntityId) -> Option<SnapshotTick>,
+    ) -> Vec<u8> {
+        let mut buf = [0u8; 256];
+        let bytes = encode_delta_snapshot_multi_baseline(
+            schema,
+            current.tick,
+            SnapshotTick::new(10),
+            store,
+            current,
+            entity_baseline,
+            &CodecLimits::for_testing(),
+            &mut buf,
+        )
+        .unwrap();
+        buf[..bytes].to_vec()
+    }
+
+    #[test]
+    fn per_entity_baseline_roundtrip() {
+        let schema = schema_fields();
+        let store = history();
+        let current = snapshot(
+            13,
+            vec![entity(1, 5, 1), entity(2, 201, 300), entity(3, 3, 3)],
+        );
+        let bytes = encode(&schema, &store, &current, |id| {
+            (id == EntityId::new(2)).then_some(SnapshotTick::new(12))
+        });
+
+        let packet = decode_packet(&bytes, &wire::Limits::for_testing()).unwrap();
+        assert!(packet
+            .sections
+            .iter()
+            .any(|section| section.tag == SectionTag::EntityBaselineRef));
+
+        let applied = apply_delta_snapshot_multi_baseline(
+            &schema,
+            &store,
+            &bytes,
+            &wire::Limits::for_testing(),
+            &CodecLimits::for_testing(),
+        )
+        .unwrap();
+        assert_eq!(applied, current);
+    }
+
+    #[test]
+    fn newer_entity_baseline_shrinks_packet() {
+        let schema = schema_fields();
+        let store = history();
+        let current = snapshot(13, vec![entity(1, 1, 1), entity(2, 201, 300)]);
+        let single = encode(&schema, &store, &current, |_| None);
+        let multi = encode(&schema, &store, &current, |_| Some(SnapshotTick::new(12)));
+        assert!(multi.len() < single.len());
+
+        for bytes in [&single, &multi] {
+            let applied = apply_delta_snapshot_multi_baseline(
+                &schema,
+                &store,
+                bytes,
+                &wire::Limits::for_testing(),
+                &CodecLimits::for_testing(),
+            )
+            .unwrap();
+            assert_eq!(applied, current);
+        }
+    }
+
+    #[test]
+    fn missing_entity_baseline_is_error() {
+        let schema = schema_fields();
+        let store = history();
+        let current = snapshot(13, vec![entity(1, 1, 1), entity(2, 201, 300)]);
+        let bytes = encode(&schema, &store, &current, |_| Some(SnapshotTick::new(12)));
+
+        let mut client = BaselineStore::new(NonZeroUsize::new(8).unwrap());
+        let t10 = store.get(SnapshotTick::new(10)).unwrap().clone();
+        client.insert(t10.tick, t10).unwrap();
+        let err = apply_delta_snapshot_multi_baseline(
+            &schema,
+            &client,
+            &bytes,
+            &wire::Limits::for_testing(),
+            &CodecLimits::for_testing(),
+        )
+        .unwrap_err();
+        assert_eq!(err, CodecError::BaselineNotFound { requested_tick: 12 });
+        assert!(err.needs_resync());
+    }
+
+    #[test]
+    fn single_baseline_apply_rejects_baseline_refs() {
+        let schema = schema_fields();
+        let store = history();
+        let current = snapshot(13, vec![entity(1, 1, 1), entity(2, 201, 300)]);
+        let bytes = encode(&schema, &store, &current, |_| Some(SnapshotTick::new(12)));
+        let err = crate::delta::apply_delta_snapshot(
+            &schema,
+            store.get(SnapshotTick::new(10)).unwrap(),
+            &bytes,
+            &wire::Limits::for_testing(),
+            &CodecLimits::for_testing(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            CodecError::UnexpectedSection {
+                section: SectionTag::EntityBaselineRef,
+            }
+        );
+    }
+
+    #[test]
+    fn baseline_ref_must_point_backwards() {
+        let err = decode_baseline_ref_section(
+            &[1, 4, 0],
+            13,
+            EntityIdWidth::U32,
+            &CodecLimits::for_testing(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            CodecError::InvalidBaselineRef {
+                entity_id: 4,
+                baseline_delta: 0,
+            }
+        );
+    }
+}
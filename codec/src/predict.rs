@@ -0,0 +1,618 @@
+//! Residual and predicted-value encoding for delta updates.
+//!
+//! Fields with `DeltaMode::Residual`, `DeltaMode::ResidualVarint` or
+//! `DeltaMode::Predicted` are written in update sections as a flag bit
+//! followed by either a signed residual or, when the residual does not fit or
+//! is not smaller, the absolute value. Residual fields are relative to the
+//! baseline value; predicted fields are relative to
+//! `baseline + (velocity * dt) >> velocity_shift`. The predictor is
+//! integer-only so encoder and decoder reconstruct identical values.
+
+use bitstream::{BitReader, BitWriter};
+use schema::{ComponentDef, ComponentId, DeltaMode, FieldCodec, FieldDef};
+
+use crate::error::{CodecError, CodecResult, ValueReason};
+use crate::snapshot::{codec_name, required_bits, FieldValue};
+
+/// A changed field value as carried in a delta update.
+///
+/// Updates built by callers hold plain values. Updates decoded without a
+/// baseline (see [`decode_delta_packet`](crate::decode_delta_packet)) may also
+/// hold residuals for fields with a residual or predicted `DeltaMode`; these
+/// are resolved against the baseline when the delta is applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateValue {
+    /// The field's new value.
+    Value(FieldValue),
+    /// Signed difference from the field's baseline or predicted value.
+    Residual(i64),
+}
+
+impl From<FieldValue> for UpdateValue {
+    fn from(value: FieldValue) -> Self {
+        Self::Value(value)
+    }
+}
+
+/// How a residual is written after its flag bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResidualForm {
+    /// Two's complement in the given number of bits.
+    Fixed(u8),
+    /// Zigzag varint, byte-aligned.
+    Varint,
+}
+
+/// Predicts a value from its baseline and velocity `dt` ticks later.
+///
+/// This is the predictor both sides of a predicted delta run; it saturates
+/// instead of overflowing.
+#[must_use]
+pub fn predict(baseline: i64, velocity: i64, dt: u32, velocity_shift: u8) -> i64 {
+    let step = (i128::from(velocity) * i128::from(dt)) >> velocity_shift;
+    let predicted = i128::from(baseline) + step;
+    predicted.clamp(i128::from(i64::MIN), i128::from(i64::MAX)) as i64
+}
+
+/// Returns the value to place in an update for `current`: a residual when the
+/// field has a residual delta mode and the residual encodes in fewer bits than
+/// the absolute value, otherwise `current` itself.
+pub(crate) fn encode_update_value(
+    component: &ComponentDef,
+    field_idx: usize,
+    baseline: &[FieldValue],
+    current: FieldValue,
+    dt: u32,
+) -> UpdateValue {
+    let field = component.fields[field_idx];
+    let Some(form) = residual_form(field.delta) else {
+        return current.into();
+    };
+    let Some(predicted) = predicted_value(component, field_idx, baseline, dt) else {
+        return current.into();
+    };
+    let residual = numeric(current)
+        .map(|value| i128::from(value) - i128::from(predicted))
+        .and_then(|residual| i64::try_from(residual).ok());
+    match residual {
+        Some(residual)
+            if residual_bits(form, residual)
+                .is_some_and(|bits| bits < absolute_bits(field.codec, current)) =>
+        {
+            UpdateValue::Residual(residual)
+        }
+        _ => current.into(),
+    }
+}
+
+/// Writes a changed field value in an update section.
+///
+/// Absolute values go through `write_absolute`, the section's field writer.
+pub(crate) fn write_update_value<W>(
+    component_id: ComponentId,
+    field: FieldDef,
+    value: UpdateValue,
+    writer: &mut BitWriter<'_>,
+    write_absolute: W,
+) -> CodecResult<()>
+where
+    W: FnOnce(ComponentId, FieldDef, FieldValue, &mut BitWriter<'_>) -> CodecResult<()>,
+{
+    let form = residual_form(field.delta);
+    match (form, value) {
+        (None, UpdateValue::Value(value)) => write_absolute(component_id, field, value, writer),
+        (Some(_), UpdateValue::Value(value)) => {
+            writer.write_bit(false)?;
+            write_absolute(component_id, field, value, writer)
+        }
+        (None, UpdateValue::Residual(_)) => Err(CodecError::InvalidValue {
+            component: component_id,
+            field: field.id,
+            reason: ValueReason::TypeMismatch {
+                expected: codec_name(field.codec),
+                found: "residual",
+            },
+        }),
+        (Some(ResidualForm::Fixed(bits)), UpdateValue::Residual(residual)) => {
+            if residual_bits(ResidualForm::Fixed(bits), residual).is_none() {
+                return Err(CodecError::InvalidValue {
+                    component: component_id,
+                    field: field.id,
+                    reason: ValueReason::ResidualOutOfRange {
+                        bits,
+                        value: residual,
+                    },
+                });
+            }
+            writer.write_bit(true)?;
+            let mask = u64::MAX >> (64 - u32::from(bits));
+            writer.write_bits(residual as u64 & mask, bits)?;
+            Ok(())
+        }
+        (Some(ResidualForm::Varint), UpdateValue::Residual(residual)) => {
+            writer.write_bit(true)?;
+            writer.align_to_byte()?;
+            writer.write_varu64(zigzag(residual))?;
+            Ok(())
+        }
+    }
+}
+
+/// Reads a changed field value from an update section.
+///
+/// Residual and predicted fields may yield [`UpdateValue::Residual`],
+/// resolved when applied. Absolute values go through `read_absolute`, the
+/// section's field reader.
+pub(crate) fn read_update_value<R>(
+    component_id: ComponentId,
+    field: FieldDef,
+    reader: &mut BitReader<'_>,
+    read_absolute: R,
+) -> CodecResult<UpdateValue>
+where
+    R: FnOnce(ComponentId, FieldDef, &mut BitReader<'_>) -> CodecResult<FieldValue>,
+{
+    let Some(form) = residual_form(field.delta) else {
+        return read_absolute(component_id, field, reader).map(UpdateValue::Value);
+    };
+    if !reader.read_bit()? {
+        return read_absolute(component_id, field, reader).map(UpdateValue::Value);
+    }
+    let residual = match form {
+        ResidualForm::Fixed(bits) => {
+            let raw = reader.read_bits(bits)?;
+            let shift = 64 - u32::from(bits);
+            ((raw << shift) as i64) >> shift
+        }
+        ResidualForm::Varint => {
+            reader.align_to_byte()?;
+            let raw = reader.read_varu64()?;
+            ((raw >> 1) as i64) ^ -((raw & 1) as i64)
+        }
+    };
+    Ok(UpdateValue::Residual(residual))
+}
+
+/// Reconstructs the absolute value of a residual or predicted field from its
+/// baseline component values.
+pub(crate) fn resolve_residual(
+    component: &ComponentDef,
+    field_idx: usize,
+    baseline: &[FieldValue],
+    residual: i64,
+    dt: u32,
+) -> CodecResult<FieldValue> {
+    let field = component.fields[field_idx];
+    let Some(predicted) = predicted_value(component, field_idx, baseline, dt) else {
+        return Err(CodecError::InvalidValue {
+            component: component.id,
+            field: field.id,
+            reason: ValueReason::TypeMismatch {
+                expected: codec_name(field.codec),
+                found: "residual",
+            },
+        });
+    };
+    let value = i128::from(predicted) + i128::from(residual);
+    value_for_codec(field.codec, value).ok_or(CodecError::InvalidValue {
+        component: component.id,
+        field: field.id,
+        reason: ValueReason::PredictionOutOfRange {
+            value: value.clamp(i128::from(i64::MIN), i128::from(i64::MAX)) as i64,
+        },
+    })
+}
+
+/// Returns the value a residual is relative to, if the field has one.
+fn predicted_value(
+    component: &ComponentDef,
+    field_idx: usize,
+    baseline: &[FieldValue],
+    dt: u32,
+) -> Option<i64> {
+    let base = numeric(*baseline.get(field_idx)?)?;
+    match component.fields[field_idx].delta {
+        DeltaMode::Absolute => None,
+        DeltaMode::Residual { .. } | DeltaMode::ResidualVarint => Some(base),
+        DeltaMode::Predicted {
+            velocity,
+            velocity_shift,
+            ..
+        } => {
+            let velocity_idx = component.fields.iter().position(|f| f.id == velocity)?;
+            let velocity = numeric(*baseline.get(velocity_idx)?)?;
+            Some(predict(base, velocity, dt, velocity_shift))
+        }
+    }
+}
+
+fn residual_form(mode: DeltaMode) -> Option<ResidualForm> {
+    match mode {
+        DeltaMode::Absolute => None,
+        DeltaMode::Predicted { residual_bits, .. } | DeltaMode::Residual { residual_bits } => {
+            Some(ResidualForm::Fixed(residual_bits))
+        }
+        DeltaMode::ResidualVarint => Some(ResidualForm::Varint),
+    }
+}
+
+/// Bits `residual` takes in `form`, or `None` if it does not fit.
+fn residual_bits(form: ResidualForm, residual: i64) -> Option<u32> {
+    match form {
+        ResidualForm::Fixed(bits) => {
+            let half = 1i128 << (bits - 1);
+            (-half..half)
+                .contains(&i128::from(residual))
+                .then_some(u32::from(bits))
+        }
+        ResidualForm::Varint => Some(varint_bits(zigzag(residual))),
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Bits the absolute encoding of `value` takes (varints counted as whole bytes).
+fn absolute_bits(codec: FieldCodec, value: FieldValue) -> u32 {
+    match (codec, value) {
+        (FieldCodec::UInt { bits } | FieldCodec::SInt { bits }, _) => u32::from(bits),
+        (FieldCodec::FixedPoint(fp), _) => u32::from(required_bits((fp.max_q - fp.min_q) as u64)),
+        (FieldCodec::VarUInt, FieldValue::VarUInt(v)) => varint_bits(v),
+        (FieldCodec::VarSInt, FieldValue::VarSInt(v)) => varint_bits(zigzag(v)),
+        _ => 1,
+    }
+}
+
+fn varint_bits(mut value: u64) -> u32 {
+    let mut bytes = 1;
+    while value >= 0x80 {
+        value >>= 7;
+        bytes += 1;
+    }
+    bytes * 8
+}
+
+fn numeric(value: FieldValue) -> Option<i64> {
+    match value {
+        FieldValue::UInt(v) | FieldValue::VarUInt(v) => i64::try_from(v).ok(),
+        FieldValue::SInt(v) | FieldValue::VarSInt(v) | FieldValue::FixedPoint(v) => Some(v),
+        FieldValue::Bool(_) => None,
+    }
+}
+
+fn value_for_codec(codec: FieldCodec, value: i128) -> Option<FieldValue> {
+    match codec {
+        FieldCodec::Bool => None,
+        FieldCodec::UInt { bits } => {
+            let max = (1u128 << bits) - 1;
+            u64::try_from(value)
+                .ok()
+                .filter(|v| u128::from(*v) <= max)
+                .map(FieldValue::UInt)
+        }
+        FieldCodec::SInt { bits } => {
+            let half = 1i128 << (bits - 1);
+            (-half..half)
+                .contains(&value)
+                .then_some(FieldValue::SInt(value as i64))
+        }
+        FieldCodec::VarUInt => u32::try_from(value)
+            .ok()
+            .map(|v| FieldValue::VarUInt(u64::from(v))),
+        FieldCodec::VarSInt => i32::try_from(value)
+            .ok()
+            .map(|v| FieldValue::VarSInt(i64::from(v))),
+        FieldCodec::FixedPoint(fp) => (i128::from(fp.min_q)..=i128::from(fp.max_q))
+            .contains(&value)
+            .then_some(FieldValue::FixedPoint(value as i64)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schema::FieldId;
+
+    fn fid(id: u16) -> FieldId {
+        FieldId::new(id).unwrap()
+    }
+
+    fn moving_component(residual_bits: u8) -> ComponentDef {
+        ComponentDef::new(ComponentId::new(1).unwrap())
+            .field(
+                FieldDef::new(fid(1), FieldCodec::fixed_point(-10_000, 10_000, 100)).delta(
+                    DeltaMode::Predicted {
+                        velocity: fid(2),
+                        velocity_shift: 1,
+                        residual_bits,
+                    },
+                ),
+            )
+            .field(FieldDef::new(fid(2), FieldCodec::sint(12)))
+    }
+
+    #[test]
+    fn predict_is_integer_and_saturating() {
+        assert_eq!(predict(100, 10, 3, 0), 130);
+        assert_eq!(predict(100, 10, 3, 1), 115);
+        assert_eq!(predict(100, -3, 1, 1), 98);
+        assert_eq!(predict(i64::MAX, 1, 1, 0), i64::MAX);
+    }
+
+    #[test]
+    fn residual_roundtrips_through_prediction() {
+        let component = moving_component(4);
+        let baseline = [FieldValue::FixedPoint(500), FieldValue::SInt(20)];
+        let current = FieldValue::FixedPoint(532);
+
+        // Prediction: 500 + (20 * 3) >> 1 = 530.
+        let value = encode_update_value(&component, 0, &baseline, current, 3);
+        assert_eq!(value, UpdateValue::Residual(2));
+        assert_eq!(
+            resolve_residual(&component, 0, &baseline, 2, 3).unwrap(),
+            current
+        );
+    }
+
+    #[test]
+    fn large_error_falls_back_to_absolute() {
+        let component = moving_component(4);
+        let baseline = [FieldValue::FixedPoint(500), FieldValue::SInt(20)];
+        let current = FieldValue::FixedPoint(-900);
+        assert_eq!(
+            encode_update_value(&component, 0, &baseline, current, 3),
+            UpdateValue::Value(current)
+        );
+    }
+
+    #[test]
+    fn write_read_residual_and_fallback() {
+        let component = moving_component(4);
+        let field = component.fields[0];
+        let mut buf = [0u8; 16];
+        let mut writer = BitWriter::new(&mut buf);
+        let values = [
+            UpdateValue::Residual(-8),
+            UpdateValue::Value(FieldValue::FixedPoint(-900)),
+        ];
+        for value in values {
+            write_update_value(
+                component.id,
+                field,
+                value,
+                &mut writer,
+                crate::snapshot::write_field_value,
+            )
+            .unwrap();
+        }
+        let len = writer.finish();
+
+        let mut reader = BitReader::new(&buf[..len]);
+        for expected in values {
+            let value = read_update_value(
+                component.id,
+                field,
+                &mut reader,
+                crate::snapshot::read_field_value,
+            )
+            .unwrap();
+            assert_eq!(value, expected);
+        }
+    }
+
+    #[test]
+    fn residual_out_of_range_is_error() {
+        let component = moving_component(4);
+        let mut buf = [0u8; 16];
+        let mut writer = BitWriter::new(&mut buf);
+        let err = write_update_value(
+            component.id,
+            component.fields[0],
+            UpdateValue::Residual(8),
+            &mut writer,
+            crate::snapshot::write_field_value,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            CodecError::InvalidValue {
+                reason: ValueReason::ResidualOutOfRange { bits: 4, value: 8 },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn resolved_value_outside_codec_range_is_error() {
+        let component = moving_component(4);
+        let baseline = [FieldValue::FixedPoint(10_000), FieldValue::SInt(20)];
+        let err = resolve_residual(&component, 0, &baseline, 7, 1).unwrap_err();
+        assert!(matches!(
+            err,
+            CodecError::InvalidValue {
+                reason: ValueReason::PredictionOutOfRange { .. },
+                ..
+            }
+        ));
+    }
+
+    fn moving_snapshot(tick: u32, position: i64, velocity: i64) -> crate::Snapshot {
+        crate::Snapshot {
+            tick: crate::SnapshotTick::new(tick),
+            entities: vec![crate::EntitySnapshot {
+                id: crate::EntityId::new(1),
+                components: vec![crate::ComponentSnapshot {
+                    id: ComponentId::new(1).unwrap(),
+                    fields: vec![FieldValue::FixedPoint(position), FieldValue::SInt(velocity)],
+                }],
+            }],
+        }
+    }
+
+    #[test]
+    fn predicted_delta_roundtrips_and_shrinks() {
+        let predicted = schema::Schema::new(vec![moving_component(4)]).unwrap();
+        let absolute = {
+            let mut component = moving_component(4);
+            component.fields[0].delta = DeltaMode::Absolute;
+            schema::Schema::new(vec![component]).unwrap()
+        };
+        let limits = crate::CodecLimits::for_testing();
+        let baseline = moving_snapshot(10, 500, 20);
+        // Prediction after 3 ticks: 500 + (20 * 3) >> 1 = 530.
+        let current = moving_snapshot(13, 531, 20);
+
+        let encode = |schema: &schema::Schema| {
+            let mut buf = [0u8; 128];
+            let len = crate::encode_delta_snapshot(
+                schema,
+                current.tick,
+                baseline.tick,
+                &baseline,
+                &current,
+                &limits,
+                &mut buf,
+            )
+            .unwrap();
+            buf[..len].to_vec()
+        };
+        let predicted_bytes = encode(&predicted);
+        let absolute_bytes = encode(&absolute);
+        assert!(predicted_bytes.len() < absolute_bytes.len());
+
+        let applied = crate::apply_delta_snapshot(
+            &predicted,
+            &baseline,
+            &predicted_bytes,
+            &wire::Limits::for_testing(),
+            &limits,
+        )
+        .unwrap();
+        assert_eq!(applied, current);
+    }
+
+    #[test]
+    fn residual_mode_is_relative_to_baseline() {
+        let component = ComponentDef::new(ComponentId::new(1).unwrap()).field(
+            FieldDef::new(fid(1), FieldCodec::uint(16))
+                .delta(DeltaMode::Residual { residual_bits: 6 }),
+        );
+        let baseline = [FieldValue::UInt(1000)];
+
+        let value = encode_update_value(&component, 0, &baseline, FieldValue::UInt(990), 7);
+        assert_eq!(value, UpdateValue::Residual(-10));
+        assert_eq!(
+            resolve_residual(&component, 0, &baseline, -10, 7).unwrap(),
+            FieldValue::UInt(990)
+        );
+    }
+
+    #[test]
+    fn residual_not_used_when_absolute_is_smaller() {
+        let component = ComponentDef::new(ComponentId::new(1).unwrap()).field(
+            FieldDef::new(fid(1), FieldCodec::uint(4))
+                .delta(DeltaMode::Residual { residual_bits: 4 }),
+        );
+        let baseline = [FieldValue::UInt(3)];
+        assert_eq!(
+            encode_update_value(&component, 0, &baseline, FieldValue::UInt(4), 1),
+            UpdateValue::Value(FieldValue::UInt(4))
+        );
+    }
+
+    #[test]
+    fn residual_delta_roundtrips_with_absolute_fallback() {
+        let component = ComponentDef::new(ComponentId::new(1).unwrap())
+            .field(
+                FieldDef::new(fid(1), FieldCodec::fixed_point(-100_000, 100_000, 100))
+                    .delta(DeltaMode::Residual { residual_bits: 5 }),
+            )
+            .field(FieldDef::new(fid(2), FieldCodec::bool()));
+        let schema = schema::Schema::new(vec![component]).unwrap();
+        let limits = crate::CodecLimits::for_testing();
+        let snapshot = |tick, position, flag| crate::Snapshot {
+            tick: crate::SnapshotTick::new(tick),
+            entities: vec![crate::EntitySnapshot {
+                id: crate::EntityId::new(1),
+                components: vec![crate::ComponentSnapshot {
+                    id: ComponentId::new(1).unwrap(),
+                    fields: vec![FieldValue::FixedPoint(position), FieldValue::Bool(flag)],
+                }],
+            }],
+        };
+        let baseline = snapshot(1, 40_000, false);
+
+        for current in [snapshot(2, 40_009, true), snapshot(2, -50_000, false)] {
+            let mut buf = [0u8; 128];
+            let len = crate::encode_delta_snapshot(
+                &schema,
+                current.tick,
+                baseline.tick,
+                &baseline,
+                &current,
+                &limits,
+                &mut buf,
+            )
+            .unwrap();
+            let applied = crate::apply_delta_snapshot(
+                &schema,
+                &baseline,
+                &buf[..len],
+                &wire::Limits::for_testing(),
+                &limits,
+            )
+            .unwrap();
+            assert_eq!(applied, current);
+        }
+    }
+
+    #[test]
+    fn residual_varint_roundtrips_and_falls_back() {
+        let component = ComponentDef::new(ComponentId::new(1).unwrap())
+            .field(FieldDef::new(fid(1), FieldCodec::uint(32)).delta(DeltaMode::ResidualVarint));
+        let field = component.fields[0];
+        let baseline = [FieldValue::UInt(1_000)];
+
+        let small = encode_update_value(&component, 0, &baseline, FieldValue::UInt(937), 1);
+        assert_eq!(small, UpdateValue::Residual(-63));
+        // A residual whose varint is wider than 32 bits is sent absolute.
+        let large = FieldValue::UInt(1_000 + (1 << 30));
+        assert_eq!(
+            encode_update_value(&component, 0, &baseline, large, 1),
+            UpdateValue::Value(large)
+        );
+
+        let mut buf = [0u8; 16];
+        let mut writer = BitWriter::new(&mut buf);
+        for value in [small, UpdateValue::Value(large)] {
+            write_update_value(
+                component.id,
+                field,
+                value,
+                &mut writer,
+                crate::snapshot::write_field_value,
+            )
+            .unwrap();
+        }
+        let len = writer.finish();
+        // Flag bit + padding, one varint byte, then flag bit + 32-bit value.
+        assert_eq!(len, 7);
+
+        let mut reader = BitReader::new(&buf[..len]);
+        for expected in [small, UpdateValue::Value(large)] {
+            let value = read_update_value(
+                component.id,
+                field,
+                &mut reader,
+                crate::snapshot::read_field_value,
+            )
+            .unwrap();
+            assert_eq!(value, expected);
+        }
+        assert_eq!(
+            resolve_residual(&component, 0, &baseline, -63, 1).unwrap(),
+            FieldValue::UInt(937)
+        );
+    }
+}
@@ -5,6 +5,8 @@
 pub struct CodecScratch {
     component_changed: Vec<bool>,
     field_mask: Vec<bool>,
+    measure: Vec<u8>,
+    selected: Vec<bool>,
 }
 
 impl CodecScratch {
@@ -39,4 +41,22 @@ impl CodecScratch {
         let field_mask = &mut self.field_mask[..fields];
         (component_changed, field_mask)
     }
+
+    /// Returns a byte buffer for measuring encoded sizes and a cleared
+    /// selection flag per change-list entry.
+    pub(crate) fn budget_buffers_mut(
+        &mut self,
+        measure_len: usize,
+        changes: usize,
+    ) -> (&mut [u8], &mut [bool]) {
+        if self.measure.len() < measure_len {
+            self.measure.resize(measure_len, 0);
+        }
+        if self.selected.len() < changes {
+            self.selected.resize(changes, false);
+        }
+        let selected = &mut self.selected[..changes];
+        selected.fill(false);
+        (&mut self.measure[..measure_len], selected)
+    }
 }
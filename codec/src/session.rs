@@ -4,22 +4,30 @@ use bitstream::{BitReader, BitWriter};
 use schema::schema_hash;
 use wire::{PacketFlags, PacketHeader, SectionTag, WirePacket, WireSection};
 
+use crate::caps::NegotiatedCaps;
 use crate::error::{CodecError, CodecResult};
 use crate::limits::CodecLimits;
+use crate::model::CodecModel;
 use crate::snapshot::write_section;
 use crate::types::SnapshotTick;
 
 /// Compact header mode negotiated via session init.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CompactHeaderMode {
-    /// Compact session header v1.
+    /// Compact session header v1: the tick is a delta from the previous
+    /// packet, so a lost or reordered packet desynchronizes the receiver.
     SessionV1 = 1,
+    /// Compact session header v2: the tick is sent as its low 16 bits and
+    /// reconstructed against the newest tick received, which survives loss
+    /// and reordering.
+    SessionV2 = 2,
 }
 
 impl CompactHeaderMode {
     fn from_raw(raw: u8) -> Option<Self> {
         match raw {
             1 => Some(Self::SessionV1),
+            2 => Some(Self::SessionV2),
             _ => None,
         }
     }
@@ -32,23 +40,120 @@ pub struct SessionState {
     pub session_id: Option<u64>,
     pub last_tick: SnapshotTick,
     pub compact_mode: CompactHeaderMode,
+    /// Hash of the trained model announced by the server, if any.
+    pub model_hash: Option<u64>,
+    /// Token that resumes this session after a reconnect. The server issues
+    /// it in its session init; a reconnecting client sends it back in its own
+    /// init, with the newest tick it holds as the header tick.
+    pub resume_token: Option<u64>,
+    /// Capabilities the sender listed in its session init, or `None` if it
+    /// predates capability negotiation.
+    pub capabilities: Option<NegotiatedCaps>,
+}
+
+impl SessionState {
+    /// Returns the capabilities both the sender and `local` support.
+    #[must_use]
+    pub fn negotiate(&self, local: NegotiatedCaps) -> NegotiatedCaps {
+        local.intersect(self.capabilities.unwrap_or_default())
+    }
+
+    /// Checks that the locally loaded model matches the one the server announced.
+    pub fn ensure_model(&self, model: Option<&CodecModel>) -> CodecResult<()> {
+        let local = model.map(CodecModel::hash);
+        if local == self.model_hash {
+            return Ok(());
+        }
+        Err(CodecError::ModelMismatch {
+            expected: self.model_hash.unwrap_or(0),
+            found: local.unwrap_or(0),
+        })
+    }
+}
+
+/// Contents of a session init packet beyond its tick.
+///
+/// Start from [`SessionInitOptions::new`] and add the optional fields that
+/// apply; any of the optional fields moves the body to the extension layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionInitOptions {
+    pub session_id: Option<u64>,
+    pub compact_mode: CompactHeaderMode,
+    /// Hash of the trained model the sender uses. The receiver compares it
+    /// against its own model with [`SessionState::ensure_model`].
+    pub model_hash: Option<u64>,
+    /// A server passes the token a client may later present to resume the
+    /// session; a reconnecting client passes the token it was issued, with
+    /// the newest tick it holds as the packet tick.
+    pub resume_token: Option<u64>,
+    /// The sender's capabilities. The receiver answers with a session accept
+    /// packet holding the subset it supports (see [`SessionState::negotiate`]).
+    pub capabilities: Option<NegotiatedCaps>,
+}
+
+impl SessionInitOptions {
+    /// Creates options for `compact_mode` with no optional fields.
+    #[must_use]
+    pub const fn new(compact_mode: CompactHeaderMode) -> Self {
+        Self {
+            session_id: None,
+            compact_mode,
+            model_hash: None,
+            resume_token: None,
+            capabilities: None,
+        }
+    }
+
+    /// Sets the session id.
+    #[must_use]
+    pub const fn session_id(mut self, session_id: Option<u64>) -> Self {
+        self.session_id = session_id;
+        self
+    }
+
+    /// Announces `model`, if any.
+    #[must_use]
+    pub fn model(mut self, model: Option<&CodecModel>) -> Self {
+        self.model_hash = model.map(CodecModel::hash);
+        self
+    }
+
+    /// Sets the resume token.
+    #[must_use]
+    pub const fn resume_token(mut self, resume_token: Option<u64>) -> Self {
+        self.resume_token = resume_token;
+        self
+    }
+
+    /// Lists the sender's capabilities.
+    #[must_use]
+    pub const fn capabilities(mut self, capabilities: Option<NegotiatedCaps>) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
 }
 
 /// Encodes a session init packet.
 pub fn encode_session_init_packet(
     schema: &schema::Schema,
     tick: SnapshotTick,
-    session_id: Option<u64>,
-    compact_mode: CompactHeaderMode,
+    options: &SessionInitOptions,
     limits: &CodecLimits,
     out: &mut [u8],
 ) -> CodecResult<usize> {
+    let body = SessionInitBody {
+        session_id: options.session_id,
+        compact_mode: options.compact_mode,
+        model_hash: options.model_hash,
+        resume_token: options.resume_token,
+        capabilities: options.capabilities,
+    };
     let mut offset = wire::HEADER_SIZE;
     let body_len = write_section(
         SectionTag::SessionInit,
         &mut out[offset..],
         limits,
-        |writer| encode_session_init_body(session_id, compact_mode, writer),
+        |writer| encode_session_init_body(&body, writer),
     )?;
     offset += body_len;
 
@@ -71,18 +176,54 @@ pub fn encode_session_init_packet(
     Ok(offset)
 }
 
-fn encode_session_init_body(
+/// Set in the `compact_mode` byte when init extensions follow it.
+const INIT_EXTENSIONS: u8 = 0x80;
+const INIT_EXT_MODEL_HASH: u8 = 1;
+const INIT_EXT_RESUME_TOKEN: u8 = 2;
+const INIT_EXT_CAPABILITIES: u8 = 3;
+
+/// Decoded fields of a `SESSION_INIT` section.
+struct SessionInitBody {
     session_id: Option<u64>,
     compact_mode: CompactHeaderMode,
-    writer: &mut BitWriter<'_>,
-) -> CodecResult<()> {
+    model_hash: Option<u64>,
+    resume_token: Option<u64>,
+    capabilities: Option<NegotiatedCaps>,
+}
+
+fn encode_session_init_body(body: &SessionInitBody, writer: &mut BitWriter<'_>) -> CodecResult<()> {
     writer.align_to_byte()?;
-    writer.write_u64_aligned(session_id.unwrap_or(0))?;
-    writer.write_u8_aligned(compact_mode as u8)?;
+    writer.write_u64_aligned(body.session_id.unwrap_or(0))?;
+    let mode = body.compact_mode as u8;
+    if body.model_hash.is_none() && body.resume_token.is_none() && body.capabilities.is_none() {
+        writer.write_u8_aligned(mode)?;
+    } else {
+        writer.write_u8_aligned(mode | INIT_EXTENSIONS)?;
+        if let Some(model_hash) = body.model_hash {
+            write_init_extension(writer, INIT_EXT_MODEL_HASH, &model_hash.to_le_bytes())?;
+        }
+        if let Some(token) = body.resume_token {
+            write_init_extension(writer, INIT_EXT_RESUME_TOKEN, &token.to_le_bytes())?;
+        }
+        if let Some(capabilities) = body.capabilities {
+            writer.write_u8_aligned(INIT_EXT_CAPABILITIES)?;
+            writer.write_u8_aligned(capabilities.encoded_len() as u8)?;
+            capabilities.encode(writer)?;
+        }
+    }
     writer.align_to_byte()?;
     Ok(())
 }
 
+fn write_init_extension(writer: &mut BitWriter<'_>, tag: u8, value: &[u8]) -> CodecResult<()> {
+    writer.write_u8_aligned(tag)?;
+    writer.write_u8_aligned(value.len() as u8)?;
+    for &byte in value {
+        writer.write_u8_aligned(byte)?;
+    }
+    Ok(())
+}
+
 /// Decodes a session init packet into session state.
 pub fn decode_session_init_packet(
     schema: &schema::Schema,
@@ -124,20 +265,20 @@ pub fn decode_session_init_packet(
         }
     }
     let section = init_section.ok_or(CodecError::SessionInitInvalid)?;
-    let (session_id, compact_mode) = decode_session_init_body(section.body, limits)?;
+    let body = decode_session_init_body(section.body, limits)?;
 
     Ok(SessionState {
         schema_hash: header.schema_hash,
-        session_id,
+        session_id: body.session_id,
         last_tick: SnapshotTick::new(header.tick),
-        compact_mode,
+        compact_mode: body.compact_mode,
+        model_hash: body.model_hash,
+        resume_token: body.resume_token,
+        capabilities: body.capabilities,
     })
 }
 
-fn decode_session_init_body(
-    body: &[u8],
-    limits: &CodecLimits,
-) -> CodecResult<(Option<u64>, CompactHeaderMode)> {
+fn decode_session_init_body(body: &[u8], limits: &CodecLimits) -> CodecResult<SessionInitBody> {
     if body.len() > limits.max_section_bytes {
         return Err(CodecError::LimitsExceeded {
             kind: crate::error::LimitKind::SectionBytes,
@@ -148,7 +289,37 @@ fn decode_session_init_body(
     let mut reader = BitReader::new(body);
     reader.align_to_byte()?;
     let session_id = reader.read_u64_aligned()?;
-    let mode = reader.read_u8_aligned()?;
+    let raw_mode = reader.read_u8_aligned()?;
+    let mode = raw_mode & !INIT_EXTENSIONS;
+    let mut model_hash = None;
+    let mut resume_token = None;
+    let mut capabilities = None;
+    if raw_mode & INIT_EXTENSIONS != 0 {
+        while reader.bits_remaining() != 0 {
+            let tag = reader.read_u8_aligned()?;
+            let len = reader.read_u8_aligned()?;
+            let slot = match tag {
+                INIT_EXT_MODEL_HASH => &mut model_hash,
+                INIT_EXT_RESUME_TOKEN => &mut resume_token,
+                INIT_EXT_CAPABILITIES => {
+                    if capabilities.is_some() || reader.bits_remaining() / 8 < usize::from(len) {
+                        return Err(CodecError::SessionInitInvalid);
+                    }
+                    capabilities = Some(NegotiatedCaps::decode_len(&mut reader, len.into())?);
+                    continue;
+                }
+                // Unknown extensions are skipped for forward compatibility.
+                _ => {
+                    skip_bytes(&mut reader, len)?;
+                    continue;
+                }
+            };
+            if len != 8 || slot.is_some() {
+                return Err(CodecError::SessionInitInvalid);
+            }
+            *slot = Some(reader.read_u64_aligned()?);
+        }
+    }
     reader.align_to_byte()?;
     if reader.bits_remaining() != 0 {
         return Err(CodecError::TrailingSectionData {
@@ -158,17 +329,124 @@ fn decode_session_init_body(
     }
     let compact_mode =
         CompactHeaderMode::from_raw(mode).ok_or(CodecError::SessionUnsupportedMode { mode })?;
-    Ok((
-        if session_id == 0 {
-            None
-        } else {
-            Some(session_id)
-        },
+    Ok(SessionInitBody {
+        session_id: (session_id != 0).then_some(session_id),
         compact_mode,
-    ))
+        model_hash,
+        resume_token: resume_token.filter(|&token| token != 0),
+        capabilities,
+    })
+}
+
+fn skip_bytes(reader: &mut BitReader<'_>, len: u8) -> CodecResult<()> {
+    for _ in 0..len {
+        reader.read_u8_aligned()?;
+    }
+    Ok(())
+}
+
+/// A receiver's answer to a session init.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionAccept {
+    pub session_id: Option<u64>,
+    /// Tick of the session init being answered.
+    pub tick: SnapshotTick,
+    /// Capabilities the receiver will use, a subset of those offered.
+    pub capabilities: NegotiatedCaps,
+}
+
+/// Encodes a session accept packet.
+pub fn encode_session_accept_packet(
+    schema: &schema::Schema,
+    accept: &SessionAccept,
+    limits: &CodecLimits,
+    out: &mut [u8],
+) -> CodecResult<usize> {
+    if out.len() < wire::HEADER_SIZE {
+        return Err(CodecError::OutputTooSmall {
+            needed: wire::HEADER_SIZE,
+            available: out.len(),
+        });
+    }
+    let payload_len = write_section(
+        SectionTag::SessionAccept,
+        &mut out[wire::HEADER_SIZE..],
+        limits,
+        |writer| {
+            writer.write_u64_aligned(accept.session_id.unwrap_or(0))?;
+            accept.capabilities.encode(writer)
+        },
+    )?;
+    let header = PacketHeader {
+        version: wire::VERSION,
+        flags: PacketFlags::session_accept(),
+        schema_hash: schema_hash(schema),
+        tick: accept.tick.raw(),
+        baseline_tick: 0,
+        payload_len: payload_len as u32,
+    };
+    wire::encode_header(&header, &mut out[..wire::HEADER_SIZE]).map_err(|_| {
+        CodecError::OutputTooSmall {
+            needed: wire::HEADER_SIZE,
+            available: out.len(),
+        }
+    })?;
+    Ok(wire::HEADER_SIZE + payload_len)
+}
+
+/// Decodes a session accept packet.
+///
+/// The packet must hold exactly one `SESSION_ACCEPT` section. Unknown
+/// capabilities in it are ignored.
+pub fn decode_session_accept_packet(
+    schema: &schema::Schema,
+    packet: &WirePacket<'_>,
+    limits: &CodecLimits,
+) -> CodecResult<SessionAccept> {
+    let header = packet.header;
+    if !header.flags.is_session_accept() {
+        return Err(CodecError::Wire(wire::DecodeError::InvalidFlags {
+            flags: header.flags.raw(),
+        }));
+    }
+    let expected_hash = schema_hash(schema);
+    if header.schema_hash != expected_hash {
+        return Err(CodecError::SchemaMismatch {
+            expected: expected_hash,
+            found: header.schema_hash,
+        });
+    }
+    let [section] = packet.sections.as_slice() else {
+        return Err(CodecError::SessionInitInvalid);
+    };
+    if section.tag != SectionTag::SessionAccept {
+        return Err(CodecError::UnexpectedSection {
+            section: section.tag,
+        });
+    }
+    if section.body.len() > limits.max_section_bytes {
+        return Err(CodecError::LimitsExceeded {
+            kind: crate::error::LimitKind::SectionBytes,
+            limit: limits.max_section_bytes,
+            actual: section.body.len(),
+        });
+    }
+    let mut reader = BitReader::new(section.body);
+    let session_id = reader.read_u64_aligned()?;
+    let capabilities = NegotiatedCaps::decode(&mut reader)?;
+    Ok(SessionAccept {
+        session_id: (session_id != 0).then_some(session_id),
+        tick: SnapshotTick::new(header.tick),
+        capabilities,
+    })
 }
 
 /// Decodes a compact packet using session state.
+///
+/// A packet that is not newer than `session.last_tick` (possible with
+/// [`CompactHeaderMode::SessionV2`]) fails with
+/// [`CodecError::SessionLatePacket`], which callers should drop rather than
+/// treat as a reason to resync.
 pub fn decode_session_packet<'a>(
     schema: &schema::Schema,
     session: &mut SessionState,
@@ -181,12 +459,22 @@ pub fn decode_session_packet<'a>(
             found: session.schema_hash,
         });
     }
-    let header =
-        wire::decode_session_header(bytes, session.last_tick.raw()).map_err(CodecError::Wire)?;
-    if header.tick <= session.last_tick.raw() {
-        return Err(CodecError::SessionOutOfOrder {
-            previous: session.last_tick.raw(),
-            current: header.tick,
+    let header = match session.compact_mode {
+        CompactHeaderMode::SessionV1 => wire::decode_session_header(bytes, session.last_tick.raw()),
+        CompactHeaderMode::SessionV2 => {
+            wire::decode_session_header_v2(bytes, session.last_tick.raw())
+        }
+    }
+    .map_err(CodecError::Wire)?;
+    if header.flags.is_ack() {
+        return Err(CodecError::Wire(wire::DecodeError::InvalidFlags {
+            flags: u16::from(header.flags.raw()),
+        }));
+    }
+    if !SnapshotTick::new(header.tick).is_after(session.last_tick) {
+        return Err(CodecError::SessionLatePacket {
+            latest: session.last_tick.raw(),
+            tick: header.tick,
         });
     }
 
@@ -199,7 +487,8 @@ pub fn decode_session_packet<'a>(
         }));
     }
     let payload = &bytes[payload_start..payload_end];
-    let sections = wire::decode_sections(payload, wire_limits).map_err(CodecError::Wire)?;
+    let (sections, skipped) =
+        wire::decode_sections_with_skipped(payload, wire_limits).map_err(CodecError::Wire)?;
 
     session.last_tick = SnapshotTick::new(header.tick);
     let flags = if header.flags.is_full_snapshot() {
@@ -217,6 +506,7 @@ pub fn decode_session_packet<'a>(
             payload_len: header.payload_len,
         },
         sections,
+        skipped,
     })
 }
 
@@ -240,8 +530,7 @@ mod tests {
         let bytes = encode_session_init_packet(
             &schema,
             SnapshotTick::new(5),
-            Some(42),
-            CompactHeaderMode::SessionV1,
+            &SessionInitOptions::new(CompactHeaderMode::SessionV1).session_id(Some(42)),
             &CodecLimits::for_testing(),
             &mut buf,
         )
@@ -251,6 +540,204 @@ mod tests {
             decode_session_init_packet(&schema, &packet, &CodecLimits::for_testing()).unwrap();
         assert_eq!(session.session_id, Some(42));
         assert_eq!(session.last_tick.raw(), 5);
+        assert_eq!(session.model_hash, None);
+        assert!(session.ensure_model(None).is_ok());
+    }
+
+    #[test]
+    fn session_init_announces_model() {
+        let schema = schema_one_bool();
+        let model = CodecModel {
+            schema_hash: schema_hash(&schema),
+            field_histograms: Vec::new(),
+            create_templates: Vec::new(),
+            dictionary: b"dictionary".to_vec(),
+        };
+        let mut buf = [0u8; 128];
+        let bytes = encode_session_init_packet(
+            &schema,
+            SnapshotTick::new(5),
+            &SessionInitOptions::new(CompactHeaderMode::SessionV1)
+                .session_id(Some(42))
+                .model(Some(&model)),
+            &CodecLimits::for_testing(),
+            &mut buf,
+        )
+        .unwrap();
+        let packet = wire::decode_packet(&buf[..bytes], &wire::Limits::for_testing()).unwrap();
+        let session =
+            decode_session_init_packet(&schema, &packet, &CodecLimits::for_testing()).unwrap();
+        assert_eq!(session.model_hash, Some(model.hash()));
+        assert!(session.ensure_model(Some(&model)).is_ok());
+        assert_eq!(
+            session.ensure_model(None),
+            Err(CodecError::ModelMismatch {
+                expected: model.hash(),
+                found: 0,
+            })
+        );
+        let mut other = model.clone();
+        other.dictionary.clear();
+        assert!(matches!(
+            session.ensure_model(Some(&other)),
+            Err(CodecError::ModelMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn session_init_carries_resume_token() {
+        let schema = schema_one_bool();
+        let model = CodecModel {
+            schema_hash: schema_hash(&schema),
+            field_histograms: Vec::new(),
+            create_templates: Vec::new(),
+            dictionary: Vec::new(),
+        };
+        let limits = CodecLimits::for_testing();
+        for model in [None, Some(&model)] {
+            let mut buf = [0u8; 128];
+            let bytes = encode_session_init_packet(
+                &schema,
+                SnapshotTick::new(9),
+                &SessionInitOptions::new(CompactHeaderMode::SessionV2)
+                    .session_id(Some(42))
+                    .model(model)
+                    .resume_token(Some(0xDEAD_BEEF)),
+                &limits,
+                &mut buf,
+            )
+            .unwrap();
+            let packet = wire::decode_packet(&buf[..bytes], &wire::Limits::for_testing()).unwrap();
+            let session = decode_session_init_packet(&schema, &packet, &limits).unwrap();
+            assert_eq!(session.resume_token, Some(0xDEAD_BEEF));
+            assert_eq!(session.model_hash, model.map(CodecModel::hash));
+            assert_eq!(session.compact_mode, CompactHeaderMode::SessionV2);
+            assert_eq!(session.last_tick, SnapshotTick::new(9));
+        }
+    }
+
+    #[test]
+    fn session_init_extensions() {
+        let limits = CodecLimits::for_testing();
+        let mut body = 42u64.to_le_bytes().to_vec();
+        body.push(CompactHeaderMode::SessionV1 as u8 | INIT_EXTENSIONS);
+        // An unknown extension is skipped.
+        body.extend_from_slice(&[0x7F, 3, 1, 2, 3]);
+        body.extend_from_slice(&[INIT_EXT_RESUME_TOKEN, 8]);
+        body.extend_from_slice(&5u64.to_le_bytes());
+        let decoded = decode_session_init_body(&body, &limits).unwrap();
+        assert_eq!(decoded.session_id, Some(42));
+        assert_eq!(decoded.resume_token, Some(5));
+        assert_eq!(decoded.model_hash, None);
+
+        // Known extensions must have their fixed size and appear once.
+        let mut duplicate = body.clone();
+        duplicate.extend_from_slice(&[INIT_EXT_RESUME_TOKEN, 8]);
+        duplicate.extend_from_slice(&6u64.to_le_bytes());
+        assert!(matches!(
+            decode_session_init_body(&duplicate, &limits),
+            Err(CodecError::SessionInitInvalid)
+        ));
+        let mut short = body[..9].to_vec();
+        short.extend_from_slice(&[INIT_EXT_MODEL_HASH, 4, 0, 0, 0, 0]);
+        assert!(matches!(
+            decode_session_init_body(&short, &limits),
+            Err(CodecError::SessionInitInvalid)
+        ));
+        let truncated = &body[..body.len() - 1];
+        assert!(decode_session_init_body(truncated, &limits).is_err());
+
+        // Without the extensions flag nothing may follow the mode.
+        let mut trailing = body[..8].to_vec();
+        trailing.push(CompactHeaderMode::SessionV1 as u8);
+        trailing.extend_from_slice(&7u64.to_le_bytes());
+        assert!(matches!(
+            decode_session_init_body(&trailing, &limits),
+            Err(CodecError::TrailingSectionData { .. })
+        ));
+    }
+
+    #[test]
+    fn session_init_lists_capabilities() {
+        let schema = schema_one_bool();
+        let limits = CodecLimits::for_testing();
+        let mut buf = [0u8; 128];
+        let bytes = encode_session_init_packet(
+            &schema,
+            SnapshotTick::new(3),
+            &SessionInitOptions::new(CompactHeaderMode::SessionV2)
+                .session_id(Some(42))
+                .capabilities(Some(NegotiatedCaps::all())),
+            &limits,
+            &mut buf,
+        )
+        .unwrap();
+        let packet = wire::decode_packet(&buf[..bytes], &wire::Limits::for_testing()).unwrap();
+        let session = decode_session_init_packet(&schema, &packet, &limits).unwrap();
+        assert_eq!(session.capabilities, Some(NegotiatedCaps::all()));
+        assert_eq!(session.resume_token, None);
+        assert_eq!(
+            session.negotiate(NegotiatedCaps::baseline()),
+            NegotiatedCaps::baseline()
+        );
+
+        // Without a list the sender predates negotiation.
+        let bytes = encode_session_init_packet(
+            &schema,
+            SnapshotTick::new(3),
+            &SessionInitOptions::new(CompactHeaderMode::SessionV2).session_id(Some(42)),
+            &limits,
+            &mut buf,
+        )
+        .unwrap();
+        let packet = wire::decode_packet(&buf[..bytes], &wire::Limits::for_testing()).unwrap();
+        let session = decode_session_init_packet(&schema, &packet, &limits).unwrap();
+        assert_eq!(session.capabilities, None);
+        assert_eq!(
+            session.negotiate(NegotiatedCaps::all()),
+            NegotiatedCaps::baseline()
+        );
+
+        // A list cut short by its extension length is invalid.
+        let mut body = 42u64.to_le_bytes().to_vec();
+        body.push(CompactHeaderMode::SessionV1 as u8 | INIT_EXTENSIONS);
+        body.extend_from_slice(&[INIT_EXT_CAPABILITIES, 3, 2, 1]);
+        assert!(matches!(
+            decode_session_init_body(&body, &limits),
+            Err(CodecError::SessionInitInvalid)
+        ));
+        // So is an entry running past the extension into the next one.
+        let mut body = body[..9].to_vec();
+        body.extend_from_slice(&[INIT_EXT_CAPABILITIES, 2, 2, 1, 0]);
+        body.extend_from_slice(&[INIT_EXT_RESUME_TOKEN, 8]);
+        body.extend_from_slice(&5u64.to_le_bytes());
+        assert!(matches!(
+            decode_session_init_body(&body, &limits),
+            Err(CodecError::SessionInitInvalid)
+        ));
+    }
+
+    #[test]
+    fn session_accept_roundtrip() {
+        let schema = schema_one_bool();
+        let limits = CodecLimits::for_testing();
+        let accept = SessionAccept {
+            session_id: Some(42),
+            tick: SnapshotTick::new(3),
+            capabilities: NegotiatedCaps::baseline(),
+        };
+        let mut buf = [0u8; 128];
+        let bytes = encode_session_accept_packet(&schema, &accept, &limits, &mut buf).unwrap();
+        let packet = wire::decode_packet(&buf[..bytes], &wire::Limits::for_testing()).unwrap();
+        assert!(packet.header.flags.is_session_accept());
+        assert_eq!(
+            decode_session_accept_packet(&schema, &packet, &limits).unwrap(),
+            accept
+        );
+        assert!(matches!(
+            decode_session_init_packet(&schema, &packet, &limits),
+            Err(CodecError::SessionMissing)
+        ));
     }
 
     #[test]
@@ -281,6 +768,9 @@ mod tests {
             session_id: Some(1),
             last_tick: baseline.tick,
             compact_mode: CompactHeaderMode::SessionV1,
+            model_hash: None,
+            resume_token: None,
+            capabilities: None,
         };
         let mut buf = [0u8; 256];
         let bytes = crate::delta::encode_delta_snapshot_for_client_session_with_scratch(
@@ -0,0 +1,299 @@
+//! Server-side baseline history shared by all clients.
+//!
+//! A per-client `BaselineStore<Snapshot>` keeps a full copy of the world for
+//! every tick and every client. [`SharedBaselineHistory`] stores each tick
+//! once, and consecutive ticks share unchanged entities through `Arc`, so
+//! entity data grows with the number of changes rather than with
+//! clients × ticks. Clients keep cheap [`SharedSnapshot`] handles to the
+//! baselines they were sent, and the encoders read those handles directly
+//! through [`SnapshotView`].
+
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+use crate::baseline::{BaselineError, BaselineStore};
+use crate::snapshot::{EntitySnapshot, Snapshot, SnapshotView};
+use crate::types::{EntityId, SnapshotTick};
+
+/// An immutable snapshot whose entities are reference-counted.
+///
+/// Cloning is a reference-count increment; entities are shared with the
+/// neighbouring ticks they did not change in.
+#[derive(Debug, Clone)]
+pub struct SharedSnapshot {
+    tick: SnapshotTick,
+    entities: Arc<[Arc<EntitySnapshot>]>,
+}
+
+impl SharedSnapshot {
+    /// Wraps `snapshot` without sharing any entities.
+    #[must_use]
+    pub fn new(snapshot: Snapshot) -> Self {
+        Self {
+            tick: snapshot.tick,
+            entities: snapshot.entities.into_iter().map(Arc::new).collect(),
+        }
+    }
+
+    /// Returns the snapshot tick.
+    #[must_use]
+    pub fn tick(&self) -> SnapshotTick {
+        self.tick
+    }
+
+    /// Returns the number of entities.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns `true` if the snapshot has no entities.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Returns the entity `id`, which must match both index and generation.
+    #[must_use]
+    pub fn get(&self, id: EntityId) -> Option<&EntitySnapshot> {
+        let idx = self
+            .entities
+            .binary_search_by_key(&id.index(), |entity| entity.id.index())
+            .ok()?;
+        let entity = &self.entities[idx];
+        (entity.id == id).then_some(entity.as_ref())
+    }
+
+    /// Returns the entities in order.
+    #[must_use]
+    pub fn entities(&self) -> &[Arc<EntitySnapshot>] {
+        &self.entities
+    }
+}
+
+impl SnapshotView for SharedSnapshot {
+    type Entity = Arc<EntitySnapshot>;
+
+    fn tick(&self) -> SnapshotTick {
+        self.tick
+    }
+
+    fn entities(&self) -> &[Arc<EntitySnapshot>] {
+        &self.entities
+    }
+}
+
+/// A fixed-capacity history of [`SharedSnapshot`]s keyed by tick.
+#[derive(Debug)]
+pub struct SharedBaselineHistory {
+    baselines: BaselineStore<SharedSnapshot>,
+}
+
+impl SharedBaselineHistory {
+    /// Creates an empty history holding at most `capacity` ticks.
+    #[must_use]
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            baselines: BaselineStore::new(capacity),
+        }
+    }
+
+    /// Inserts the snapshot for a new tick and returns a handle to it.
+    ///
+    /// Entities equal to the same entity (index and generation) in the newest
+    /// stored tick are shared with it instead of stored again. Entities must
+    /// be sorted by `EntityId` and ticks must be strictly increasing.
+    pub fn insert(&mut self, snapshot: Snapshot) -> Result<SharedSnapshot, BaselineError> {
+        let shared = match self.baselines.newest() {
+            Some((_, previous)) => share_unchanged(previous, snapshot),
+            None => SharedSnapshot::new(snapshot),
+        };
+        self.baselines.insert(shared.tick, shared.clone())?;
+        Ok(shared)
+    }
+
+    /// Returns a handle to the baseline for `tick`, if stored.
+    #[must_use]
+    pub fn get(&self, tick: SnapshotTick) -> Option<SharedSnapshot> {
+        self.baselines.get(tick).cloned()
+    }
+
+    /// Returns the underlying store, e.g. for [`crate::ClientAckTracker`].
+    #[must_use]
+    pub fn baselines(&self) -> &BaselineStore<SharedSnapshot> {
+        &self.baselines
+    }
+
+    /// Returns the underlying store mutably, e.g. for ack-driven eviction.
+    pub fn baselines_mut(&mut self) -> &mut BaselineStore<SharedSnapshot> {
+        &mut self.baselines
+    }
+
+    /// Returns the number of distinct entity allocations across all stored
+    /// ticks.
+    #[must_use]
+    pub fn unique_entity_count(&self) -> usize {
+        let mut seen = HashSet::new();
+        for (_, snapshot) in self.baselines.iter() {
+            seen.extend(snapshot.entities.iter().map(Arc::as_ptr));
+        }
+        seen.len()
+    }
+}
+
+/// Builds a shared snapshot from `current`, reusing `previous`'s entity
+/// allocations for entities that did not change.
+fn share_unchanged(previous: &SharedSnapshot, current: Snapshot) -> SharedSnapshot {
+    let mut prev = previous.entities.iter().peekable();
+    let entities = current
+        .entities
+        .into_iter()
+        .map(|entity| {
+            while prev
+                .next_if(|candidate| candidate.id.index() < entity.id.index())
+                .is_some()
+            {}
+            match prev.peek() {
+                Some(candidate) if ***candidate == entity => Arc::clone(candidate),
+                _ => Arc::new(entity),
+            }
+        })
+        .collect();
+    SharedSnapshot {
+        tick: current.tick,
+        entities,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::delta::encode_delta_snapshot;
+    use crate::limits::CodecLimits;
+    use crate::snapshot::{
+        encode_full_snapshot, encode_full_snapshot_from_view, ComponentSnapshot, FieldValue,
+    };
+    use schema::{ComponentDef, ComponentId, FieldCodec, FieldDef, FieldId, Schema};
+
+    fn entity(id: u64, value: u64) -> EntitySnapshot {
+        EntitySnapshot {
+            id: EntityId::new(id),
+            components: vec![ComponentSnapshot {
+                id: ComponentId::new(1).unwrap(),
+                fields: vec![FieldValue::UInt(value)],
+            }],
+        }
+    }
+
+    fn snapshot(tick: u32, entities: Vec<EntitySnapshot>) -> Snapshot {
+        Snapshot {
+            tick: SnapshotTick::new(tick),
+            entities,
+        }
+    }
+
+    #[test]
+    fn consecutive_ticks_share_unchanged_entities() {
+        let mut history = SharedBaselineHistory::new(NonZeroUsize::new(4).unwrap());
+        let first = history
+            .insert(snapshot(
+                1,
+                vec![entity(1, 10), entity(2, 20), entity(3, 30)],
+            ))
+            .unwrap();
+        let second = history
+            .insert(snapshot(
+                2,
+                vec![entity(1, 10), entity(3, 31), entity(4, 40)],
+            ))
+            .unwrap();
+
+        assert!(Arc::ptr_eq(&first.entities[0], &second.entities[0]));
+        assert!(!Arc::ptr_eq(&first.entities[2], &second.entities[1]));
+        assert_eq!(history.unique_entity_count(), 5);
+
+        assert_eq!(second.len(), 3);
+        assert_eq!(second.get(EntityId::new(3)), Some(&entity(3, 31)));
+        assert_eq!(second.get(EntityId::new(2)), None);
+        assert_eq!(second.get(EntityId::with_generation(1, 1)), None);
+        let entities: Vec<_> = second.entities().iter().map(|e| (**e).clone()).collect();
+        assert_eq!(entities, vec![entity(1, 10), entity(3, 31), entity(4, 40)]);
+    }
+
+    #[test]
+    fn generation_change_is_not_shared() {
+        let mut history = SharedBaselineHistory::new(NonZeroUsize::new(2).unwrap());
+        let first = history.insert(snapshot(1, vec![entity(1, 10)])).unwrap();
+        let mut reused = entity(1, 10);
+        reused.id = EntityId::with_generation(1, 1);
+        let second = history.insert(snapshot(2, vec![reused])).unwrap();
+        assert!(!Arc::ptr_eq(&first.entities[0], &second.entities[0]));
+    }
+
+    #[test]
+    fn client_handles_outlive_eviction() {
+        let mut history = SharedBaselineHistory::new(NonZeroUsize::new(2).unwrap());
+        let held = history.insert(snapshot(1, vec![entity(1, 10)])).unwrap();
+        history.insert(snapshot(2, vec![entity(1, 11)])).unwrap();
+        history.insert(snapshot(3, vec![entity(1, 12)])).unwrap();
+
+        assert!(history.get(SnapshotTick::new(1)).is_none());
+        assert_eq!(held.get(EntityId::new(1)), Some(&entity(1, 10)));
+
+        assert_eq!(
+            history.baselines_mut().remove_before(SnapshotTick::new(3)),
+            1
+        );
+        assert_eq!(history.baselines().len(), 1);
+        assert!(history.insert(snapshot(3, Vec::new())).is_err());
+    }
+
+    #[test]
+    fn encoders_read_shared_snapshots_in_place() {
+        let schema = Schema::new(vec![ComponentDef::new(ComponentId::new(1).unwrap())
+            .field(FieldDef::new(FieldId::new(1).unwrap(), FieldCodec::uint(8)))])
+        .unwrap();
+        let limits = CodecLimits::for_testing();
+        let owned_baseline = snapshot(1, vec![entity(1, 10), entity(2, 20)]);
+        let current = snapshot(2, vec![entity(1, 11), entity(3, 30)]);
+        let mut history = SharedBaselineHistory::new(NonZeroUsize::new(2).unwrap());
+        let baseline = history.insert(owned_baseline.clone()).unwrap();
+
+        let mut shared_out = [0u8; 256];
+        let mut owned_out = [0u8; 256];
+        let shared_len = encode_delta_snapshot(
+            &schema,
+            current.tick,
+            baseline.tick(),
+            &baseline,
+            &current,
+            &limits,
+            &mut shared_out,
+        )
+        .unwrap();
+        let owned_len = encode_delta_snapshot(
+            &schema,
+            current.tick,
+            owned_baseline.tick,
+            &owned_baseline,
+            &current,
+            &limits,
+            &mut owned_out,
+        )
+        .unwrap();
+        assert_eq!(shared_out[..shared_len], owned_out[..owned_len]);
+
+        let shared_len =
+            encode_full_snapshot_from_view(&schema, &baseline, &limits, &mut shared_out).unwrap();
+        let owned_len = encode_full_snapshot(
+            &schema,
+            owned_baseline.tick,
+            &owned_baseline.entities,
+            &limits,
+            &mut owned_out,
+        )
+        .unwrap();
+        assert_eq!(shared_out[..shared_len], owned_out[..owned_len]);
+    }
+}
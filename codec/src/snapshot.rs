@@ -1,14 +1,17 @@
 //! Full snapshot encoding/decoding.
 
+use std::borrow::Borrow;
+
 use bitstream::{BitReader, BitWriter};
 use schema::{schema_hash, ComponentDef, ComponentId, FieldCodec, FieldDef, FieldId};
 use wire::{decode_packet, encode_header, SectionTag, WirePacket};
 
 use crate::error::{CodecError, CodecResult, LimitKind, MaskKind, MaskReason, ValueReason};
+use crate::ids::{IdGapReader, IdGapWriter, IdRun};
 use crate::limits::CodecLimits;
 use crate::types::{EntityId, SnapshotTick};
 
-const VARINT_MAX_BYTES: usize = 5;
+pub(crate) const VARINT_MAX_BYTES: usize = 5;
 
 /// A decoded snapshot.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -17,6 +20,34 @@ pub struct Snapshot {
     pub entities: Vec<EntitySnapshot>,
 }
 
+/// Read access to a snapshot's tick and its entities sorted by `EntityId`.
+///
+/// The snapshot encoders take any view, so baselines held as
+/// [`SharedSnapshot`](crate::SharedSnapshot) are encoded in place instead of
+/// being copied into a `Snapshot` first.
+pub trait SnapshotView {
+    /// How each entity is stored: inline or behind a shared pointer.
+    type Entity: Borrow<EntitySnapshot>;
+
+    /// Returns the snapshot tick.
+    fn tick(&self) -> SnapshotTick;
+
+    /// Returns the entities in order.
+    fn entities(&self) -> &[Self::Entity];
+}
+
+impl SnapshotView for Snapshot {
+    type Entity = EntitySnapshot;
+
+    fn tick(&self) -> SnapshotTick {
+        self.tick
+    }
+
+    fn entities(&self) -> &[EntitySnapshot] {
+        &self.entities
+    }
+}
+
 /// An entity snapshot.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct EntitySnapshot {
@@ -43,6 +74,230 @@ pub enum FieldValue {
     FixedPoint(i64),
 }
 
+impl Snapshot {
+    /// Returns the entity `id`, which must match both index and generation.
+    ///
+    /// Entities must be sorted by `EntityId`; the lookup is a binary search.
+    #[must_use]
+    pub fn get(&self, id: EntityId) -> Option<&EntitySnapshot> {
+        self.position(id).ok().map(|idx| &self.entities[idx])
+    }
+
+    /// Returns the entity `id` mutably. See [`Snapshot::get`].
+    #[must_use]
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut EntitySnapshot> {
+        self.position(id).ok().map(|idx| &mut self.entities[idx])
+    }
+
+    /// Returns component `component` of entity `entity`.
+    #[must_use]
+    pub fn get_component(
+        &self,
+        entity: EntityId,
+        component: ComponentId,
+    ) -> Option<&ComponentSnapshot> {
+        self.get(entity)?.component(component)
+    }
+
+    /// Returns the value of field `field` in `component` of `entity`.
+    pub fn field(
+        &self,
+        schema: &schema::Schema,
+        entity: EntityId,
+        component: ComponentId,
+        field: FieldId,
+    ) -> CodecResult<FieldValue> {
+        let index = field_index(schema, component, field)?;
+        let snapshot = self
+            .get(entity)
+            .ok_or(CodecError::EntityNotFound {
+                entity_id: entity.raw(),
+            })?
+            .component(component)
+            .ok_or(CodecError::ComponentNotFound {
+                entity_id: entity.raw(),
+                component_id: component.get(),
+            })?;
+        snapshot
+            .fields
+            .get(index)
+            .copied()
+            .ok_or(CodecError::InvalidMask {
+                kind: MaskKind::FieldMask { component },
+                reason: MaskReason::MissingField { field },
+            })
+    }
+
+    /// Sets field `field` in `component` of `entity`, checking the value
+    /// against the field's codec.
+    pub fn set_field(
+        &mut self,
+        schema: &schema::Schema,
+        entity: EntityId,
+        component: ComponentId,
+        field: FieldId,
+        value: FieldValue,
+    ) -> CodecResult<()> {
+        let index = field_index(schema, component, field)?;
+        let def = find_component_def(schema, component)?;
+        validate_field_value(component, def.fields[index], value)?;
+        let snapshot = self
+            .get_mut(entity)
+            .ok_or(CodecError::EntityNotFound {
+                entity_id: entity.raw(),
+            })?
+            .component_mut(component)
+            .ok_or(CodecError::ComponentNotFound {
+                entity_id: entity.raw(),
+                component_id: component.get(),
+            })?;
+        let slot = snapshot
+            .fields
+            .get_mut(index)
+            .ok_or(CodecError::InvalidMask {
+                kind: MaskKind::FieldMask { component },
+                reason: MaskReason::MissingField { field },
+            })?;
+        *slot = value;
+        Ok(())
+    }
+
+    /// Inserts `entity`, keeping entities sorted by `EntityId`.
+    ///
+    /// Every component must be known to the schema, appear once and carry a
+    /// valid value for each of its fields. Fails if an entity with the same
+    /// index is already present.
+    pub fn insert_entity(
+        &mut self,
+        schema: &schema::Schema,
+        entity: EntitySnapshot,
+    ) -> CodecResult<()> {
+        validate_entity(schema, &entity)?;
+        let idx = match self
+            .entities
+            .binary_search_by_key(&entity.id.index(), |existing| existing.id.index())
+        {
+            Ok(_) => {
+                return Err(CodecError::EntityAlreadyExists {
+                    entity_id: entity.id.raw(),
+                })
+            }
+            Err(idx) => idx,
+        };
+        self.entities.insert(idx, entity);
+        Ok(())
+    }
+
+    /// Removes and returns the entity `id`, if present with that generation.
+    pub fn remove_entity(&mut self, id: EntityId) -> Option<EntitySnapshot> {
+        let idx = self.position(id).ok()?;
+        Some(self.entities.remove(idx))
+    }
+
+    fn position(&self, id: EntityId) -> Result<usize, usize> {
+        let idx = self
+            .entities
+            .binary_search_by_key(&id.index(), |entity| entity.id.index())?;
+        if self.entities[idx].id == id {
+            Ok(idx)
+        } else {
+            Err(idx)
+        }
+    }
+}
+
+impl EntitySnapshot {
+    /// Returns the component with `id`, if present.
+    #[must_use]
+    pub fn component(&self, id: ComponentId) -> Option<&ComponentSnapshot> {
+        self.components.iter().find(|c| c.id == id)
+    }
+
+    /// Returns the component with `id` mutably, if present.
+    #[must_use]
+    pub fn component_mut(&mut self, id: ComponentId) -> Option<&mut ComponentSnapshot> {
+        self.components.iter_mut().find(|c| c.id == id)
+    }
+}
+
+pub(crate) fn find_component_def(
+    schema: &schema::Schema,
+    id: ComponentId,
+) -> CodecResult<&ComponentDef> {
+    schema
+        .components
+        .iter()
+        .find(|component| component.id == id)
+        .ok_or(CodecError::InvalidMask {
+            kind: MaskKind::ComponentMask,
+            reason: MaskReason::UnknownComponent { component: id },
+        })
+}
+
+fn field_index(
+    schema: &schema::Schema,
+    component: ComponentId,
+    field: FieldId,
+) -> CodecResult<usize> {
+    find_component_def(schema, component)?
+        .fields
+        .iter()
+        .position(|def| def.id == field)
+        .ok_or(CodecError::FieldNotFound {
+            component_id: component.get(),
+            field_id: field.get(),
+        })
+}
+
+fn validate_entity(schema: &schema::Schema, entity: &EntitySnapshot) -> CodecResult<()> {
+    for (idx, component) in entity.components.iter().enumerate() {
+        let def = find_component_def(schema, component.id)?;
+        if entity.components[..idx]
+            .iter()
+            .any(|previous| previous.id == component.id)
+        {
+            return Err(CodecError::InvalidMask {
+                kind: MaskKind::ComponentMask,
+                reason: MaskReason::UnorderedComponent {
+                    component: component.id,
+                },
+            });
+        }
+        if component.fields.len() != def.fields.len() {
+            return Err(CodecError::InvalidMask {
+                kind: MaskKind::FieldMask {
+                    component: component.id,
+                },
+                reason: MaskReason::FieldCountMismatch {
+                    expected: def.fields.len(),
+                    actual: component.fields.len(),
+                },
+            });
+        }
+        for (field, value) in def.fields.iter().zip(&component.fields) {
+            validate_field_value(component.id, *field, *value)?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks `value` against the field codec by encoding it into a scratch
+/// buffer, so validation matches the encoder exactly.
+fn validate_field_value(
+    component_id: ComponentId,
+    field: FieldDef,
+    value: FieldValue,
+) -> CodecResult<()> {
+    // Widest encoding: a 64-bit value, or alignment plus a varint.
+    let mut scratch = [0u8; 16];
+    write_field_value(
+        component_id,
+        field,
+        value,
+        &mut BitWriter::new(&mut scratch),
+    )
+}
+
 /// Encodes a full snapshot into the provided output buffer.
 ///
 /// Entities must be in deterministic order (ascending `EntityId` recommended).
@@ -52,6 +307,29 @@ pub fn encode_full_snapshot(
     entities: &[EntitySnapshot],
     limits: &CodecLimits,
     out: &mut [u8],
+) -> CodecResult<usize> {
+    encode_full_snapshot_entities(schema, tick, entities, limits, out)
+}
+
+/// Encodes a full snapshot of `snapshot` at its tick.
+///
+/// Unlike [`encode_full_snapshot`] this reads the entities in place, so a
+/// [`SharedSnapshot`](crate::SharedSnapshot) is encoded without copying it.
+pub fn encode_full_snapshot_from_view<S: SnapshotView>(
+    schema: &schema::Schema,
+    snapshot: &S,
+    limits: &CodecLimits,
+    out: &mut [u8],
+) -> CodecResult<usize> {
+    encode_full_snapshot_entities(schema, snapshot.tick(), snapshot.entities(), limits, out)
+}
+
+fn encode_full_snapshot_entities<E: Borrow<EntitySnapshot>>(
+    schema: &schema::Schema,
+    tick: SnapshotTick,
+    entities: &[E],
+    limits: &CodecLimits,
+    out: &mut [u8],
 ) -> CodecResult<usize> {
     if out.len() < wire::HEADER_SIZE {
         return Err(CodecError::OutputTooSmall {
@@ -110,25 +388,7 @@ pub fn decode_full_snapshot_from_packet(
     limits: &CodecLimits,
 ) -> CodecResult<Snapshot> {
     let header = packet.header;
-    if !header.flags.is_full_snapshot() {
-        return Err(CodecError::Wire(wire::DecodeError::InvalidFlags {
-            flags: header.flags.raw(),
-        }));
-    }
-    if header.baseline_tick != 0 {
-        return Err(CodecError::Wire(wire::DecodeError::InvalidBaselineTick {
-            baseline_tick: header.baseline_tick,
-            flags: header.flags.raw(),
-        }));
-    }
-
-    let expected_hash = schema_hash(schema);
-    if header.schema_hash != expected_hash {
-        return Err(CodecError::SchemaMismatch {
-            expected: expected_hash,
-            found: header.schema_hash,
-        });
-    }
+    validate_full_snapshot_header(schema, &header)?;
 
     let mut entities: Vec<EntitySnapshot> = Vec::new();
     let mut create_seen = false;
@@ -144,6 +404,8 @@ pub fn decode_full_snapshot_from_packet(
                 let decoded = decode_create_section(schema, section.body, limits)?;
                 entities = decoded;
             }
+            // Events ride alongside state and are decoded by `decode_events`.
+            SectionTag::Events => {}
             _ => {
                 return Err(CodecError::UnexpectedSection {
                     section: section.tag,
@@ -158,6 +420,32 @@ pub fn decode_full_snapshot_from_packet(
     })
 }
 
+pub(crate) fn validate_full_snapshot_header(
+    schema: &schema::Schema,
+    header: &wire::PacketHeader,
+) -> CodecResult<()> {
+    if !header.flags.is_full_snapshot() {
+        return Err(CodecError::Wire(wire::DecodeError::InvalidFlags {
+            flags: header.flags.raw(),
+        }));
+    }
+    if header.baseline_tick != 0 {
+        return Err(CodecError::Wire(wire::DecodeError::InvalidBaselineTick {
+            baseline_tick: header.baseline_tick,
+            flags: header.flags.raw(),
+        }));
+    }
+
+    let expected_hash = schema_hash(schema);
+    if header.schema_hash != expected_hash {
+        return Err(CodecError::SchemaMismatch {
+            expected: expected_hash,
+            found: header.schema_hash,
+        });
+    }
+    Ok(())
+}
+
 pub(crate) fn write_section<F>(
     tag: SectionTag,
     out: &mut [u8],
@@ -210,9 +498,9 @@ where
     Ok(total_needed)
 }
 
-fn encode_create_body(
+pub(crate) fn encode_create_body<E: Borrow<EntitySnapshot>>(
     schema: &schema::Schema,
-    entities: &[EntitySnapshot],
+    entities: &[E],
     limits: &CodecLimits,
     writer: &mut BitWriter<'_>,
 ) -> CodecResult<()> {
@@ -227,8 +515,8 @@ fn encode_create_body(
     writer.align_to_byte()?;
     writer.write_varu32(entities.len() as u32)?;
 
-    let mut prev_id: Option<u32> = None;
-    for entity in entities {
+    let mut prev_id: Option<u64> = None;
+    for entity in entities.iter().map(Borrow::borrow) {
         if let Some(prev) = prev_id {
             if entity.id.raw() <= prev {
                 return Err(CodecError::InvalidEntityOrder {
@@ -238,9 +526,13 @@ fn encode_create_body(
             }
         }
         prev_id = Some(entity.id.raw());
+    }
 
+    let run = IdRun::from_ids(entities.iter().map(|entity| entity.borrow().id));
+    let mut ids = IdGapWriter::begin(&run, schema.entity_id_width, writer)?;
+    for entity in entities.iter().map(Borrow::borrow) {
         writer.align_to_byte()?;
-        writer.write_u32_aligned(entity.id.raw())?;
+        ids.write(entity.id, writer)?;
 
         if entity.components.len() > limits.max_components_per_entity {
             return Err(CodecError::LimitsExceeded {
@@ -255,7 +547,7 @@ fn encode_create_body(
         write_component_mask(schema, entity, writer)?;
 
         for component in schema.components.iter() {
-            if let Some(snapshot) = find_component(entity, component.id) {
+            if let Some(snapshot) = entity.component(component.id) {
                 write_component_fields(component, snapshot, limits, writer)?;
             }
         }
@@ -271,7 +563,7 @@ fn write_component_mask(
     writer: &mut BitWriter<'_>,
 ) -> CodecResult<()> {
     for component in &schema.components {
-        let present = find_component(entity, component.id).is_some();
+        let present = entity.component(component.id).is_some();
         writer.write_bit(present)?;
     }
     Ok(())
@@ -461,7 +753,7 @@ pub(crate) fn write_field_value_sparse(
     Ok(())
 }
 
-fn decode_create_section(
+pub(crate) fn decode_create_section(
     schema: &schema::Schema,
     body: &[u8],
     limits: &CodecLimits,
@@ -495,19 +787,10 @@ fn decode_create_section(
     }
 
     let mut entities = Vec::with_capacity(count);
-    let mut prev_id: Option<u32> = None;
+    let mut ids = IdGapReader::begin(&mut reader, count, schema.entity_id_width)?;
     for _ in 0..count {
         reader.align_to_byte()?;
-        let entity_id = reader.read_u32_aligned()?;
-        if let Some(prev) = prev_id {
-            if entity_id <= prev {
-                return Err(CodecError::InvalidEntityOrder {
-                    previous: prev,
-                    current: entity_id,
-                });
-            }
-        }
-        prev_id = Some(entity_id);
+        let entity_id = ids.read(&mut reader)?;
 
         let component_mask = read_mask(
             &mut reader,
@@ -527,7 +810,7 @@ fn decode_create_section(
         }
 
         entities.push(EntitySnapshot {
-            id: EntityId::new(entity_id),
+            id: entity_id,
             components,
         });
     }
@@ -724,10 +1007,6 @@ pub(crate) fn ensure_known_components(
     Ok(())
 }
 
-fn find_component(entity: &EntitySnapshot, id: ComponentId) -> Option<&ComponentSnapshot> {
-    entity.components.iter().find(|c| c.id == id)
-}
-
 fn validate_uint(
     component_id: ComponentId,
     field_id: FieldId,
@@ -795,7 +1074,7 @@ pub(crate) fn required_bits(range: u64) -> u8 {
     (64 - range.leading_zeros()) as u8
 }
 
-fn codec_name(codec: FieldCodec) -> &'static str {
+pub(crate) fn codec_name(codec: FieldCodec) -> &'static str {
     match codec {
         FieldCodec::Bool => "bool",
         FieldCodec::UInt { .. } => "uint",
@@ -806,7 +1085,7 @@ fn codec_name(codec: FieldCodec) -> &'static str {
     }
 }
 
-fn value_name(value: FieldValue) -> &'static str {
+pub(crate) fn value_name(value: FieldValue) -> &'static str {
     match value {
         FieldValue::Bool(_) => "bool",
         FieldValue::UInt(_) => "uint",
@@ -863,6 +1142,133 @@ mod tests {
         Schema::new(vec![component]).unwrap()
     }
 
+    fn bool_uint_entity(id: u64, flag: bool, value: u64) -> EntitySnapshot {
+        EntitySnapshot {
+            id: EntityId::new(id),
+            components: vec![ComponentSnapshot {
+                id: ComponentId::new(1).unwrap(),
+                fields: vec![FieldValue::Bool(flag), FieldValue::UInt(value)],
+            }],
+        }
+    }
+
+    #[test]
+    fn snapshot_indexed_queries_and_mutations() {
+        let schema = schema_bool_uint10();
+        let component = ComponentId::new(1).unwrap();
+        let value_field = FieldId::new(2).unwrap();
+        let mut snapshot = Snapshot {
+            tick: SnapshotTick::new(1),
+            entities: Vec::new(),
+        };
+        for id in [5, 1, 3] {
+            snapshot
+                .insert_entity(&schema, bool_uint_entity(id, false, id))
+                .unwrap();
+        }
+        let ids: Vec<_> = snapshot.entities.iter().map(|e| e.id.raw()).collect();
+        assert_eq!(ids, vec![1, 3, 5]);
+
+        assert_eq!(
+            snapshot.field(&schema, EntityId::new(3), component, value_field),
+            Ok(FieldValue::UInt(3))
+        );
+        snapshot
+            .set_field(
+                &schema,
+                EntityId::new(3),
+                component,
+                value_field,
+                FieldValue::UInt(700),
+            )
+            .unwrap();
+        assert_eq!(
+            snapshot.get_component(EntityId::new(3), component),
+            Some(&ComponentSnapshot {
+                id: component,
+                fields: vec![FieldValue::Bool(false), FieldValue::UInt(700)],
+            })
+        );
+
+        // Lookups match the generation as well as the index.
+        assert!(snapshot.get(EntityId::with_generation(3, 1)).is_none());
+        assert!(snapshot
+            .remove_entity(EntityId::with_generation(3, 1))
+            .is_none());
+        assert_eq!(
+            snapshot.remove_entity(EntityId::new(3)).map(|e| e.id),
+            Some(EntityId::new(3))
+        );
+        assert!(snapshot.get(EntityId::new(3)).is_none());
+    }
+
+    #[test]
+    fn snapshot_api_validates_against_schema() {
+        let schema = schema_bool_uint10();
+        let component = ComponentId::new(1).unwrap();
+        let mut snapshot = Snapshot {
+            tick: SnapshotTick::new(1),
+            entities: vec![bool_uint_entity(1, false, 0)],
+        };
+
+        assert!(matches!(
+            snapshot.set_field(
+                &schema,
+                EntityId::new(1),
+                component,
+                FieldId::new(2).unwrap(),
+                FieldValue::UInt(1 << 10),
+            ),
+            Err(CodecError::InvalidValue {
+                reason: ValueReason::UnsignedOutOfRange { bits: 10, .. },
+                ..
+            })
+        ));
+        assert!(matches!(
+            snapshot.set_field(
+                &schema,
+                EntityId::new(1),
+                component,
+                FieldId::new(1).unwrap(),
+                FieldValue::UInt(1),
+            ),
+            Err(CodecError::InvalidValue {
+                reason: ValueReason::TypeMismatch { .. },
+                ..
+            })
+        ));
+        assert_eq!(
+            snapshot.field(
+                &schema,
+                EntityId::new(1),
+                component,
+                FieldId::new(9).unwrap()
+            ),
+            Err(CodecError::FieldNotFound {
+                component_id: 1,
+                field_id: 9,
+            })
+        );
+        assert_eq!(
+            snapshot.field(
+                &schema,
+                EntityId::new(2),
+                component,
+                FieldId::new(1).unwrap()
+            ),
+            Err(CodecError::EntityNotFound { entity_id: 2 })
+        );
+        assert_eq!(
+            snapshot.insert_entity(&schema, bool_uint_entity(1, true, 0)),
+            Err(CodecError::EntityAlreadyExists { entity_id: 1 })
+        );
+        assert!(matches!(
+            snapshot.insert_entity(&schema, bool_uint_entity(2, true, 1 << 10)),
+            Err(CodecError::InvalidValue { .. })
+        ));
+        assert_eq!(snapshot.entities.len(), 1);
+    }
+
     #[test]
     fn full_snapshot_roundtrip_minimal() {
         let schema = schema_one_bool();
@@ -924,12 +1330,48 @@ mod tests {
         expected.extend_from_slice(&0x32F5_A224_657B_EE15u64.to_le_bytes());
         expected.extend_from_slice(&1u32.to_le_bytes());
         expected.extend_from_slice(&0u32.to_le_bytes());
-        expected.extend_from_slice(&8u32.to_le_bytes());
-        expected.extend_from_slice(&[SectionTag::EntityCreate as u8, 6, 1, 1, 0, 0, 0, 0xE0]);
+        expected.extend_from_slice(&6u32.to_le_bytes());
+        // count, first_id, gap_bits, then the component/field masks.
+        expected.extend_from_slice(&[SectionTag::EntityCreate as u8, 4, 1, 1, 0, 0xE0]);
 
         assert_eq!(&buf[..bytes], expected.as_slice());
     }
 
+    #[test]
+    fn full_snapshot_golden_with_optional_section_from_later_version() {
+        let schema = schema_one_bool();
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&wire::MAGIC.to_le_bytes());
+        bytes.extend_from_slice(&wire::VERSION.to_le_bytes());
+        bytes.extend_from_slice(&wire::PacketFlags::full_snapshot().raw().to_le_bytes());
+        bytes.extend_from_slice(&0x32F5_A224_657B_EE15u64.to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&10u32.to_le_bytes());
+        bytes.extend_from_slice(&[0xC1, 2, 0xDE, 0xAD]);
+        bytes.extend_from_slice(&[SectionTag::EntityCreate as u8, 4, 1, 1, 0, 0xE0]);
+
+        let limits = CodecLimits::for_testing();
+        let snapshot =
+            decode_full_snapshot(&schema, &bytes, &wire::Limits::for_testing(), &limits).unwrap();
+        assert_eq!(snapshot.entities.len(), 1);
+        assert_eq!(
+            snapshot.entities[0].components[0].fields,
+            vec![FieldValue::Bool(true)]
+        );
+
+        bytes[wire::HEADER_SIZE] = 0x41;
+        assert!(matches!(
+            decode_full_snapshot(&schema, &bytes, &wire::Limits::for_testing(), &limits),
+            Err(CodecError::Wire(
+                wire::DecodeError::UnknownCriticalSection {
+                    tag: 0x41,
+                    offset: 0
+                }
+            ))
+        ));
+    }
+
     #[test]
     fn full_snapshot_golden_fixture_two_fields() {
         let schema = schema_bool_uint10();
@@ -958,8 +1400,8 @@ mod tests {
         expected.extend_from_slice(&0x57B2_2433_26F2_2706u64.to_le_bytes());
         expected.extend_from_slice(&1u32.to_le_bytes());
         expected.extend_from_slice(&0u32.to_le_bytes());
-        expected.extend_from_slice(&9u32.to_le_bytes());
-        expected.extend_from_slice(&[SectionTag::EntityCreate as u8, 7, 1, 1, 0, 0, 0, 0xF8, 0x04]);
+        expected.extend_from_slice(&7u32.to_le_bytes());
+        expected.extend_from_slice(&[SectionTag::EntityCreate as u8, 5, 1, 1, 0, 0xF8, 0x04]);
 
         assert_eq!(&buf[..bytes], expected.as_slice());
     }
@@ -987,8 +1429,8 @@ mod tests {
 
         // Add a trailing padding byte to the section body and patch lengths.
         let mut extra = buf[..bytes].to_vec();
-        extra[wire::HEADER_SIZE + 1] = 7; // section length varint
-        let payload_len = 9u32;
+        extra[wire::HEADER_SIZE + 1] = 5; // section length varint
+        let payload_len = 7u32;
         extra[24..28].copy_from_slice(&payload_len.to_le_bytes());
         extra.push(0);
 
@@ -1126,7 +1568,7 @@ mod tests {
 
         // Flip the field mask bit off (component mask stays on).
         let payload_start = wire::HEADER_SIZE;
-        let mask_offset = payload_start + 2 + 1 + 4; // tag + len + count + entity_id
+        let mask_offset = payload_start + 2 + 1 + 2; // tag + len + count + id header
         buf[mask_offset] &= 0b1011_1111;
 
         let err = decode_full_snapshot(
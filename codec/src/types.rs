@@ -1,8 +1,17 @@
 //! Core types for the codec.
 
+use std::cmp::Ordering;
+use std::num::TryFromIntError;
+
+use schema::EntityIdWidth;
+
 /// A simulation tick number.
 ///
-/// Ticks are monotonically increasing identifiers for simulation states.
+/// Ticks are monotonically increasing identifiers for simulation states. They
+/// wrap around at `u32::MAX`, so recency is decided with serial-number
+/// arithmetic (RFC 1982): [`Self::is_after`] and [`Self::is_before`] hold
+/// across the wrap as long as the ticks are less than 2^31 apart. The derived
+/// `Ord` compares raw values and is only meant for sorting and map keys.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct SnapshotTick(u32);
 
@@ -24,8 +33,59 @@ impl SnapshotTick {
     pub const fn is_zero(self) -> bool {
         self.0 == 0
     }
+
+    /// Returns the tick after this one, wrapping from `u32::MAX` to 1.
+    ///
+    /// Tick 0 is skipped because the wire format uses it for "no baseline".
+    #[must_use]
+    pub const fn next(self) -> Self {
+        match self.0.wrapping_add(1) {
+            0 => Self(1),
+            next => Self(next),
+        }
+    }
+
+    /// Returns the number of ticks from `earlier` to this tick, modulo 2^32.
+    #[must_use]
+    pub const fn since(self, earlier: Self) -> u32 {
+        self.0.wrapping_sub(earlier.0)
+    }
+
+    /// Returns `true` if this tick is newer than `other` in serial-number
+    /// order, i.e. at most 2^31 - 1 ticks ahead of it.
+    #[must_use]
+    pub const fn is_after(self, other: Self) -> bool {
+        let distance = self.since(other);
+        distance != 0 && distance < SERIAL_HALF
+    }
+
+    /// Returns `true` if this tick is older than `other` in serial-number
+    /// order.
+    #[must_use]
+    pub const fn is_before(self, other: Self) -> bool {
+        other.is_after(self)
+    }
+
+    /// Compares two ticks in serial-number order.
+    ///
+    /// Returns `None` for ticks exactly 2^31 apart, whose order is undefined.
+    #[must_use]
+    pub const fn serial_cmp(self, other: Self) -> Option<Ordering> {
+        if self.0 == other.0 {
+            Some(Ordering::Equal)
+        } else if self.is_after(other) {
+            Some(Ordering::Greater)
+        } else if other.is_after(self) {
+            Some(Ordering::Less)
+        } else {
+            None
+        }
+    }
 }
 
+/// Half the tick space; ticks this far apart have no serial order.
+const SERIAL_HALF: u32 = 1 << 31;
+
 impl From<u32> for SnapshotTick {
     fn from(tick: u32) -> Self {
         Self(tick)
@@ -41,33 +101,95 @@ impl From<SnapshotTick> for u32 {
 /// A stable entity identifier.
 ///
 /// Entity IDs are assigned by the simulation layer and must remain stable
-/// for the lifetime of an entity.
+/// for the lifetime of an entity. An id is an index plus a generation: an
+/// index may be reused for a new entity once the old one is gone, with a
+/// bumped generation so stale references can be told apart (see
+/// [`EntityIdAllocator`](crate::EntityIdAllocator)). Ordering is by index,
+/// then generation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
-pub struct EntityId(u32);
+pub struct EntityId {
+    index: u64,
+    generation: u16,
+}
 
 impl EntityId {
-    /// Creates a new entity ID.
+    /// Creates a new entity ID with generation 0.
     #[must_use]
-    pub const fn new(id: u32) -> Self {
-        Self(id)
+    pub const fn new(index: u64) -> Self {
+        Self {
+            index,
+            generation: 0,
+        }
     }
 
-    /// Returns the raw entity ID value.
+    /// Creates an entity ID with an explicit generation.
     #[must_use]
-    pub const fn raw(self) -> u32 {
-        self.0
+    pub const fn with_generation(index: u64, generation: u16) -> Self {
+        Self { index, generation }
+    }
+
+    /// Returns the raw entity index (the value sent on the wire).
+    #[must_use]
+    pub const fn raw(self) -> u64 {
+        self.index
+    }
+
+    /// Returns the entity index.
+    #[must_use]
+    pub const fn index(self) -> u64 {
+        self.index
+    }
+
+    /// Returns the generation of this index.
+    #[must_use]
+    pub const fn generation(self) -> u16 {
+        self.generation
+    }
+
+    /// Returns `true` if the index is representable at `width`.
+    #[must_use]
+    pub const fn fits(self, width: EntityIdWidth) -> bool {
+        self.index <= width.max_index()
+    }
+}
+
+impl From<u16> for EntityId {
+    fn from(index: u16) -> Self {
+        Self::new(u64::from(index))
     }
 }
 
 impl From<u32> for EntityId {
-    fn from(id: u32) -> Self {
-        Self(id)
+    fn from(index: u32) -> Self {
+        Self::new(u64::from(index))
     }
 }
 
-impl From<EntityId> for u32 {
+impl From<u64> for EntityId {
+    fn from(index: u64) -> Self {
+        Self::new(index)
+    }
+}
+
+impl From<EntityId> for u64 {
     fn from(id: EntityId) -> Self {
-        id.0
+        id.index
+    }
+}
+
+impl TryFrom<EntityId> for u32 {
+    type Error = TryFromIntError;
+
+    fn try_from(id: EntityId) -> Result<Self, Self::Error> {
+        Self::try_from(id.index)
+    }
+}
+
+impl TryFrom<EntityId> for u16 {
+    type Error = TryFromIntError;
+
+    fn try_from(id: EntityId) -> Result<Self, Self::Error> {
+        Self::try_from(id.index)
     }
 }
 
@@ -117,6 +239,29 @@ mod tests {
         assert!(t2 <= t3);
     }
 
+    #[test]
+    fn snapshot_tick_serial_order_across_wrap() {
+        let max = SnapshotTick::new(u32::MAX);
+        let one = SnapshotTick::new(1);
+
+        assert!(one.is_after(max));
+        assert!(max.is_before(one));
+        assert_eq!(one.since(max), 2);
+        assert_eq!(max.next(), one);
+        assert_eq!(SnapshotTick::new(7).next(), SnapshotTick::new(8));
+
+        assert!(!one.is_after(one));
+        assert_eq!(one.serial_cmp(max), Some(Ordering::Greater));
+        assert_eq!(max.serial_cmp(one), Some(Ordering::Less));
+
+        // Exactly half the tick space apart has no defined order.
+        let half = SnapshotTick::new(1 << 31);
+        let zero = SnapshotTick::new(0);
+        assert!(!half.is_after(zero) && !zero.is_after(half));
+        assert_eq!(half.serial_cmp(zero), None);
+        assert!(SnapshotTick::new((1 << 31) - 1).is_after(zero));
+    }
+
     #[test]
     fn snapshot_tick_hash() {
         use std::collections::HashSet;
@@ -183,8 +328,22 @@ mod tests {
     #[test]
     fn entity_id_into_u32() {
         let id = EntityId::new(99);
-        let value: u32 = id.into();
+        let value: u32 = id.try_into().unwrap();
         assert_eq!(value, 99);
+        assert!(u32::try_from(EntityId::new(1 << 40)).is_err());
+    }
+
+    #[test]
+    fn entity_id_widths() {
+        let id: EntityId = 70_000u32.into();
+        assert!(!id.fits(EntityIdWidth::U16));
+        assert!(id.fits(EntityIdWidth::U32));
+        assert!(u16::try_from(id).is_err());
+
+        let global: EntityId = u64::MAX.into();
+        assert!(global.fits(EntityIdWidth::U64));
+        assert!(!global.fits(EntityIdWidth::U32));
+        assert_eq!(u64::from(global), u64::MAX);
     }
 
     #[test]
@@ -235,4 +394,16 @@ mod tests {
         const ID: EntityId = EntityId::new(999);
         assert_eq!(ID.raw(), 999);
     }
+
+    #[test]
+    fn entity_id_generation() {
+        let old = EntityId::new(7);
+        let new = EntityId::with_generation(7, 1);
+        assert_eq!(old.generation(), 0);
+        assert_eq!(new.index(), 7);
+        assert_eq!(new.generation(), 1);
+        assert_ne!(old, new);
+        assert!(old < new);
+        assert!(new < EntityId::new(8));
+    }
 }
@@ -18,7 +18,7 @@ fn schema_with_uint_fields(field_count: usize) -> Schema {
 
 fn entity_with_uint_fields(id: u32, field_count: usize, value: u64) -> EntitySnapshot {
     EntitySnapshot {
-        id: codec::EntityId::new(id),
+        id: codec::EntityId::from(id),
         components: vec![ComponentSnapshot {
             id: ComponentId::new(1).unwrap(),
             fields: (0..field_count).map(|_| FieldValue::UInt(value)).collect(),
@@ -1,8 +1,9 @@
 use codec::{
     apply_delta_snapshot_from_packet, decode_session_init_packet, decode_session_packet,
-    encode_delta_snapshot, encode_delta_snapshot_for_client_session_with_scratch,
-    encode_session_init_packet, CodecError, CodecLimits, CodecScratch, CompactHeaderMode,
-    ComponentSnapshot, EntityId, EntitySnapshot, FieldValue, Snapshot, SnapshotTick,
+    encode_delta_snapshot, encode_delta_snapshot_for_client_session_with_mode,
+    encode_delta_snapshot_for_client_session_with_scratch, encode_session_init_packet, CodecError,
+    CodecLimits, CodecScratch, CompactHeaderMode, ComponentSnapshot, EntityId, EntitySnapshot,
+    FieldValue, SessionInitOptions, Snapshot, SnapshotTick,
 };
 use schema::{ComponentDef, FieldCodec, FieldDef, FieldId, Schema};
 
@@ -35,8 +36,7 @@ fn session_sequence_valid() {
     let init_len = encode_session_init_packet(
         &schema,
         SnapshotTick::new(1),
-        Some(1),
-        CompactHeaderMode::SessionV1,
+        &SessionInitOptions::new(CompactHeaderMode::SessionV1).session_id(Some(1)),
         &limits,
         &mut init_buf,
     )
@@ -78,8 +78,7 @@ fn session_drop_hits_baseline_mismatch() {
     let init_len = encode_session_init_packet(
         &schema,
         SnapshotTick::new(1),
-        Some(1),
-        CompactHeaderMode::SessionV1,
+        &SessionInitOptions::new(CompactHeaderMode::SessionV1).session_id(Some(1)),
         &limits,
         &mut init_buf,
     )
@@ -146,8 +145,7 @@ fn baseline_mismatch_is_error() {
     let init_len = encode_session_init_packet(
         &schema,
         SnapshotTick::new(1),
-        Some(1),
-        CompactHeaderMode::SessionV1,
+        &SessionInitOptions::new(CompactHeaderMode::SessionV1).session_id(Some(1)),
         &limits,
         &mut init_buf,
     )
@@ -181,3 +179,66 @@ fn baseline_mismatch_is_error() {
         apply_delta_snapshot_from_packet(&schema, &wrong_baseline, &packet, &limits).unwrap_err();
     assert!(matches!(err, CodecError::BaselineTickMismatch { .. }));
 }
+
+#[test]
+fn session_v2_survives_loss_and_drops_late_packets() {
+    let schema = schema_one_bool();
+    let limits = CodecLimits::for_testing();
+    let wire_limits = wire::Limits::for_testing();
+
+    let mut init_buf = [0u8; 128];
+    let init_len = encode_session_init_packet(
+        &schema,
+        SnapshotTick::new(1),
+        &SessionInitOptions::new(CompactHeaderMode::SessionV2).session_id(Some(1)),
+        &limits,
+        &mut init_buf,
+    )
+    .unwrap();
+    let init_packet = wire::decode_packet(&init_buf[..init_len], &wire_limits).unwrap();
+    let mut session = decode_session_init_packet(&schema, &init_packet, &limits).unwrap();
+    assert_eq!(session.compact_mode, CompactHeaderMode::SessionV2);
+
+    // Ticks 2..=4, all against baseline 1.
+    let baseline = snapshot_with_bool(1, false);
+    let mut scratch = CodecScratch::default();
+    let mut last_tick = baseline.tick;
+    let packets: Vec<Vec<u8>> = (2..=4)
+        .map(|tick| {
+            let current = snapshot_with_bool(tick, tick % 2 == 0);
+            let mut buf = vec![0u8; 256];
+            let len = encode_delta_snapshot_for_client_session_with_mode(
+                &schema,
+                CompactHeaderMode::SessionV2,
+                current.tick,
+                baseline.tick,
+                &baseline,
+                &current,
+                &limits,
+                &mut scratch,
+                &mut last_tick,
+                &mut buf,
+            )
+            .unwrap();
+            buf.truncate(len);
+            buf
+        })
+        .collect();
+
+    // Tick 2 is lost and tick 3 arrives after tick 4.
+    let packet = decode_session_packet(&schema, &mut session, &packets[2], &wire_limits).unwrap();
+    assert_eq!(packet.header.tick, 4);
+    let applied = apply_delta_snapshot_from_packet(&schema, &baseline, &packet, &limits).unwrap();
+    assert_eq!(applied, snapshot_with_bool(4, true));
+
+    for late in [&packets[1], &packets[2]] {
+        let err = decode_session_packet(&schema, &mut session, late, &wire_limits).unwrap_err();
+        assert!(matches!(
+            err,
+            CodecError::SessionLatePacket { latest: 4, .. }
+        ));
+        assert!(err.is_droppable());
+        assert!(!err.needs_resync());
+    }
+    assert_eq!(session.last_tick, SnapshotTick::new(4));
+}
@@ -6,8 +6,8 @@ use clap::Parser;
 use codec::{
     apply_delta_snapshot_from_packet, decode_full_snapshot_from_packet, decode_session_init_packet,
     decode_session_packet, encode_delta_snapshot_for_client_session, encode_full_snapshot,
-    encode_session_init_packet, CodecLimits, CompactHeaderMode, SessionState, Snapshot,
-    SnapshotTick, WireLimits,
+    encode_session_init_packet, CodecLimits, CompactHeaderMode, SessionInitOptions, SessionState,
+    Snapshot, SnapshotTick, WireLimits,
 };
 use demo_schema::{demo_schema, DemoEntityState, POS_MAX, POS_MIN, VEL_MAX, VEL_MIN};
 use serde::Serialize;
@@ -66,8 +66,7 @@ fn main() -> Result<()> {
     let session_len = encode_session_init_packet(
         &schema,
         session_tick,
-        Some(cli.seed),
-        CompactHeaderMode::SessionV1,
+        &SessionInitOptions::new(CompactHeaderMode::SessionV1).session_id(Some(cli.seed)),
         &limits,
         &mut session_buf,
     )
@@ -262,7 +261,7 @@ fn build_visible_snapshot(
 fn init_states(players: u32, rng: &mut Rng) -> Vec<DemoEntityState> {
     let mut states = Vec::with_capacity(players as usize);
     for idx in 0..players {
-        let id = codec::EntityId::new(idx + 1);
+        let id = codec::EntityId::from(idx + 1);
         let pos_q = [
             rng.range_i64(POS_MIN / 2, POS_MAX / 2),
             rng.range_i64(POS_MIN / 2, POS_MAX / 2),
@@ -0,0 +1,41 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wire::{
+    decode_ack_body, decode_ack_packet, decode_packet, decode_session_ack_packet,
+    encode_ack_packet, encode_session_ack_packet, AckErrorClass, AckPacket, Limits,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let limits = Limits::for_testing();
+
+    // Arbitrary bytes through every ack decode path.
+    let _ = decode_ack_body(data);
+    let _ = decode_session_ack_packet(data, &limits);
+    if let Ok(packet) = decode_packet(data, &limits) {
+        let _ = decode_ack_packet(&packet);
+    }
+
+    // Well-formed acks built from the input must roundtrip in both framings.
+    if data.len() >= 9 {
+        let latest_tick = u32::from_le_bytes(data[0..4].try_into().unwrap());
+        let mut ack = AckPacket::new(latest_tick);
+        for &distance in &data[4..8] {
+            let _ = ack.mark_received(latest_tick.wrapping_sub(u32::from(distance)));
+        }
+        if let Some(error) = AckErrorClass::parse(data[8]) {
+            ack = ack.with_error(error);
+        }
+
+        let mut buf = [0u8; 64];
+        let len = encode_ack_packet(&ack, 0, &mut buf).expect("encode ack");
+        let packet = decode_packet(&buf[..len], &limits).expect("decode ack header");
+        assert_eq!(decode_ack_packet(&packet).expect("decode ack"), ack);
+
+        let len = encode_session_ack_packet(&ack, &mut buf).expect("encode session ack");
+        assert_eq!(
+            decode_session_ack_packet(&buf[..len], &limits).expect("decode session ack"),
+            ack
+        );
+    }
+});
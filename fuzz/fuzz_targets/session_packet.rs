@@ -29,13 +29,18 @@ fuzz_target!(|data: &[u8]| {
                 if let Ok(state) = decode_session_init_packet(&schema, &packet, &limits) {
                     session = Some(state);
                 }
+            } else if packet.header.flags.is_ack() {
+                let _ = wire::decode_ack_packet(&packet);
             }
         }
+        // Acks travel the other way, but the same frames must not confuse either side.
+        let _ = wire::decode_session_ack_packet(frame, &wire_limits);
 
         if let Some(state) = session.as_mut() {
             let _ = decode_session_packet(&schema, state, frame, &wire_limits);
         } else if !frame.is_empty() {
             let _ = wire::decode_session_header(frame, 0);
+            let _ = wire::decode_session_header_v2(frame, u32::MAX);
         }
     }
 
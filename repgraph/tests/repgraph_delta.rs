@@ -85,13 +85,13 @@ impl WorldView for TestWorld {
         if fields.is_empty() {
             return None;
         }
-        Some(DeltaUpdateEntity {
-            id: entity,
-            components: vec![DeltaUpdateComponent {
+        Some(DeltaUpdateEntity::new(
+            entity,
+            vec![DeltaUpdateComponent {
                 id: self.component_id,
                 fields,
             }],
-        })
+        ))
     }
 }
 
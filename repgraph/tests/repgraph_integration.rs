@@ -52,13 +52,13 @@ impl<'a> WorldView for WorldAdapter<'a> {
             .entities
             .iter()
             .find(|entry| entry.id == entity)?;
-        Some(DeltaUpdateEntity {
-            id: world.id,
-            components: vec![DeltaUpdateComponent {
+        Some(DeltaUpdateEntity::new(
+            world.id,
+            vec![DeltaUpdateComponent {
                 id: self.schema.components[0].id,
                 fields: vec![(0, FieldValue::Bool(world.value))],
             }],
-        })
+        ))
     }
 }
 
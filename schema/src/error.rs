@@ -15,6 +15,28 @@ pub enum SchemaError {
         field: crate::FieldId,
     },
 
+    /// Duplicate event ID in a schema.
+    DuplicateEventId { id: crate::EventId },
+
+    /// Duplicate field ID within an event.
+    DuplicateEventFieldId {
+        event: crate::EventId,
+        field: crate::FieldId,
+    },
+
+    /// Predicted or residual delta mode is invalid (non-numeric field, unknown
+    /// or predicted velocity field, or out-of-range widths).
+    InvalidPrediction {
+        component: crate::ComponentId,
+        field: crate::FieldId,
+    },
+
+    /// Angle interpolation is invalid (boolean field or zero period).
+    InvalidInterpolation {
+        component: crate::ComponentId,
+        field: crate::FieldId,
+    },
+
     /// Invalid bit width for fixed-width integers.
     InvalidBitWidth { bits: u8 },
 
@@ -100,6 +100,54 @@ pub enum ChangePolicy {
     Threshold { threshold_q: u32 },
 }
 
+/// How a changed field is written in delta update sections.
+///
+/// Full snapshots and creates always carry absolute values.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeltaMode {
+    /// Write the new value in full.
+    #[default]
+    Absolute,
+    /// Write the residual against `baseline + (velocity * dt) >> velocity_shift`,
+    /// where `velocity` is another field of the same component read from the
+    /// baseline and `dt` is the tick distance to the baseline.
+    ///
+    /// Residuals that do not fit in `residual_bits` (signed) fall back to the
+    /// absolute value.
+    Predicted {
+        velocity: FieldId,
+        velocity_shift: u8,
+        residual_bits: u8,
+    },
+    /// Write the signed difference from the baseline value in `residual_bits`
+    /// when that is smaller than the absolute value.
+    Residual { residual_bits: u8 },
+    /// Write the signed difference from the baseline value as a zigzag varint
+    /// when that is smaller than the absolute value.
+    ResidualVarint,
+}
+
+/// How a client blends a field between two snapshots when rendering.
+///
+/// This is a client-side hint only: it has no effect on the wire format and
+/// is not part of the schema hash.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    /// Linear for fixed-point fields, step for everything else.
+    #[default]
+    Auto,
+    /// Hold the earlier value until the later snapshot is reached.
+    Step,
+    /// Blend linearly, rounding to the nearest quantized value.
+    Linear,
+    /// Blend along the shortest arc of a circle that is `period_q` quantized
+    /// units around (e.g. `360 * scale` for degrees), wrapping the result
+    /// back into the field range.
+    Angle { period_q: u64 },
+}
+
 /// Field definition within a component.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -107,6 +155,10 @@ pub struct FieldDef {
     pub id: FieldId,
     pub codec: FieldCodec,
     pub change: ChangePolicy,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub delta: DeltaMode,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub interpolation: Interpolation,
 }
 
 impl FieldDef {
@@ -117,6 +169,8 @@ impl FieldDef {
             id,
             codec,
             change: ChangePolicy::Always,
+            delta: DeltaMode::Absolute,
+            interpolation: Interpolation::Auto,
         }
     }
 
@@ -127,6 +181,8 @@ impl FieldDef {
             id,
             codec,
             change: ChangePolicy::Threshold { threshold_q },
+            delta: DeltaMode::Absolute,
+            interpolation: Interpolation::Auto,
         }
     }
 
@@ -136,6 +192,20 @@ impl FieldDef {
         self.change = change;
         self
     }
+
+    /// Sets the delta mode for a field definition.
+    #[must_use]
+    pub const fn delta(mut self, delta: DeltaMode) -> Self {
+        self.delta = delta;
+        self
+    }
+
+    /// Sets the client-side interpolation for a field definition.
+    #[must_use]
+    pub const fn interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
 }
 
 #[cfg(test)]
@@ -185,4 +255,16 @@ mod tests {
             .change(ChangePolicy::Threshold { threshold_q: 2 });
         assert_eq!(field.change, ChangePolicy::Threshold { threshold_q: 2 });
     }
+
+    #[test]
+    fn field_def_interpolation_override() {
+        let id = FieldId::new(4).unwrap();
+        let field = FieldDef::new(id, FieldCodec::fixed_point(0, 36_000, 100));
+        assert_eq!(field.interpolation, Interpolation::Auto);
+        let field = field.interpolation(Interpolation::Angle { period_q: 36_000 });
+        assert_eq!(
+            field.interpolation,
+            Interpolation::Angle { period_q: 36_000 }
+        );
+    }
 }
@@ -2,7 +2,7 @@
 
 use blake3::Hasher;
 
-use crate::{ChangePolicy, FieldCodec, FixedPoint, Schema};
+use crate::{ChangePolicy, DeltaMode, EntityIdWidth, FieldCodec, FixedPoint, Schema};
 
 /// Computes a deterministic hash for schema validation.
 #[must_use]
@@ -21,6 +21,35 @@ pub fn schema_hash(schema: &Schema) -> u64 {
         }
     }
 
+    // Events are appended only when present so event-free schemas keep their hash.
+    if !schema.events.is_empty() {
+        write_u32(&mut hasher, schema.events.len() as u32);
+        for event in &schema.events {
+            write_u16(&mut hasher, event.id.get());
+            write_u32(&mut hasher, event.fields.len() as u32);
+            for field in &event.fields {
+                write_u16(&mut hasher, field.id.get());
+                write_codec(&mut hasher, field.codec);
+            }
+        }
+    }
+
+    // Delta modes are appended only for fields that opt in, for the same reason.
+    for component in &schema.components {
+        for field in &component.fields {
+            if field.delta != DeltaMode::Absolute {
+                write_u16(&mut hasher, component.id.get());
+                write_u16(&mut hasher, field.id.get());
+                write_delta_mode(&mut hasher, field.delta);
+            }
+        }
+    }
+
+    // Likewise the entity id width, which is only hashed when not the default.
+    if schema.entity_id_width != EntityIdWidth::U32 {
+        write_u8(&mut hasher, schema.entity_id_width.bits());
+    }
+
     let hash = hasher.finalize();
     let bytes = hash.as_bytes();
     u64::from_le_bytes(bytes[0..8].try_into().unwrap())
@@ -64,6 +93,31 @@ fn write_change_policy(hasher: &mut Hasher, policy: ChangePolicy) {
     }
 }
 
+fn write_delta_mode(hasher: &mut Hasher, mode: DeltaMode) {
+    match mode {
+        DeltaMode::Absolute => {
+            write_u8(hasher, 0);
+        }
+        DeltaMode::Predicted {
+            velocity,
+            velocity_shift,
+            residual_bits,
+        } => {
+            write_u8(hasher, 1);
+            write_u16(hasher, velocity.get());
+            write_u8(hasher, velocity_shift);
+            write_u8(hasher, residual_bits);
+        }
+        DeltaMode::Residual { residual_bits } => {
+            write_u8(hasher, 2);
+            write_u8(hasher, residual_bits);
+        }
+        DeltaMode::ResidualVarint => {
+            write_u8(hasher, 3);
+        }
+    }
+}
+
 fn write_fixed_point(hasher: &mut Hasher, fp: FixedPoint) {
     write_i64(hasher, fp.min_q);
     write_i64(hasher, fp.max_q);
@@ -89,7 +143,9 @@ fn write_i64(hasher: &mut Hasher, value: i64) {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{ComponentDef, ComponentId, FieldCodec, FieldDef, FieldId, Schema};
+    use crate::{
+        ComponentDef, ComponentId, EventDef, EventId, FieldCodec, FieldDef, FieldId, Schema,
+    };
 
     fn cid(value: u16) -> ComponentId {
         ComponentId::new(value).unwrap()
@@ -152,4 +208,52 @@ mod tests {
 
         assert_ne!(schema_hash(&schema_a), schema_hash(&schema_b));
     }
+
+    #[test]
+    fn schema_hash_changes_with_events() {
+        let component = ComponentDef::new(cid(1)).field(FieldDef::new(fid(1), FieldCodec::bool()));
+        let event = EventDef::new(EventId::new(1).unwrap())
+            .field(FieldDef::new(fid(1), FieldCodec::uint(8)));
+
+        let without = Schema::new(vec![component.clone()]).unwrap();
+        let with = Schema::with_events(vec![component], vec![event]).unwrap();
+
+        assert_ne!(schema_hash(&without), schema_hash(&with));
+    }
+
+    #[test]
+    fn schema_hash_changes_with_delta_mode() {
+        let position = FieldDef::new(fid(1), FieldCodec::sint(16));
+        let velocity = FieldDef::new(fid(2), FieldCodec::sint(8));
+        let absolute = ComponentDef::new(cid(1)).field(position).field(velocity);
+        let predicted = ComponentDef::new(cid(1))
+            .field(position.delta(DeltaMode::Predicted {
+                velocity: fid(2),
+                velocity_shift: 0,
+                residual_bits: 4,
+            }))
+            .field(velocity);
+
+        let absolute = Schema::new(vec![absolute]).unwrap();
+        let predicted = Schema::new(vec![predicted]).unwrap();
+
+        assert_ne!(schema_hash(&absolute), schema_hash(&predicted));
+    }
+
+    #[test]
+    fn schema_hash_changes_with_entity_id_width() {
+        let component = ComponentDef::new(cid(1)).field(FieldDef::new(fid(1), FieldCodec::bool()));
+        let default = Schema::new(vec![component.clone()]).unwrap();
+        let explicit = Schema::builder()
+            .component(component.clone())
+            .entity_id_width(EntityIdWidth::U32)
+            .build()
+            .unwrap();
+        let wide = Schema::new(vec![component])
+            .unwrap()
+            .with_entity_id_width(EntityIdWidth::U64);
+
+        assert_eq!(schema_hash(&default), schema_hash(&explicit));
+        assert_ne!(schema_hash(&default), schema_hash(&wide));
+    }
 }
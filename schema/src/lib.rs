@@ -1,7 +1,7 @@
 //! Replication schema and field codec definitions for the sdec codec.
 //!
 //! This crate defines how game state is represented for replication:
-//! - Schema model for entity types, components, and fields
+//! - Schema model for entity types, components, fields, and events
 //! - Field codecs (bool, integers, fixed-point, varints)
 //! - Quantization and threshold configuration
 //! - Deterministic schema hashing
@@ -23,9 +23,9 @@ use std::num::NonZeroU16;
 use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
 
 pub use error::{SchemaError, SchemaResult};
-pub use field::{ChangePolicy, FieldCodec, FieldDef, FixedPoint};
+pub use field::{ChangePolicy, DeltaMode, FieldCodec, FieldDef, FixedPoint, Interpolation};
 pub use hash::schema_hash;
-pub use schema::{ComponentDef, Schema, SchemaBuilder};
+pub use schema::{ComponentDef, EntityIdWidth, EventDef, Schema, SchemaBuilder};
 
 /// A component ID within a schema (non-zero).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -99,6 +99,42 @@ impl<'de> Deserialize<'de> for FieldId {
     }
 }
 
+/// An event ID within a schema (non-zero).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EventId(NonZeroU16);
+
+impl EventId {
+    /// Creates a new event ID. Returns `None` if `value` is zero.
+    #[must_use]
+    pub const fn new(value: u16) -> Option<Self> {
+        match NonZeroU16::new(value) {
+            Some(value) => Some(Self(value)),
+            None => None,
+        }
+    }
+
+    /// Returns the underlying numeric value.
+    #[must_use]
+    pub const fn get(self) -> u16 {
+        self.0.get()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for EventId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.get())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for EventId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = u16::deserialize(deserializer)?;
+        EventId::new(value).ok_or_else(|| D::Error::custom("event id must be non-zero"))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -146,4 +182,9 @@ mod tests {
     fn field_id_zero_is_invalid() {
         assert!(FieldId::new(0).is_none());
     }
+
+    #[test]
+    fn event_id_zero_is_invalid() {
+        assert!(EventId::new(0).is_none());
+    }
 }
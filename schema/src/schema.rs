@@ -3,7 +3,9 @@
 use std::collections::HashSet;
 
 use crate::error::{SchemaError, SchemaResult};
-use crate::{ChangePolicy, ComponentId, FieldCodec, FieldDef, FixedPoint};
+use crate::{
+    ChangePolicy, ComponentId, DeltaMode, EventId, FieldCodec, FieldDef, FixedPoint, Interpolation,
+};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -40,27 +42,120 @@ impl ComponentDef {
     }
 }
 
-/// A schema consisting of ordered components.
+/// An event message definition within a schema.
+///
+/// Events are one-shot messages (hit confirms, sounds) rather than replicated
+/// state; every field is sent with each event, and change policies and delta
+/// modes are ignored.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventDef {
+    pub id: EventId,
+    pub fields: Vec<FieldDef>,
+}
+
+impl EventDef {
+    /// Creates a new event with no fields.
+    #[must_use]
+    pub fn new(id: EventId) -> Self {
+        Self {
+            id,
+            fields: Vec::new(),
+        }
+    }
+
+    /// Adds a field to the event.
+    #[must_use]
+    pub fn field(mut self, field: FieldDef) -> Self {
+        self.fields.push(field);
+        self
+    }
+}
+
+/// Width of entity ids on the wire.
+///
+/// Ids are still gap-coded; the width bounds the largest index a peer will
+/// accept and decides how wide a single gap may be.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EntityIdWidth {
+    /// Indices up to `u16::MAX`, for small matches.
+    U16,
+    /// Indices up to `u32::MAX`.
+    #[default]
+    U32,
+    /// Indices up to `u64::MAX`, e.g. globally unique ids across shards.
+    U64,
+}
+
+impl EntityIdWidth {
+    /// Returns the width in bits.
+    #[must_use]
+    pub const fn bits(self) -> u8 {
+        match self {
+            Self::U16 => 16,
+            Self::U32 => 32,
+            Self::U64 => 64,
+        }
+    }
+
+    /// Returns the largest representable index.
+    #[must_use]
+    pub const fn max_index(self) -> u64 {
+        match self {
+            Self::U16 => u16::MAX as u64,
+            Self::U32 => u32::MAX as u64,
+            Self::U64 => u64::MAX,
+        }
+    }
+}
+
+/// A schema consisting of ordered components and events.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Schema {
     pub components: Vec<ComponentDef>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub events: Vec<EventDef>,
+    /// Width of entity ids for every packet encoded with this schema.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub entity_id_width: EntityIdWidth,
 }
 
 impl Schema {
     /// Creates a schema from components after validation.
     pub fn new(components: Vec<ComponentDef>) -> SchemaResult<Self> {
-        let schema = Self { components };
+        Self::with_events(components, Vec::new())
+    }
+
+    /// Creates a schema from components and events after validation.
+    pub fn with_events(components: Vec<ComponentDef>, events: Vec<EventDef>) -> SchemaResult<Self> {
+        let schema = Self {
+            components,
+            events,
+            entity_id_width: EntityIdWidth::default(),
+        };
         schema.validate()?;
         Ok(schema)
     }
 
+    /// Returns this schema with a different entity id width.
+    #[must_use]
+    pub fn with_entity_id_width(mut self, width: EntityIdWidth) -> Self {
+        self.entity_id_width = width;
+        self
+    }
+
     /// Creates a schema builder.
     #[must_use]
     pub fn builder() -> SchemaBuilder {
-        SchemaBuilder {
-            components: Vec::new(),
-        }
+        SchemaBuilder::default()
+    }
+
+    /// Returns the event definition for `id`, if present.
+    #[must_use]
+    pub fn event(&self, id: EventId) -> Option<&EventDef> {
+        self.events.iter().find(|event| event.id == id)
     }
 
     /// Validates schema invariants.
@@ -80,6 +175,28 @@ impl Schema {
                     });
                 }
                 validate_field(field)?;
+                validate_interpolation(component, field)?;
+            }
+            for field in &component.fields {
+                validate_delta_mode(component, field)?;
+            }
+        }
+
+        let mut event_ids = HashSet::new();
+        for event in &self.events {
+            if !event_ids.insert(event.id) {
+                return Err(SchemaError::DuplicateEventId { id: event.id });
+            }
+
+            let mut field_ids = HashSet::new();
+            for field in &event.fields {
+                if !field_ids.insert(field.id) {
+                    return Err(SchemaError::DuplicateEventFieldId {
+                        event: event.id,
+                        field: field.id,
+                    });
+                }
+                validate_field(field)?;
             }
         }
         Ok(())
@@ -90,6 +207,8 @@ impl Schema {
 #[derive(Debug, Default)]
 pub struct SchemaBuilder {
     components: Vec<ComponentDef>,
+    events: Vec<EventDef>,
+    entity_id_width: EntityIdWidth,
 }
 
 impl SchemaBuilder {
@@ -100,9 +219,24 @@ impl SchemaBuilder {
         self
     }
 
+    /// Adds an event definition.
+    #[must_use]
+    pub fn event(mut self, event: EventDef) -> Self {
+        self.events.push(event);
+        self
+    }
+
+    /// Sets the entity id width (defaults to [`EntityIdWidth::U32`]).
+    #[must_use]
+    pub fn entity_id_width(mut self, width: EntityIdWidth) -> Self {
+        self.entity_id_width = width;
+        self
+    }
+
     /// Builds the schema after validation.
     pub fn build(self) -> SchemaResult<Schema> {
-        Schema::new(self.components)
+        Schema::with_events(self.components, self.events)
+            .map(|schema| schema.with_entity_id_width(self.entity_id_width))
     }
 }
 
@@ -127,6 +261,59 @@ fn validate_field(field: &FieldDef) -> SchemaResult<()> {
     Ok(())
 }
 
+fn validate_delta_mode(component: &ComponentDef, field: &FieldDef) -> SchemaResult<()> {
+    let invalid = SchemaError::InvalidPrediction {
+        component: component.id,
+        field: field.id,
+    };
+    let (velocity, velocity_shift, residual_bits) = match field.delta {
+        DeltaMode::Absolute => return Ok(()),
+        DeltaMode::Residual { residual_bits } => {
+            if field.codec == FieldCodec::Bool || residual_bits == 0 || residual_bits > 32 {
+                return Err(invalid);
+            }
+            return Ok(());
+        }
+        DeltaMode::ResidualVarint => {
+            if field.codec == FieldCodec::Bool {
+                return Err(invalid);
+            }
+            return Ok(());
+        }
+        DeltaMode::Predicted {
+            velocity,
+            velocity_shift,
+            residual_bits,
+        } => (velocity, velocity_shift, residual_bits),
+    };
+    if field.codec == FieldCodec::Bool
+        || velocity == field.id
+        || velocity_shift > 32
+        || residual_bits == 0
+        || residual_bits > 32
+    {
+        return Err(invalid);
+    }
+    // The velocity must be readable from the baseline before any residual is
+    // resolved, so it must itself be written as an absolute value.
+    match component.fields.iter().find(|f| f.id == velocity) {
+        Some(v) if v.codec != FieldCodec::Bool && v.delta == DeltaMode::Absolute => Ok(()),
+        _ => Err(invalid),
+    }
+}
+
+fn validate_interpolation(component: &ComponentDef, field: &FieldDef) -> SchemaResult<()> {
+    match field.interpolation {
+        Interpolation::Angle { period_q } if period_q == 0 || field.codec == FieldCodec::Bool => {
+            Err(SchemaError::InvalidInterpolation {
+                component: component.id,
+                field: field.id,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
 fn validate_fixed_point(fp: FixedPoint) -> SchemaResult<()> {
     if fp.scale == 0 {
         return Err(SchemaError::InvalidFixedPointScale { scale: fp.scale });
@@ -171,6 +358,100 @@ mod tests {
         assert!(matches!(err, SchemaError::DuplicateComponentId { .. }));
     }
 
+    #[test]
+    fn schema_builder_with_events() {
+        let event = EventDef::new(EventId::new(1).unwrap())
+            .field(FieldDef::new(fid(1), FieldCodec::uint(8)));
+        let schema = Schema::builder()
+            .component(ComponentDef::new(cid(1)))
+            .event(event.clone())
+            .build()
+            .unwrap();
+        assert_eq!(schema.event(event.id), Some(&event));
+    }
+
+    #[test]
+    fn schema_rejects_duplicate_event_ids() {
+        let event = EventDef::new(EventId::new(1).unwrap());
+        let err = Schema::with_events(Vec::new(), vec![event.clone(), event]).unwrap_err();
+        assert!(matches!(err, SchemaError::DuplicateEventId { .. }));
+    }
+
+    #[test]
+    fn schema_rejects_duplicate_event_field_ids() {
+        let event = EventDef::new(EventId::new(1).unwrap())
+            .field(FieldDef::new(fid(1), FieldCodec::bool()))
+            .field(FieldDef::new(fid(1), FieldCodec::bool()));
+        let err = Schema::with_events(Vec::new(), vec![event]).unwrap_err();
+        assert!(matches!(err, SchemaError::DuplicateEventFieldId { .. }));
+    }
+
+    #[test]
+    fn schema_accepts_predicted_field() {
+        let component = ComponentDef::new(cid(1))
+            .field(
+                FieldDef::new(fid(1), FieldCodec::fixed_point(-1000, 1000, 10)).delta(
+                    DeltaMode::Predicted {
+                        velocity: fid(2),
+                        velocity_shift: 0,
+                        residual_bits: 4,
+                    },
+                ),
+            )
+            .field(FieldDef::new(fid(2), FieldCodec::sint(8)));
+        assert!(Schema::new(vec![component]).is_ok());
+    }
+
+    #[test]
+    fn schema_rejects_invalid_residual() {
+        for delta in [
+            DeltaMode::Residual { residual_bits: 4 },
+            DeltaMode::ResidualVarint,
+        ] {
+            let component = ComponentDef::new(cid(1))
+                .field(FieldDef::new(fid(1), FieldCodec::bool()).delta(delta));
+            let err = Schema::new(vec![component]).unwrap_err();
+            assert!(matches!(err, SchemaError::InvalidPrediction { .. }));
+        }
+    }
+
+    #[test]
+    fn schema_rejects_invalid_prediction() {
+        let predicted = |velocity| {
+            FieldDef::new(fid(1), FieldCodec::sint(16)).delta(DeltaMode::Predicted {
+                velocity: fid(velocity),
+                velocity_shift: 0,
+                residual_bits: 4,
+            })
+        };
+        for component in [
+            // Unknown velocity field.
+            ComponentDef::new(cid(1)).field(predicted(3)),
+            // Self-referential velocity.
+            ComponentDef::new(cid(1)).field(predicted(1)),
+            // Boolean velocity.
+            ComponentDef::new(cid(1))
+                .field(predicted(2))
+                .field(FieldDef::new(fid(2), FieldCodec::bool())),
+        ] {
+            let err = Schema::new(vec![component]).unwrap_err();
+            assert!(matches!(err, SchemaError::InvalidPrediction { .. }));
+        }
+    }
+
+    #[test]
+    fn schema_rejects_invalid_angle_interpolation() {
+        for field in [
+            FieldDef::new(fid(1), FieldCodec::bool())
+                .interpolation(Interpolation::Angle { period_q: 360 }),
+            FieldDef::new(fid(1), FieldCodec::uint(9))
+                .interpolation(Interpolation::Angle { period_q: 0 }),
+        ] {
+            let err = Schema::new(vec![ComponentDef::new(cid(1)).field(field)]).unwrap_err();
+            assert!(matches!(err, SchemaError::InvalidInterpolation { .. }));
+        }
+    }
+
     #[test]
     fn schema_rejects_duplicate_field_ids() {
         let component = ComponentDef::new(cid(1))
@@ -266,7 +266,7 @@ fn main() -> Result<()> {
     let schema = schema_builder.build().context("build schema")?;
 
     let mut server_world = World::new();
-    let mut server_entities = EntityMap::new();
+    let mut server_entities = EntityMap::with_width(schema.schema().entity_id_width);
 
     let mut rng = Rng::new(cli.seed);
     for _ in 0..cli.entities {
@@ -276,7 +276,7 @@ fn main() -> Result<()> {
             yaw: (rng.next_u32() % 4096) as u16,
         };
         let server = server_world.spawn(position).id();
-        let _ = server_entities.entity_id(server);
+        server_entities.entity_id(server)?;
     }
 
     let mut bytes = Vec::new();
@@ -350,8 +350,8 @@ fn main() -> Result<()> {
             let init_len = codec::encode_session_init_packet(
                 schema.schema(),
                 SnapshotTick::new(0),
-                Some((idx + 1) as u64),
-                codec::CompactHeaderMode::SessionV1,
+                &codec::SessionInitOptions::new(codec::CompactHeaderMode::SessionV1)
+                    .session_id(Some((idx + 1) as u64)),
                 &sdec_limits,
                 &mut init_buf,
             )?;
@@ -373,11 +373,11 @@ fn main() -> Result<()> {
         let all_ids: HashSet<codec::EntityId> = positions
             .iter()
             .map(|(entity, _)| server_entities.entity_id(*entity))
-            .collect();
-        let changes = extract_changes(&schema, &mut server_world, &mut server_entities);
+            .collect::<Result<_, _>>()?;
+        let changes = extract_changes(&schema, &mut server_world, &mut server_entities)?;
 
         if cli.validate {
-            let snapshot = build_server_snapshot(&mut server_world, &mut server_entities);
+            let snapshot = build_server_snapshot(&mut server_world, &mut server_entities)?;
             server_snapshots.push_back(ServerSnapshot {
                 tick,
                 entities: snapshot,
@@ -397,7 +397,7 @@ fn main() -> Result<()> {
             }
         }
         for (entity, pos) in &positions {
-            let id = server_entities.entity_id(*entity);
+            let id = server_entities.entity_id(*entity)?;
             let dirty = dirty_map.remove(&id).unwrap_or_default();
             graph.update_entity(
                 id,
@@ -422,7 +422,7 @@ fn main() -> Result<()> {
                     &mut server_entities,
                     client_positions[client_idx],
                     visibility_radius,
-                );
+                )?;
             }
 
             let start = Instant::now();
@@ -500,7 +500,7 @@ fn main() -> Result<()> {
                         &mut server_world,
                         &mut server_entities,
                         &client_state.visible,
-                    );
+                    )?;
                     let replication_elapsed = replication_start.elapsed();
                     let codec_start = Instant::now();
                     let payload =
@@ -518,7 +518,7 @@ fn main() -> Result<()> {
                         &mut server_world,
                         &mut server_entities,
                         &client_state.visible,
-                    );
+                    )?;
                     let replication_elapsed = replication_start.elapsed();
                     let codec_start = Instant::now();
                     let payload = bitcode::encode(&snapshot);
@@ -570,7 +570,7 @@ fn main() -> Result<()> {
                             &mut client_state.world,
                             &mut client_state.entities,
                             &snapshot,
-                        );
+                        )?;
                         client_state.last_applied_tick = SnapshotTick::new(tick);
                         client_state.last_applied_full = true;
                         Ok(AppliedPacket::Full(SnapshotTick::new(tick)))
@@ -582,7 +582,7 @@ fn main() -> Result<()> {
                             &mut client_state.world,
                             &mut client_state.entities,
                             &snapshot,
-                        );
+                        )?;
                         client_state.last_applied_tick = SnapshotTick::new(tick);
                         client_state.last_applied_full = true;
                         Ok(AppliedPacket::Full(SnapshotTick::new(tick)))
@@ -632,7 +632,7 @@ fn main() -> Result<()> {
                                     let snapshot = build_server_snapshot(
                                         &mut server_world,
                                         &mut server_entities,
-                                    );
+                                    )?;
                                     server_snapshots.push_back(ServerSnapshot {
                                         tick: resync_tick.raw(),
                                         entities: snapshot,
@@ -818,7 +818,7 @@ fn visible_entity_ids(
     entities: &mut EntityMap,
     client_pos: (i64, i64),
     radius: i64,
-) -> HashSet<codec::EntityId> {
+) -> Result<HashSet<codec::EntityId>> {
     let mut visible = HashSet::new();
     let radius_sq = radius.saturating_mul(radius);
     for (entity, pos) in positions {
@@ -826,11 +826,11 @@ fn visible_entity_ids(
         let dy = pos.y_q - client_pos.1;
         let dist_sq = dx.saturating_mul(dx).saturating_add(dy.saturating_mul(dy));
         if dist_sq <= radius_sq {
-            let id = entities.entity_id(*entity);
+            let id = entities.entity_id(*entity)?;
             visible.insert(id);
         }
     }
-    visible
+    Ok(visible)
 }
 
 fn visible_entity_ids_snapshot(
@@ -1072,35 +1072,42 @@ fn build_snapshot_for_ids(
     world: &mut World,
     entities: &mut EntityMap,
     ids: &HashSet<codec::EntityId>,
-) -> SnapshotData {
+) -> Result<SnapshotData> {
     let mut query = world.query::<(Entity, &PositionYaw)>();
     let mut snapshots = Vec::new();
     for (entity, position) in query.iter(world) {
-        let id = entities.entity_id(entity);
+        let id = entities.entity_id(entity)?;
         if !ids.contains(&id) {
             continue;
         }
         snapshots.push(SnapshotEntity {
-            id: id.raw(),
+            id: u32::try_from(id).expect("demo entity ids fit in u32"),
             position: *position,
         });
     }
     snapshots.sort_by_key(|entry| entry.id);
-    SnapshotData {
+    Ok(SnapshotData {
         entities: snapshots,
-    }
+    })
 }
 
-fn apply_snapshot(world: &mut World, entities: &mut EntityMap, snapshot: &SnapshotData) {
+fn apply_snapshot(
+    world: &mut World,
+    entities: &mut EntityMap,
+    snapshot: &SnapshotData,
+) -> Result<()> {
     let mut query = world.query::<&mut PositionYaw>();
     let mut seen = HashSet::new();
     for entry in &snapshot.entities {
-        let id = codec::EntityId::new(entry.id);
-        let entity = entities.entity(id).unwrap_or_else(|| {
-            let new_entity = world.spawn(entry.position).id();
-            entities.register(id, new_entity);
-            new_entity
-        });
+        let id = codec::EntityId::from(entry.id);
+        let entity = match entities.entity(id) {
+            Some(entity) => entity,
+            None => {
+                let new_entity = world.spawn(entry.position).id();
+                entities.register(id, new_entity)?;
+                new_entity
+            }
+        };
         if let Ok(mut pos) = query.get_mut(world, entity) {
             *pos = entry.position;
         }
@@ -1115,6 +1122,7 @@ fn apply_snapshot(world: &mut World, entities: &mut EntityMap, snapshot: &Snapsh
             entities.unregister(id);
         }
     }
+    Ok(())
 }
 
 fn apply_full_snapshot(
@@ -1125,11 +1133,14 @@ fn apply_full_snapshot(
 ) -> Result<()> {
     let mut seen = HashSet::new();
     for entity_snapshot in &snapshot.entities {
-        let entity = entities.entity(entity_snapshot.id).unwrap_or_else(|| {
-            let new_entity = world.spawn_empty().id();
-            entities.register(entity_snapshot.id, new_entity);
-            new_entity
-        });
+        let entity = match entities.entity(entity_snapshot.id) {
+            Some(entity) => entity,
+            None => {
+                let new_entity = world.spawn_empty().id();
+                entities.register(entity_snapshot.id, new_entity)?;
+                new_entity
+            }
+        };
         for component in &entity_snapshot.components {
             let fields: Vec<(usize, FieldValue)> =
                 component.fields.iter().copied().enumerate().collect();
@@ -1179,15 +1190,15 @@ fn state_digest(
 fn build_server_snapshot(
     world: &mut World,
     entities: &mut EntityMap,
-) -> Vec<(codec::EntityId, PositionYaw)> {
+) -> Result<Vec<(codec::EntityId, PositionYaw)>> {
     let mut entries = Vec::new();
     let mut query = world.query::<(Entity, &PositionYaw)>();
     for (entity, position) in query.iter(world) {
-        let id = entities.entity_id(entity);
+        let id = entities.entity_id(entity)?;
         entries.push((id, *position));
     }
     entries.sort_by_key(|(id, _)| id.raw());
-    entries
+    Ok(entries)
 }
 
 fn state_digest_snapshot(
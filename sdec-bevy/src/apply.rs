@@ -1,18 +1,28 @@
 use anyhow::{anyhow, Result};
-use bevy_ecs::prelude::World;
-use codec::{DeltaUpdateEntity, EntityId, EntitySnapshot};
+use bevy_ecs::prelude::{Entity, World};
+use codec::{DeltaUpdateEntity, EntityId, EntitySnapshot, FieldValue, UpdateValue};
 
 use crate::mapping::EntityMap;
 use crate::schema::BevySchema;
 
-pub fn apply_changes(
+/// Applies one decoded delta.
+///
+/// Updates only carry an entity index on the wire, so they target whichever
+/// entity held it before this delta's destroys and creates. An update whose
+/// index was destroyed or re-created in the same delta is stale and dropped.
+pub fn apply_changes<V: Copy + Into<UpdateValue>>(
     schema: &BevySchema,
     world: &mut World,
     entities: &mut EntityMap,
     creates: &[EntitySnapshot],
     destroys: &[EntityId],
-    updates: &[DeltaUpdateEntity],
+    updates: &[DeltaUpdateEntity<V>],
 ) -> Result<()> {
+    let targets: Vec<Option<EntityId>> = updates
+        .iter()
+        .map(|update| entities.id_by_index(update.id.index()))
+        .collect();
+
     for destroy in destroys {
         if let Some(entity) = entities.entity(*destroy) {
             world.despawn(entity);
@@ -22,7 +32,7 @@ pub fn apply_changes(
 
     for create in creates {
         let entity = world.spawn_empty().id();
-        entities.register(create.id, entity);
+        entities.register(create.id, entity)?;
         for component in &create.components {
             let adapter = schema
                 .adapter_by_component(component.id)
@@ -31,26 +41,64 @@ pub fn apply_changes(
         }
     }
 
-    apply_delta_updates(schema, world, entities, updates)?;
+    for (update, target) in updates.iter().zip(targets) {
+        if let Some(entity) = target.and_then(|id| entities.entity(id)) {
+            apply_update(schema, world, entity, update)?;
+        }
+    }
     Ok(())
 }
 
-pub fn apply_delta_updates(
+/// Applies updates to the entities they name. Updates whose id does not
+/// match the live generation of its index are stale and dropped.
+pub fn apply_delta_updates<V: Copy + Into<UpdateValue>>(
     schema: &BevySchema,
     world: &mut World,
     entities: &mut EntityMap,
-    updates: &[DeltaUpdateEntity],
+    updates: &[DeltaUpdateEntity<V>],
 ) -> Result<()> {
     for update in updates {
-        let Some(entity) = entities.entity(update.id) else {
-            continue;
-        };
-        for component in &update.components {
-            let adapter = schema
-                .adapter_by_component(component.id)
-                .ok_or_else(|| anyhow!("unknown component {:?}", component.id))?;
-            adapter.apply_update(world, entity, &component.fields)?;
+        if let Some(entity) = entities.entity(update.id) {
+            apply_update(schema, world, entity, update)?;
         }
     }
     Ok(())
 }
+
+fn apply_update<V: Copy + Into<UpdateValue>>(
+    schema: &BevySchema,
+    world: &mut World,
+    entity: Entity,
+    update: &DeltaUpdateEntity<V>,
+) -> Result<()> {
+    for component_id in &update.removed {
+        let adapter = schema
+            .adapter_by_component(*component_id)
+            .ok_or_else(|| anyhow!("unknown component {:?}", component_id))?;
+        adapter.remove_component(world, entity);
+    }
+    for component in &update.inserted {
+        let adapter = schema
+            .adapter_by_component(component.id)
+            .ok_or_else(|| anyhow!("unknown component {:?}", component.id))?;
+        adapter.insert_component(world, entity, &component.fields)?;
+    }
+    for component in &update.components {
+        let adapter = schema
+            .adapter_by_component(component.id)
+            .ok_or_else(|| anyhow!("unknown component {:?}", component.id))?;
+        let fields = component
+            .fields
+            .iter()
+            .map(|(index, value)| match (*value).into() {
+                UpdateValue::Value(value) => Ok((*index, value)),
+                UpdateValue::Residual(_) => Err(anyhow!(
+                    "residual update for component {:?} needs its baseline",
+                    component.id
+                )),
+            })
+            .collect::<Result<Vec<(usize, FieldValue)>>>()?;
+        adapter.apply_update(world, entity, &fields)?;
+    }
+    Ok(())
+}
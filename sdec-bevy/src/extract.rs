@@ -1,7 +1,7 @@
 use std::collections::{HashMap, HashSet};
 
 use bevy_ecs::prelude::{Entity, World};
-use codec::{DeltaUpdateEntity, EntityId, EntitySnapshot};
+use codec::{CodecResult, DeltaUpdateEntity, EntityId, EntitySnapshot};
 use schema::ComponentId;
 
 use crate::mapping::EntityMap;
@@ -18,6 +18,9 @@ pub struct BevyChangeSet {
 pub(crate) struct ExtractScratch {
     create_entities: HashSet<Entity>,
     update_entities: HashMap<Entity, Vec<ComponentId>>,
+    inserted_components: HashMap<Entity, Vec<ComponentId>>,
+    removed_components: HashMap<Entity, Vec<ComponentId>>,
+    touched_entities: HashSet<Entity>,
     destroys: HashSet<EntityId>,
 }
 
@@ -25,11 +28,11 @@ pub fn extract_changes(
     schema: &BevySchema,
     world: &mut World,
     entities: &mut EntityMap,
-) -> BevyChangeSet {
+) -> CodecResult<BevyChangeSet> {
     let mut scratch = ExtractScratch::default();
     let mut changes = BevyChangeSet::default();
-    extract_changes_with_scratch(schema, world, entities, &mut scratch, &mut changes);
-    changes
+    extract_changes_with_scratch(schema, world, entities, &mut scratch, &mut changes)?;
+    Ok(changes)
 }
 
 pub(crate) fn extract_changes_with_scratch(
@@ -38,9 +41,11 @@ pub(crate) fn extract_changes_with_scratch(
     entities: &mut EntityMap,
     scratch: &mut ExtractScratch,
     out: &mut BevyChangeSet,
-) {
+) -> CodecResult<()> {
     scratch.create_entities.clear();
     scratch.update_entities.clear();
+    scratch.inserted_components.clear();
+    scratch.removed_components.clear();
     scratch.destroys.clear();
     out.creates.clear();
     out.updates.clear();
@@ -48,7 +53,17 @@ pub(crate) fn extract_changes_with_scratch(
 
     for adapter in schema.adapters() {
         for entity in adapter.added_entities(world) {
-            scratch.create_entities.insert(entity);
+            // A component added to an already replicated entity is inserted
+            // rather than re-creating the entity.
+            if entities.entity_id_known(entity).is_some() {
+                scratch
+                    .inserted_components
+                    .entry(entity)
+                    .or_default()
+                    .push(adapter.component_id());
+            } else {
+                scratch.create_entities.insert(entity);
+            }
         }
         for entity in adapter.changed_entities(world) {
             scratch
@@ -58,7 +73,17 @@ pub(crate) fn extract_changes_with_scratch(
                 .push(adapter.component_id());
         }
         for entity in adapter.removed_entities(world) {
-            if let Some(id) = entities.entity_id_known(entity) {
+            let still_replicated = world.get_entity(entity).is_ok()
+                && !schema.snapshot_entity(world, entity).is_empty();
+            if still_replicated {
+                if entities.entity_id_known(entity).is_some() {
+                    scratch
+                        .removed_components
+                        .entry(entity)
+                        .or_default()
+                        .push(adapter.component_id());
+                }
+            } else if let Some(id) = entities.release(entity) {
                 scratch.destroys.insert(id);
             }
         }
@@ -66,7 +91,7 @@ pub(crate) fn extract_changes_with_scratch(
 
     out.creates.reserve(scratch.create_entities.len());
     for entity in scratch.create_entities.iter().copied() {
-        let id = entities.entity_id(entity);
+        let id = entities.entity_id(entity)?;
         let components = schema.snapshot_entity(world, entity);
         if components.is_empty() {
             continue;
@@ -74,25 +99,69 @@ pub(crate) fn extract_changes_with_scratch(
         out.creates.push(EntitySnapshot { id, components });
     }
 
-    out.updates.reserve(scratch.update_entities.len());
-    for (entity, components) in scratch.update_entities.iter() {
+    for (entity, removed) in &mut scratch.removed_components {
+        let inserted = scratch.inserted_components.entry(*entity).or_default();
+        // Removed and re-added within one tick: send the new state as an
+        // update of the existing component.
+        removed.retain(|component_id| {
+            let Some(idx) = inserted.iter().position(|id| id == component_id) else {
+                return true;
+            };
+            inserted.swap_remove(idx);
+            false
+        });
+    }
+
+    scratch.touched_entities.clear();
+    scratch.touched_entities.extend(
+        scratch
+            .update_entities
+            .keys()
+            .chain(scratch.inserted_components.keys())
+            .chain(scratch.removed_components.keys())
+            .copied(),
+    );
+    out.updates.reserve(scratch.touched_entities.len());
+    for entity in &scratch.touched_entities {
         if scratch.create_entities.contains(entity) {
             continue;
         }
-        let id = entities.entity_id(*entity);
+        let Some(id) = entities.entity_id_known(*entity) else {
+            continue;
+        };
+        let inserted_ids = scratch
+            .inserted_components
+            .get(entity)
+            .map_or(&[][..], Vec::as_slice);
+        let removed_ids = scratch
+            .removed_components
+            .get(entity)
+            .map_or(&[][..], Vec::as_slice);
+
         let mut delta_components = Vec::new();
-        for component_id in components {
+        for component_id in scratch.update_entities.get(entity).into_iter().flatten() {
+            if inserted_ids.contains(component_id) {
+                continue;
+            }
             if let Some(adapter) = schema.adapter_by_component(*component_id) {
                 if let Some(component_update) = adapter.update_component(world, *entity) {
                     delta_components.push(component_update);
                 }
             }
         }
-        if !delta_components.is_empty() {
-            out.updates.push(DeltaUpdateEntity {
-                id,
-                components: delta_components,
-            });
+        let mut update = DeltaUpdateEntity::new(id, delta_components);
+        for adapter in schema.adapters() {
+            let component_id = adapter.component_id();
+            if removed_ids.contains(&component_id) {
+                update.removed.push(component_id);
+            } else if inserted_ids.contains(&component_id) {
+                update
+                    .inserted
+                    .extend(adapter.snapshot_component(world, *entity));
+            }
+        }
+        if !update.components.is_empty() || update.has_component_ops() {
+            out.updates.push(update);
         }
     }
 
@@ -101,4 +170,5 @@ pub(crate) fn extract_changes_with_scratch(
     out.destroys.sort_by_key(|id| id.raw());
     out.creates.sort_by_key(|entity| entity.id.raw());
     out.updates.sort_by_key(|entity| entity.id.raw());
+    Ok(())
 }
@@ -1,13 +1,14 @@
 use std::collections::HashMap;
 
 use bevy_ecs::prelude::Entity;
-use codec::EntityId;
+use codec::{CodecResult, EntityId, EntityIdAllocator, EntityIdWidth, SnapshotTick};
 
 #[derive(Debug, Default)]
 pub struct EntityMap {
-    next_id: u32,
+    ids: EntityIdAllocator,
+    tick: SnapshotTick,
     to_id: HashMap<Entity, EntityId>,
-    to_entity: HashMap<EntityId, Entity>,
+    to_entity: HashMap<u64, (EntityId, Entity)>,
 }
 
 impl EntityMap {
@@ -16,17 +17,32 @@ impl EntityMap {
         Self::default()
     }
 
+    /// Creates a map whose allocated ids fit the schema's `width`.
     #[must_use]
-    pub fn entity_id(&mut self, entity: Entity) -> EntityId {
+    pub fn with_width(width: EntityIdWidth) -> Self {
+        Self {
+            ids: EntityIdAllocator::with_width(width),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the tick being extracted and the oldest baseline tick still in
+    /// flight. Released ids are freed at `tick` and their indices are only
+    /// reused once `oldest_in_flight` has caught up.
+    pub fn advance(&mut self, tick: SnapshotTick, oldest_in_flight: SnapshotTick) {
+        self.tick = tick;
+        self.ids.set_oldest_in_flight(oldest_in_flight);
+    }
+
+    /// Returns the id of `entity`, allocating one on first use.
+    pub fn entity_id(&mut self, entity: Entity) -> CodecResult<EntityId> {
         if let Some(id) = self.to_id.get(&entity) {
-            return *id;
+            return Ok(*id);
         }
-        let next = self.next_id.saturating_add(1).max(1);
-        self.next_id = next;
-        let id = EntityId::new(next);
+        let id = self.ids.allocate()?;
         self.to_id.insert(entity, id);
-        self.to_entity.insert(id, entity);
-        id
+        self.to_entity.insert(id.index(), (id, entity));
+        Ok(id)
     }
 
     #[must_use]
@@ -34,25 +50,54 @@ impl EntityMap {
         self.to_id.get(&entity).copied()
     }
 
+    /// Returns the entity for `id` if its index is live with the same
+    /// generation.
     #[must_use]
     pub fn entity(&self, id: EntityId) -> Option<Entity> {
-        self.to_entity.get(&id).copied()
+        self.to_entity
+            .get(&id.index())
+            .filter(|(live, _)| *live == id)
+            .map(|(_, entity)| *entity)
+    }
+
+    /// Returns the live id holding `index`. Decoded updates only carry the
+    /// index.
+    #[must_use]
+    pub fn id_by_index(&self, index: u64) -> Option<EntityId> {
+        self.to_entity.get(&index).map(|(id, _)| *id)
     }
 
     #[must_use]
     pub fn ids(&self) -> Vec<EntityId> {
-        self.to_entity.keys().copied().collect()
+        self.to_entity.values().map(|(id, _)| *id).collect()
     }
 
-    pub fn register(&mut self, id: EntityId, entity: Entity) {
+    /// Maps a peer-assigned `id` to `entity` and reserves it so this map
+    /// never allocates it again.
+    pub fn register(&mut self, id: EntityId, entity: Entity) -> CodecResult<()> {
+        self.ids.reserve(id)?;
         self.to_id.insert(entity, id);
-        self.to_entity.insert(id, entity);
-        self.next_id = self.next_id.max(id.raw());
+        if let Some((_, previous)) = self.to_entity.insert(id.index(), (id, entity)) {
+            if previous != entity {
+                self.to_id.remove(&previous);
+            }
+        }
+        Ok(())
     }
 
     pub fn unregister(&mut self, id: EntityId) {
-        if let Some(entity) = self.to_entity.remove(&id) {
+        if let Some(entity) = self.entity(id) {
+            self.to_entity.remove(&id.index());
             self.to_id.remove(&entity);
+            let _ = self.ids.free(id, self.tick);
         }
     }
+
+    /// Forgets a server-side entity and frees its id for later reuse.
+    pub fn release(&mut self, entity: Entity) -> Option<EntityId> {
+        let id = self.to_id.remove(&entity)?;
+        self.to_entity.remove(&id.index());
+        let _ = self.ids.free(id, self.tick);
+        Some(id)
+    }
 }
@@ -27,11 +27,12 @@ pub struct BevyReplicator {
 impl BevyReplicator {
     #[must_use]
     pub fn new(schema: BevySchema) -> Self {
+        let entities = EntityMap::with_width(schema.schema().entity_id_width);
         Self {
             schema,
             limits: CodecLimits::default(),
             wire_limits: WireLimits::default(),
-            entities: EntityMap::new(),
+            entities,
             session: None,
             metrics: None,
             change_set: BevyChangeSet::default(),
@@ -60,13 +61,14 @@ impl BevyReplicator {
         baseline_tick: codec::SnapshotTick,
         out: &mut [u8],
     ) -> Result<usize> {
+        self.entities.advance(tick, baseline_tick);
         extract_changes_with_scratch(
             &self.schema,
             world,
             &mut self.entities,
             &mut self.extract_scratch,
             &mut self.change_set,
-        );
+        )?;
         let mut encoder = SessionEncoder::new(self.schema.schema(), &self.limits);
         let start = Instant::now();
         let bytes = encode_delta_from_changes(
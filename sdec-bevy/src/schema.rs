@@ -47,6 +47,7 @@ pub(crate) trait ComponentAdapter {
         entity: Entity,
         fields: &[FieldValue],
     ) -> Result<()>;
+    fn remove_component(&self, world: &mut World, entity: Entity);
     fn added_entities(&self, world: &mut World) -> Vec<Entity>;
     fn changed_entities(&self, world: &mut World) -> Vec<Entity>;
     fn removed_entities(&self, world: &World) -> Vec<Entity>;
@@ -150,6 +151,10 @@ impl<T: ReplicatedComponent> ComponentAdapter for ComponentAdapterImpl<T> {
         Ok(())
     }
 
+    fn remove_component(&self, world: &mut World, entity: Entity) {
+        world.entity_mut(entity).remove::<T>();
+    }
+
     fn added_entities(&self, world: &mut World) -> Vec<Entity> {
         let mut query = world.query_filtered::<Entity, bevy_ecs::query::Added<T>>();
         query.iter(world).collect()
@@ -239,10 +244,7 @@ impl BevySchema {
         if components.is_empty() {
             None
         } else {
-            Some(DeltaUpdateEntity {
-                id: entity_id,
-                components,
-            })
+            Some(DeltaUpdateEntity::new(entity_id, components))
         }
     }
 }
@@ -122,6 +122,7 @@ fn main() -> Result<()> {
 
     let mut sdec = EncoderStats::default();
     let mut sdec_dirty = EncoderStats::default();
+    let mut sdec_entropy = EncoderStats::default();
     let mut naive = EncoderStats::default();
     let mut bincode_full = SizeTimeStats::default();
     let mut lightyear_bitcode = SizeTimeStats::default();
@@ -151,8 +152,8 @@ fn main() -> Result<()> {
         let init_len = codec::encode_session_init_packet(
             &schema,
             codec::SnapshotTick::new(0),
-            Some(1),
-            codec::CompactHeaderMode::SessionV1,
+            &codec::SessionInitOptions::new(codec::CompactHeaderMode::SessionV1)
+                .session_id(Some(1)),
             &limits,
             &mut init_buf,
         )?;
@@ -205,6 +206,10 @@ fn main() -> Result<()> {
             let elapsed = start.elapsed();
             let sdec_us = elapsed.as_micros() as u64;
             sdec.add_with_tick(delta_bytes.len() as u64, sdec_us, tick);
+            let entropy_start = Instant::now();
+            let entropy_bytes = entropy_code_delta(&delta_bytes)?;
+            let entropy_us = sdec_us + entropy_start.elapsed().as_micros() as u64;
+            sdec_entropy.add_with_tick(entropy_bytes as u64, entropy_us, tick);
             let dirty_updates = build_dirty_updates(&schema, &baseline_snapshot, &snapshot)?;
             let dirty_start = Instant::now();
             let dirty_bytes = encode_dirty_delta(
@@ -306,6 +311,7 @@ fn main() -> Result<()> {
         full_bytes_total,
         sdec,
         sdec_dirty,
+        sdec_entropy,
         naive,
         bincode_delta,
         bincode_full,
@@ -405,6 +411,11 @@ fn encode_delta_with_scratch(
     Ok(buf)
 }
 
+fn entropy_code_delta(delta_bytes: &[u8]) -> Result<usize> {
+    let mut buf = delta_bytes.to_vec();
+    codec::entropy_code_update_sections(&mut buf, delta_bytes.len()).context("entropy code delta")
+}
+
 fn encode_dirty_delta(
     session: &mut SessionEncoder<'_>,
     tick: codec::SnapshotTick,
@@ -519,12 +530,17 @@ struct SizeTimeSummary {
     encode_us_p95: u64,
 }
 
+/// Comparison formats keep 32-bit ids, which every bench scenario fits.
+fn bench_id(id: codec::EntityId) -> u32 {
+    u32::try_from(id).expect("bench entity ids fit in u32")
+}
+
 fn encode_bincode_snapshot(states: &[DemoEntityState]) -> Result<usize> {
     let snapshot = SerdeSnapshot {
         entities: states
             .iter()
             .map(|state| SerdeEntity {
-                id: state.id.raw(),
+                id: bench_id(state.id),
                 pos_q: state.pos_q,
                 vel_q: state.vel_q,
                 yaw: state.yaw,
@@ -541,7 +557,7 @@ fn encode_bitcode_snapshot(states: &[DemoEntityState]) -> Result<usize> {
         entities: states
             .iter()
             .map(|state| SerdeEntity {
-                id: state.id.raw(),
+                id: bench_id(state.id),
                 pos_q: state.pos_q,
                 vel_q: state.vel_q,
                 yaw: state.yaw,
@@ -560,7 +576,7 @@ fn encode_bincode_delta(
 ) -> Result<usize> {
     let mut entities = Vec::new();
     for entity in &current.entities {
-        let base = baseline.entities.iter().find(|e| e.id == entity.id);
+        let base = baseline.get(entity.id);
         if let Some(base) = base {
             let changed_fields = diff_entity_fields(schema, base, entity)?;
             if !changed_fields.is_empty() {
@@ -572,7 +588,7 @@ fn encode_bincode_delta(
                     })
                     .collect();
                 entities.push(SerdeDeltaEntity {
-                    id: entity.id.raw(),
+                    id: bench_id(entity.id),
                     fields,
                 });
             }
@@ -599,7 +615,7 @@ fn encode_lightyear_delta(
         let delta = prev_state.diff(&curr_state);
         if delta.mask != 0 {
             entities.push(LightyearDeltaEntity {
-                id: curr.id.raw(),
+                id: bench_id(curr.id),
                 delta: LightyearDeltaMessage {
                     delta_type: DeltaType::Normal { previous_tick },
                     delta,
@@ -625,7 +641,7 @@ fn encode_naive_delta(
     let mut entity_offsets = Vec::new();
 
     for entity in &current.entities {
-        let base = baseline.entities.iter().find(|e| e.id == entity.id);
+        let base = baseline.get(entity.id);
         if let Some(base) = base {
             let changed_fields = diff_entity_fields(schema, base, entity)?;
             if !changed_fields.is_empty() {
@@ -637,7 +653,7 @@ fn encode_naive_delta(
 
     writer.write_varu32(changed_entities)?;
     for (entity_id, fields) in entity_offsets {
-        writer.write_varu32(entity_id)?;
+        writer.write_varu64(entity_id)?;
         writer.write_varu32(fields.len() as u32)?;
         for (field_idx, value) in fields {
             writer.write_varu32(field_idx as u32)?;
@@ -942,9 +958,9 @@ impl<'a> WorldView for SimbenchWorldView<'a> {
             return None;
         }
         let state = self.states.iter().find(|state| state.id == entity)?;
-        Some(DeltaUpdateEntity {
-            id: state.id,
-            components: vec![DeltaUpdateComponent {
+        Some(DeltaUpdateEntity::new(
+            state.id,
+            vec![DeltaUpdateComponent {
                 id: component_id(),
                 fields: vec![
                     (0, codec::FieldValue::FixedPoint(state.pos_q[0])),
@@ -959,7 +975,7 @@ impl<'a> WorldView for SimbenchWorldView<'a> {
                     (9, codec::FieldValue::Bool(state.flags[2])),
                 ],
             }],
-        })
+        ))
     }
 }
 
@@ -968,7 +984,7 @@ fn init_states(players: u32, rng: &mut Rng, world_size_q: i64) -> Vec<DemoEntity
     let grid = (players as f64).sqrt().ceil() as u32;
     let spacing = (world_size_q / grid.max(1) as i64).max(1);
     for idx in 0..players {
-        let id = codec::EntityId::new(idx + 1);
+        let id = codec::EntityId::from(idx + 1);
         let row = idx / grid;
         let col = idx % grid;
         let base_x = (col as i64 * spacing) - world_size_q / 2;
@@ -1251,10 +1267,7 @@ fn build_dirty_updates(
                         }
                     }
                     if !component_updates.is_empty() {
-                        updates.push(DeltaUpdateEntity {
-                            id: c.id,
-                            components: component_updates,
-                        });
+                        updates.push(DeltaUpdateEntity::new(c.id, component_updates));
                     }
                     i += 1;
                     j += 1;
@@ -1372,6 +1385,8 @@ struct Summary {
     scenario: ScenarioConfig,
     sdec: EncoderSummary,
     sdec_dirty: EncoderSummary,
+    sdec_entropy: EncoderSummary,
+    entropy_savings_bytes_total: u64,
     delta_naive: EncoderSummary,
     delta_bincode: EncoderSummary,
     lightyear_delta: EncoderSummary,
@@ -1390,6 +1405,7 @@ impl Summary {
         full_bytes_total: u64,
         mut sdec: EncoderStats,
         mut sdec_dirty: EncoderStats,
+        mut sdec_entropy: EncoderStats,
         mut naive: EncoderStats,
         mut bincode_delta: EncoderStats,
         mut bincode_full: SizeTimeStats,
@@ -1401,6 +1417,10 @@ impl Summary {
     ) -> Self {
         let sdec_summary = sdec.finalize();
         let sdec_dirty_summary = sdec_dirty.finalize();
+        let sdec_entropy_summary = sdec_entropy.finalize();
+        let entropy_savings_bytes_total = sdec_summary
+            .delta_bytes_total
+            .saturating_sub(sdec_entropy_summary.delta_bytes_total);
         let naive_summary = naive.finalize();
         let bincode_delta_summary = bincode_delta.finalize();
         let bincode_full_summary = bincode_full.finalize();
@@ -1433,6 +1453,8 @@ impl Summary {
             scenario: ScenarioConfig::from(cli),
             sdec: sdec_summary,
             sdec_dirty: sdec_dirty_summary,
+            sdec_entropy: sdec_entropy_summary,
+            entropy_savings_bytes_total,
             delta_naive: naive_summary,
             delta_bincode: bincode_delta_summary,
             lightyear_delta: lightyear_delta_summary,
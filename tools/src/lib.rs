@@ -2,22 +2,28 @@
 //!
 //! This crate provides utilities for inspecting and understanding encoded packets.
 
+mod train;
+
 use std::collections::BTreeMap;
 
 use anyhow::{Context, Result};
 use bitstream::BitReader;
 use codec::{
     decode_delta_packet, decode_full_snapshot_from_packet, CodecLimits, DeltaDecoded,
-    DeltaUpdateEntity, FieldValue, Snapshot,
+    DeltaUpdateEntity, FieldValue, Snapshot, UpdateValue,
 };
 use serde::Serialize;
 use serde_json::{json, Value};
 use wire::{decode_packet, PacketHeader, SectionTag, WirePacket};
 
+pub use train::{train_model, CaptureFile, TrainReport};
+
 #[derive(Debug, Clone)]
 pub struct InspectReport {
     pub header: PacketHeader,
     pub sections: Vec<SectionReport>,
+    /// Optional sections with tags this build does not know.
+    pub skipped: Vec<SkippedSectionReport>,
     pub update_summary: Option<UpdateSummary>,
 }
 
@@ -28,6 +34,13 @@ pub struct SectionReport {
     pub entity_count: Option<usize>,
 }
 
+#[derive(Debug, Clone)]
+pub struct SkippedSectionReport {
+    pub tag: u8,
+    pub offset: usize,
+    pub byte_len: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct UpdateSummary {
     pub changed_components: usize,
@@ -70,7 +83,7 @@ pub struct FullSnapshotOutput {
 
 #[derive(Debug, Serialize)]
 pub struct FullEntityOutput {
-    pub id: u32,
+    pub id: u64,
     pub components: Vec<FullComponentOutput>,
 }
 
@@ -82,14 +95,14 @@ pub struct FullComponentOutput {
 
 #[derive(Debug, Serialize)]
 pub struct DeltaSnapshotOutput {
-    pub destroys: Vec<u32>,
+    pub destroys: Vec<u64>,
     pub creates: Vec<FullEntityOutput>,
     pub updates: Vec<DeltaUpdateEntityOutput>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct DeltaUpdateEntityOutput {
-    pub id: u32,
+    pub id: u64,
     pub components: Vec<DeltaUpdateComponentOutput>,
 }
 
@@ -184,7 +197,9 @@ pub fn inspect_packet(
             | SectionTag::EntityCreate
             | SectionTag::EntityUpdate
             | SectionTag::EntityUpdateSparse
-            | SectionTag::EntityUpdateSparsePacked => {
+            | SectionTag::EntityUpdateSparsePacked
+            | SectionTag::EntityComponentOps
+            | SectionTag::EntityBaselineRef => {
                 Some(read_section_count(section.body).context("read section count")?)
             }
             _ => None,
@@ -205,9 +220,20 @@ pub fn inspect_packet(
         _ => None,
     };
 
+    let skipped = packet
+        .skipped
+        .iter()
+        .map(|section| SkippedSectionReport {
+            tag: section.tag,
+            offset: section.offset,
+            byte_len: section.body.len(),
+        })
+        .collect();
+
     Ok(InspectReport {
         header: packet.header,
         sections,
+        skipped,
         update_summary,
     })
 }
@@ -270,7 +296,7 @@ fn read_section_count(body: &[u8]) -> Result<usize> {
     Ok(count)
 }
 
-fn summarize_updates(updates: &[DeltaUpdateEntity]) -> UpdateSummary {
+fn summarize_updates(updates: &[DeltaUpdateEntity<UpdateValue>]) -> UpdateSummary {
     let mut changed_components = 0usize;
     let mut changed_fields = 0usize;
     let mut by_component: BTreeMap<u16, usize> = BTreeMap::new();
@@ -367,7 +393,7 @@ fn delta_snapshot_output(delta: &DeltaDecoded) -> DeltaSnapshotOutput {
                             .iter()
                             .map(|(index, value)| DeltaUpdateFieldOutput {
                                 index: *index,
-                                value: field_value_output(*index, *value),
+                                value: update_value_output(*index, *value),
                             })
                             .collect(),
                     })
@@ -377,6 +403,16 @@ fn delta_snapshot_output(delta: &DeltaDecoded) -> DeltaSnapshotOutput {
     }
 }
 
+fn update_value_output(index: usize, value: UpdateValue) -> FieldValueOutput {
+    match value {
+        UpdateValue::Value(value) => field_value_output(index, value),
+        UpdateValue::Residual(value) => FieldValueOutput {
+            kind: format!("residual[{}]", index),
+            value: json!(value),
+        },
+    }
+}
+
 fn field_value_output(index: usize, value: FieldValue) -> FieldValueOutput {
     let (kind, value) = match value {
         FieldValue::Bool(value) => ("bool", json!(value)),
@@ -458,6 +494,40 @@ mod tests {
         assert_eq!(summary.changed_fields, 1);
     }
 
+    #[test]
+    fn inspect_reports_skipped_optional_sections() {
+        let schema = schema_one_bool();
+        let baseline = baseline_snapshot();
+        let mut buf = [0u8; 128];
+        let bytes = encode_full_snapshot(
+            &schema,
+            baseline.tick,
+            &baseline.entities,
+            &CodecLimits::for_testing(),
+            &mut buf,
+        )
+        .unwrap();
+        let mut packet = buf[..bytes].to_vec();
+        packet.extend_from_slice(&[0x90, 2, 0, 0]);
+        let payload_len = (packet.len() - wire::HEADER_SIZE) as u32;
+        packet[wire::HEADER_SIZE - 4..wire::HEADER_SIZE]
+            .copy_from_slice(&payload_len.to_le_bytes());
+
+        let report = inspect_packet(
+            &packet,
+            Some(&schema),
+            &wire::Limits::for_testing(),
+            &CodecLimits::for_testing(),
+        )
+        .unwrap();
+
+        assert_eq!(report.sections.len(), 1);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].tag, 0x90);
+        assert_eq!(report.skipped[0].offset, bytes - wire::HEADER_SIZE);
+        assert_eq!(report.skipped[0].byte_len, 2);
+    }
+
     #[test]
     fn decode_full_snapshot_json() {
         let schema = schema_one_bool();
@@ -4,7 +4,10 @@ use std::path::PathBuf;
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand, ValueEnum};
 use glob::Pattern;
-use sdec_tools::{decode_packet_json, format_decode_pretty, inspect_packet, InspectReport};
+use sdec_tools::{
+    decode_packet_json, format_decode_pretty, inspect_packet, train_model, CaptureFile,
+    InspectReport,
+};
 
 #[derive(Parser)]
 #[command(
@@ -47,6 +50,26 @@ enum Command {
         #[arg(long, value_enum, default_value_t = DecodeFormat::Json)]
         format: DecodeFormat,
     },
+    /// Train a codec model from a demo-sim capture directory.
+    Train {
+        /// Capture directory (packets plus session_init.bin).
+        capture_dir: PathBuf,
+        /// Schema JSON (defaults to <capture_dir>/schema.json).
+        #[arg(long)]
+        schema: Option<PathBuf>,
+        /// Output path for the model artifact.
+        #[arg(long, default_value = "model.bin")]
+        out: PathBuf,
+        /// Most frequent values kept per field.
+        #[arg(long, default_value_t = codec::ModelTrainingConfig::default().max_values_per_field)]
+        max_values_per_field: usize,
+        /// Most frequent create templates kept.
+        #[arg(long, default_value_t = codec::ModelTrainingConfig::default().max_create_templates)]
+        max_create_templates: usize,
+        /// Compression dictionary size in bytes.
+        #[arg(long, default_value_t = codec::ModelTrainingConfig::default().dictionary_bytes)]
+        dictionary_bytes: usize,
+    },
 }
 
 #[derive(Clone, Copy, Debug, ValueEnum)]
@@ -130,6 +153,71 @@ fn main() -> Result<()> {
                 }
             }
         }
+        Command::Train {
+            capture_dir,
+            schema,
+            out,
+            max_values_per_field,
+            max_create_templates,
+            dictionary_bytes,
+        } => {
+            let schema_path = schema.unwrap_or_else(|| capture_dir.join("schema.json"));
+            let schema = load_schema(&schema_path).context("load schema")?;
+            let session_init_path = capture_dir.join("session_init.bin");
+            let session_init = if session_init_path.is_file() {
+                Some(
+                    fs::read(&session_init_path)
+                        .with_context(|| format!("read {}", session_init_path.display()))?,
+                )
+            } else {
+                None
+            };
+            let captures = collect_packet_entries(&capture_dir, Some("*_client_*.bin"))?
+                .into_iter()
+                .map(|entry| {
+                    let name = entry
+                        .path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .unwrap_or_default()
+                        .to_string();
+                    let bytes = fs::read(&entry.path)
+                        .with_context(|| format!("read packet {}", entry.path.display()))?;
+                    Ok(CaptureFile { name, bytes })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let config = codec::ModelTrainingConfig {
+                max_values_per_field,
+                max_create_templates,
+                dictionary_bytes,
+            };
+            let report = train_model(
+                &schema,
+                session_init.as_deref(),
+                &captures,
+                &config,
+                &wire::Limits::default(),
+                &codec::CodecLimits::default(),
+            )?;
+            let bytes = report.model.to_bytes();
+            fs::write(&out, &bytes).with_context(|| format!("write model {}", out.display()))?;
+            println!(
+                "trained on {} full and {} delta packets ({} files skipped)",
+                report.full_packets, report.delta_packets, report.skipped_files
+            );
+            println!(
+                "model: {} ({} bytes) hash: 0x{:016x}",
+                out.display(),
+                bytes.len(),
+                report.model.hash()
+            );
+            println!(
+                "  field histograms: {} create templates: {} dictionary: {} bytes",
+                report.model.field_histograms.len(),
+                report.model.create_templates.len(),
+                report.model.dictionary.len()
+            );
+        }
     }
     Ok(())
 }
@@ -218,6 +306,12 @@ fn print_inspect_report(report: &InspectReport) {
             .unwrap_or_else(|| "count n/a".to_string());
         println!("  {tag}: {count} ({} bytes)", section.byte_len);
     }
+    for section in &report.skipped {
+        println!(
+            "  unknown optional 0x{:02x} at offset {}: skipped ({} bytes)",
+            section.tag, section.offset, section.byte_len
+        );
+    }
     let update_encoding = if report
         .sections
         .iter()
@@ -0,0 +1,249 @@
+//! Model training from `demo-sim` capture directories.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use codec::{
+    decode_delta_packet, decode_full_snapshot, decode_session_init_packet, decode_session_packet,
+    CodecLimits, CodecModel, ModelTrainer, ModelTrainingConfig,
+};
+use wire::decode_packet;
+
+/// A captured packet file.
+#[derive(Debug, Clone)]
+pub struct CaptureFile {
+    /// File name, e.g. `delta_client_001_tick_000002_base_000001.bin`.
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Result of training a model from captures.
+#[derive(Debug, Clone)]
+pub struct TrainReport {
+    pub model: CodecModel,
+    pub full_packets: usize,
+    pub delta_packets: usize,
+    pub skipped_files: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum CaptureKind {
+    Full,
+    Delta,
+}
+
+/// Trains a model from `demo-sim` captures.
+///
+/// Full snapshots (`full_client_*`) are decoded directly. Session deltas
+/// (`delta_client_*`) are replayed per client in tick order from
+/// `session_init`, since their compact headers are relative to the previous
+/// tick. Files with other names are skipped.
+pub fn train_model(
+    schema: &schema::Schema,
+    session_init: Option<&[u8]>,
+    captures: &[CaptureFile],
+    config: &ModelTrainingConfig,
+    wire_limits: &wire::Limits,
+    codec_limits: &CodecLimits,
+) -> Result<TrainReport> {
+    let mut trainer = ModelTrainer::new(schema);
+    let mut ordered: BTreeMap<(CaptureKind, u32, u32), &CaptureFile> = BTreeMap::new();
+    let mut skipped_files = 0;
+    for capture in captures {
+        match parse_capture_name(&capture.name) {
+            Some(key) => {
+                ordered.insert(key, capture);
+            }
+            None => skipped_files += 1,
+        }
+    }
+
+    let session = session_init
+        .map(|bytes| {
+            let packet = decode_packet(bytes, wire_limits).context("decode session init")?;
+            decode_session_init_packet(schema, &packet, codec_limits)
+                .context("decode session init packet")
+        })
+        .transpose()?;
+
+    let mut full_packets = 0;
+    let mut delta_packets = 0;
+    let mut sessions = BTreeMap::new();
+    for ((kind, client, _), capture) in ordered {
+        match kind {
+            CaptureKind::Full => {
+                let snapshot =
+                    decode_full_snapshot(schema, &capture.bytes, wire_limits, codec_limits)
+                        .with_context(|| format!("decode {}", capture.name))?;
+                for entity in &snapshot.entities {
+                    trainer.observe_create(entity);
+                }
+                trainer.observe_payload(&capture.bytes[wire::HEADER_SIZE..]);
+                full_packets += 1;
+            }
+            CaptureKind::Delta => {
+                let initial = session
+                    .as_ref()
+                    .context("delta captures require a session init packet")?;
+                let session = sessions.entry(client).or_insert_with(|| initial.clone());
+                let header = wire::decode_session_header(&capture.bytes, session.last_tick.raw())
+                    .with_context(|| format!("decode session header {}", capture.name))?;
+                let packet = decode_session_packet(schema, session, &capture.bytes, wire_limits)
+                    .with_context(|| format!("decode {}", capture.name))?;
+                let delta = decode_delta_packet(schema, &packet, codec_limits)
+                    .with_context(|| format!("decode delta {}", capture.name))?;
+                for entity in &delta.creates {
+                    trainer.observe_create(entity);
+                }
+                trainer.observe_updates(&delta.updates);
+                trainer.observe_payload(&capture.bytes[header.header_len..]);
+                delta_packets += 1;
+            }
+        }
+    }
+
+    Ok(TrainReport {
+        model: trainer.finish(config),
+        full_packets,
+        delta_packets,
+        skipped_files,
+    })
+}
+
+/// Parses `full_client_<c>_tick_<t>.bin` and
+/// `delta_client_<c>_tick_<t>_base_<b>.bin`.
+fn parse_capture_name(name: &str) -> Option<(CaptureKind, u32, u32)> {
+    let stem = name.strip_suffix(".bin")?;
+    let parts: Vec<&str> = stem.split('_').collect();
+    let kind = match parts.as_slice() {
+        ["full", "client", _, "tick", _] => CaptureKind::Full,
+        ["delta", "client", _, "tick", _, "base", _] => CaptureKind::Delta,
+        _ => return None,
+    };
+    Some((kind, parts[2].parse().ok()?, parts[4].parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::{
+        encode_delta_snapshot_for_client_session, encode_full_snapshot, encode_session_init_packet,
+        CompactHeaderMode, ComponentSnapshot, EntityId, EntitySnapshot, FieldValue,
+        SessionInitOptions, Snapshot, SnapshotTick,
+    };
+    use schema::{ComponentDef, ComponentId, FieldCodec, FieldDef, FieldId, Schema};
+
+    fn schema() -> Schema {
+        let component = ComponentDef::new(ComponentId::new(1).unwrap())
+            .field(FieldDef::new(FieldId::new(1).unwrap(), FieldCodec::uint(8)));
+        Schema::new(vec![component]).unwrap()
+    }
+
+    fn snapshot(tick: u32, value: u64) -> Snapshot {
+        Snapshot {
+            tick: SnapshotTick::new(tick),
+            entities: (1..=4)
+                .map(|id| EntitySnapshot {
+                    id: EntityId::new(id),
+                    components: vec![ComponentSnapshot {
+                        id: ComponentId::new(1).unwrap(),
+                        fields: vec![FieldValue::UInt(value)],
+                    }],
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn parse_capture_names() {
+        assert_eq!(
+            parse_capture_name("full_client_002_tick_000001.bin"),
+            Some((CaptureKind::Full, 2, 1))
+        );
+        assert_eq!(
+            parse_capture_name("delta_client_001_tick_000003_base_000002.bin"),
+            Some((CaptureKind::Delta, 1, 3))
+        );
+        assert_eq!(parse_capture_name("schema.json"), None);
+        assert_eq!(parse_capture_name("session_init.bin"), None);
+    }
+
+    #[test]
+    fn train_from_session_captures() {
+        let schema = schema();
+        let limits = CodecLimits::for_testing();
+        let wire_limits = wire::Limits::for_testing();
+
+        let mut init = vec![0u8; 64];
+        let init_len = encode_session_init_packet(
+            &schema,
+            SnapshotTick::new(1),
+            &SessionInitOptions::new(CompactHeaderMode::SessionV1).session_id(Some(7)),
+            &limits,
+            &mut init,
+        )
+        .unwrap();
+        init.truncate(init_len);
+
+        let mut captures = Vec::new();
+        let first = snapshot(1, 3);
+        let mut buf = vec![0u8; 512];
+        let len =
+            encode_full_snapshot(&schema, first.tick, &first.entities, &limits, &mut buf).unwrap();
+        captures.push(CaptureFile {
+            name: "full_client_001_tick_000001.bin".to_string(),
+            bytes: buf[..len].to_vec(),
+        });
+
+        let mut last_tick = SnapshotTick::new(1);
+        let mut baseline = first;
+        for tick in 2..=3 {
+            let current = snapshot(tick, 3 + u64::from(tick));
+            let len = encode_delta_snapshot_for_client_session(
+                &schema,
+                current.tick,
+                baseline.tick,
+                &baseline,
+                &current,
+                &limits,
+                &mut last_tick,
+                &mut buf,
+            )
+            .unwrap();
+            captures.push(CaptureFile {
+                name: format!(
+                    "delta_client_001_tick_{tick:06}_base_{:06}.bin",
+                    baseline.tick.raw()
+                ),
+                bytes: buf[..len].to_vec(),
+            });
+            baseline = current;
+        }
+        // Shuffled input order must not matter.
+        captures.reverse();
+        captures.push(CaptureFile {
+            name: "summary.json".to_string(),
+            bytes: Vec::new(),
+        });
+
+        let report = train_model(
+            &schema,
+            Some(&init),
+            &captures,
+            &ModelTrainingConfig::default(),
+            &wire_limits,
+            &limits,
+        )
+        .unwrap();
+        assert_eq!(report.full_packets, 1);
+        assert_eq!(report.delta_packets, 2);
+        assert_eq!(report.skipped_files, 1);
+
+        let histogram = report
+            .model
+            .histogram(ComponentId::new(1).unwrap(), FieldId::new(1).unwrap())
+            .unwrap();
+        assert_eq!(histogram.values, vec![(3, 4), (5, 4), (6, 4)]);
+        assert_eq!(report.model.create_templates[0].count, 4);
+    }
+}
@@ -0,0 +1,341 @@
+//! Receiver-to-sender ack packets.
+//!
+//! An ack packet reports the newest tick the receiver has applied, which of
+//! the ticks just before it were received, and optionally the class of a
+//! decode error that requires the sender to resync. It is framed either with
+//! the standard header (`ACK` flag) or a compact session header.
+
+use crate::error::{DecodeError, EncodeError, WireResult};
+use crate::header::{PacketFlags, PacketHeader, HEADER_SIZE, VERSION};
+use crate::limits::Limits;
+use crate::packet::{
+    decode_sections, encode_header, encode_section, read_varu32, write_varu32, SectionTag,
+    WirePacket, WireSection,
+};
+use crate::session::{decode_session_header, encode_session_header, SessionFlags};
+
+/// Number of ticks before `latest_tick` covered by the received bitfield.
+pub const ACK_WINDOW: u32 = 32;
+
+/// Maximum encoded size of an ack section body in bytes.
+const ACK_MAX_BODY_SIZE: usize = 5 + 4 + 1;
+
+/// Class of a receiver decode error reported in an ack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum AckErrorClass {
+    /// Session state is missing, invalid or out of order.
+    Session = 1,
+    /// The schema hash does not match.
+    Schema = 2,
+    /// The referenced baseline is missing or mismatched.
+    Baseline = 3,
+    /// The packet header or framing is invalid.
+    Framing = 4,
+}
+
+impl AckErrorClass {
+    /// Parses an error class from a raw byte.
+    #[must_use]
+    pub const fn parse(raw: u8) -> Option<Self> {
+        match raw {
+            1 => Some(Self::Session),
+            2 => Some(Self::Schema),
+            3 => Some(Self::Baseline),
+            4 => Some(Self::Framing),
+            _ => None,
+        }
+    }
+}
+
+/// Decoded ack packet contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AckPacket {
+    /// Newest tick the receiver has applied.
+    pub latest_tick: u32,
+    /// Bit `i` set means tick `latest_tick - 1 - i` was received.
+    pub received_mask: u32,
+    /// Decode error that requires a resync, if any.
+    pub error: Option<AckErrorClass>,
+}
+
+impl AckPacket {
+    /// Creates an ack for `latest_tick` with no other ticks marked received.
+    #[must_use]
+    pub const fn new(latest_tick: u32) -> Self {
+        Self {
+            latest_tick,
+            received_mask: 0,
+            error: None,
+        }
+    }
+
+    /// Returns this ack with a resync error class attached.
+    #[must_use]
+    pub const fn with_error(mut self, error: AckErrorClass) -> Self {
+        self.error = Some(error);
+        self
+    }
+
+    /// Marks `tick` as received. Returns `false` if it is outside the
+    /// [`ACK_WINDOW`] ticks before `latest_tick`.
+    pub fn mark_received(&mut self, tick: u32) -> bool {
+        match self.window_bit(tick) {
+            Some(bit) => {
+                self.received_mask |= 1 << bit;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns `true` if `tick` is `latest_tick` or marked received.
+    #[must_use]
+    pub fn was_received(&self, tick: u32) -> bool {
+        tick == self.latest_tick
+            || self
+                .window_bit(tick)
+                .is_some_and(|bit| self.received_mask & (1 << bit) != 0)
+    }
+
+    /// Returns the received ticks, newest first, starting with `latest_tick`.
+    pub fn received_ticks(&self) -> impl Iterator<Item = u32> + '_ {
+        std::iter::once(self.latest_tick).chain(
+            (0..ACK_WINDOW)
+                .filter(|bit| self.received_mask & (1 << bit) != 0)
+                .map(|bit| self.latest_tick - 1 - bit),
+        )
+    }
+
+    fn window_bit(&self, tick: u32) -> Option<u32> {
+        let distance = self.latest_tick.checked_sub(tick)?;
+        (1..=ACK_WINDOW).contains(&distance).then(|| distance - 1)
+    }
+}
+
+/// Encodes an ack packet with the standard header.
+///
+/// The header carries `latest_tick` as its tick and a zero baseline tick.
+pub fn encode_ack_packet(
+    ack: &AckPacket,
+    schema_hash: u64,
+    out: &mut [u8],
+) -> Result<usize, EncodeError> {
+    if out.len() < HEADER_SIZE {
+        return Err(EncodeError::BufferTooSmall {
+            needed: HEADER_SIZE,
+            available: out.len(),
+        });
+    }
+    let payload_len = encode_ack_section(ack, &mut out[HEADER_SIZE..])?;
+    let header = PacketHeader {
+        version: VERSION,
+        flags: PacketFlags::ack(),
+        schema_hash,
+        tick: ack.latest_tick,
+        baseline_tick: 0,
+        payload_len: payload_len as u32,
+    };
+    encode_header(&header, out)?;
+    Ok(HEADER_SIZE + payload_len)
+}
+
+/// Decodes the ack carried by a packet with the `ACK` flag.
+///
+/// The packet must hold exactly one `ACK` section whose tick matches the header.
+pub fn decode_ack_packet(packet: &WirePacket<'_>) -> WireResult<AckPacket> {
+    let flags = packet.header.flags;
+    if !flags.is_ack() {
+        return Err(DecodeError::InvalidFlags { flags: flags.raw() });
+    }
+    let ack = decode_ack_sections(&packet.sections)?;
+    if ack.latest_tick != packet.header.tick {
+        return Err(DecodeError::InvalidAck);
+    }
+    Ok(ack)
+}
+
+/// Encodes an ack packet with a compact session header.
+pub fn encode_session_ack_packet(ack: &AckPacket, out: &mut [u8]) -> Result<usize, EncodeError> {
+    let mut section = [0u8; 1 + 1 + ACK_MAX_BODY_SIZE];
+    let payload_len = encode_ack_section(ack, &mut section)?;
+    let header_len = encode_session_header(out, SessionFlags::ack(), 0, 0, payload_len as u32)?;
+    let needed = header_len + payload_len;
+    if out.len() < needed {
+        return Err(EncodeError::BufferTooSmall {
+            needed,
+            available: out.len(),
+        });
+    }
+    out[header_len..needed].copy_from_slice(&section[..payload_len]);
+    Ok(needed)
+}
+
+/// Decodes an ack packet framed with a compact session header.
+pub fn decode_session_ack_packet(buf: &[u8], limits: &Limits) -> WireResult<AckPacket> {
+    if buf.len() > limits.max_packet_bytes {
+        return Err(DecodeError::LimitsExceeded {
+            kind: crate::error::LimitKind::PacketBytes,
+            limit: limits.max_packet_bytes,
+            actual: buf.len(),
+        });
+    }
+    let header = decode_session_header(buf, 0)?;
+    if !header.flags.is_ack() {
+        return Err(DecodeError::InvalidFlags {
+            flags: u16::from(header.flags.raw()),
+        });
+    }
+    let payload = &buf[header.header_len..];
+    if payload.len() != header.payload_len as usize {
+        return Err(DecodeError::PayloadLengthMismatch {
+            header_len: header.payload_len,
+            actual_len: payload.len(),
+        });
+    }
+    decode_ack_sections(&decode_sections(payload, limits)?)
+}
+
+/// Decodes an `ACK` section body.
+pub fn decode_ack_body(body: &[u8]) -> WireResult<AckPacket> {
+    let (latest_tick, offset) = read_varu32(body, 0)?;
+    let mask_bytes = body
+        .get(offset..offset + 4)
+        .ok_or(DecodeError::InvalidAck)?;
+    let received_mask = u32::from_le_bytes(mask_bytes.try_into().unwrap());
+    let error = match &body[offset + 4..] {
+        [] => None,
+        [raw] => Some(AckErrorClass::parse(*raw).ok_or(DecodeError::InvalidAck)?),
+        _ => return Err(DecodeError::InvalidAck),
+    };
+    // Bits for ticks before tick 0 cannot be set.
+    if latest_tick < ACK_WINDOW && received_mask >> latest_tick != 0 {
+        return Err(DecodeError::InvalidAck);
+    }
+    Ok(AckPacket {
+        latest_tick,
+        received_mask,
+        error,
+    })
+}
+
+fn decode_ack_sections(sections: &[WireSection<'_>]) -> WireResult<AckPacket> {
+    match sections {
+        [section] if section.tag == SectionTag::Ack => decode_ack_body(section.body),
+        _ => Err(DecodeError::InvalidAck),
+    }
+}
+
+fn encode_ack_section(ack: &AckPacket, out: &mut [u8]) -> Result<usize, EncodeError> {
+    let mut body = [0u8; ACK_MAX_BODY_SIZE];
+    let mut len = write_varu32(ack.latest_tick, &mut body);
+    body[len..len + 4].copy_from_slice(&ack.received_mask.to_le_bytes());
+    len += 4;
+    if let Some(error) = ack.error {
+        body[len] = error as u8;
+        len += 1;
+    }
+    encode_section(SectionTag::Ack, &body[..len], out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::decode_packet;
+
+    fn sample_ack() -> AckPacket {
+        let mut ack = AckPacket::new(100);
+        assert!(ack.mark_received(99));
+        assert!(ack.mark_received(97));
+        assert!(ack.mark_received(68));
+        assert!(!ack.mark_received(67));
+        assert!(!ack.mark_received(101));
+        ack
+    }
+
+    #[test]
+    fn ack_window_bits() {
+        let ack = sample_ack();
+        assert_eq!(ack.received_mask, 0b101 | 1 << 31);
+        assert!(ack.was_received(100));
+        assert!(ack.was_received(97));
+        assert!(!ack.was_received(98));
+        assert_eq!(
+            ack.received_ticks().collect::<Vec<_>>(),
+            vec![100, 99, 97, 68]
+        );
+    }
+
+    #[test]
+    fn ack_packet_roundtrip_standard_header() {
+        let limits = Limits::for_testing();
+        for ack in [
+            sample_ack(),
+            sample_ack().with_error(AckErrorClass::Baseline),
+        ] {
+            let mut buf = [0u8; 64];
+            let len = encode_ack_packet(&ack, 0xABCD, &mut buf).unwrap();
+            let packet = decode_packet(&buf[..len], &limits).unwrap();
+            assert!(packet.header.flags.is_ack());
+            assert_eq!(packet.header.schema_hash, 0xABCD);
+            assert_eq!(packet.header.tick, 100);
+            assert_eq!(decode_ack_packet(&packet).unwrap(), ack);
+        }
+    }
+
+    #[test]
+    fn ack_packet_roundtrip_session_header() {
+        let limits = Limits::for_testing();
+        let ack = sample_ack().with_error(AckErrorClass::Session);
+        let mut buf = [0u8; 32];
+        let len = encode_session_ack_packet(&ack, &mut buf).unwrap();
+        // flags + payload_len + tag + section_len + tick(1) + mask(4) + error(1)
+        assert_eq!(len, 2 + 2 + 1 + 4 + 1);
+        assert_eq!(
+            decode_session_ack_packet(&buf[..len], &limits).unwrap(),
+            ack
+        );
+
+        let err = decode_session_ack_packet(&buf[..len - 1], &limits).unwrap_err();
+        assert!(matches!(err, DecodeError::PayloadLengthMismatch { .. }));
+    }
+
+    #[test]
+    fn ack_rejects_malformed_bodies() {
+        // Truncated mask.
+        assert_eq!(decode_ack_body(&[5, 0, 0]), Err(DecodeError::InvalidAck));
+        // Unknown error class.
+        assert_eq!(
+            decode_ack_body(&[5, 0, 0, 0, 0, 9]),
+            Err(DecodeError::InvalidAck)
+        );
+        // Trailing bytes.
+        assert_eq!(
+            decode_ack_body(&[5, 0, 0, 0, 0, 1, 0]),
+            Err(DecodeError::InvalidAck)
+        );
+        // Bit for a tick before 0.
+        assert_eq!(
+            decode_ack_body(&[2, 0b100, 0, 0, 0]),
+            Err(DecodeError::InvalidAck)
+        );
+        assert!(decode_ack_body(&[2, 0b11, 0, 0, 0]).is_ok());
+    }
+
+    #[test]
+    fn ack_packet_rejects_mismatched_header() {
+        let limits = Limits::for_testing();
+        let mut buf = [0u8; 64];
+        let len = encode_ack_packet(&sample_ack(), 0, &mut buf).unwrap();
+        buf[16..20].copy_from_slice(&7u32.to_le_bytes());
+        let packet = decode_packet(&buf[..len], &limits).unwrap();
+        assert_eq!(decode_ack_packet(&packet), Err(DecodeError::InvalidAck));
+
+        let mut buf = [0u8; 64];
+        let len = encode_ack_packet(&sample_ack(), 0, &mut buf).unwrap();
+        buf[20..24].copy_from_slice(&1u32.to_le_bytes());
+        let err = decode_packet(&buf[..len], &limits).unwrap_err();
+        assert!(matches!(err, DecodeError::InvalidBaselineTick { .. }));
+    }
+}
@@ -0,0 +1,507 @@
+//! Optional whole-payload compression.
+//!
+//! A packet with the `COMPRESSED` flag carries its payload as
+//! `compressor_id (u8) | decoded_len (varu32) | compressed bytes`. The header
+//! is otherwise unchanged, so tick and schema checks still work without
+//! decompressing. Compression is pluggable through [`Compressor`];
+//! [`Lz4Compressor`] is the built-in pure-Rust implementation and supports a
+//! shared dictionary.
+
+use crate::error::{DecodeError, EncodeError, LimitKind, WireResult};
+use crate::header::{PacketFlags, FLAGS_OFFSET, HEADER_SIZE, PAYLOAD_LEN_OFFSET};
+use crate::limits::Limits;
+use crate::packet::{decode_header, read_varu32, varu32_len, write_varu32};
+
+/// A payload compressor.
+///
+/// Implementations must be deterministic and `decompress` must reject any
+/// input that does not produce exactly `out.len()` bytes.
+pub trait Compressor {
+    /// Identifier written into compressed payloads.
+    fn id(&self) -> u8;
+
+    /// Appends the compressed form of `input` to `out`.
+    fn compress(&self, input: &[u8], out: &mut Vec<u8>);
+
+    /// Decompresses `input` into `out`, which has the exact decoded length.
+    fn decompress(&self, input: &[u8], out: &mut [u8]) -> WireResult<()>;
+}
+
+/// Compresses the payload of an encoded packet.
+///
+/// The compressed packet is written to `out` only when it is smaller than
+/// `packet`; otherwise `packet` is copied unchanged. Returns the number of
+/// bytes written.
+pub fn compress_packet(
+    packet: &[u8],
+    compressor: &dyn Compressor,
+    out: &mut [u8],
+) -> Result<usize, EncodeError> {
+    if packet.len() < HEADER_SIZE {
+        return Err(EncodeError::BufferTooSmall {
+            needed: HEADER_SIZE,
+            available: packet.len(),
+        });
+    }
+    let flags = PacketFlags::from_raw(u16::from_le_bytes([
+        packet[FLAGS_OFFSET],
+        packet[FLAGS_OFFSET + 1],
+    ]));
+    let payload = &packet[HEADER_SIZE..];
+    let decoded_len = u32::try_from(payload.len()).map_err(|_| EncodeError::LengthOverflow {
+        length: payload.len(),
+    })?;
+
+    let mut compressed = Vec::new();
+    if !flags.is_compressed() {
+        compressor.compress(payload, &mut compressed);
+    }
+    let payload_len = 1 + varu32_len(decoded_len) + compressed.len();
+    if flags.is_compressed() || HEADER_SIZE + payload_len >= packet.len() {
+        return copy_into(packet, out);
+    }
+
+    let needed = HEADER_SIZE + payload_len;
+    if out.len() < needed {
+        return Err(EncodeError::BufferTooSmall {
+            needed,
+            available: out.len(),
+        });
+    }
+    out[..HEADER_SIZE].copy_from_slice(&packet[..HEADER_SIZE]);
+    patch_header(
+        &mut out[..HEADER_SIZE],
+        flags.with_compressed(true),
+        payload_len as u32,
+    );
+    let mut offset = HEADER_SIZE;
+    out[offset] = compressor.id();
+    offset += 1;
+    offset += write_varu32(decoded_len, &mut out[offset..]);
+    out[offset..needed].copy_from_slice(&compressed);
+    Ok(needed)
+}
+
+/// Restores a packet compressed by [`compress_packet`] into `out`.
+///
+/// The header is validated first and the declared decoded length is checked
+/// against `limits.max_packet_bytes` before any allocation, so a small packet
+/// cannot expand beyond the limits. Uncompressed packets are copied as-is.
+/// The result can be passed to [`decode_packet`](crate::decode_packet).
+pub fn decompress_packet(
+    buf: &[u8],
+    compressor: &dyn Compressor,
+    limits: &Limits,
+    out: &mut Vec<u8>,
+) -> WireResult<()> {
+    let header = decode_header(buf, limits)?;
+    out.clear();
+    if !header.flags.is_compressed() {
+        out.extend_from_slice(buf);
+        return Ok(());
+    }
+
+    let payload = &buf[HEADER_SIZE..];
+    let Some(&id) = payload.first() else {
+        return Err(DecodeError::InvalidCompressedData);
+    };
+    if id != compressor.id() {
+        return Err(DecodeError::UnknownCompressor { id });
+    }
+    let (decoded_len, offset) = read_varu32(payload, 1)?;
+    let packet_len = HEADER_SIZE + decoded_len as usize;
+    if packet_len > limits.max_packet_bytes {
+        return Err(DecodeError::LimitsExceeded {
+            kind: LimitKind::PacketBytes,
+            limit: limits.max_packet_bytes,
+            actual: packet_len,
+        });
+    }
+
+    out.resize(packet_len, 0);
+    out[..HEADER_SIZE].copy_from_slice(&buf[..HEADER_SIZE]);
+    patch_header(
+        &mut out[..HEADER_SIZE],
+        header.flags.with_compressed(false),
+        decoded_len,
+    );
+    compressor.decompress(&payload[offset..], &mut out[HEADER_SIZE..])
+}
+
+/// Rewrites the flags and payload length of an encoded header in place.
+fn patch_header(header: &mut [u8], flags: PacketFlags, payload_len: u32) {
+    header[FLAGS_OFFSET..FLAGS_OFFSET + 2].copy_from_slice(&flags.raw().to_le_bytes());
+    header[PAYLOAD_LEN_OFFSET..PAYLOAD_LEN_OFFSET + 4].copy_from_slice(&payload_len.to_le_bytes());
+}
+
+fn copy_into(packet: &[u8], out: &mut [u8]) -> Result<usize, EncodeError> {
+    if out.len() < packet.len() {
+        return Err(EncodeError::BufferTooSmall {
+            needed: packet.len(),
+            available: out.len(),
+        });
+    }
+    out[..packet.len()].copy_from_slice(packet);
+    Ok(packet.len())
+}
+
+const MIN_MATCH: usize = 4;
+const LAST_LITERALS: usize = 5;
+const MF_LIMIT: usize = 12;
+const MAX_OFFSET: usize = u16::MAX as usize;
+const HASH_LOG: u32 = 12;
+const NO_POSITION: u32 = u32::MAX;
+
+/// LZ4 block-format compressor with an optional shared dictionary.
+///
+/// Output is a standard LZ4 block; with a dictionary, matches may reach back
+/// into the last 64 KiB of the dictionary as if it preceded the payload. Both
+/// sides must use byte-identical dictionaries.
+#[derive(Debug, Clone)]
+pub struct Lz4Compressor {
+    dictionary: Vec<u8>,
+    dictionary_table: Vec<u32>,
+}
+
+impl Lz4Compressor {
+    /// Compressor id written into compressed payloads.
+    pub const ID: u8 = 1;
+
+    /// Creates a compressor without a dictionary.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_dictionary(Vec::new())
+    }
+
+    /// Creates a compressor primed with a shared dictionary.
+    ///
+    /// Only the last 64 KiB of the dictionary are reachable and kept.
+    #[must_use]
+    pub fn with_dictionary(mut dictionary: Vec<u8>) -> Self {
+        if dictionary.len() > MAX_OFFSET {
+            dictionary.drain(..dictionary.len() - MAX_OFFSET);
+        }
+        let mut dictionary_table = vec![NO_POSITION; 1 << HASH_LOG];
+        if dictionary.len() >= MIN_MATCH {
+            for pos in 0..=dictionary.len() - MIN_MATCH {
+                dictionary_table[hash(read_u32(&dictionary, pos))] = pos as u32;
+            }
+        }
+        Self {
+            dictionary,
+            dictionary_table,
+        }
+    }
+
+    /// Returns the dictionary in use.
+    #[must_use]
+    pub fn dictionary(&self) -> &[u8] {
+        &self.dictionary
+    }
+}
+
+impl Default for Lz4Compressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compressor for Lz4Compressor {
+    fn id(&self) -> u8 {
+        Self::ID
+    }
+
+    fn compress(&self, input: &[u8], out: &mut Vec<u8>) {
+        let base = self.dictionary.len();
+        let mut src = Vec::with_capacity(base + input.len());
+        src.extend_from_slice(&self.dictionary);
+        src.extend_from_slice(input);
+        let mut table = self.dictionary_table.clone();
+
+        let end = src.len();
+        let mut anchor = base;
+        let mut pos = base;
+        if input.len() > MF_LIMIT {
+            while pos + MF_LIMIT <= end {
+                let seq = read_u32(&src, pos);
+                let slot = hash(seq);
+                let candidate = table[slot];
+                table[slot] = pos as u32;
+                if candidate != NO_POSITION {
+                    let candidate = candidate as usize;
+                    if pos - candidate <= MAX_OFFSET && read_u32(&src, candidate) == seq {
+                        let mut len = MIN_MATCH;
+                        while pos + len < end - LAST_LITERALS
+                            && src[candidate + len] == src[pos + len]
+                        {
+                            len += 1;
+                        }
+                        write_sequence(out, &src[anchor..pos], pos - candidate, len);
+                        pos += len;
+                        anchor = pos;
+                        continue;
+                    }
+                }
+                pos += 1;
+            }
+        }
+        write_last_literals(out, &src[anchor..]);
+    }
+
+    fn decompress(&self, input: &[u8], out: &mut [u8]) -> WireResult<()> {
+        let dictionary = &self.dictionary;
+        let mut ip = 0usize;
+        let mut op = 0usize;
+        loop {
+            let token = *input.get(ip).ok_or(DecodeError::InvalidCompressedData)?;
+            ip += 1;
+
+            let literal_len = read_length(input, &mut ip, usize::from(token >> 4))?;
+            let literal_end = ip
+                .checked_add(literal_len)
+                .filter(|&end| end <= input.len())
+                .ok_or(DecodeError::InvalidCompressedData)?;
+            let out_end = op
+                .checked_add(literal_len)
+                .filter(|&end| end <= out.len())
+                .ok_or(DecodeError::InvalidCompressedData)?;
+            out[op..out_end].copy_from_slice(&input[ip..literal_end]);
+            ip = literal_end;
+            op = out_end;
+            if ip == input.len() {
+                break;
+            }
+
+            let offset_bytes = input
+                .get(ip..ip + 2)
+                .ok_or(DecodeError::InvalidCompressedData)?;
+            let offset = usize::from(u16::from_le_bytes([offset_bytes[0], offset_bytes[1]]));
+            ip += 2;
+            if offset == 0 || offset > op + dictionary.len() {
+                return Err(DecodeError::InvalidCompressedData);
+            }
+            let match_len = read_length(input, &mut ip, usize::from(token & 0x0F))? + MIN_MATCH;
+            if match_len > out.len() - op {
+                return Err(DecodeError::InvalidCompressedData);
+            }
+            for _ in 0..match_len {
+                out[op] = if offset > op {
+                    dictionary[dictionary.len() - (offset - op)]
+                } else {
+                    out[op - offset]
+                };
+                op += 1;
+            }
+        }
+        if op != out.len() {
+            return Err(DecodeError::InvalidCompressedData);
+        }
+        Ok(())
+    }
+}
+
+fn hash(seq: u32) -> usize {
+    (seq.wrapping_mul(2_654_435_761) >> (32 - HASH_LOG)) as usize
+}
+
+fn read_u32(buf: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([buf[pos], buf[pos + 1], buf[pos + 2], buf[pos + 3]])
+}
+
+fn read_length(input: &[u8], ip: &mut usize, nibble: usize) -> WireResult<usize> {
+    let mut len = nibble;
+    if nibble == 15 {
+        loop {
+            let byte = *input.get(*ip).ok_or(DecodeError::InvalidCompressedData)?;
+            *ip += 1;
+            len += usize::from(byte);
+            if byte != 255 {
+                break;
+            }
+        }
+    }
+    Ok(len)
+}
+
+fn write_length(out: &mut Vec<u8>, mut len: usize) {
+    while len >= 255 {
+        out.push(255);
+        len -= 255;
+    }
+    out.push(len as u8);
+}
+
+fn write_sequence(out: &mut Vec<u8>, literals: &[u8], offset: usize, match_len: usize) {
+    let extra = match_len - MIN_MATCH;
+    let token = (literals.len().min(15) << 4) | extra.min(15);
+    out.push(token as u8);
+    if literals.len() >= 15 {
+        write_length(out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+    out.extend_from_slice(&(offset as u16).to_le_bytes());
+    if extra >= 15 {
+        write_length(out, extra - 15);
+    }
+}
+
+fn write_last_literals(out: &mut Vec<u8>, literals: &[u8]) {
+    out.push((literals.len().min(15) << 4) as u8);
+    if literals.len() >= 15 {
+        write_length(out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::PacketHeader;
+    use crate::packet::{decode_packet, encode_header};
+
+    fn roundtrip(compressor: &Lz4Compressor, input: &[u8]) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        compressor.compress(input, &mut compressed);
+        let mut decoded = vec![0u8; input.len()];
+        compressor.decompress(&compressed, &mut decoded).unwrap();
+        assert_eq!(decoded, input);
+        compressed
+    }
+
+    fn packet_with_payload(payload: &[u8]) -> Vec<u8> {
+        let header = PacketHeader::full_snapshot(0xABCD, 7, payload.len() as u32);
+        let mut packet = vec![0u8; HEADER_SIZE];
+        encode_header(&header, &mut packet).unwrap();
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    #[test]
+    fn lz4_roundtrip_edge_sizes() {
+        let compressor = Lz4Compressor::new();
+        for len in [0usize, 1, 5, 12, 13, 300] {
+            let input: Vec<u8> = (0..len).map(|i| (i % 3) as u8).collect();
+            roundtrip(&compressor, &input);
+        }
+    }
+
+    #[test]
+    fn lz4_compresses_repetitive_input() {
+        let input: Vec<u8> = b"entity-update ".repeat(40);
+        let compressed = roundtrip(&Lz4Compressor::new(), &input);
+        assert!(compressed.len() < input.len() / 4);
+    }
+
+    #[test]
+    fn lz4_dictionary_helps_short_input() {
+        let template = b"position velocity health armor ammo".to_vec();
+        let input = b"position velocity health armor ammo!".to_vec();
+        let plain = roundtrip(&Lz4Compressor::new(), &input);
+        let primed = roundtrip(&Lz4Compressor::with_dictionary(template), &input);
+        assert!(primed.len() < plain.len());
+    }
+
+    #[test]
+    fn lz4_matches_reference_block() {
+        // 20 x 'a': literal 'a', then a match at offset 1 of length 14, then 5 literals.
+        let input = [b'a'; 20];
+        let mut compressed = Vec::new();
+        Lz4Compressor::new().compress(&input, &mut compressed);
+        assert_eq!(
+            compressed,
+            [0x1A, b'a', 0x01, 0x00, 0x50, b'a', b'a', b'a', b'a', b'a']
+        );
+    }
+
+    #[test]
+    fn lz4_rejects_corrupt_input() {
+        let compressor = Lz4Compressor::new();
+        let mut out = [0u8; 8];
+        // Match offset reaches before the start of the output.
+        assert_eq!(
+            compressor.decompress(&[0x10, b'a', 0x05, 0x00, 0x00], &mut out),
+            Err(DecodeError::InvalidCompressedData)
+        );
+        // Declared output longer than what the stream produces.
+        assert_eq!(
+            compressor.decompress(&[0x10, b'a'], &mut out),
+            Err(DecodeError::InvalidCompressedData)
+        );
+        // Match would overrun the output buffer.
+        assert_eq!(
+            compressor.decompress(&[0x1F, b'a', 0x01, 0x00, 0xFF, 0x10], &mut out),
+            Err(DecodeError::InvalidCompressedData)
+        );
+    }
+
+    #[test]
+    fn packet_roundtrip() {
+        let packet = packet_with_payload(&[1, 4, 0, 0, 0, 0].repeat(30));
+        let compressor = Lz4Compressor::new();
+        let mut compressed = vec![0u8; packet.len()];
+        let len = compress_packet(&packet, &compressor, &mut compressed).unwrap();
+        assert!(len < packet.len());
+        assert!(matches!(
+            decode_packet(&compressed[..len], &Limits::for_testing()),
+            Err(DecodeError::CompressedPacket)
+        ));
+
+        let mut restored = Vec::new();
+        decompress_packet(
+            &compressed[..len],
+            &compressor,
+            &Limits::for_testing(),
+            &mut restored,
+        )
+        .unwrap();
+        assert_eq!(restored, packet);
+    }
+
+    #[test]
+    fn incompressible_packet_is_copied() {
+        let packet = packet_with_payload(&[1, 3, 9, 8, 7]);
+        let mut out = vec![0u8; packet.len()];
+        let len = compress_packet(&packet, &Lz4Compressor::new(), &mut out).unwrap();
+        assert_eq!(&out[..len], packet.as_slice());
+    }
+
+    #[test]
+    fn decompression_bomb_is_rejected() {
+        let header = PacketHeader {
+            flags: PacketFlags::full_snapshot().with_compressed(true),
+            ..PacketHeader::full_snapshot(0, 1, 4)
+        };
+        let mut packet = vec![0u8; HEADER_SIZE];
+        encode_header(&header, &mut packet).unwrap();
+        // id, decoded_len = 1 MiB (varu32), one empty token.
+        packet.extend_from_slice(&[Lz4Compressor::ID, 0x80, 0x80, 0x40]);
+        let limits = Limits::for_testing();
+        let mut out = Vec::new();
+        assert!(matches!(
+            decompress_packet(&packet, &Lz4Compressor::new(), &limits, &mut out),
+            Err(DecodeError::LimitsExceeded {
+                kind: LimitKind::PacketBytes,
+                ..
+            })
+        ));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn unknown_compressor_is_rejected() {
+        let packet = packet_with_payload(&[1, 4, 0, 0, 0, 0].repeat(30));
+        let mut compressed = vec![0u8; packet.len()];
+        let len = compress_packet(&packet, &Lz4Compressor::new(), &mut compressed).unwrap();
+        compressed[HEADER_SIZE] = 9;
+        let mut out = Vec::new();
+        assert_eq!(
+            decompress_packet(
+                &compressed[..len],
+                &Lz4Compressor::new(),
+                &Limits::for_testing(),
+                &mut out
+            ),
+            Err(DecodeError::UnknownCompressor { id: 9 })
+        );
+    }
+}
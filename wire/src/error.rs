@@ -30,6 +30,9 @@ pub enum DecodeError {
     /// Unknown section tag encountered.
     UnknownSectionTag { tag: u8 },
 
+    /// Unknown section tag without the optional bit, at `offset` in the payload.
+    UnknownCriticalSection { tag: u8, offset: usize },
+
     /// Limits exceeded.
     LimitsExceeded {
         kind: LimitKind,
@@ -39,6 +42,19 @@ pub enum DecodeError {
 
     /// Section framing error.
     SectionFraming(SectionFramingError),
+
+    /// Packet payload is compressed and must be decompressed first.
+    CompressedPacket,
+
+    /// Compressed payload names a compressor that is not available.
+    UnknownCompressor { id: u8 },
+
+    /// Compressed payload is corrupt or does not match its declared length.
+    InvalidCompressedData,
+
+    /// Ack packet is malformed (missing or extra sections, tick mismatch,
+    /// invalid body).
+    InvalidAck,
 }
 
 /// Specific wire limits that can be exceeded.
@@ -103,6 +119,12 @@ impl fmt::Display for DecodeError {
             Self::UnknownSectionTag { tag } => {
                 write!(f, "unknown section tag: {tag}")
             }
+            Self::UnknownCriticalSection { tag, offset } => {
+                write!(
+                    f,
+                    "unknown critical section tag {tag} at payload offset {offset}"
+                )
+            }
             Self::LimitsExceeded {
                 kind,
                 limit,
@@ -111,6 +133,12 @@ impl fmt::Display for DecodeError {
                 write!(f, "{kind} limit exceeded: {actual} > {limit}")
             }
             Self::SectionFraming(err) => write!(f, "section framing error: {err}"),
+            Self::CompressedPacket => {
+                write!(f, "packet is compressed; decompress before decoding")
+            }
+            Self::UnknownCompressor { id } => write!(f, "unknown compressor id {id}"),
+            Self::InvalidCompressedData => write!(f, "invalid compressed data"),
+            Self::InvalidAck => write!(f, "invalid ack packet"),
         }
     }
 }
@@ -181,6 +209,24 @@ mod tests {
         assert!(msg.contains("10"));
     }
 
+    #[test]
+    fn decode_error_display_compression() {
+        let err = DecodeError::UnknownCompressor { id: 7 };
+        assert!(err.to_string().contains("compressor id 7"));
+        assert!(DecodeError::CompressedPacket
+            .to_string()
+            .contains("decompress"));
+    }
+
+    #[test]
+    fn decode_error_display_unknown_critical_section() {
+        let err = DecodeError::UnknownCriticalSection { tag: 32, offset: 5 };
+        assert_eq!(
+            err.to_string(),
+            "unknown critical section tag 32 at payload offset 5"
+        );
+    }
+
     #[test]
     fn section_framing_display() {
         let err = SectionFramingError::Truncated {
@@ -6,11 +6,17 @@
 pub const MAGIC: u32 = 0x5344_4543; // "SDEC" in ASCII
 
 /// Current wire format version.
-pub const VERSION: u16 = 2;
+pub const VERSION: u16 = 3;
 
 /// Header size in bytes (28 total).
 pub const HEADER_SIZE: usize = 4 + 2 + 2 + 8 + 4 + 4 + 4;
 
+/// Byte offset of the `flags` field (u16) within the header.
+pub(crate) const FLAGS_OFFSET: usize = 6;
+
+/// Byte offset of the `payload_len` field (u32) within the header.
+pub(crate) const PAYLOAD_LEN_OFFSET: usize = 24;
+
 /// Packet flags.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub struct PacketFlags(u16);
@@ -25,8 +31,20 @@ impl PacketFlags {
     /// Flag indicating a session init packet.
     pub const SESSION_INIT: u16 = 1 << 2;
 
+    /// Flag indicating the payload is compressed (see `compress_packet`).
+    ///
+    /// May be combined with any valid packet kind.
+    pub const COMPRESSED: u16 = 1 << 3;
+
+    /// Flag indicating a receiver-to-sender ack packet (see `encode_ack_packet`).
+    pub const ACK: u16 = 1 << 4;
+
+    /// Flag indicating a session accept packet, the receiver's answer to a
+    /// session init.
+    pub const SESSION_ACCEPT: u16 = 1 << 5;
+
     /// Reserved bits mask (must be zero in version 2).
-    const RESERVED_MASK: u16 = !0b111;
+    const RESERVED_MASK: u16 = !0b11_1111;
 
     /// Creates new flags from a raw value.
     #[must_use]
@@ -71,23 +89,55 @@ impl PacketFlags {
         self.0 & Self::SESSION_INIT != 0
     }
 
+    /// Returns `true` if this is an ack packet.
+    #[must_use]
+    pub const fn is_ack(self) -> bool {
+        self.0 & Self::ACK != 0
+    }
+
+    /// Returns `true` if this is a session accept packet.
+    #[must_use]
+    pub const fn is_session_accept(self) -> bool {
+        self.0 & Self::SESSION_ACCEPT != 0
+    }
+
+    /// Returns `true` if the payload is compressed.
+    #[must_use]
+    pub const fn is_compressed(self) -> bool {
+        self.0 & Self::COMPRESSED != 0
+    }
+
+    /// Returns these flags with `COMPRESSED` set or cleared.
+    #[must_use]
+    pub const fn with_compressed(self, compressed: bool) -> Self {
+        if compressed {
+            Self(self.0 | Self::COMPRESSED)
+        } else {
+            Self(self.0 & !Self::COMPRESSED)
+        }
+    }
+
     /// Returns `true` if the flags are valid for version 2.
     ///
     /// Valid means either:
-    /// - session init set alone (no full/delta), or
-    /// - exactly one of full/delta set, with no session init,
-    ///   and no reserved bits are set.
+    /// - one of session init, ack or session accept set alone (no full/delta), or
+    /// - exactly one of full/delta set, with none of those,
+    ///   and no reserved bits are set. `COMPRESSED` may accompany any of them.
     #[must_use]
     pub const fn is_valid_v2(self) -> bool {
         let has_full = self.is_full_snapshot();
         let has_delta = self.is_delta_snapshot();
         let has_session = self.is_session_init();
+        let has_ack = self.is_ack();
+        let has_accept = self.is_session_accept();
         let has_reserved = self.0 & Self::RESERVED_MASK != 0;
         if has_reserved {
             return false;
         }
-        if has_session {
-            return !has_full && !has_delta;
+        match has_session as u8 + has_ack as u8 + has_accept as u8 {
+            0 => {}
+            1 => return !has_full && !has_delta,
+            _ => return false,
         }
         has_full ^ has_delta
     }
@@ -109,6 +159,18 @@ impl PacketFlags {
     pub const fn session_init() -> Self {
         Self(Self::SESSION_INIT)
     }
+
+    /// Creates flags for an ack packet.
+    #[must_use]
+    pub const fn ack() -> Self {
+        Self(Self::ACK)
+    }
+
+    /// Creates flags for a session accept packet.
+    #[must_use]
+    pub const fn session_accept() -> Self {
+        Self(Self::SESSION_ACCEPT)
+    }
 }
 
 /// Packet header (version 0).
@@ -181,8 +243,8 @@ mod tests {
     }
 
     #[test]
-    fn version_is_two() {
-        assert_eq!(VERSION, 2);
+    fn version_is_three() {
+        assert_eq!(VERSION, 3);
     }
 
     #[test]
@@ -238,11 +300,51 @@ mod tests {
     #[test]
     fn flags_invalid_reserved_bits() {
         // Full snapshot + reserved bit
-        assert!(!PacketFlags::from_raw(0b1001).is_valid_v2());
+        assert!(!PacketFlags::from_raw(0b100_0001).is_valid_v2());
         // High bits set
         assert!(!PacketFlags::from_raw(0xFF01).is_valid_v2());
     }
 
+    #[test]
+    fn flags_compressed_combines_with_kind() {
+        let flags = PacketFlags::delta_snapshot().with_compressed(true);
+        assert!(flags.is_compressed());
+        assert!(flags.is_delta_snapshot());
+        assert!(flags.is_valid_v2());
+        assert!(!PacketFlags::from_raw(PacketFlags::COMPRESSED).is_valid_v2());
+        assert_eq!(flags.with_compressed(false), PacketFlags::delta_snapshot());
+    }
+
+    #[test]
+    fn flags_ack_stands_alone() {
+        let flags = PacketFlags::ack();
+        assert!(flags.is_ack());
+        assert!(flags.is_valid_v2());
+        assert!(flags.with_compressed(true).is_valid_v2());
+        for other in [
+            PacketFlags::FULL_SNAPSHOT,
+            PacketFlags::DELTA_SNAPSHOT,
+            PacketFlags::SESSION_INIT,
+        ] {
+            assert!(!PacketFlags::from_raw(PacketFlags::ACK | other).is_valid_v2());
+        }
+    }
+
+    #[test]
+    fn flags_session_accept_stands_alone() {
+        let flags = PacketFlags::session_accept();
+        assert!(flags.is_session_accept());
+        assert!(flags.is_valid_v2());
+        for other in [
+            PacketFlags::FULL_SNAPSHOT,
+            PacketFlags::DELTA_SNAPSHOT,
+            PacketFlags::SESSION_INIT,
+            PacketFlags::ACK,
+        ] {
+            assert!(!PacketFlags::from_raw(PacketFlags::SESSION_ACCEPT | other).is_valid_v2());
+        }
+    }
+
     #[test]
     fn flags_default() {
         let flags = PacketFlags::default();
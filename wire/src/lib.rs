@@ -12,22 +12,30 @@
 //!
 //! See `WIRE_FORMAT.md` for the complete specification.
 
+mod ack;
+mod compress;
 mod error;
 mod header;
 mod limits;
 mod packet;
 mod session;
 
+pub use ack::{
+    decode_ack_body, decode_ack_packet, decode_session_ack_packet, encode_ack_packet,
+    encode_session_ack_packet, AckErrorClass, AckPacket, ACK_WINDOW,
+};
+pub use compress::{compress_packet, decompress_packet, Compressor, Lz4Compressor};
 pub use error::{DecodeError, EncodeError, LimitKind, SectionFramingError, WireResult};
 pub use header::{PacketFlags, PacketHeader, HEADER_SIZE, MAGIC, VERSION};
 pub use limits::Limits;
 pub use packet::{
-    decode_packet, decode_sections, encode_header, encode_section, SectionTag, WirePacket,
-    WireSection,
+    decode_packet, decode_sections, decode_sections_with_skipped, encode_header, encode_section,
+    SectionTag, SkippedSection, WirePacket, WireSection,
 };
 pub use session::{
-    decode_session_header, encode_session_header, SessionFlags, SessionHeader,
-    SESSION_MAX_HEADER_SIZE,
+    decode_session_header, decode_session_header_v2, encode_session_header,
+    encode_session_header_v2, SessionFlags, SessionHeader, SESSION_MAX_HEADER_SIZE,
+    SESSION_MAX_TICK_DELTA, SESSION_V2_TICK_BITS,
 };
 
 #[cfg(test)]
@@ -5,6 +5,10 @@ use crate::header::{PacketFlags, PacketHeader, HEADER_SIZE, MAGIC, VERSION};
 use crate::limits::Limits;
 
 /// Section tags for version 2.
+///
+/// Tags follow the v3 section registry: bit 7 of the tag byte
+/// ([`SectionTag::OPTIONAL`]) marks a section that decoders which do not know
+/// the tag may skip. Every tag defined here is critical.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[non_exhaustive]
 #[repr(u8)]
@@ -15,9 +19,29 @@ pub enum SectionTag {
     EntityUpdateSparse = 4,
     EntityUpdateSparsePacked = 5,
     SessionInit = 6,
+    Fragment = 7,
+    EntityBaselineRef = 8,
+    Events = 9,
+    EntityUpdateCoded = 10,
+    EntityUpdateSparsePackedCoded = 11,
+    EntityComponentOps = 12,
+    Ack = 13,
+    SessionAccept = 14,
+    EntityCreateModeled = 15,
+    EntityUpdateModeled = 16,
 }
 
 impl SectionTag {
+    /// Tag byte bit marking an optional section.
+    pub const OPTIONAL: u8 = 0x80;
+
+    /// Returns `true` if a decoder that does not know the raw tag `tag` must
+    /// reject the packet rather than skip the section.
+    #[must_use]
+    pub const fn is_critical(tag: u8) -> bool {
+        tag & Self::OPTIONAL == 0
+    }
+
     /// Parses a section tag from a raw byte.
     pub fn parse(tag: u8) -> Result<Self, DecodeError> {
         match tag {
@@ -27,6 +51,16 @@ impl SectionTag {
             4 => Ok(Self::EntityUpdateSparse),
             5 => Ok(Self::EntityUpdateSparsePacked),
             6 => Ok(Self::SessionInit),
+            7 => Ok(Self::Fragment),
+            8 => Ok(Self::EntityBaselineRef),
+            9 => Ok(Self::Events),
+            10 => Ok(Self::EntityUpdateCoded),
+            11 => Ok(Self::EntityUpdateSparsePackedCoded),
+            12 => Ok(Self::EntityComponentOps),
+            13 => Ok(Self::Ack),
+            14 => Ok(Self::SessionAccept),
+            15 => Ok(Self::EntityCreateModeled),
+            16 => Ok(Self::EntityUpdateModeled),
             _ => Err(DecodeError::UnknownSectionTag { tag }),
         }
     }
@@ -39,15 +73,48 @@ pub struct WireSection<'a> {
     pub body: &'a [u8],
 }
 
+/// An optional section with a tag this decoder does not know.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkippedSection<'a> {
+    /// Raw tag byte (with [`SectionTag::OPTIONAL`] set).
+    pub tag: u8,
+    /// Offset of the tag byte within the payload.
+    pub offset: usize,
+    /// Section body, left undecoded.
+    pub body: &'a [u8],
+}
+
 /// A decoded wire packet.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct WirePacket<'a> {
     pub header: PacketHeader,
     pub sections: Vec<WireSection<'a>>,
+    /// Optional sections that were skipped because their tag is unknown.
+    pub skipped: Vec<SkippedSection<'a>>,
 }
 
 /// Decodes a wire packet into header + section slices.
+///
+/// Packets with the `COMPRESSED` flag are rejected; pass them through
+/// [`decompress_packet`](crate::decompress_packet) first.
 pub fn decode_packet<'a>(buf: &'a [u8], limits: &Limits) -> WireResult<WirePacket<'a>> {
+    let header = decode_header(buf, limits)?;
+    if header.flags.is_compressed() {
+        return Err(DecodeError::CompressedPacket);
+    }
+
+    let payload = &buf[HEADER_SIZE..];
+    let (sections, skipped) = decode_sections_with_skipped(payload, limits)?;
+
+    Ok(WirePacket {
+        header,
+        sections,
+        skipped,
+    })
+}
+
+/// Decodes and validates a packet header, including the payload length.
+pub(crate) fn decode_header(buf: &[u8], limits: &Limits) -> WireResult<PacketHeader> {
     if buf.len() < HEADER_SIZE {
         return Err(DecodeError::PacketTooSmall {
             actual: buf.len(),
@@ -95,6 +162,12 @@ pub fn decode_packet<'a>(buf: &'a [u8], limits: &Limits) -> WireResult<WirePacke
             flags: flags_raw,
         });
     }
+    if (flags.is_ack() || flags.is_session_accept()) && baseline_tick != 0 {
+        return Err(DecodeError::InvalidBaselineTick {
+            baseline_tick,
+            flags: flags_raw,
+        });
+    }
 
     let actual_payload_len = buf.len() - HEADER_SIZE;
     if payload_len as usize != actual_payload_len {
@@ -104,35 +177,48 @@ pub fn decode_packet<'a>(buf: &'a [u8], limits: &Limits) -> WireResult<WirePacke
         });
     }
 
-    let header = PacketHeader {
+    Ok(PacketHeader {
         version,
         flags,
         schema_hash,
         tick,
         baseline_tick,
         payload_len,
-    };
-
-    let payload = &buf[HEADER_SIZE..];
-    let sections = decode_sections(payload, limits)?;
-
-    Ok(WirePacket { header, sections })
+    })
 }
 
 /// Decodes sections from a payload buffer (no packet header).
+///
+/// Optional sections with unknown tags are skipped; use
+/// [`decode_sections_with_skipped`] to inspect them.
 pub fn decode_sections<'a>(payload: &'a [u8], limits: &Limits) -> WireResult<Vec<WireSection<'a>>> {
+    decode_sections_with_skipped(payload, limits).map(|(sections, _)| sections)
+}
+
+/// Decodes sections from a payload buffer, also returning the optional
+/// sections that were skipped because their tag is unknown.
+///
+/// An unknown critical tag fails with [`DecodeError::UnknownCriticalSection`].
+/// Skipped sections count towards `limits.max_sections`.
+pub fn decode_sections_with_skipped<'a>(
+    payload: &'a [u8],
+    limits: &Limits,
+) -> WireResult<(Vec<WireSection<'a>>, Vec<SkippedSection<'a>>)> {
     let mut offset = 0usize;
     let mut sections = Vec::new();
+    let mut skipped = Vec::new();
 
     while offset < payload.len() {
-        if sections.len() >= limits.max_sections {
+        let count = sections.len() + skipped.len();
+        if count >= limits.max_sections {
             return Err(DecodeError::LimitsExceeded {
                 kind: LimitKind::SectionCount,
                 limit: limits.max_sections,
-                actual: sections.len() + 1,
+                actual: count + 1,
             });
         }
 
+        let tag_offset = offset;
         let tag = payload[offset];
         offset += 1;
         let (len, new_offset) = read_varu32(payload, offset)?;
@@ -155,13 +241,25 @@ pub fn decode_sections<'a>(payload: &'a [u8], limits: &Limits) -> WireResult<Vec
             ));
         }
 
-        let tag = SectionTag::parse(tag)?;
         let body = &payload[offset..offset + len_usize];
-        sections.push(WireSection { tag, body });
         offset += len_usize;
+        match SectionTag::parse(tag) {
+            Ok(tag) => sections.push(WireSection { tag, body }),
+            Err(_) if !SectionTag::is_critical(tag) => skipped.push(SkippedSection {
+                tag,
+                offset: tag_offset,
+                body,
+            }),
+            Err(_) => {
+                return Err(DecodeError::UnknownCriticalSection {
+                    tag,
+                    offset: tag_offset,
+                })
+            }
+        }
     }
 
-    Ok(sections)
+    Ok((sections, skipped))
 }
 
 /// Encodes a packet header into the provided output buffer.
@@ -204,7 +302,7 @@ pub fn encode_section(tag: SectionTag, body: &[u8], out: &mut [u8]) -> Result<us
     Ok(needed)
 }
 
-fn read_varu32(buf: &[u8], mut offset: usize) -> Result<(u32, usize), DecodeError> {
+pub(crate) fn read_varu32(buf: &[u8], mut offset: usize) -> Result<(u32, usize), DecodeError> {
     let mut value = 0u32;
     let mut shift = 0u32;
     for _ in 0..5 {
@@ -229,7 +327,7 @@ fn read_varu32(buf: &[u8], mut offset: usize) -> Result<(u32, usize), DecodeErro
     ))
 }
 
-fn write_varu32(mut value: u32, out: &mut [u8]) -> usize {
+pub(crate) fn write_varu32(mut value: u32, out: &mut [u8]) -> usize {
     let mut offset = 0;
     loop {
         let mut byte = (value & 0x7F) as u8;
@@ -246,7 +344,7 @@ fn write_varu32(mut value: u32, out: &mut [u8]) -> usize {
     offset
 }
 
-fn varu32_len(mut value: u32) -> usize {
+pub(crate) fn varu32_len(mut value: u32) -> usize {
     let mut len = 1;
     while value >= 0x80 {
         value >>= 7;
@@ -433,4 +531,161 @@ mod tests {
             }
         ));
     }
+
+    #[test]
+    fn section_tag_parse_roundtrip() {
+        for tag in [
+            SectionTag::EntityCreate,
+            SectionTag::EntityDestroy,
+            SectionTag::EntityUpdate,
+            SectionTag::EntityUpdateSparse,
+            SectionTag::EntityUpdateSparsePacked,
+            SectionTag::SessionInit,
+            SectionTag::Fragment,
+            SectionTag::EntityBaselineRef,
+            SectionTag::Events,
+            SectionTag::EntityUpdateCoded,
+            SectionTag::EntityUpdateSparsePackedCoded,
+            SectionTag::EntityComponentOps,
+            SectionTag::Ack,
+            SectionTag::SessionAccept,
+            SectionTag::EntityCreateModeled,
+            SectionTag::EntityUpdateModeled,
+        ] {
+            assert_eq!(SectionTag::parse(tag as u8).unwrap(), tag);
+        }
+        assert!(matches!(
+            SectionTag::parse(0),
+            Err(DecodeError::UnknownSectionTag { tag: 0 })
+        ));
+    }
+
+    /// A version 3 delta as written before the v3 section registry: entity 2
+    /// created and entity 1 updated, each with one bool component set to
+    /// `true`. `codec`'s delta tests apply these same bytes.
+    const V3_GOLDEN: [u8; 40] = [
+        0x43, 0x45, 0x44, 0x53, // magic "SDEC"
+        0x03, 0x00, // version
+        0x02, 0x00, // flags: DELTA_SNAPSHOT
+        0x15, 0xEE, 0x7B, 0x65, 0x24, 0xA2, 0xF5, 0x32, // schema_hash
+        0x0B, 0x00, 0x00, 0x00, // tick
+        0x0A, 0x00, 0x00, 0x00, // baseline_tick
+        0x0C, 0x00, 0x00, 0x00, // payload_len
+        0x01, 0x04, 0x01, 0x02, 0x00, 0xE0, // ENTITY_CREATE
+        0x03, 0x04, 0x01, 0x01, 0x00, 0xE0, // ENTITY_UPDATE
+    ];
+
+    /// Length of the golden packet's `ENTITY_CREATE` section.
+    const GOLDEN_CREATE_LEN: usize = 6;
+
+    /// Returns the golden packet with `extra` inserted between its two
+    /// sections, as a later encoder would write it.
+    fn golden_with(extra: &[u8]) -> Vec<u8> {
+        let mut buf = V3_GOLDEN[..HEADER_SIZE + GOLDEN_CREATE_LEN].to_vec();
+        buf.extend_from_slice(extra);
+        buf.extend_from_slice(&V3_GOLDEN[HEADER_SIZE + GOLDEN_CREATE_LEN..]);
+        let payload_len = (buf.len() - HEADER_SIZE) as u32;
+        buf[HEADER_SIZE - 4..HEADER_SIZE].copy_from_slice(&payload_len.to_le_bytes());
+        buf
+    }
+
+    #[test]
+    fn v3_golden_packet_decodes_unchanged() {
+        let packet = decode_packet(&V3_GOLDEN, &Limits::for_testing()).unwrap();
+        assert_eq!(
+            packet.header,
+            PacketHeader::delta_snapshot(0x32F5_A224_657B_EE15, 11, 10, 12)
+        );
+        assert_eq!(
+            packet.sections,
+            vec![
+                WireSection {
+                    tag: SectionTag::EntityCreate,
+                    body: &[0x01, 0x02, 0x00, 0xE0],
+                },
+                WireSection {
+                    tag: SectionTag::EntityUpdate,
+                    body: &[0x01, 0x01, 0x00, 0xE0],
+                },
+            ]
+        );
+        assert!(packet.skipped.is_empty());
+
+        let mut out = [0u8; V3_GOLDEN.len()];
+        encode_header(&packet.header, &mut out).unwrap();
+        let mut offset = HEADER_SIZE;
+        for section in &packet.sections {
+            offset += encode_section(section.tag, section.body, &mut out[offset..]).unwrap();
+        }
+        assert_eq!(out[..offset], V3_GOLDEN);
+    }
+
+    #[test]
+    fn version_2_packets_are_rejected() {
+        let mut buf = V3_GOLDEN;
+        buf[4..6].copy_from_slice(&2u16.to_le_bytes());
+        assert_eq!(
+            decode_packet(&buf, &Limits::for_testing()).unwrap_err(),
+            DecodeError::UnsupportedVersion { found: 2 }
+        );
+    }
+
+    #[test]
+    fn optional_unknown_section_is_skipped_and_reported() {
+        let buf = golden_with(&[0x90, 0x03, 0x01, 0x02, 0x03]);
+        let packet = decode_packet(&buf, &Limits::for_testing()).unwrap();
+        let plain = decode_packet(&V3_GOLDEN, &Limits::for_testing()).unwrap();
+        assert_eq!(packet.sections, plain.sections);
+        assert_eq!(
+            packet.skipped,
+            vec![SkippedSection {
+                tag: 0x90,
+                offset: GOLDEN_CREATE_LEN,
+                body: &[1, 2, 3],
+            }]
+        );
+        assert_eq!(
+            super::decode_sections(&buf[HEADER_SIZE..], &Limits::for_testing()).unwrap(),
+            plain.sections
+        );
+    }
+
+    #[test]
+    fn critical_unknown_section_fails_with_offset() {
+        let buf = golden_with(&[0x20, 0x01, 0xFF]);
+        assert_eq!(
+            decode_packet(&buf, &Limits::for_testing()).unwrap_err(),
+            DecodeError::UnknownCriticalSection {
+                tag: 0x20,
+                offset: GOLDEN_CREATE_LEN
+            }
+        );
+        assert!(SectionTag::is_critical(0x20));
+        assert!(!SectionTag::is_critical(0x90));
+    }
+
+    #[test]
+    fn skipped_sections_count_towards_section_limit() {
+        let buf = golden_with(&[0x90, 0x00]);
+        let limits = Limits {
+            max_sections: 2,
+            ..Limits::for_testing()
+        };
+        assert!(matches!(
+            decode_packet(&buf, &limits),
+            Err(DecodeError::LimitsExceeded {
+                kind: LimitKind::SectionCount,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn defined_tags_are_critical() {
+        for raw in 0..=u8::MAX {
+            if SectionTag::parse(raw).is_ok() {
+                assert!(SectionTag::is_critical(raw), "tag {raw}");
+            }
+        }
+    }
 }
@@ -5,6 +5,12 @@ use crate::error::{DecodeError, EncodeError, SectionFramingError, WireResult};
 /// Maximum encoded size of a session header in bytes.
 pub const SESSION_MAX_HEADER_SIZE: usize = 1 + 5 + 5 + 5;
 
+/// Exclusive upper bound for tick and baseline deltas.
+///
+/// Ticks wrap around at `u32::MAX` and are ordered with serial-number
+/// arithmetic (RFC 1982), so a delta of 2^31 or more has no defined direction.
+pub const SESSION_MAX_TICK_DELTA: u32 = 1 << 31;
+
 /// Flags for session headers (compact, 1 byte).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub struct SessionFlags(u8);
@@ -14,8 +20,10 @@ impl SessionFlags {
     pub const FULL_SNAPSHOT: u8 = 1 << 0;
     /// Flag indicating a delta snapshot packet.
     pub const DELTA_SNAPSHOT: u8 = 1 << 1;
+    /// Flag indicating an ack packet (no tick fields; see `encode_session_ack_packet`).
+    pub const ACK: u8 = 1 << 2;
     /// Reserved bits mask (must be zero).
-    const RESERVED_MASK: u8 = !0b11;
+    const RESERVED_MASK: u8 = !0b111;
 
     /// Creates flags from a raw value.
     #[must_use]
@@ -41,13 +49,20 @@ impl SessionFlags {
         self.0 & Self::DELTA_SNAPSHOT != 0
     }
 
-    /// Returns `true` if flags are valid (exactly one snapshot bit, no reserved).
+    /// Returns `true` if this is an ack packet.
+    #[must_use]
+    pub const fn is_ack(self) -> bool {
+        self.0 & Self::ACK != 0
+    }
+
+    /// Returns `true` if flags are valid (exactly one of full, delta or ack,
+    /// no reserved bits).
     #[must_use]
     pub const fn is_valid(self) -> bool {
-        let has_full = self.is_full_snapshot();
-        let has_delta = self.is_delta_snapshot();
+        let kinds =
+            self.is_full_snapshot() as u8 + self.is_delta_snapshot() as u8 + self.is_ack() as u8;
         let has_reserved = self.0 & Self::RESERVED_MASK != 0;
-        (has_full ^ has_delta) && !has_reserved
+        kinds == 1 && !has_reserved
     }
 
     /// Creates flags for a full snapshot.
@@ -61,9 +76,17 @@ impl SessionFlags {
     pub const fn delta_snapshot() -> Self {
         Self(Self::DELTA_SNAPSHOT)
     }
+
+    /// Creates flags for an ack packet.
+    #[must_use]
+    pub const fn ack() -> Self {
+        Self(Self::ACK)
+    }
 }
 
 /// Decoded session header (compact format).
+///
+/// Ack headers carry no tick fields; `tick` and `baseline_tick` are 0.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SessionHeader {
     pub flags: SessionFlags,
@@ -73,7 +96,16 @@ pub struct SessionHeader {
     pub header_len: usize,
 }
 
+/// Number of low tick bits carried by a v2 compact session header.
+///
+/// The receiver can reconstruct the tick as long as it is within 2^15 ticks
+/// of the reference tick, so up to 32767 consecutive losses are tolerated.
+pub const SESSION_V2_TICK_BITS: u32 = 16;
+
 /// Encodes a compact session header into the provided buffer.
+///
+/// For ack flags only `flags` and `payload_len` are written; the tick deltas
+/// are ignored.
 pub fn encode_session_header(
     out: &mut [u8],
     flags: SessionFlags,
@@ -81,6 +113,43 @@ pub fn encode_session_header(
     baseline_delta: u32,
     payload_len: u32,
 ) -> Result<usize, EncodeError> {
+    let mut offset = begin_session_header(out, flags)?;
+    if flags.is_ack() {
+        offset += write_varu32(payload_len, &mut out[offset..]);
+        return Ok(offset);
+    }
+    offset += write_varu32(tick_delta, &mut out[offset..]);
+    offset += write_varu32(baseline_delta, &mut out[offset..]);
+    offset += write_varu32(payload_len, &mut out[offset..]);
+    Ok(offset)
+}
+
+/// Encodes a v2 compact session header, which carries the low
+/// [`SESSION_V2_TICK_BITS`] bits of the absolute `tick` instead of a delta
+/// from the previous packet, so losing or reordering packets does not
+/// desynchronize tick reconstruction.
+///
+/// Ack headers are identical to v1.
+pub fn encode_session_header_v2(
+    out: &mut [u8],
+    flags: SessionFlags,
+    tick: u32,
+    baseline_delta: u32,
+    payload_len: u32,
+) -> Result<usize, EncodeError> {
+    let mut offset = begin_session_header(out, flags)?;
+    if flags.is_ack() {
+        offset += write_varu32(payload_len, &mut out[offset..]);
+        return Ok(offset);
+    }
+    out[offset..offset + 2].copy_from_slice(&(tick as u16).to_le_bytes());
+    offset += 2;
+    offset += write_varu32(baseline_delta, &mut out[offset..]);
+    offset += write_varu32(payload_len, &mut out[offset..]);
+    Ok(offset)
+}
+
+fn begin_session_header(out: &mut [u8], flags: SessionFlags) -> Result<usize, EncodeError> {
     if out.len() < SESSION_MAX_HEADER_SIZE {
         return Err(EncodeError::BufferTooSmall {
             needed: SESSION_MAX_HEADER_SIZE,
@@ -90,54 +159,89 @@ pub fn encode_session_header(
     if !flags.is_valid() {
         return Err(EncodeError::LengthOverflow { length: 0 });
     }
-
-    let mut offset = 0;
-    out[offset] = flags.raw();
-    offset += 1;
-    offset += write_varu32(tick_delta, &mut out[offset..]);
-    offset += write_varu32(baseline_delta, &mut out[offset..]);
-    offset += write_varu32(payload_len, &mut out[offset..]);
-    Ok(offset)
+    out[0] = flags.raw();
+    Ok(1)
 }
 
 /// Decodes a compact session header from the provided buffer.
 pub fn decode_session_header(buf: &[u8], last_tick: u32) -> WireResult<SessionHeader> {
-    if buf.is_empty() {
-        return Err(DecodeError::PacketTooSmall {
-            actual: buf.len(),
-            required: 1,
+    let flags = read_session_flags(buf)?;
+    if flags.is_ack() {
+        return decode_ack_session_header(buf, flags);
+    }
+    let (tick_delta, offset) = read_varu32(buf, 1)?;
+    if tick_delta == 0 || tick_delta >= SESSION_MAX_TICK_DELTA {
+        return Err(DecodeError::InvalidFlags {
+            flags: flags.raw() as u16,
         });
     }
+    let tick = last_tick.wrapping_add(tick_delta);
+    decode_session_header_tail(buf, offset, flags, tick)
+}
+
+/// Decodes a v2 compact session header.
+///
+/// The tick is reconstructed as the tick nearest to `reference_tick` (usually
+/// the newest tick received) whose low [`SESSION_V2_TICK_BITS`] bits match,
+/// so it may be older than `reference_tick` for a late packet.
+pub fn decode_session_header_v2(buf: &[u8], reference_tick: u32) -> WireResult<SessionHeader> {
+    let flags = read_session_flags(buf)?;
+    if flags.is_ack() {
+        return decode_ack_session_header(buf, flags);
+    }
+    let low = buf
+        .get(1..3)
+        .ok_or(DecodeError::PacketTooSmall {
+            actual: buf.len(),
+            required: 3,
+        })
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))?;
+    let distance = low.wrapping_sub(reference_tick as u16) as i16;
+    let tick = reference_tick.wrapping_add(i32::from(distance) as u32);
+    decode_session_header_tail(buf, 3, flags, tick)
+}
 
-    let flags = SessionFlags::from_raw(buf[0]);
+fn read_session_flags(buf: &[u8]) -> WireResult<SessionFlags> {
+    let raw = *buf.first().ok_or(DecodeError::PacketTooSmall {
+        actual: buf.len(),
+        required: 1,
+    })?;
+    let flags = SessionFlags::from_raw(raw);
     if !flags.is_valid() {
         return Err(DecodeError::InvalidFlags {
             flags: flags.raw() as u16,
         });
     }
+    Ok(flags)
+}
 
-    let mut offset = 1;
-    let (tick_delta, new_offset) = read_varu32(buf, offset)?;
-    offset = new_offset;
-    if tick_delta == 0 {
-        return Err(DecodeError::InvalidFlags {
+fn decode_ack_session_header(buf: &[u8], flags: SessionFlags) -> WireResult<SessionHeader> {
+    let (payload_len, header_len) = read_varu32(buf, 1)?;
+    Ok(SessionHeader {
+        flags,
+        tick: 0,
+        baseline_tick: 0,
+        payload_len,
+        header_len,
+    })
+}
+
+/// Decodes the `baseline_delta` and `payload_len` fields shared by both
+/// header versions.
+fn decode_session_header_tail(
+    buf: &[u8],
+    offset: usize,
+    flags: SessionFlags,
+    tick: u32,
+) -> WireResult<SessionHeader> {
+    let (baseline_delta, offset) = read_varu32(buf, offset)?;
+    if baseline_delta >= SESSION_MAX_TICK_DELTA {
+        return Err(DecodeError::InvalidBaselineTick {
+            baseline_tick: baseline_delta,
             flags: flags.raw() as u16,
         });
     }
-    let tick = last_tick
-        .checked_add(tick_delta)
-        .ok_or(DecodeError::InvalidFlags {
-            flags: flags.raw() as u16,
-        })?;
-
-    let (baseline_delta, new_offset) = read_varu32(buf, offset)?;
-    offset = new_offset;
-    let baseline_tick =
-        tick.checked_sub(baseline_delta)
-            .ok_or(DecodeError::InvalidBaselineTick {
-                baseline_tick: baseline_delta,
-                flags: flags.raw() as u16,
-            })?;
+    let baseline_tick = tick.wrapping_sub(baseline_delta);
     if flags.is_full_snapshot() && baseline_delta != 0 {
         return Err(DecodeError::InvalidBaselineTick {
             baseline_tick,
@@ -151,8 +255,7 @@ pub fn decode_session_header(buf: &[u8], last_tick: u32) -> WireResult<SessionHe
         });
     }
 
-    let (payload_len, new_offset) = read_varu32(buf, offset)?;
-    offset = new_offset;
+    let (payload_len, offset) = read_varu32(buf, offset)?;
 
     Ok(SessionHeader {
         flags,
@@ -225,6 +328,22 @@ mod tests {
         assert_eq!(decoded.payload_len, 123);
     }
 
+    #[test]
+    fn session_header_roundtrip_ack() {
+        let mut buf = [0u8; SESSION_MAX_HEADER_SIZE];
+        let len = encode_session_header(&mut buf, SessionFlags::ack(), 7, 3, 9).unwrap();
+        assert_eq!(len, 2);
+        let decoded = decode_session_header(&buf[..len], 10).unwrap();
+        assert!(decoded.flags.is_ack());
+        assert_eq!(decoded.tick, 0);
+        assert_eq!(decoded.baseline_tick, 0);
+        assert_eq!(decoded.payload_len, 9);
+        assert_eq!(decoded.header_len, 2);
+
+        let both = SessionFlags::from_raw(SessionFlags::ACK | SessionFlags::DELTA_SNAPSHOT);
+        assert!(!both.is_valid());
+    }
+
     #[test]
     fn session_header_rejects_zero_tick_delta() {
         let mut buf = [0u8; SESSION_MAX_HEADER_SIZE];
@@ -233,4 +352,82 @@ mod tests {
         let err = decode_session_header(&buf[..len], 1).unwrap_err();
         assert!(matches!(err, DecodeError::InvalidFlags { .. }));
     }
+
+    #[test]
+    fn session_header_ticks_wrap_around() {
+        let mut buf = [0u8; SESSION_MAX_HEADER_SIZE];
+        let len =
+            encode_session_header(&mut buf, SessionFlags::delta_snapshot(), 3, 4, 10).unwrap();
+        let decoded = decode_session_header(&buf[..len], u32::MAX - 1).unwrap();
+        assert_eq!(decoded.tick, 1);
+        assert_eq!(decoded.baseline_tick, u32::MAX - 2);
+
+        // A baseline that lands on tick 0 is still "no baseline".
+        let len =
+            encode_session_header(&mut buf, SessionFlags::delta_snapshot(), 3, 1, 10).unwrap();
+        let err = decode_session_header(&buf[..len], u32::MAX - 1).unwrap_err();
+        assert!(matches!(err, DecodeError::InvalidBaselineTick { .. }));
+    }
+
+    #[test]
+    fn session_header_v2_survives_loss_and_reordering() {
+        let mut buf = [0u8; SESSION_MAX_HEADER_SIZE];
+        let len = encode_session_header_v2(&mut buf, SessionFlags::delta_snapshot(), 70_005, 3, 9)
+            .unwrap();
+        assert_eq!(len, 5);
+
+        // Reconstructed from any reference within 2^15 ticks, either side.
+        for reference in [70_004, 70_005, 69_000, 70_005 + 32_768, 70_005 - 32_767] {
+            let decoded = decode_session_header_v2(&buf[..len], reference).unwrap();
+            assert_eq!(decoded.tick, 70_005);
+            assert_eq!(decoded.baseline_tick, 70_002);
+            assert_eq!(decoded.payload_len, 9);
+            assert_eq!(decoded.header_len, len);
+        }
+        let far = decode_session_header_v2(&buf[..len], 70_005 - 32_768).unwrap();
+        assert_eq!(far.tick, 70_005 - 65_536);
+
+        // Across the u32 wrap.
+        let len =
+            encode_session_header_v2(&mut buf, SessionFlags::delta_snapshot(), 2, 4, 0).unwrap();
+        let decoded = decode_session_header_v2(&buf[..len], u32::MAX - 10).unwrap();
+        assert_eq!(decoded.tick, 2);
+        assert_eq!(decoded.baseline_tick, u32::MAX - 1);
+
+        let len = encode_session_header_v2(&mut buf, SessionFlags::ack(), 0, 0, 4).unwrap();
+        assert!(decode_session_header_v2(&buf[..len], 9)
+            .unwrap()
+            .flags
+            .is_ack());
+        assert!(matches!(
+            decode_session_header_v2(&[SessionFlags::DELTA_SNAPSHOT, 1], 9),
+            Err(DecodeError::PacketTooSmall { .. })
+        ));
+    }
+
+    #[test]
+    fn session_header_rejects_deltas_without_serial_order() {
+        let mut buf = [0u8; SESSION_MAX_HEADER_SIZE];
+        let len = encode_session_header(
+            &mut buf,
+            SessionFlags::delta_snapshot(),
+            SESSION_MAX_TICK_DELTA,
+            1,
+            10,
+        )
+        .unwrap();
+        let err = decode_session_header(&buf[..len], 1).unwrap_err();
+        assert!(matches!(err, DecodeError::InvalidFlags { .. }));
+
+        let len = encode_session_header(
+            &mut buf,
+            SessionFlags::delta_snapshot(),
+            1,
+            SESSION_MAX_TICK_DELTA,
+            10,
+        )
+        .unwrap();
+        let err = decode_session_header(&buf[..len], 1).unwrap_err();
+        assert!(matches!(err, DecodeError::InvalidBaselineTick { .. }));
+    }
 }